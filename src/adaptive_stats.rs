@@ -28,19 +28,19 @@ pub fn clear_adaptive_stats() -> Result<()> {
 
     let (uid, _) = keymux::get_actual_user_uid();
     let home = keymux::get_user_home_dir(uid).expect("Failed to get user home directory");
-    let config_dir = home.join(".config").join("keymux");
+    let config_dir = keymux::paths::config_dir_in(&home);
 
     let mt_stats = config_dir.join("adaptive_stats.json");
     let all_stats = config_dir.join("all_key_stats.json");
 
     let mut deleted = 0;
-    if mt_stats.exists() {
-        std::fs::remove_file(&mt_stats)?;
-        deleted += 1;
-    }
-    if all_stats.exists() {
-        std::fs::remove_file(&all_stats)?;
-        deleted += 1;
+    for path in [&mt_stats, &all_stats] {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+            deleted += 1;
+        }
+        // The backup would otherwise be loaded in place of the deleted file
+        let _ = std::fs::remove_file(keymux::event_processor::stats_file::backup_path(path));
     }
 
     println!();
@@ -78,7 +78,7 @@ pub fn show_adaptive_stats(config_path: Option<&std::path::Path>) -> Result<()>
     let config_path = config_path.map(|p| p.to_path_buf()).unwrap_or_else(|| {
         let (uid, _) = keymux::get_actual_user_uid();
         let home = keymux::get_user_home_dir(uid).expect("Failed to get user home directory");
-        home.join(".config").join("keymux").join("config.ron")
+        keymux::paths::config_path_in(&home)
     });
 
     print!("  → Loading config... ");
@@ -114,13 +114,10 @@ pub fn show_adaptive_stats(config_path: Option<&std::path::Path>) -> Result<()>
     }
 
     let all_stats_path = config_path.parent().unwrap().join("all_key_stats.json");
-    let stats = if all_stats_path.exists() {
-        let json = std::fs::read_to_string(&all_stats_path).unwrap_or_default();
-        let stats_map: std::collections::HashMap<
-            String,
-            keymux::event_processor::actions::mt::RollingStats,
-        > = serde_json::from_str(&json).unwrap_or_default();
-
+    let stats_map = keymux::event_processor::stats_file::read::<
+        std::collections::HashMap<String, keymux::event_processor::actions::mt::RollingStats>,
+    >(&all_stats_path, keymux::get_actual_user_uid().0)?;
+    let stats = if let Some(stats_map) = stats_map {
         let mut result = Vec::new();
         for (key_str, stats) in stats_map {
             let key_json = format!("\"KC_{}\"", key_str);
@@ -159,32 +156,51 @@ pub fn show_adaptive_stats(config_path: Option<&std::path::Path>) -> Result<()>
     let mut sorted_stats = stats;
     sorted_stats.sort_by_key(|(k, _)| format!("{:?}", k));
 
-    println!("  ┌───────┬────────┬─────────┬──────────┐");
+    println!("  ┌───────┬────────┬─────────┬─────────┬──────────┐");
     println!(
-        "  │ {:^5} │ {:^6} │ {:^7} │ {:^8} │",
+        "  │ {:^5} │ {:^6} │ {:^7} │ {:^7} │ {:^8} │",
         "Key".bright_white().bold(),
         "Samples".bright_white().bold(),
         "Avg(ms)".bright_white().bold(),
+        "Dev(ms)".bright_white().bold(),
         "Thresh(ms)".bright_white().bold()
     );
-    println!("  ├───────┼────────┼─────────┼──────────┤");
+    println!("  ├───────┼────────┼─────────┼─────────┼──────────┤");
 
     for (keycode, key_stats) in sorted_stats {
         let key_name = format!("{:?}", keycode).replace("KC_", "");
         let samples = key_stats.tap_sample_count;
         let avg_tap = key_stats.avg_tap_duration;
-        let threshold = key_stats.adaptive_threshold;
+        let threshold = format!("{:.1}", key_stats.adaptive_threshold);
+        // Not used until the key has enough consistent taps
+        let threshold = if key_stats.is_confident(
+            config.mt_config.adaptive_min_samples,
+            config.mt_config.adaptive_max_stddev_ms,
+        ) {
+            threshold.bright_yellow()
+        } else {
+            threshold.dimmed()
+        };
 
         println!(
-            "  │ {:^5} │ {:^6} │ {:^7} │ {:^8} │",
+            "  │ {:^5} │ {:^6} │ {:^7} │ {:^7} │ {:^8} │",
             key_name.bright_cyan(),
             samples.to_string().bright_green(),
             format!("{:.1}", avg_tap).bright_blue(),
-            format!("{:.1}", threshold).bright_yellow()
+            format!("{:.1}", key_stats.tap_stddev()).bright_blue(),
+            threshold
         );
     }
 
-    println!("  └───────┴────────┴─────────┴──────────┘");
+    println!("  └───────┴────────┴─────────┴─────────┴──────────┘");
+    println!(
+        "  {}",
+        format!(
+            "Dimmed thresholds aren't used yet: they need {} taps spread at most {}ms",
+            config.mt_config.adaptive_min_samples, config.mt_config.adaptive_max_stddev_ms
+        )
+        .dimmed()
+    );
 
     println!(
         "{}",
@@ -0,0 +1,107 @@
+//! Battery level of wireless keyboards, from `/sys/class/power_supply`
+//!
+//! The kernel's HID driver registers a power supply for keyboards that report their
+//! battery, named after the device's unique ID (e.g. `hid-c8:7f:54:aa:bb:cc-battery`)
+//! and parented to the HID device the keyboard's input nodes sit under. A supply
+//! matches a keyboard by either.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatteryLevel {
+    /// Charge left, 0-100
+    pub percent: u8,
+    pub charging: bool,
+}
+
+impl std::fmt::Display for BatteryLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}%", self.percent)?;
+        if self.charging {
+            write!(f, " (charging)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Battery of the keyboard an event node belongs to, if it reports one
+pub fn battery_for_node(event_path: &Path) -> Option<BatteryLevel> {
+    let event_name = event_path.file_name()?.to_str()?;
+    let input_dir = Path::new("/sys/class/input")
+        .join(event_name)
+        .join("device");
+    let uniq = fs::read_to_string(input_dir.join("uniq")).unwrap_or_default();
+    let input_device = fs::canonicalize(&input_dir).ok();
+
+    fs::read_dir(POWER_SUPPLY_DIR)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|supply| {
+            let name = supply.file_name().unwrap_or_default().to_string_lossy();
+            is_device_supply(supply)
+                && (name_has_uniq(&name, uniq.trim())
+                    || input_device
+                        .as_deref()
+                        .is_some_and(|input| parents_input(supply, input)))
+        })
+        .and_then(|supply| read_level(&supply))
+}
+
+/// Whether a supply belongs to a peripheral, not the machine (laptop battery, AC)
+fn is_device_supply(supply: &Path) -> bool {
+    fs::read_to_string(supply.join("scope")).is_ok_and(|scope| scope.trim() == "Device")
+}
+
+/// Whether a supply's name carries a unique ID, ignoring case and separators
+fn name_has_uniq(name: &str, uniq: &str) -> bool {
+    let normalize = |s: &str| -> String {
+        s.chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_lowercase()
+    };
+    let uniq = normalize(uniq);
+    !uniq.is_empty() && normalize(name).contains(&uniq)
+}
+
+/// Whether the input device sits under the supply's parent device
+fn parents_input(supply: &Path, input_device: &Path) -> bool {
+    fs::canonicalize(supply.join("device")).is_ok_and(|parent| input_device.starts_with(parent))
+}
+
+fn read_level(supply: &Path) -> Option<BatteryLevel> {
+    let percent: u8 = fs::read_to_string(supply.join("capacity"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    let status = fs::read_to_string(supply.join("status")).unwrap_or_default();
+    Some(BatteryLevel {
+        percent: percent.min(100),
+        charging: matches!(status.trim(), "Charging" | "Full"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supply_name_matches_uniq() {
+        assert!(name_has_uniq(
+            "hid-c8:7f:54:aa:bb:cc-battery",
+            "C8:7F:54:AA:BB:CC"
+        ));
+        assert!(!name_has_uniq(
+            "hid-c8:7f:54:aa:bb:cd-battery",
+            "C8:7F:54:AA:BB:CC"
+        ));
+        // Wired keyboards have no unique ID
+        assert!(!name_has_uniq("BAT0", ""));
+    }
+}
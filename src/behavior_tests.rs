@@ -0,0 +1,74 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use keymux::config::Config;
+use keymux::event_processor::behavior_test::{format_events, run_behavior_test};
+use std::path::Path;
+
+/// Run the config's `tests` and print a line per test; fails if any test fails
+pub fn run_tests(config_path: Option<&Path>, filter: Option<&str>) -> Result<()> {
+    let config_path = match config_path {
+        Some(path) => path.to_path_buf(),
+        None => Config::default_path()?,
+    };
+    let config = Config::load(&config_path)
+        .with_context(|| format!("Failed to load {}", config_path.display()))?;
+
+    let tests: Vec<_> = config
+        .tests
+        .iter()
+        .filter(|test| filter.is_none_or(|filter| test.name.contains(filter)))
+        .collect();
+    if tests.is_empty() {
+        println!(
+            "  {} No tests to run (add a {} section to {})",
+            "ℹ".bright_blue(),
+            "tests".bright_white(),
+            config_path.display()
+        );
+        return Ok(());
+    }
+
+    println!();
+    let mut failed = 0;
+    for test in &tests {
+        match run_behavior_test(&config, &config_path, test) {
+            Ok(outcome) if outcome.passed() => {
+                println!("  {} {}", "✓".bright_green().bold(), test.name);
+            }
+            Ok(outcome) => {
+                failed += 1;
+                println!("  {} {}", "✗".bright_red(), test.name.bright_white().bold());
+                println!(
+                    "      {} {}",
+                    "expected:".dimmed(),
+                    format_events(&outcome.expected)
+                );
+                println!(
+                    "      {}   {}",
+                    "actual:".dimmed(),
+                    format_events(&outcome.actual)
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                println!(
+                    "  {} {}: {}",
+                    "✗".bright_red(),
+                    test.name.bright_white().bold(),
+                    e
+                );
+            }
+        }
+    }
+    println!();
+
+    if failed > 0 {
+        bail!("{} of {} tests failed", failed, tests.len());
+    }
+    println!(
+        "  {} All {} tests passed",
+        "✓".bright_green().bold(),
+        tests.len()
+    );
+    Ok(())
+}
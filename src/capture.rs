@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::Select;
+use evdev::{Device, EventType};
+use keymux::config::Config;
+use keymux::keyboard_id::{find_all_keyboards, LogicalKeyboard};
+use keymux::keycode::KeyCode;
+use std::collections::HashMap;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// evdev MSC_SCAN, the scancode sent just before a key event
+const MSC_SCAN: u16 = 0x04;
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Grab a keyboard for `seconds` and print the config name of every key pressed on it
+pub fn run_capture(keyboard: Option<&str>, seconds: u64) -> Result<()> {
+    let mut keyboards: Vec<LogicalKeyboard> = find_all_keyboards()
+        .into_values()
+        .filter(|kb| {
+            keyboard.is_none_or(|pattern| {
+                kb.id.to_string().contains(pattern) || kb.name.contains(pattern)
+            })
+        })
+        .collect();
+    keyboards.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let chosen = match keyboards.len() {
+        0 => match keyboard {
+            Some(pattern) => anyhow::bail!("No keyboard matches \"{}\"", pattern),
+            None => anyhow::bail!("No keyboards found (are you in the 'input' group?)"),
+        },
+        1 => keyboards.remove(0),
+        _ => {
+            let items: Vec<String> = keyboards
+                .iter()
+                .map(|kb| format!("{}  ({})", kb.name, kb.id))
+                .collect();
+            let index = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Keyboard to capture")
+                .items(&items)
+                .default(0)
+                .interact()?;
+            keyboards.swap_remove(index)
+        }
+    };
+
+    let positions = key_positions(&chosen.id.to_string());
+    let name = chosen.name;
+    let mut devices: Vec<(PathBuf, Device)> = chosen.devices;
+    for (path, device) in &mut devices {
+        if let Err(e) = device.grab() {
+            if e.raw_os_error() == Some(libc::EBUSY) {
+                anyhow::bail!(
+                    "{} is grabbed by another program, most likely the keymux daemon; \
+                     run `keymux disable \"{}\"` first and enable it again afterwards",
+                    name,
+                    name
+                );
+            }
+            return Err(e).with_context(|| format!("Failed to grab {}", path.display()));
+        }
+        set_nonblocking(device);
+    }
+
+    println!();
+    println!(
+        "{}",
+        format!("═══ Capturing {} ═══", name).bright_cyan().bold()
+    );
+    println!(
+        "  {}",
+        format!(
+            "Press keys on it for the next {} seconds; they won't reach other programs",
+            seconds
+        )
+        .dimmed()
+    );
+    println!();
+
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    let mut scancodes: Vec<Option<u32>> = vec![None; devices.len()];
+    while Instant::now() < deadline {
+        for ((_, device), last_scancode) in devices.iter_mut().zip(&mut scancodes) {
+            let Ok(events) = device.fetch_events() else {
+                continue;
+            };
+            for ev in events {
+                if ev.event_type() == EventType::MISC && ev.code() == MSC_SCAN {
+                    *last_scancode = Some(ev.value() as u32);
+                    continue;
+                }
+                if ev.event_type() != EventType::KEY {
+                    continue;
+                }
+                let scancode = last_scancode.take();
+                // Presses only: releases and autorepeat add nothing when looking up names
+                if ev.value() != 1 {
+                    continue;
+                }
+                match KeyCode::from_evdev_code(ev.code()) {
+                    Some(key) => print_key(key, positions.get(&key), scancode),
+                    None => print_unknown(scancode),
+                }
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    for (_, device) in &mut devices {
+        let _ = device.ungrab();
+    }
+    println!();
+    Ok(())
+}
+
+/// Where the keyboard's `hardware_layout` puts the keys it sends, if the config declares one
+fn key_positions(keyboard_id: &str) -> HashMap<KeyCode, KeyCode> {
+    Config::default_path()
+        .and_then(|path| Config::load_with_state(&path))
+        .map(|config| config.for_keyboard(keyboard_id).key_positions())
+        .unwrap_or_default()
+}
+
+/// `KC_VOLU   115  KEY_VOLUMEUP  scancode 0xc00e9`
+fn print_key(key: KeyCode, position: Option<&KeyCode>, scancode: Option<u32>) {
+    let evdev_name = if matches!(key, KeyCode::KC_RAW(_)) {
+        "no name, use it as written"
+    } else {
+        key.evdev_name()
+    };
+    let mut details = scancode.map_or(String::new(), |scancode| {
+        format!("  scancode {:#x}", scancode)
+    });
+    if let Some(position) = position {
+        details.push_str(&format!("  at {} on QWERTY", position));
+    }
+    println!(
+        "  {:<10} {:>5}  {:<22}{}",
+        key.to_string().bright_white().bold(),
+        key.code(),
+        evdev_name.dimmed(),
+        details.dimmed()
+    );
+}
+
+/// A key the kernel has no code for; only its scancode tells it apart
+fn print_unknown(scancode: Option<u32>) {
+    match scancode {
+        Some(scancode) => println!(
+            "  {:<10} {:>5}  {}",
+            "KEY_UNKNOWN".bright_yellow().bold(),
+            format!("{:#x}", scancode),
+            format!(
+                "give it a name with scancodes: {{ {:#x}: KC_F20 }}",
+                scancode
+            )
+            .dimmed()
+        ),
+        None => println!(
+            "  {:<10} {}",
+            "KEY_UNKNOWN".bright_yellow().bold(),
+            "no scancode reported, this key can't be remapped".dimmed()
+        ),
+    }
+}
+
+fn set_nonblocking(device: &Device) {
+    let fd = device.as_raw_fd();
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL, 0);
+        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+    }
+}
@@ -80,6 +80,18 @@ pub fn get_subcommands() -> Vec<(String, String)> {
 
 #[derive(Subcommand)]
 pub enum GamemodeAction {
+    /// Turn game mode on
+    On,
+    /// Turn game mode off
+    Off,
+    /// Flip the current game mode state
+    Toggle,
+    /// Show whether game mode is active
+    Status,
+    /// Drop overrides and let the focused window decide again
+    Auto,
+    /// Use a game_mode overlay from the config ("auto" = let the focused window pick)
+    Overlay { name: String },
     /// Control game mode for currently focused window
     Window {
         #[command(subcommand)]
@@ -103,6 +115,82 @@ pub enum WindowGamemodeAction {
     List,
 }
 
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Add a generated block to your config
+    Preset {
+        #[command(subcommand)]
+        preset: PresetAction,
+    },
+    /// Print the config a keyboard actually runs with: the active profile and the
+    /// keyboard's overrides merged into the global config
+    Effective {
+        /// Keyboard pattern (ID or name) matching a single keyboard
+        keyboard: String,
+
+        /// Print JSON instead of RON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PresetAction {
+    /// Home-row mods: hold A S D F / J K L ; for modifiers
+    Hrm {
+        /// Modifier order from pinky to index, mirrored on the right hand (gacs, scag, ...)
+        #[arg(long, default_value = "gacs")]
+        mods: String,
+
+        /// Letters the home row types: qwerty, or colemak when keymux remaps the layout
+        #[arg(long, default_value = "qwerty")]
+        layout: String,
+
+        /// Path to config file (default: ~/.config/keymux/config.ron)
+        #[arg(short = 'f', long = "file", aliases = ["config", "c"])]
+        config: Option<std::path::PathBuf>,
+
+        /// Print the result instead of writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Layer typing Colemak, Colemak-DH, Dvorak or Workman on a QWERTY system layout
+    Layout {
+        /// colemak, colemak-dh, colemak-dh-matrix, dvorak or workman
+        name: String,
+
+        /// Base-layer key that toggles the layer (e.g. KC_SCRL)
+        #[arg(long)]
+        toggle: Option<String>,
+
+        /// Path to config file (default: ~/.config/keymux/config.ron)
+        #[arg(short = 'f', long = "file", aliases = ["config", "c"])]
+        config: Option<std::path::PathBuf>,
+
+        /// Print the result instead of writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Numpad layer: 789 on U I O, 456 on J K L, 123 on M , . and 0 on /
+    Numpad {
+        /// Base-layer key that holds the layer on while pressed (e.g. KC_RALT)
+        #[arg(long)]
+        hold: Option<String>,
+
+        /// Base-layer key that toggles the layer (e.g. KC_SCRL)
+        #[arg(long)]
+        toggle: Option<String>,
+
+        /// Path to config file (default: ~/.config/keymux/config.ron)
+        #[arg(short = 'f', long = "file", aliases = ["config", "c"])]
+        config: Option<std::path::PathBuf>,
+
+        /// Print the result instead of writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 #[derive(Parser)]
 #[command(name = "keymux")]
 #[command(about = "QMK-inspired keyboard middleware for Linux", long_about = None)]
@@ -117,13 +205,26 @@ pub enum Commands {
     /// Run the keyboard middleware daemon
     #[command(hide = true)]
     Daemon {
-        /// Path to config file (default: ~/.config/keymux/config.ron)
+        /// Config file of every user, "~" being each user's home
+        /// (default: $KEYMUX_CONFIG or ~/.config/keymux/config.ron)
         #[arg(short, long)]
         config: Option<std::path::PathBuf>,
 
+        /// IPC socket to listen on (default: $KEYMUX_SOCKET or /run/keymux.sock)
+        #[arg(long)]
+        socket: Option<std::path::PathBuf>,
+
+        /// Directory for state kept across restarts (default: $KEYMUX_STATE_DIR or /run/keymux)
+        #[arg(long)]
+        state_dir: Option<std::path::PathBuf>,
+
         /// User to run as (for root execution, uses that user's config)
         #[arg(short, long)]
         user: Option<String>,
+
+        /// Check keyboards, configs, uinput and the IPC socket, then exit without grabbing anything
+        #[arg(long)]
+        check: bool,
     },
 
     /// Run the niri window watcher daemon
@@ -147,7 +248,11 @@ pub enum Commands {
     BspwmDaemon,
 
     /// List all detected keyboards
-    List,
+    List {
+        /// Show this user's enabled keyboards instead of the caller's (root only)
+        #[arg(short, long)]
+        user: Option<String>,
+    },
 
     /// Toggle keyboard enable/disable state (opens selection menu)
     Toggle {
@@ -157,6 +262,10 @@ pub enum Commands {
         /// Open multi-select menu to choose keyboards
         #[arg(long, short)]
         multi: bool,
+
+        /// Toggle this user's keyboards instead of the caller's (root only)
+        #[arg(short, long)]
+        user: Option<String>,
     },
 
     /// Enable specific keyboards
@@ -167,6 +276,14 @@ pub enum Commands {
         /// Open multi-select menu to choose keyboards
         #[arg(long, short)]
         multi: bool,
+
+        /// Update this user's config instead of the caller's (for root-run hooks)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Only print errors (for scripts and udev hooks)
+        #[arg(short, long)]
+        quiet: bool,
     },
 
     /// Disable specific keyboards
@@ -177,12 +294,123 @@ pub enum Commands {
         /// Open multi-select menu to choose keyboards
         #[arg(long, short)]
         multi: bool,
+
+        /// Update this user's config instead of the caller's (for root-run hooks)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Only print errors (for scripts and udev hooks)
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Let a keyboard quarantined for crashing its processors run again
+    Unquarantine {
+        /// Keyboard pattern (ID, name, or "*" for all)
+        keyboard: String,
     },
 
     /// Control game mode settings
+    #[command(visible_alias = "game-mode")]
     Gamemode {
         #[command(subcommand)]
         action: GamemodeAction,
+
+        /// Only affect keyboards matching this pattern (ID or name)
+        #[arg(short, long, global = true)]
+        keyboard: Option<String>,
+    },
+
+    /// Type text through your virtual keyboard
+    Type {
+        /// Text to type
+        text: String,
+
+        /// Press Enter after the text
+        #[arg(long)]
+        enter: bool,
+
+        /// Type through this user's keyboards (default: the calling user)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Type through the keyboard matching this pattern (ID or name)
+        #[arg(short, long)]
+        keyboard: Option<String>,
+    },
+
+    /// Inject key events into a keyboard as if they were typed on it
+    Inject {
+        /// Keys to inject: KC_A taps, +KC_A presses, -KC_A releases
+        #[arg(required = true, allow_hyphen_values = true)]
+        keys: Vec<String>,
+
+        /// Inject into this user's keyboards (default: the calling user)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Inject into the keyboard matching this pattern (ID or name)
+        #[arg(short, long)]
+        keyboard: Option<String>,
+    },
+
+    /// Show or switch the active profile
+    Profile {
+        /// Profile to switch to ("default" for none, "auto" to follow the schedule)
+        name: Option<String>,
+
+        /// Switch this user's profile (default: the calling user)
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+
+    /// Print the modifiers locked on with TapLock keys, one per line (for status bars)
+    Locked {
+        /// Show this user's locked modifiers (default: the calling user)
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+
+    /// Show active layers and recent layer changes, then follow new ones
+    Watch {
+        /// Watch this user's keyboards (default: the calling user)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Only keyboards matching this pattern (ID or name)
+        #[arg(short, long)]
+        keyboard: Option<String>,
+    },
+
+    /// Show why recent MT keys resolved to tap or hold (needs mt_config.trace_decisions)
+    MtTrace {
+        /// Show this user's keyboards (default: the calling user)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Only keyboards matching this pattern (ID or name)
+        #[arg(short, long)]
+        keyboard: Option<String>,
+
+        /// Keep printing new decisions as they happen
+        #[arg(short, long)]
+        follow: bool,
+    },
+
+    /// Compare a config against the running one on live input, logging where they differ
+    Shadow {
+        /// Config to compare (leave out to stop comparing)
+        file: Option<std::path::PathBuf>,
+
+        /// Compare on this user's keyboards (default: the calling user)
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+
+    /// Edit your config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
     },
 
     /// Reload configuration from disk
@@ -195,6 +423,53 @@ pub enum Commands {
         config: Option<std::path::PathBuf>,
     },
 
+    /// Run the behavior tests in your config's `tests` section
+    Test {
+        /// Only run tests whose name contains this
+        filter: Option<String>,
+
+        /// Path to config file (default: ~/.config/keymux/config.ron)
+        #[arg(short = 'f', long = "file", aliases = ["config", "c"])]
+        config: Option<std::path::PathBuf>,
+    },
+
+    /// List the key names a config can use, with their evdev codes and aliases
+    Keycodes {
+        /// Only keys whose name, alias or evdev name contains this (e.g. vol)
+        #[arg(short, long)]
+        search: Option<String>,
+    },
+
+    /// Grab a keyboard for a few seconds and print the name of each key pressed on it
+    Capture {
+        /// Keyboard to capture (ID or name; asks when several match)
+        #[arg(short, long)]
+        keyboard: Option<String>,
+
+        /// How long to capture for
+        #[arg(short, long, default_value_t = 10)]
+        seconds: u64,
+    },
+
+    /// Measure the latency keymux adds: tap a key on a virtual keyboard and time it
+    /// through a processor running your config against the kernel alone
+    LatencyTest {
+        /// Taps per run
+        #[arg(short, long, default_value_t = 200, value_parser = clap::value_parser!(u32).range(1..))]
+        samples: u32,
+
+        /// Path to config file (default: ~/.config/keymux/config.ron)
+        #[arg(short = 'f', long = "file", aliases = ["config", "c"])]
+        config: Option<std::path::PathBuf>,
+    },
+
+    /// Show the daemon's uptime and memory use
+    Status {
+        /// Also show each processor thread: liveness, heartbeat and queued commands
+        #[arg(long)]
+        internals: bool,
+    },
+
     /// Show debugging information
     Debug,
 
@@ -261,6 +536,11 @@ pub fn print_help() {
         "validate".bright_green().bold(),
         "Validate configuration file".dimmed()
     );
+    println!(
+        "  {}    {}",
+        "test".bright_green().bold(),
+        "Run the behavior tests in your config".dimmed()
+    );
     println!(
         "  {}    {}",
         "help".bright_green().bold(),
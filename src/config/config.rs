@@ -1,7 +1,8 @@
+use crate::keyboard_id::KeyboardId;
 use crate::keycode::KeyCode;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Enable or Disable action for a keyboard entry
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -213,6 +214,47 @@ pub enum KeyAction {
     /// Run arbitrary shell command
     /// Example: CMD("/usr/bin/notify-send 'Hello'")
     CMD(String),
+    /// Run a Rhai script from ~/.config/keymux/scripts/<name>.rhai
+    /// Requires the `scripting` build feature
+    /// Example: Script("vim_escape")
+    Script(String),
+    /// Autofire - tap a key repeatedly while held
+    /// Format: Turbo(key_action, rate_hz)
+    /// Only fires in game mode unless game_mode.turbo_outside_game_mode is set
+    /// Example: Turbo(KC_X, 20) - tap X twenty times per second
+    Turbo(Box<Self>, u32),
+    /// Switch the system keyboard layout: a name from keyboard_layouts, "next" or "prev"
+    /// Example: LayoutSwitch("de")
+    LayoutSwitch(String),
+    /// Enable or disable the user's keyboards matching a pattern (ID or name), like
+    /// `keymux toggle` does; a disabled keyboard is left alone and types unremapped
+    /// Example: KeyboardToggle("AT Translated Set 2")
+    KeyboardToggle(String),
+    /// Switch the profile of all the user's keyboards, like `keymux profile`
+    /// ("default" = no profile, "auto" = follow the schedule again)
+    /// Example: ProfileSwitch("gaming")
+    ProfileSwitch(String),
+    /// Tap-lock - acts like the wrapped modifier, but a double-tap locks the modifier
+    /// on (Caps Lock style) and the next tap releases it
+    /// Format: TapLock(Key(modifier)) or TapLock(OSM(Key(modifier)))
+    /// Example: TapLock(OSM(KC_LSFT)) - one-shot shift, double-tap for shift lock
+    TapLock(Box<Self>),
+    /// Fixed tapping term - the wrapped MT always uses tapping_term_ms, and adaptive
+    /// timing neither applies to it nor learns from it
+    /// Example: FixedTerm(MT(KC_SPC, KC_LSFT)) - a space-bar MT that never drifts
+    FixedTerm(Box<Self>),
+    /// Conditional - decide between two actions when the key goes down
+    /// Format: If(condition, then_action, else_action)
+    /// Example: If(layer_active("nav"), KC_HOME, KC_H) - Home while nav is on, H otherwise
+    If(Condition, Box<Self>, Box<Self>),
+    /// Only in game mode - the wrapped action while game mode is on; otherwise the key
+    /// falls through to the layer below, as if it were Transparent
+    /// Example: OnlyGameMode(KC_SPC) on a layer key that should be Space in games
+    OnlyGameMode(Box<Self>),
+    /// Only while typing - the wrapped action while game mode is off; in game mode the
+    /// key falls through to the layer below, as if it were Transparent
+    /// Example: OnlyTyping(MT(KC_F, KC_LSFT)) - home row mod that steps aside in games
+    OnlyTyping(Box<Self>),
     /// Transparent - fall through to lower layer
     /// Like QMK's underscore key - ignores this position on current layer
     /// and looks it up on the next layer down (or base)
@@ -220,11 +262,68 @@ pub enum KeyAction {
 }
 
 impl KeyAction {
+    /// Collect every key this action can emit when bound to `keycode`
+    pub fn collect_output_keys(&self, keycode: KeyCode, out: &mut HashSet<KeyCode>) {
+        match self {
+            Self::Key(key) => {
+                out.insert(*key);
+            }
+            Self::MT(tap, hold) | Self::DT(tap, hold) | Self::If(_, tap, hold) => {
+                tap.collect_output_keys(keycode, out);
+                hold.collect_output_keys(keycode, out);
+            }
+            Self::SOCD(this, opposing) => {
+                this.collect_output_keys(keycode, out);
+                for action in opposing {
+                    action.collect_output_keys(keycode, out);
+                }
+            }
+            Self::OSM(action)
+            | Self::Turbo(action, _)
+            | Self::TapLock(action)
+            | Self::FixedTerm(action) => action.collect_output_keys(keycode, out),
+            Self::OnlyGameMode(action) | Self::OnlyTyping(action) => {
+                action.collect_output_keys(keycode, out);
+                out.insert(keycode);
+            }
+            Self::Transparent => {
+                out.insert(keycode);
+            }
+            Self::TO(_)
+            | Self::TG(_)
+            | Self::MO(_)
+            | Self::CMD(_)
+            | Self::Script(_)
+            | Self::LayoutSwitch(_)
+            | Self::KeyboardToggle(_)
+            | Self::ProfileSwitch(_) => {}
+        }
+    }
+
     /// Check if this action is Transparent
     pub const fn is_transparent(&self) -> bool {
         matches!(self, Self::Transparent)
     }
 
+    /// The action with its `OnlyGameMode`/`OnlyTyping` wrappers taken off
+    pub fn without_mode(&self) -> &Self {
+        match self {
+            Self::OnlyGameMode(inner) | Self::OnlyTyping(inner) => inner.without_mode(),
+            other => other,
+        }
+    }
+
+    /// The action a key runs with game mode on or off, `None` when it is wrapped for
+    /// the other mode
+    pub fn in_mode(&self, game_mode: bool) -> Option<&Self> {
+        match self {
+            Self::OnlyGameMode(inner) if game_mode => inner.in_mode(game_mode),
+            Self::OnlyTyping(inner) if !game_mode => inner.in_mode(game_mode),
+            Self::OnlyGameMode(_) | Self::OnlyTyping(_) => None,
+            other => Some(other),
+        }
+    }
+
     /// Check if this action is a layer switch (To, Tg, Mo)
     pub const fn is_layer_action(&self) -> bool {
         matches!(self, Self::TO(_) | Self::TG(_) | Self::MO(_))
@@ -247,6 +346,25 @@ impl KeyAction {
     }
 }
 
+/// What an `If` action checks when its key is pressed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// Game mode is on
+    GameModeActive,
+    /// The layer is on, held or toggled on this keyboard or shared from another
+    LayerActive(Layer),
+    /// This exact modifier is down (KC_LSFT and KC_RSFT are different), held by a key,
+    /// an MT hold or a TapLock
+    ModifierHeld(#[serde(deserialize_with = "deserialize_key")] KeyCode),
+    /// The focused window's app_id matches the regex
+    AppMatches(String),
+    /// Caps Lock is on
+    CapsLock,
+    /// Num Lock is on
+    NumLock,
+}
+
 /// Game mode detection methods
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DetectionMethod {
@@ -260,6 +378,278 @@ pub enum DetectionMethod {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LayerConfig {
     pub remaps: HashMap<KeyCode, KeyAction>,
+    /// Modifiers held around every plain key this layer binds, e.g. `[KC_LSFT]` for a
+    /// symbols layer of shifted numbers
+    #[serde(default, deserialize_with = "deserialize_key_list")]
+    pub with_mods: Vec<KeyCode>,
+    /// What keys this layer doesn't bind do while it's active (default: pass)
+    #[serde(default)]
+    pub unmapped: Unmapped,
+}
+
+/// What a layer does with keys it has no binding for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Unmapped {
+    /// Fall through to the layers below and the base remaps
+    #[default]
+    Pass,
+    /// Send nothing, making the layer a strict overlay; `Transparent` bindings still
+    /// fall through
+    Block,
+}
+
+/// Activate a layer while the focused window matches
+/// Both patterns are regexes; a rule with neither pattern never matches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowLayerRule {
+    /// Pattern matched against the window's app_id (e.g. "^(kitty|foot)$")
+    #[serde(default)]
+    pub app_id: Option<String>,
+    /// Pattern matched against the window title (e.g. "(?i)sheets|calc")
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Layer to activate while the window is focused
+    pub layer: Layer,
+    /// `app_id` and `title` compiled, filled in when the config is parsed (an invalid
+    /// pattern never matches; validation reports it)
+    #[serde(skip)]
+    compiled: CompiledPatterns,
+}
+
+/// A compiled rule pattern (None = no pattern)
+type CompiledPattern = Option<Result<regex::Regex, regex::Error>>;
+
+/// Regexes compiled once per config and copied along with it
+#[derive(Debug, Clone, Default)]
+struct CompiledPatterns(std::sync::OnceLock<[CompiledPattern; 2]>);
+
+/// Compiled patterns are derived from the rule, so they never make two rules differ
+impl PartialEq for CompiledPatterns {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for CompiledPatterns {}
+
+impl WindowLayerRule {
+    fn patterns(&self) -> &[CompiledPattern; 2] {
+        self.compiled.0.get_or_init(|| {
+            [&self.app_id, &self.title].map(|pattern| pattern.as_deref().map(regex::Regex::new))
+        })
+    }
+
+    /// Whether every pattern in this rule matches the window
+    pub fn matches(&self, app_id: Option<&str>, title: Option<&str>) -> bool {
+        let check = |pattern: &CompiledPattern, value: Option<&str>| match pattern {
+            None => true,
+            Some(Ok(re)) => value.is_some_and(|v| re.is_match(v)),
+            Some(Err(_)) => false,
+        };
+        let [app_id_re, title_re] = self.patterns();
+        (self.app_id.is_some() || self.title.is_some())
+            && check(app_id_re, app_id)
+            && check(title_re, title)
+    }
+
+    /// Problems compiling the rule's patterns
+    pub fn pattern_errors(&self) -> Vec<String> {
+        self.patterns()
+            .iter()
+            .zip([&self.app_id, &self.title])
+            .filter_map(|(compiled, pattern)| match (compiled, pattern) {
+                (Some(Err(e)), Some(pattern)) => Some(format!("{:?}: {}", pattern, e)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Run an action when keys are typed in order, e.g. Q Q within half a second
+///
+/// Unlike a combo the keys aren't pressed together, and they still type as usual.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequenceRule {
+    /// Physical keys, in the order they have to be pressed
+    #[serde(deserialize_with = "deserialize_key_list")]
+    pub keys: Vec<KeyCode>,
+    /// Action tapped once the last key is pressed
+    pub action: KeyAction,
+    /// Time allowed from the first key to the last
+    #[serde(default = "default_sequence_timeout")]
+    pub timeout_ms: u32,
+}
+
+const fn default_sequence_timeout() -> u32 {
+    500
+}
+
+/// Behavior check run by `keymux test`: physical key events in, virtual key events out
+///
+/// Events are written `+KC_F` (press), `-KC_F` (release) or `KC_F` (tap), separated by
+/// spaces; the input can pause with e.g. `250ms`. Time only passes during pauses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BehaviorTest {
+    pub name: String,
+    /// Physical key events and pauses, e.g. "+KC_F 250ms KC_J -KC_F"
+    pub input: String,
+    /// Events the virtual keyboard should send, e.g. "+KC_LSFT KC_J -KC_LSFT"
+    pub expect: String,
+    /// Run the input with game mode on
+    #[serde(default)]
+    pub game_mode: bool,
+}
+
+/// Text expansion: an abbreviation typed before a trigger key is replaced by its text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnippetsConfig {
+    /// Abbreviation -> expansion, e.g. "btw": "by the way"
+    #[serde(default)]
+    pub expansions: HashMap<String, String>,
+    /// Keys that end an abbreviation (default: space and tab)
+    #[serde(
+        default = "default_snippet_triggers",
+        deserialize_with = "deserialize_key_list"
+    )]
+    pub triggers: Vec<KeyCode>,
+    /// Only expand in windows whose app_id matches one of these regexes (empty = everywhere)
+    #[serde(default)]
+    pub enabled_apps: Vec<String>,
+    /// Never expand in windows whose app_id matches one of these regexes
+    #[serde(default)]
+    pub disabled_apps: Vec<String>,
+}
+
+impl Default for SnippetsConfig {
+    fn default() -> Self {
+        Self {
+            expansions: HashMap::new(),
+            triggers: default_snippet_triggers(),
+            enabled_apps: Vec::new(),
+            disabled_apps: Vec::new(),
+        }
+    }
+}
+
+fn default_snippet_triggers() -> Vec<KeyCode> {
+    vec![KeyCode::KC_SPC, KeyCode::KC_TAB]
+}
+
+/// Pauses the virtual keyboard leaves between the events of one output
+///
+/// 0 sends the events in a single write, each in its own report, so nothing waits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputPacing {
+    /// Hold (ms) between the press and release of a tapped key, e.g. an MT tap
+    #[serde(default = "default_tap_hold")]
+    pub tap_hold_ms: u32,
+    /// Pause (ms) between the events of a multi-key output, e.g. a key with modifiers
+    #[serde(default = "default_event_gap")]
+    pub event_gap_ms: u32,
+}
+
+impl Default for OutputPacing {
+    fn default() -> Self {
+        Self {
+            tap_hold_ms: default_tap_hold(),
+            event_gap_ms: default_event_gap(),
+        }
+    }
+}
+
+const fn default_tap_hold() -> u32 {
+    5
+}
+
+const fn default_event_gap() -> u32 {
+    2
+}
+
+impl SnippetsConfig {
+    /// Whether snippets expand in a window (None = no window information)
+    pub fn enabled_for(&self, app_id: Option<&str>) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| match regex::Regex::new(pattern) {
+                    Ok(re) => app_id.is_some_and(|id| re.is_match(id)),
+                    Err(e) => {
+                        tracing::warn!("Invalid snippets app pattern {:?}: {}", pattern, e);
+                        false
+                    }
+                })
+        };
+        (self.enabled_apps.is_empty() || matches(&self.enabled_apps))
+            && !matches(&self.disabled_apps)
+    }
+}
+
+/// When udev events make the daemon rescan for keyboards
+///
+/// The daemon is shared, so it combines every loaded user's settings: the longest
+/// debounce wins and any user's ignored paths are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotplugConfig {
+    /// Quiet time after the last event before keyboards are resynced (default: 300)
+    #[serde(default = "default_hotplug_debounce")]
+    pub debounce_ms: u32,
+    /// udev actions that trigger a resync (default: ["add", "remove"])
+    #[serde(default = "default_hotplug_actions")]
+    pub actions: Vec<String>,
+    /// Device paths whose events are ignored (substring of the udev DEVPATH), e.g. a
+    /// hub that floods events: "/usb3/3-2/"
+    #[serde(default)]
+    pub ignore_paths: Vec<String>,
+}
+
+impl Default for HotplugConfig {
+    fn default() -> Self {
+        Self {
+            debounce_ms: default_hotplug_debounce(),
+            actions: default_hotplug_actions(),
+            ignore_paths: Vec::new(),
+        }
+    }
+}
+
+const fn default_hotplug_debounce() -> u32 {
+    300
+}
+
+fn default_hotplug_actions() -> Vec<String> {
+    vec!["add".to_string(), "remove".to_string()]
+}
+
+impl HotplugConfig {
+    /// Whether a `udevadm monitor` line should (re)arm the resync debounce, e.g.
+    /// "UDEV  [1234.5678] add      /devices/.../input/input27/event5 (input)"
+    pub fn triggers(&self, event: &str) -> bool {
+        let mut fields = event.split_whitespace().skip(2);
+        let (Some(action), Some(devpath)) = (fields.next(), fields.next()) else {
+            return false;
+        };
+        self.actions.iter().any(|a| a == action)
+            && !self
+                .ignore_paths
+                .iter()
+                .any(|p| devpath.contains(p.as_str()))
+    }
+
+    /// Combine settings from several configs (see the type docs)
+    pub fn merge(&mut self, other: &Self) {
+        self.debounce_ms = self.debounce_ms.max(other.debounce_ms);
+        for action in &other.actions {
+            if !self.actions.contains(action) {
+                self.actions.push(action.clone());
+            }
+        }
+        for path in &other.ignore_paths {
+            if !self.ignore_paths.contains(path) {
+                self.ignore_paths.push(path.clone());
+            }
+        }
+    }
 }
 
 /// Game mode configuration
@@ -267,9 +657,172 @@ pub struct LayerConfig {
 pub struct GameMode {
     #[serde(default)]
     pub remaps: HashMap<KeyCode, KeyAction>,
+    /// Let Turbo keys autofire outside game mode too (default: false)
+    #[serde(default)]
+    pub turbo_outside_game_mode: bool,
+    /// Enable game mode whenever the focused window is fullscreen, whatever its app_id
+    /// (niri only, default: false)
+    #[serde(default)]
+    pub fullscreen_enables_game_mode: bool,
+    /// Named remap sets laid over `remaps` for different kinds of games, e.g. "fps" or
+    /// "racing"; picked by the focused window or `keymux gamemode overlay <name>`
+    #[serde(default)]
+    pub overlays: HashMap<String, GameModeOverlay>,
+    /// SOCD presets, expanded into `remaps` when the config is loaded (keys already
+    /// bound there keep their binding)
+    /// Example: socd: [wasd_neutral, axis(KC_LEFT, KC_RIGHT, first_input)]
+    #[serde(default)]
+    pub socd: Vec<SocdPreset>,
+}
+
+/// Ready-made SOCD bindings, so opposite keys don't have to be written as SOCD pairs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SocdPreset {
+    /// W/S and A/D, the key pressed last wins
+    WasdLastInput,
+    /// W/S and A/D, opposite keys held together cancel out
+    WasdNeutral,
+    /// A/D only, the key pressed last wins; W and S stay plain keys
+    NullbindAdOnly,
+    /// Two opposite keys with their own resolution
+    Axis(
+        #[serde(deserialize_with = "deserialize_key")] KeyCode,
+        #[serde(deserialize_with = "deserialize_key")] KeyCode,
+        SocdMode,
+    ),
+}
+
+/// Which of two opposite keys gets through while both are held
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SocdMode {
+    /// The key pressed last (what plain SOCD pairs do)
+    #[default]
+    LastInput,
+    /// The key pressed first, until it is released
+    FirstInput,
+    /// Neither
+    Neutral,
+}
+
+impl SocdPreset {
+    /// The axes the preset binds: two opposite keys and how they resolve
+    pub fn axes(&self) -> Vec<(KeyCode, KeyCode, SocdMode)> {
+        let wasd = |mode| {
+            vec![
+                (KeyCode::KC_W, KeyCode::KC_S, mode),
+                (KeyCode::KC_A, KeyCode::KC_D, mode),
+            ]
+        };
+        match self {
+            Self::WasdLastInput => wasd(SocdMode::LastInput),
+            Self::WasdNeutral => wasd(SocdMode::Neutral),
+            Self::NullbindAdOnly => vec![(KeyCode::KC_A, KeyCode::KC_D, SocdMode::LastInput)],
+            Self::Axis(a, b, mode) => vec![(*a, *b, *mode)],
+        }
+    }
+}
+
+/// Bind both keys of every preset axis as an SOCD pair, unless they're bound already
+fn expand_socd_presets(presets: &[SocdPreset], remaps: &mut HashMap<KeyCode, KeyAction>) {
+    for (a, b, _) in presets.iter().flat_map(SocdPreset::axes) {
+        for (this, opposing) in [(a, b), (b, a)] {
+            remaps.entry(this).or_insert_with(|| {
+                KeyAction::SOCD(
+                    Box::new(KeyAction::Key(this)),
+                    vec![Box::new(KeyAction::Key(opposing))],
+                )
+            });
+        }
+    }
+}
+
+/// Game mode remaps for one kind of game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct GameModeOverlay {
+    /// Laid over game_mode.remaps while the overlay is selected
+    #[serde(default)]
+    pub remaps: HashMap<KeyCode, KeyAction>,
+    /// Regexes on the focused window's app_id that select this overlay; such a window
+    /// turns game mode on even if it isn't detected as a game
+    #[serde(default)]
+    pub app_ids: Vec<String>,
+    /// SOCD presets for this overlay, on top of game_mode.socd
+    #[serde(default)]
+    pub socd: Vec<SocdPreset>,
+}
+
+impl GameModeOverlay {
+    /// Whether one of the app_id patterns matches the window
+    pub fn matches(&self, app_id: Option<&str>) -> bool {
+        let Some(app_id) = app_id else {
+            return false;
+        };
+        self.app_ids
+            .iter()
+            .any(|pattern| match regex::Regex::new(pattern) {
+                Ok(re) => re.is_match(app_id),
+                Err(e) => {
+                    tracing::warn!("Invalid game mode overlay pattern {:?}: {}", pattern, e);
+                    false
+                }
+            })
+    }
 }
 
 impl GameMode {
+    /// The overlay a window selects: the first, by name, whose app_ids match it
+    pub fn overlay_for_app(&self, app_id: Option<&str>) -> Option<&str> {
+        let mut names: Vec<&String> = self.overlays.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .find(|name| self.overlays[*name].matches(app_id))
+            .map(String::as_str)
+    }
+
+    /// Game mode remaps with an overlay laid over them (an unknown name adds nothing)
+    pub fn remaps_with_overlay(&self, overlay: Option<&str>) -> HashMap<KeyCode, KeyAction> {
+        let mut remaps = self.remaps.clone();
+        if let Some(overlay) = overlay.and_then(|name| self.overlays.get(name)) {
+            remaps.extend(overlay.remaps.clone());
+        }
+        remaps
+    }
+
+    /// Turn the SOCD presets of game mode and its overlays into SOCD pairs
+    pub fn expand_socd_presets(&mut self) {
+        expand_socd_presets(&self.socd, &mut self.remaps);
+        for overlay in self.overlays.values_mut() {
+            expand_socd_presets(&overlay.socd, &mut overlay.remaps);
+        }
+    }
+
+    /// How each preset SOCD key resolves, with an overlay's presets over game mode's
+    pub fn socd_modes(&self, overlay: Option<&str>) -> HashMap<KeyCode, SocdMode> {
+        let overlay_presets = overlay
+            .and_then(|name| self.overlays.get(name))
+            .map(|overlay| overlay.socd.as_slice())
+            .unwrap_or_default();
+        let mut modes = HashMap::new();
+        for (a, b, mode) in self
+            .socd
+            .iter()
+            .chain(overlay_presets)
+            .flat_map(SocdPreset::axes)
+        {
+            modes.insert(a, mode);
+            modes.insert(b, mode);
+        }
+        modes
+    }
+
+    /// `remaps` and the remaps of every overlay
+    pub fn all_remaps(&self) -> impl Iterator<Item = &HashMap<KeyCode, KeyAction>> {
+        std::iter::once(&self.remaps).chain(self.overlays.values().map(|overlay| &overlay.remaps))
+    }
+
     #[must_use]
     pub const fn auto_detect_enabled() -> bool {
         true
@@ -301,9 +854,81 @@ pub struct PerKeyboardConfig {
     pub mt_config: Option<MtConfig>,
     pub double_tap_window_ms: Option<u64>,
     pub oneshot_timeout_ms: Option<u64>,
+    pub layer_idle_timeout_ms: Option<u64>,
     pub remaps: Option<HashMap<KeyCode, KeyAction>>,
     pub layers: Option<HashMap<Layer, LayerConfig>>,
     pub game_mode: Option<GameMode>,
+    /// MT roll detection window (ms), applied on top of mt_config
+    #[serde(default, alias = "roll_threshold_ms")]
+    pub roll_detection_window_ms: Option<u32>,
+    /// MT chord detection window (ms), applied on top of mt_config
+    #[serde(default, alias = "chord_threshold_ms")]
+    pub chord_detection_window_ms: Option<u32>,
+    /// MT quick-tap term (ms), applied on top of mt_config
+    #[serde(default)]
+    pub quick_tap_term_ms: Option<u32>,
+    /// Read the keyboard without grabbing it and emit nothing (see `Config::observe_only`)
+    #[serde(default)]
+    pub observe_only: Option<bool>,
+    /// Leave this keyboard's pointer nodes to the OS (see `Config::skip_pointer_nodes`)
+    #[serde(default)]
+    pub skip_pointer_nodes: Option<bool>,
+    /// Chatter filter window (ms) for every key of this keyboard
+    #[serde(default)]
+    pub debounce_ms: Option<u32>,
+    /// Chatter filter windows (ms) for individual keys, merged over the global ones
+    #[serde(default)]
+    pub debounce_keys: Option<HashMap<KeyCode, u32>>,
+    /// Window (ms) for dropping keys this keyboard repeats on a second event node
+    #[serde(default)]
+    pub dedupe_nodes_ms: Option<u32>,
+    /// Random pause range (ms) after each key of typed text (see `Config::typing_jitter_ms`)
+    #[serde(default)]
+    pub typing_jitter_ms: Option<(u32, u32)>,
+    /// Pauses between output events (see `Config::output_pacing`)
+    #[serde(default)]
+    pub output_pacing: Option<OutputPacing>,
+    /// Layout this keyboard's firmware sends, e.g. "dvorak" (see `Config::hardware_layout`)
+    #[serde(default)]
+    pub hardware_layout: Option<String>,
+    /// Keys this keyboard sends from another position, merged over the global ones
+    #[serde(default, deserialize_with = "deserialize_optional_key_map")]
+    pub hardware_swaps: Option<HashMap<KeyCode, KeyCode>>,
+    /// Keys dropped from this keyboard, added to the global ones
+    #[serde(default, deserialize_with = "deserialize_optional_key_list")]
+    pub ignored_keys: Option<Vec<KeyCode>>,
+    /// Keys this keyboard passes through as typed, added to the global ones
+    #[serde(default, deserialize_with = "deserialize_optional_key_list")]
+    pub wake_keys: Option<Vec<KeyCode>>,
+    /// Only send keys that are mapped (see `Config::macro_pad`)
+    #[serde(default)]
+    pub macro_pad: Option<bool>,
+    /// Scancodes of this keyboard's vendor keys, added to the global ones
+    #[serde(default, deserialize_with = "deserialize_optional_scancode_map")]
+    pub scancodes: Option<HashMap<u32, KeyCode>>,
+    /// Command run as the owning user when keymux grabs this keyboard, like
+    /// `hooks.on_keyboard_connect` (only read from per_keyboard_overrides)
+    #[serde(default)]
+    pub on_connect: Option<String>,
+    /// Command run as the owning user when this keyboard is unplugged (after
+    /// `disconnect_grace_ms`), with KEYMUX_KEYBOARD_ID and KEYMUX_KEYBOARD_NAME
+    #[serde(default)]
+    pub on_disconnect: Option<String>,
+}
+
+impl PerKeyboardConfig {
+    /// Apply the individual MT timing overrides to an MT config
+    fn apply_mt_timings(&self, mt_config: &mut MtConfig) {
+        if let Some(window) = self.roll_detection_window_ms {
+            mt_config.roll_detection_window_ms = window;
+        }
+        if let Some(window) = self.chord_detection_window_ms {
+            mt_config.chord_detection_window_ms = window;
+        }
+        if let Some(term) = self.quick_tap_term_ms {
+            mt_config.quick_tap_term_ms = term;
+        }
+    }
 }
 
 /// MT (Mod-Tap) configuration
@@ -355,6 +980,16 @@ pub struct MtConfig {
     #[serde(default = "default_double_tap_window")]
     pub double_tap_window_ms: u32,
 
+    /// Quick-tap term (ms), like QMK's QUICK_TAP_TERM - re-pressing an MT key within this
+    /// long after tapping it always resolves to tap, so holding it repeats the tap key
+    /// (default: 0 = disabled)
+    #[serde(default)]
+    pub quick_tap_term_ms: u32,
+
+    /// Per-key quick-tap terms (ms) overriding quick_tap_term_ms, e.g. { KC_SPC: 0, KC_F: 200 }
+    #[serde(default)]
+    pub quick_tap_term_per_key: HashMap<KeyCode, u32>,
+
     /// Enable cross-hand unwrap - when holding a modifier on one hand,
     /// MT keys on the opposite hand will unwrap to their tap key (default: true)
     /// Example: Hold ; (right hand, becomes Win), press f (left hand MT) → types 'f' not Shift
@@ -366,6 +1001,15 @@ pub struct MtConfig {
     #[serde(default = "default_adaptive_margin", alias = "target_margin_ms")]
     pub adaptive_target_margin_ms: u32,
 
+    /// Taps a key needs before its learned threshold replaces the tapping term (default: 20)
+    #[serde(default = "default_adaptive_min_samples")]
+    pub adaptive_min_samples: u32,
+
+    /// Learned thresholds are only used while the key's tap durations vary by at most
+    /// this standard deviation (ms), so a few odd taps can't swing them (default: 25)
+    #[serde(default = "default_adaptive_max_stddev")]
+    pub adaptive_max_stddev_ms: f32,
+
     /// Pause adaptive learning in game mode (default: true)
     #[serde(default = "default_true")]
     pub pause_learning_in_game_mode: bool,
@@ -383,6 +1027,32 @@ pub struct MtConfig {
     /// If false, holding then releasing without other action does nothing
     #[serde(default = "default_true")]
     pub hold_do_nothing_emits_tap: bool,
+
+    /// Retro tapping, like QMK's RETRO_TAPPING - an MT key held past the tapping term and
+    /// released without any other key pressed meanwhile always taps, even where
+    /// hold_do_nothing_emits_tap wouldn't (predictive scoring leaning to hold) (default: false)
+    #[serde(default)]
+    pub retro_tapping: bool,
+
+    /// Per-key retro tapping overriding retro_tapping, e.g. { KC_SPC: true, KC_A: false }
+    #[serde(default)]
+    pub retro_tapping_per_key: HashMap<KeyCode, bool>,
+
+    /// Chordal hold, like QMK's CHORDAL_HOLD - a pending MT key resolves by the hand of the
+    /// next key instead of the roll/chord windows: opposite hand holds, same hand taps.
+    /// Another MT key on the same hand leaves it pending, so same-hand mods still stack
+    /// (default: false)
+    #[serde(default)]
+    pub chordal_hold: bool,
+
+    /// Per-key chordal hold overriding chordal_hold, e.g. { KC_A: true, KC_SPC: false }
+    #[serde(default)]
+    pub chordal_hold_per_key: HashMap<KeyCode, bool>,
+
+    /// Remember why each of the last MT decisions went to tap or hold, shown by
+    /// `keymux mt-trace` (default: false)
+    #[serde(default)]
+    pub trace_decisions: bool,
 }
 
 const fn default_ema_alpha() -> f32 {
@@ -411,6 +1081,12 @@ const fn default_double_tap_window() -> u32 {
 const fn default_adaptive_margin() -> u32 {
     30
 }
+const fn default_adaptive_min_samples() -> u32 {
+    20
+}
+const fn default_adaptive_max_stddev() -> f32 {
+    25.0
+}
 
 impl Default for MtConfig {
     fn default() -> Self {
@@ -426,12 +1102,21 @@ impl Default for MtConfig {
             chord_detection_window_ms: 50,
             double_tap_then_hold: false,
             double_tap_window_ms: 300,
+            quick_tap_term_ms: 0,
+            quick_tap_term_per_key: HashMap::new(),
             cross_hand_unwrap: true,
             adaptive_target_margin_ms: 30,
+            adaptive_min_samples: 20,
+            adaptive_max_stddev_ms: 25.0,
             pause_learning_in_game_mode: true,
             ema_alpha: 0.02,
             auto_save_interval_secs: 30,
             hold_do_nothing_emits_tap: true,
+            retro_tapping: false,
+            retro_tapping_per_key: HashMap::new(),
+            chordal_hold: false,
+            chordal_hold_per_key: HashMap::new(),
+            trace_decisions: false,
         }
     }
 }
@@ -482,60 +1167,557 @@ impl Default for EnabledKeyboards {
     }
 }
 
-/// Main configuration structure
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Config {
-    #[serde(default = "default_tapping_term")]
-    pub tapping_term_ms: u32,
-    #[serde(default)]
-    pub mt_config: MtConfig,
-    #[serde(default)]
-    pub enabled_keyboards: EnabledKeyboards,
+/// User commands run on daemon lifecycle events
+///
+/// Each hook runs as the owning user from `~/.config/keymux`, with `KEYMUX_EVENT`
+/// and event-specific `KEYMUX_*` environment variables describing what happened.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// Active layer changed (KEYMUX_LAYER, KEYMUX_PREVIOUS_LAYER, KEYMUX_KEYBOARD_*)
     #[serde(default)]
-    pub remaps: HashMap<KeyCode, KeyAction>,
+    pub on_layer_change: Option<String>,
+    /// Game mode turned on or off (KEYMUX_GAME_MODE=1/0)
     #[serde(default)]
-    pub layers: HashMap<Layer, LayerConfig>,
+    pub on_game_mode: Option<String>,
+    /// One of your keyboards was grabbed by keymux (KEYMUX_KEYBOARD_ID, KEYMUX_KEYBOARD_NAME)
     #[serde(default)]
-    pub game_mode: GameMode,
+    pub on_keyboard_connect: Option<String>,
+    /// Active profile changed (KEYMUX_PROFILE, KEYMUX_PREVIOUS_PROFILE)
     #[serde(default)]
-    pub per_keyboard_overrides: HashMap<String, PerKeyboardConfig>,
-
-    /// Double-tap window (milliseconds) - QMK tap dance inspired
-    /// Default: 250ms (configurable, sensible default)
-    pub double_tap_window_ms: Option<u64>,
-
-    /// OneShot timeout (milliseconds) - QMK one-shot keys inspired
-    /// Default: 5000ms (5 seconds, like QMK)
-    pub oneshot_timeout_ms: Option<u64>,
+    pub on_profile_switch: Option<String>,
+}
 
-    /// Enable hot config reload - automatically reload config when file changes (default: false)
-    /// When enabled, changes to config.ron are immediately applied without restarting daemon
-    #[serde(default)]
-    pub hot_config_reload: bool,
+/// Kinds of desktop notification the daemon sends, for `notifications.urgency`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// "Configuration reloaded"
+    Reload,
+    /// Invalid config, quarantined or throttled keyboards
+    Error,
+    /// Layer OSD
+    Layer,
+    /// Game mode OSD
+    GameMode,
+    /// A keyboard was grabbed
+    KeyboardConnect,
+    /// Low battery warning
+    Battery,
+}
 
-    /// Per-keyboard configs inherit global layout by default (default: true)
-    /// - true: per_keyboard_overrides merge with global config (override specific fields)
-    /// - false: per_keyboard_overrides replace global config (build from scratch)
-    #[serde(default = "default_true_bool")]
-    pub per_keyboard_inherits_global_layout: bool,
+impl NotificationKind {
+    const fn default_urgency(self) -> Urgency {
+        match self {
+            Self::Error => Urgency::Critical,
+            Self::Reload | Self::Battery => Urgency::Normal,
+            Self::Layer | Self::GameMode | Self::KeyboardConnect => Urgency::Low,
+        }
+    }
 }
 
-const fn default_tapping_term() -> u32 {
-    130
+/// notify-send urgency level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
 }
 
-const fn default_true_bool() -> bool {
-    true
+impl Urgency {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::Critical => "critical",
+        }
+    }
 }
 
-impl Config {
-    /// Preprocess config text to allow bare KeyCode syntax everywhere
-    /// Transforms `KC_*` → `Key(KC_*)` in all contexts except HashMap keys
-    ///
-    /// This heuristic allows cleaner config syntax:
-    /// - `KC_CAPS: KC_ESC,` → `KC_CAPS: Key(KC_ESC),`
-    /// - `MT(KC_A, KC_LGUI)` → `MT(Key(KC_A), Key(KC_LGUI))`
-    /// - `"1234": Enable` → `("1234", Enable)`
+/// Which desktop notifications the daemon sends a user, and how
+///
+/// Layer and game mode changes are shown as an OSD that replaces the previous one
+/// where the notification daemon supports it; everything else goes through the
+/// same notify-send path as regular notifications.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationsConfig {
+    /// Notify on layer changes (default: false)
+    #[serde(default)]
+    pub layer_change: bool,
+    /// Notify when game mode turns on or off (default: false)
+    #[serde(default)]
+    pub game_mode: bool,
+    /// List what changed in the "config reloaded" notification (default: false;
+    /// the daemon log always has it)
+    #[serde(default)]
+    pub reload_changes: bool,
+    /// Text shown per layer (default: the layer name); "" keeps a layer silent
+    #[serde(default)]
+    pub layers: HashMap<Layer, String>,
+    /// Minimum time between notifications (default: 500). A change in between is
+    /// shown once the time is up, so the last state is never lost.
+    #[serde(default = "default_notification_throttle")]
+    pub throttle_ms: u64,
+    /// How long a notification stays up (default: 1500)
+    #[serde(default = "default_notification_timeout")]
+    pub timeout_ms: u32,
+    /// Warn once when a wireless keyboard's battery drops to this percentage (default: off)
+    #[serde(default)]
+    pub low_battery_percent: Option<u8>,
+    /// Notify when the config reloads (default: true)
+    #[serde(default = "default_true_bool")]
+    pub reload: bool,
+    /// Notify about an invalid config and keyboards quarantined or throttled for
+    /// misbehaving (default: true)
+    #[serde(default = "default_true_bool")]
+    pub errors: bool,
+    /// Notify when keymux grabs one of your keyboards (default: false)
+    #[serde(default)]
+    pub keyboard_connect: bool,
+    /// Urgency per kind, e.g. `{ reload: low }` (default: critical for errors, normal
+    /// for reloads and battery warnings, low for the rest)
+    #[serde(default)]
+    pub urgency: HashMap<NotificationKind, Urgency>,
+    /// Let the notification daemon play its sound (default: true)
+    #[serde(default = "default_true_bool")]
+    pub sounds: bool,
+}
+
+fn default_notification_throttle() -> u64 {
+    500
+}
+
+fn default_notification_timeout() -> u32 {
+    1500
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            layer_change: false,
+            game_mode: false,
+            reload_changes: false,
+            layers: HashMap::new(),
+            throttle_ms: default_notification_throttle(),
+            timeout_ms: default_notification_timeout(),
+            low_battery_percent: None,
+            reload: true,
+            errors: true,
+            keyboard_connect: false,
+            urgency: HashMap::new(),
+            sounds: true,
+        }
+    }
+}
+
+impl NotificationsConfig {
+    /// Whether notifications of this kind are turned on
+    pub const fn wants(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::Reload => self.reload,
+            NotificationKind::Error => self.errors,
+            NotificationKind::Layer => self.layer_change,
+            NotificationKind::GameMode => self.game_mode,
+            NotificationKind::KeyboardConnect => self.keyboard_connect,
+            NotificationKind::Battery => self.low_battery_percent.is_some(),
+        }
+    }
+
+    pub fn urgency(&self, kind: NotificationKind) -> Urgency {
+        self.urgency
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| kind.default_urgency())
+    }
+
+    /// Text to show when `layer` becomes active, if it should be shown at all
+    pub fn layer_text(&self, layer: &Layer) -> Option<String> {
+        if !self.layer_change {
+            return None;
+        }
+        match self.layers.get(layer) {
+            Some(text) if text.is_empty() => None,
+            Some(text) => Some(text.clone()),
+            None => Some(layer.0.clone()),
+        }
+    }
+}
+
+/// Time-based rule selecting a profile
+///
+/// Times are local "HH:MM"; a range whose end is before its start wraps past midnight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleRule {
+    /// Profile to activate while the rule matches (a key of `profiles`)
+    pub profile: String,
+    /// Start time, inclusive (e.g. "09:00")
+    pub from: String,
+    /// End time, exclusive (e.g. "17:30")
+    pub to: String,
+    /// Days the rule applies on ("Mon" .. "Sun"); empty = every day
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+impl ScheduleRule {
+    const DAYS: [&'static str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+    /// Parse "HH:MM" into minutes since midnight
+    fn parse_time(time: &str) -> Option<u32> {
+        let (hours, minutes) = time.trim().split_once(':')?;
+        let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+        (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+    }
+
+    /// Check the rule's times and day names
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for time in [&self.from, &self.to] {
+            if Self::parse_time(time).is_none() {
+                anyhow::bail!("invalid time {:?} in schedule (expected HH:MM)", time);
+            }
+        }
+        for day in &self.days {
+            if !Self::DAYS.contains(&day.to_lowercase().get(..3).unwrap_or_default()) {
+                anyhow::bail!("invalid day {:?} in schedule (expected Mon..Sun)", day);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the rule covers a moment (weekday 0 = Monday, minutes since midnight)
+    pub fn matches(&self, weekday: u32, minute: u32) -> bool {
+        let (Some(from), Some(to)) = (Self::parse_time(&self.from), Self::parse_time(&self.to))
+        else {
+            return false;
+        };
+        // After midnight in a wrapping range, the day that counts is the one it started on
+        let (in_range, day) = if from <= to {
+            (from <= minute && minute < to, weekday)
+        } else if minute >= from {
+            (true, weekday)
+        } else {
+            (minute < to, (weekday + 6) % 7)
+        };
+        in_range
+            && (self.days.is_empty()
+                || self
+                    .days
+                    .iter()
+                    .any(|d| d.to_lowercase().get(..3) == Self::DAYS.get(day as usize).copied()))
+    }
+}
+
+/// Main configuration structure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default = "default_tapping_term")]
+    pub tapping_term_ms: u32,
+    #[serde(default)]
+    pub mt_config: MtConfig,
+    #[serde(default)]
+    pub enabled_keyboards: EnabledKeyboards,
+    #[serde(default)]
+    pub remaps: HashMap<KeyCode, KeyAction>,
+    #[serde(default)]
+    pub layers: HashMap<Layer, LayerConfig>,
+    #[serde(default)]
+    pub game_mode: GameMode,
+    #[serde(default)]
+    pub per_keyboard_overrides: HashMap<String, PerKeyboardConfig>,
+
+    /// Double-tap window (milliseconds) - QMK tap dance inspired
+    /// Default: 250ms (configurable, sensible default)
+    pub double_tap_window_ms: Option<u64>,
+
+    /// OneShot timeout (milliseconds) - QMK one-shot keys inspired
+    /// Default: 5000ms (5 seconds, like QMK)
+    pub oneshot_timeout_ms: Option<u64>,
+
+    /// Layer idle timeout (milliseconds) - a layer held by a key (MO, TO) is dropped after
+    /// this long without any key event, in case the key's release got lost
+    /// Default: none (held layers never time out)
+    pub layer_idle_timeout_ms: Option<u64>,
+
+    /// Enable hot config reload - automatically reload config when file changes (default: false)
+    /// When enabled, changes to config.ron are immediately applied without restarting daemon
+    #[serde(default)]
+    pub hot_config_reload: bool,
+
+    /// Per-keyboard configs inherit global layout by default (default: true)
+    /// - true: per_keyboard_overrides merge with global config (override specific fields)
+    /// - false: per_keyboard_overrides replace global config (build from scratch)
+    #[serde(default = "default_true_bool")]
+    pub per_keyboard_inherits_global_layout: bool,
+
+    /// Link all of a user's keyboards so modifiers, layers and MT decisions span devices
+    /// (default: false). E.g. hold Shift on a macropad and type on the main keyboard,
+    /// or hold MO(nav) on one half of a split and use the nav layer on the other.
+    #[serde(default)]
+    pub share_state_across_keyboards: bool,
+
+    /// Split keyboards whose halves show up as separate devices, as (primary, secondary)
+    /// ID or name patterns. The secondary half's keys go through the primary's keymap
+    /// and out of its virtual keyboard, so MT chords and layers span both halves like
+    /// on a split's own firmware. Each half types alone while the other is missing.
+    #[serde(default)]
+    pub split_keyboards: Vec<(String, String)>,
+
+    /// Named groups of keyboards (ID or name patterns) that share layers, held modifiers
+    /// and game mode overrides, like `share_state_across_keyboards` but only within the
+    /// group. E.g. `{ "desk": ["Keychron", "Macropad"] }` keeps the macropad on the
+    /// main board's layer.
+    #[serde(default)]
+    pub keyboard_groups: HashMap<String, Vec<String>>,
+
+    /// Keep a keyboard's processor paused this long after it disappears (default: 0 = off)
+    /// so wireless boards that briefly drop out reconnect with their layers, MT and
+    /// adaptive state intact instead of being torn down and restarted.
+    #[serde(default)]
+    pub disconnect_grace_ms: u32,
+
+    /// Like `disconnect_grace_ms` with no time limit: a disconnected keyboard's processor
+    /// stays paused until it comes back, however long that takes (default: false).
+    /// Keyboards the daemon hasn't seen since it started have no processor to keep.
+    #[serde(default)]
+    pub disconnect_grace_forever: bool,
+
+    /// Read keyboards without grabbing them and emit nothing (default: false). Keys go
+    /// through the keymap for stats and debug logs while the keyboard keeps typing
+    /// normally, so a new config can be tried on a second keyboard. Usually set per keyboard.
+    #[serde(default)]
+    pub observe_only: bool,
+
+    /// Leave event nodes that also report a wheel, dial or mouse buttons to the OS instead
+    /// of grabbing them (default: false). Gaming keyboards put their pointer and media
+    /// wheel there; keys on such a node aren't remapped. Usually set per keyboard.
+    #[serde(default)]
+    pub skip_pointer_nodes: bool,
+
+    /// Drop switch chatter (default: 0 = off): a key released and pressed again within
+    /// this many ms counts as still held. Releases reach the keymap this much later.
+    #[serde(default)]
+    pub debounce_ms: u32,
+
+    /// Debounce windows (ms) for individual keys, overriding `debounce_ms` (0 = off)
+    #[serde(default)]
+    pub debounce_keys: HashMap<KeyCode, u32>,
+
+    /// Drop a key press another event node of the same keyboard reported within this
+    /// many ms, with its release (default: 0 = off), for boards that send every key twice
+    #[serde(default)]
+    pub dedupe_nodes_ms: u32,
+
+    /// Type text (`keymux type`, snippet expansions) a key at a time, pausing a random
+    /// number of ms in this range after each key event (default: off = all at once,
+    /// like a paste). For apps and anti-cheat that reject instant typing, e.g. `(15, 60)`
+    #[serde(default)]
+    pub typing_jitter_ms: Option<(u32, u32)>,
+
+    /// Pauses between the events of one output (default: 5 ms holding a tapped key,
+    /// 2 ms between the events of a multi-key output). 0 writes them all at once, for
+    /// apps that keep up; raise them for ones that miss taps.
+    #[serde(default)]
+    pub output_pacing: OutputPacing,
+
+    /// Layout the keyboard's firmware sends instead of QWERTY (e.g. "dvorak"), so MT
+    /// hand detection and SOCD go by where keys physically are. Usually set per keyboard.
+    #[serde(default)]
+    pub hardware_layout: Option<String>,
+
+    /// Keys the keyboard sends from another position than on a QWERTY board, as
+    /// `sent: position` (e.g. HHKB's `{ KC_LCTL: KC_CAPS }`), on top of `hardware_layout`
+    #[serde(default, deserialize_with = "deserialize_key_map")]
+    pub hardware_swaps: HashMap<KeyCode, KeyCode>,
+
+    /// Keys dropped before any mapping, e.g. a broken switch that fires on its own or
+    /// an Fn-lock key. Usually set per keyboard.
+    #[serde(default, deserialize_with = "deserialize_key_list")]
+    pub ignored_keys: Vec<KeyCode>,
+
+    /// Keys passed to the system exactly as typed, autorepeat included, skipping the
+    /// keymap: the keys you wake the screen with after a suspend, which should work
+    /// whatever layer or state keymux is in. They can't be remapped.
+    #[serde(default, deserialize_with = "deserialize_key_list")]
+    pub wake_keys: Vec<KeyCode>,
+
+    /// Treat the keyboard as a macro pad (default: false): keys without a binding in
+    /// any active layer send nothing, instead of typing as themselves, so an unmapped
+    /// key on a Stream Deck-like pad doesn't type a stray "1". Set per keyboard.
+    #[serde(default)]
+    pub macro_pad: bool,
+
+    /// Keys that raw scancodes (MSC_SCAN) report as, for vendor keys the kernel only
+    /// knows as KEY_UNKNOWN. Map them to a spare key (e.g. KC_F20) and bind that in
+    /// remaps like any other key. Unknown keys log their scancode when pressed.
+    #[serde(default, deserialize_with = "deserialize_scancode_map")]
+    pub scancodes: HashMap<u32, KeyCode>,
+
+    /// Layouts set up in the compositor or X, in the same order (default: [] = "us").
+    /// `LayoutSwitch` cycles through them and turns names into indices, and typed text
+    /// follows the active one ("us" and "de" can be typed).
+    #[serde(default)]
+    pub keyboard_layouts: Vec<String>,
+
+    /// Actions run by typing keys in order (first matching rule wins, longest first).
+    /// Off in game mode.
+    #[serde(default)]
+    pub sequences: Vec<SequenceRule>,
+
+    /// Abbreviations expanded as you type, optionally only in some apps
+    #[serde(default)]
+    pub snippets: SnippetsConfig,
+
+    /// Behavior tests for `keymux test` (ignored by the daemon)
+    #[serde(default)]
+    pub tests: Vec<BehaviorTest>,
+
+    /// Virtual keyboards of other remappers (ID or name patterns) that may be grabbed
+    /// anyway, e.g. to layer keymux on top of keyd. Others are skipped to avoid loops.
+    #[serde(default)]
+    pub grab_remapper_outputs: Vec<String>,
+
+    /// Give each virtual keyboard the bus type (USB, Bluetooth, ...) of the keyboard it
+    /// replaces (default: false = USB), for compositors and libinput quirks keyed on it
+    #[serde(default)]
+    pub copy_source_bustype: bool,
+
+    /// Give media, brightness and browser keys their own "Consumer Control" virtual
+    /// device when the source has them, like real keyboards enumerate (default: true)
+    #[serde(default = "default_true")]
+    pub split_consumer_control: bool,
+
+    /// Hotplug debounce and event filters (daemon-wide, see HotplugConfig)
+    #[serde(default)]
+    pub hotplug: HotplugConfig,
+
+    /// Commands to run on layer changes, game mode, keyboard connects, etc.
+    #[serde(default)]
+    pub hooks: HooksConfig,
+
+    /// Desktop notifications on layer and game mode changes
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+
+    /// Layers activated by the focused window (first matching rule wins)
+    #[serde(default)]
+    pub window_layers: Vec<WindowLayerRule>,
+
+    /// Named sets of mutually exclusive layers: turning one on turns the others off,
+    /// e.g. `{ "alpha": ["colemak", "gaming"] }` to switch between alternate layouts
+    /// with TG without stacking them
+    #[serde(default)]
+    pub layer_groups: HashMap<String, Vec<Layer>>,
+
+    /// Named profiles overlaid on this config, same fields as a per-keyboard block
+    #[serde(default)]
+    pub profiles: HashMap<String, PerKeyboardConfig>,
+
+    /// Time-based rules picking the active profile (first matching rule wins)
+    #[serde(default)]
+    pub schedule: Vec<ScheduleRule>,
+}
+
+/// The preprocessor turns a bare `KC_A` value into `Key(KC_A)`, so keys are read as actions
+fn action_to_key<E: serde::de::Error>(action: KeyAction) -> Result<KeyCode, E> {
+    match action {
+        KeyAction::Key(key) => Ok(key),
+        other => Err(E::custom(format!("expected a key, found {:?}", other))),
+    }
+}
+
+/// A single key, written bare (`KC_LSFT`) and so wrapped as an action by the preprocessor
+fn deserialize_key<'de, D>(deserializer: D) -> Result<KeyCode, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    action_to_key(KeyAction::deserialize(deserializer)?)
+}
+
+fn deserialize_key_list<'de, D>(deserializer: D) -> Result<Vec<KeyCode>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<KeyAction>::deserialize(deserializer)?
+        .into_iter()
+        .map(action_to_key)
+        .collect()
+}
+
+fn deserialize_optional_key_list<'de, D>(deserializer: D) -> Result<Option<Vec<KeyCode>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<Vec<KeyAction>>::deserialize(deserializer)?
+        .map(|actions| actions.into_iter().map(action_to_key).collect())
+        .transpose()
+}
+
+fn deserialize_scancode_map<'de, D>(deserializer: D) -> Result<HashMap<u32, KeyCode>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    HashMap::<u32, KeyAction>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(scancode, action)| Ok((scancode, action_to_key(action)?)))
+        .collect()
+}
+
+fn deserialize_optional_scancode_map<'de, D>(
+    deserializer: D,
+) -> Result<Option<HashMap<u32, KeyCode>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<HashMap<u32, KeyAction>>::deserialize(deserializer)?
+        .map(|map| {
+            map.into_iter()
+                .map(|(scancode, action)| Ok((scancode, action_to_key(action)?)))
+                .collect()
+        })
+        .transpose()
+}
+
+fn deserialize_key_map<'de, D>(deserializer: D) -> Result<HashMap<KeyCode, KeyCode>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    HashMap::<KeyCode, KeyAction>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, action)| Ok((key, action_to_key(action)?)))
+        .collect()
+}
+
+fn deserialize_optional_key_map<'de, D>(
+    deserializer: D,
+) -> Result<Option<HashMap<KeyCode, KeyCode>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<HashMap<KeyCode, KeyAction>>::deserialize(deserializer)?
+        .map(|map| {
+            map.into_iter()
+                .map(|(key, action)| Ok((key, action_to_key(action)?)))
+                .collect()
+        })
+        .transpose()
+}
+
+const fn default_tapping_term() -> u32 {
+    130
+}
+
+const fn default_true_bool() -> bool {
+    true
+}
+
+impl Config {
+    /// Preprocess config text to allow bare KeyCode syntax everywhere
+    /// Transforms `KC_*` → `Key(KC_*)` in all contexts except HashMap keys
+    ///
+    /// This heuristic allows cleaner config syntax:
+    /// - `KC_CAPS: KC_ESC,` → `KC_CAPS: Key(KC_ESC),`
+    /// - `MT(KC_A, KC_LGUI)` → `MT(Key(KC_A), Key(KC_LGUI))`
+    /// - `"1234": Enable` → `("1234", Enable)`
     fn find_enabled_keyboards_array(content: &str) -> Option<(usize, usize)> {
         let marker = "enabled_keyboards:";
         if let Some(start) = content.find(marker) {
@@ -584,7 +1766,7 @@ impl Config {
         None
     }
 
-    fn preprocess_config(content: &str) -> String {
+    pub(crate) fn preprocess_config(content: &str) -> String {
         use regex::Regex;
 
         // First, preprocess enabled_keyboards entries: "pattern": Enable/Disable -> ("pattern", "Enable") etc
@@ -632,7 +1814,11 @@ impl Config {
 
     fn preprocess_kc_only(content: &str) -> String {
         use regex::Regex;
-        let re = Regex::new(r"\b(KC_[A-Z0-9_]+)\b").unwrap();
+        // `Raw(code)` is the action for `KC_RAW(code)`
+        let re_raw = Regex::new(r"\bRaw\s*\(([^()]*)\)").unwrap();
+        let content = &*re_raw.replace_all(content, "KC_RAW($1)");
+        // `KC_RAW(code)` is taken whole so it gets wrapped as one key
+        let re = Regex::new(r"\bKC_RAW\s*\([^()]*\)|\bKC_[A-Z0-9_]+\b").unwrap();
 
         let mut result = String::with_capacity(content.len() * 2);
         let mut last_end = 0;
@@ -675,12 +1861,30 @@ impl Config {
     #[allow(clippy::missing_errors_doc)]
     pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
         let content = std::fs::read_to_string(path)?;
+        Self::parse(&content)
+    }
 
+    /// Parse config text as found in config.ron
+    #[allow(clippy::missing_errors_doc)]
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
         // Preprocess to support bare KeyCode syntax
-        let preprocessed = Self::preprocess_config(&content);
+        let preprocessed = Self::preprocess_config(content);
 
-        let config = ron::from_str(&preprocessed)
+        let mut config: Self = ron::from_str(&preprocessed)
             .map_err(|e| anyhow::anyhow!("Config parsing error: {}", e))?;
+        config.game_mode.expand_socd_presets();
+        for rule in &config.window_layers {
+            rule.patterns();
+        }
+        for overlay in config
+            .per_keyboard_overrides
+            .values_mut()
+            .chain(config.profiles.values_mut())
+        {
+            if let Some(game_mode) = overlay.game_mode.as_mut() {
+                game_mode.expand_socd_presets();
+            }
+        }
         Ok(config)
     }
 
@@ -700,8 +1904,11 @@ impl Config {
 
         if is_sudo {
             // When run with sudo, use actual user's home directory
-            let home_dir = crate::get_user_home_dir(uid)?;
-            Ok(home_dir.join(".config").join("keymux").join("config.ron"))
+            Self::path_for_uid(uid)
+        } else if let Some(path) =
+            dirs::home_dir().and_then(|home| crate::paths::config_override(&home))
+        {
+            Ok(path)
         } else {
             // Normal case: use dirs crate
             let config_dir =
@@ -710,6 +1917,12 @@ impl Config {
         }
     }
 
+    /// Config path for a specific user, resolved via their home directory
+    pub fn path_for_uid(uid: u32) -> anyhow::Result<std::path::PathBuf> {
+        let home_dir = crate::get_user_home_dir(uid)?;
+        Ok(crate::paths::config_path_in(&home_dir))
+    }
+
     /// Check if a keyboard should be enabled based on the enabled_keyboards config
     ///
     /// Parsing rules (applied in order):
@@ -806,15 +2019,13 @@ impl Config {
         }
     }
 
-    /// Get effective config for a specific keyboard
-    /// Applies per-keyboard overrides on top of the global config (or replaces it)
-    #[must_use]
-    #[allow(clippy::option_if_let_else)] // Complex nested logic, keeping for readability
-    pub fn for_keyboard(&self, keyboard_id: &str) -> Self {
-        // Match per_keyboard_overrides using prefix logic for backwards compatibility:
-        // a key without "@port" matches any port of that hardware ID; "@port" is exact.
+    /// The per_keyboard_overrides entry for a keyboard, if any
+    ///
+    /// Matched with prefix logic for backwards compatibility: a key without "@port"
+    /// matches any port of that hardware ID; "@port" is exact.
+    pub fn keyboard_override(&self, keyboard_id: &str) -> Option<&PerKeyboardConfig> {
         let our_base = keyboard_id.split('@').next().unwrap_or(keyboard_id);
-        let override_cfg = self.per_keyboard_overrides.iter().find_map(|(key, cfg)| {
+        self.per_keyboard_overrides.iter().find_map(|(key, cfg)| {
             let matches = if key.contains('@') {
                 key == keyboard_id
             } else {
@@ -825,42 +2036,26 @@ impl Config {
             } else {
                 None
             }
-        });
-        if let Some(override_cfg) = override_cfg {
-            if self.per_keyboard_inherits_global_layout {
-                // INHERITING MODE: Start with global config, merge/override with per-keyboard settings
-                let mut config = self.clone();
-
-                // Apply all overrides - each field is optional and only overrides if Some
-                if let Some(term) = override_cfg.tapping_term_ms {
-                    config.tapping_term_ms = term;
-                }
-                if let Some(mt) = &override_cfg.mt_config {
-                    config.mt_config = mt.clone();
-                }
-
-                // MERGE remaps: extend global remaps with per-keyboard remaps
-                // Per-keyboard remaps override global ones for the same keys
-                if let Some(remaps) = &override_cfg.remaps {
-                    config.remaps.extend(remaps.clone());
-                }
-
-                // MERGE layers: extend global layers with per-keyboard layers
-                // Per-keyboard layers override global ones for the same layer names
-                if let Some(layers) = &override_cfg.layers {
-                    config.layers.extend(layers.clone());
-                }
+        })
+    }
 
-                // MERGE game_mode: extend global game_mode remaps with per-keyboard game_mode remaps
-                if let Some(game_mode) = &override_cfg.game_mode {
-                    config.game_mode.remaps.extend(game_mode.remaps.clone());
-                }
+    /// Get effective config for a specific keyboard
+    /// Applies per-keyboard overrides on top of the global config (or replaces it)
+    #[must_use]
+    pub fn for_keyboard(&self, keyboard_id: &str) -> Self {
+        self.with_overrides_for(keyboard_id).with_socd_by_position()
+    }
 
-                config
+    #[allow(clippy::option_if_let_else)] // Complex nested logic, keeping for readability
+    fn with_overrides_for(&self, keyboard_id: &str) -> Self {
+        if let Some(override_cfg) = self.keyboard_override(keyboard_id) {
+            if self.per_keyboard_inherits_global_layout {
+                // INHERITING MODE: Start with global config, merge/override with per-keyboard settings
+                self.merged_with(override_cfg)
             } else {
                 // NON-INHERITING MODE: Build from scratch with per-keyboard config only
                 // Use defaults for any fields not specified in per-keyboard config
-                Self {
+                let mut config = Self {
                     tapping_term_ms: override_cfg
                         .tapping_term_ms
                         .unwrap_or_else(default_tapping_term),
@@ -874,9 +2069,66 @@ impl Config {
                         .double_tap_window_ms
                         .or(self.double_tap_window_ms),
                     oneshot_timeout_ms: override_cfg.oneshot_timeout_ms.or(self.oneshot_timeout_ms),
+                    layer_idle_timeout_ms: override_cfg
+                        .layer_idle_timeout_ms
+                        .or(self.layer_idle_timeout_ms),
                     hot_config_reload: self.hot_config_reload, // Keep global hot reload setting
                     per_keyboard_inherits_global_layout: self.per_keyboard_inherits_global_layout, // Keep global setting
-                }
+                    share_state_across_keyboards: self.share_state_across_keyboards, // Sharing is per-user
+                    split_keyboards: self.split_keyboards.clone(),
+                    keyboard_groups: self.keyboard_groups.clone(),
+                    disconnect_grace_ms: self.disconnect_grace_ms,
+                    disconnect_grace_forever: self.disconnect_grace_forever,
+                    observe_only: override_cfg.observe_only.unwrap_or(self.observe_only),
+                    skip_pointer_nodes: override_cfg
+                        .skip_pointer_nodes
+                        .unwrap_or(self.skip_pointer_nodes),
+                    debounce_ms: override_cfg.debounce_ms.unwrap_or(self.debounce_ms),
+                    debounce_keys: override_cfg
+                        .debounce_keys
+                        .clone()
+                        .unwrap_or_else(|| self.debounce_keys.clone()),
+                    dedupe_nodes_ms: override_cfg.dedupe_nodes_ms.unwrap_or(self.dedupe_nodes_ms),
+                    typing_jitter_ms: override_cfg.typing_jitter_ms.or(self.typing_jitter_ms),
+                    output_pacing: override_cfg.output_pacing.unwrap_or(self.output_pacing),
+                    hardware_layout: override_cfg
+                        .hardware_layout
+                        .clone()
+                        .or_else(|| self.hardware_layout.clone()),
+                    hardware_swaps: override_cfg
+                        .hardware_swaps
+                        .clone()
+                        .unwrap_or_else(|| self.hardware_swaps.clone()),
+                    ignored_keys: override_cfg
+                        .ignored_keys
+                        .clone()
+                        .unwrap_or_else(|| self.ignored_keys.clone()),
+                    wake_keys: override_cfg
+                        .wake_keys
+                        .clone()
+                        .unwrap_or_else(|| self.wake_keys.clone()),
+                    macro_pad: override_cfg.macro_pad.unwrap_or(self.macro_pad),
+                    scancodes: override_cfg
+                        .scancodes
+                        .clone()
+                        .unwrap_or_else(|| self.scancodes.clone()),
+                    keyboard_layouts: self.keyboard_layouts.clone(), // Layouts are per-session
+                    sequences: self.sequences.clone(), // Sequences are typed, not per-keyboard
+                    snippets: self.snippets.clone(),
+                    tests: self.tests.clone(),
+                    grab_remapper_outputs: self.grab_remapper_outputs.clone(),
+                    copy_source_bustype: self.copy_source_bustype,
+                    split_consumer_control: self.split_consumer_control,
+                    hotplug: self.hotplug.clone(),
+                    hooks: self.hooks.clone(), // Hooks are per-user, not per-keyboard
+                    notifications: self.notifications.clone(),
+                    window_layers: self.window_layers.clone(), // Window rules are per-user
+                    layer_groups: self.layer_groups.clone(),
+                    profiles: self.profiles.clone(), // Profiles are per-user
+                    schedule: self.schedule.clone(),
+                };
+                override_cfg.apply_mt_timings(&mut config.mt_config);
+                config
             }
         } else {
             // No per-keyboard override found, return global config as-is
@@ -884,143 +2136,404 @@ impl Config {
         }
     }
 
-    /// Save only `enabled_keyboards` field, preserving rest of file
+    /// Where each key this keyboard sends sits on a QWERTY board, for the keys
+    /// `hardware_layout` and `hardware_swaps` move (`sent -> position`)
+    pub fn key_positions(&self) -> HashMap<KeyCode, KeyCode> {
+        let mut positions: HashMap<KeyCode, KeyCode> = self
+            .hardware_layout
+            .as_deref()
+            .and_then(|layout| super::preset::layout_remaps(layout).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(position, sent)| (sent, position))
+            .collect();
+        positions.extend(&self.hardware_swaps);
+        positions
+    }
+
+    /// Move SOCD bindings, written for QWERTY positions (WASD), to the keys this
+    /// keyboard sends from those positions
+    fn with_socd_by_position(mut self) -> Self {
+        let positions = self.key_positions();
+        if positions.is_empty() {
+            return self;
+        }
+        let sent_at: HashMap<KeyCode, KeyCode> = positions
+            .iter()
+            .map(|(sent, position)| (*position, *sent))
+            .collect();
+        let sent = |key: KeyCode| sent_at.get(&key).copied().unwrap_or(key);
+        let at_position = |action: Box<KeyAction>| match *action {
+            KeyAction::Key(key) => Box::new(KeyAction::Key(sent(key))),
+            other => Box::new(other),
+        };
+        // SOCD keys stay SOCD keys inside OnlyGameMode/OnlyTyping
+        fn moved(
+            action: KeyAction,
+            at_position: &dyn Fn(Box<KeyAction>) -> Box<KeyAction>,
+        ) -> KeyAction {
+            match action {
+                KeyAction::SOCD(this, opposing) => KeyAction::SOCD(
+                    at_position(this),
+                    opposing.into_iter().map(at_position).collect(),
+                ),
+                KeyAction::OnlyGameMode(inner) => {
+                    KeyAction::OnlyGameMode(Box::new(moved(*inner, at_position)))
+                }
+                KeyAction::OnlyTyping(inner) => {
+                    KeyAction::OnlyTyping(Box::new(moved(*inner, at_position)))
+                }
+                other => other,
+            }
+        }
+        let move_socd = |remaps: &mut HashMap<KeyCode, KeyAction>| {
+            let socd_keys: Vec<KeyCode> = remaps
+                .iter()
+                .filter(|(_, action)| matches!(action.without_mode(), KeyAction::SOCD(..)))
+                .map(|(key, _)| *key)
+                .collect();
+            let mut moved_socd = Vec::with_capacity(socd_keys.len());
+            for key in socd_keys {
+                if let Some(action) = remaps.remove(&key) {
+                    moved_socd.push((sent(key), moved(action, &at_position)));
+                }
+            }
+            remaps.extend(moved_socd);
+        };
+        move_socd(&mut self.remaps);
+        for layer in self.layers.values_mut() {
+            move_socd(&mut layer.remaps);
+        }
+        move_socd(&mut self.game_mode.remaps);
+        for overlay in self.game_mode.overlays.values_mut() {
+            move_socd(&mut overlay.remaps);
+        }
+        // Preset axes too, so their resolution follows the keys to where they went
+        let at_positions = |presets: &mut Vec<SocdPreset>| {
+            *presets = presets
+                .iter()
+                .flat_map(SocdPreset::axes)
+                .map(|(a, b, mode)| SocdPreset::Axis(sent(a), sent(b), mode))
+                .collect();
+        };
+        at_positions(&mut self.game_mode.socd);
+        for overlay in self.game_mode.overlays.values_mut() {
+            at_positions(&mut overlay.socd);
+        }
+        self
+    }
+
+    /// Output keys the virtual keyboard can't send: mouse and joystick buttons
+    pub fn button_output_errors(&self) -> Vec<String> {
+        let mut buttons: Vec<KeyCode> = self
+            .output_keys()
+            .into_iter()
+            .filter(|key| key.is_button())
+            .collect();
+        buttons.sort_by_key(|key| key.code());
+        buttons
+            .into_iter()
+            .map(|key| {
+                format!(
+                    "{} ({:#x}) is a mouse or joystick button, not a key the virtual keyboard can send",
+                    key,
+                    key.code()
+                )
+            })
+            .collect()
+    }
+
+    /// Unknown `hardware_layout` names, globally or per keyboard
+    pub fn hardware_layout_errors(&self) -> Vec<String> {
+        let known = super::preset::layout_names();
+        std::iter::once(("", self.hardware_layout.as_deref()))
+            .chain(
+                self.per_keyboard_overrides
+                    .iter()
+                    .map(|(id, cfg)| (id.as_str(), cfg.hardware_layout.as_deref())),
+            )
+            .filter_map(|(id, layout)| {
+                let layout = layout?;
+                if layout == "qwerty" || known.contains(&layout) {
+                    return None;
+                }
+                let scope = if id.is_empty() {
+                    String::new()
+                } else {
+                    format!(" for keyboard {}", id)
+                };
+                Some(format!(
+                    "Unknown hardware_layout {:?}{} (expected qwerty or one of: {})",
+                    layout,
+                    scope,
+                    known.join(", ")
+                ))
+            })
+            .collect()
+    }
+
+    /// All remap tables: base, every layer, and game mode with its overlays
+    fn all_remaps(&self) -> impl Iterator<Item = &HashMap<KeyCode, KeyAction>> {
+        std::iter::once(&self.remaps)
+            .chain(self.layers.values().map(|layer| &layer.remaps))
+            .chain(self.game_mode.all_remaps())
+    }
+
+    /// Every key the remaps, layers and game mode can emit
+    pub fn output_keys(&self) -> HashSet<KeyCode> {
+        let mut keys = HashSet::new();
+        for remaps in self.all_remaps() {
+            for (keycode, action) in remaps {
+                action.collect_output_keys(*keycode, &mut keys);
+            }
+        }
+        for rule in &self.sequences {
+            if let Some(last) = rule.keys.last() {
+                rule.action.collect_output_keys(*last, &mut keys);
+            }
+        }
+        // Held around a layer's plain keys, so they go out even if nothing binds them
+        for layer in self.layers.values() {
+            keys.extend(layer.with_mods.iter().copied());
+        }
+        keys
+    }
+
+    /// Whether any key runs a script (which may emit arbitrary keys)
+    pub fn has_scripts(&self) -> bool {
+        self.all_remaps()
+            .flat_map(HashMap::values)
+            .chain(self.sequences.iter().map(|rule| &rule.action))
+            .any(|action| matches!(action, KeyAction::Script(_)))
+    }
+
+    /// Whether a remapper's virtual keyboard is listed in `grab_remapper_outputs`
+    pub fn allows_remapper_output(&self, keyboard_id: &KeyboardId, keyboard_name: &str) -> bool {
+        self.grab_remapper_outputs.iter().any(|pattern| {
+            keyboard_id.matches_config_entry(pattern) || keyboard_name.contains(pattern.as_str())
+        })
+    }
+
+    /// The primary half of the split keyboard a keyboard is the secondary half of
+    ///
+    /// Returns the primary's pattern from `split_keyboards`.
+    pub fn split_primary_of(&self, keyboard_id: &KeyboardId, keyboard_name: &str) -> Option<&str> {
+        self.split_keyboards
+            .iter()
+            .find(|(_, secondary)| {
+                keyboard_id.matches_config_entry(secondary)
+                    || keyboard_name.contains(secondary.as_str())
+            })
+            .map(|(primary, _)| primary.as_str())
+    }
+
+    /// The `keyboard_groups` group a keyboard belongs to (the first by name if several)
+    pub fn keyboard_group(&self, keyboard_id: &KeyboardId, keyboard_name: &str) -> Option<&str> {
+        let mut groups: Vec<_> = self
+            .keyboard_groups
+            .iter()
+            .filter(|(_, patterns)| {
+                patterns.iter().any(|pattern| {
+                    keyboard_id.matches_config_entry(pattern)
+                        || keyboard_name.contains(pattern.as_str())
+                })
+            })
+            .map(|(group, _)| group.as_str())
+            .collect();
+        groups.sort_unstable();
+        groups.first().copied()
+    }
+
+    /// This config with a named profile overlaid ("default" or an unknown name = unchanged)
+    #[must_use]
+    pub fn with_profile(&self, profile: &str) -> Self {
+        self.profiles
+            .get(profile)
+            .map_or_else(|| self.clone(), |overlay| self.merged_with(overlay))
+    }
+
+    /// Profile the schedule selects at a moment (weekday 0 = Monday, minutes since midnight)
+    pub fn scheduled_profile(&self, weekday: u32, minute: u32) -> Option<&str> {
+        self.schedule
+            .iter()
+            .find(|rule| rule.matches(weekday, minute))
+            .map(|rule| rule.profile.as_str())
+    }
+
+    /// Problems with the schedule: bad times/days and undefined profiles
+    pub fn schedule_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for rule in &self.schedule {
+            if let Err(e) = rule.validate() {
+                errors.push(e.to_string());
+            }
+            if rule.profile != "default" && !self.profiles.contains_key(&rule.profile) {
+                errors.push(format!(
+                    "Scheduled profile not defined: \"{}\"",
+                    rule.profile
+                ));
+            }
+        }
+        errors
+    }
+
+    /// Problems with sequences: too few keys to tell them from a remap
+    pub fn sequence_errors(&self) -> Vec<String> {
+        self.sequences
+            .iter()
+            .filter(|rule| rule.keys.len() < 2)
+            .map(|rule| {
+                format!(
+                    "Sequence needs at least two keys: {:?} -> {:?}",
+                    rule.keys, rule.action
+                )
+            })
+            .collect()
+    }
+
+    /// Overlay a per-keyboard or profile block onto this config
+    ///
+    /// Scalar fields are replaced when set; remaps, layers and game mode remaps are merged,
+    /// with the overlay winning on conflicts.
+    #[must_use]
+    pub fn merged_with(&self, override_cfg: &PerKeyboardConfig) -> Self {
+        let mut config = self.clone();
+
+        // Apply all overrides - each field is optional and only overrides if Some
+        if let Some(term) = override_cfg.tapping_term_ms {
+            config.tapping_term_ms = term;
+        }
+        if let Some(mt) = &override_cfg.mt_config {
+            config.mt_config = mt.clone();
+        }
+        override_cfg.apply_mt_timings(&mut config.mt_config);
+        if let Some(window) = override_cfg.double_tap_window_ms {
+            config.double_tap_window_ms = Some(window);
+        }
+        if let Some(timeout) = override_cfg.oneshot_timeout_ms {
+            config.oneshot_timeout_ms = Some(timeout);
+        }
+        if let Some(timeout) = override_cfg.layer_idle_timeout_ms {
+            config.layer_idle_timeout_ms = Some(timeout);
+        }
+        if let Some(observe_only) = override_cfg.observe_only {
+            config.observe_only = observe_only;
+        }
+        if let Some(skip) = override_cfg.skip_pointer_nodes {
+            config.skip_pointer_nodes = skip;
+        }
+        if let Some(debounce) = override_cfg.debounce_ms {
+            config.debounce_ms = debounce;
+        }
+        if let Some(debounce_keys) = &override_cfg.debounce_keys {
+            config.debounce_keys.extend(debounce_keys.clone());
+        }
+        if let Some(window) = override_cfg.dedupe_nodes_ms {
+            config.dedupe_nodes_ms = window;
+        }
+        if let Some(range) = override_cfg.typing_jitter_ms {
+            config.typing_jitter_ms = Some(range);
+        }
+        if let Some(pacing) = override_cfg.output_pacing {
+            config.output_pacing = pacing;
+        }
+        if let Some(layout) = &override_cfg.hardware_layout {
+            config.hardware_layout = Some(layout.clone());
+        }
+        if let Some(swaps) = &override_cfg.hardware_swaps {
+            config.hardware_swaps.extend(swaps);
+        }
+        if let Some(ignored_keys) = &override_cfg.ignored_keys {
+            config.ignored_keys.extend(ignored_keys);
+        }
+        if let Some(wake_keys) = &override_cfg.wake_keys {
+            config.wake_keys.extend(wake_keys);
+        }
+        if let Some(macro_pad) = override_cfg.macro_pad {
+            config.macro_pad = macro_pad;
+        }
+        if let Some(scancodes) = &override_cfg.scancodes {
+            config.scancodes.extend(scancodes);
+        }
+
+        // MERGE remaps: extend global remaps with per-keyboard remaps
+        // Per-keyboard remaps override global ones for the same keys
+        if let Some(remaps) = &override_cfg.remaps {
+            config.remaps.extend(remaps.clone());
+        }
+
+        // MERGE layers: extend global layers with per-keyboard layers
+        // Per-keyboard layers override global ones for the same layer names
+        if let Some(layers) = &override_cfg.layers {
+            config.layers.extend(layers.clone());
+        }
+
+        // MERGE game_mode: extend global game_mode remaps with per-keyboard game_mode remaps
+        // Per-keyboard overlays replace global ones of the same name
+        if let Some(game_mode) = &override_cfg.game_mode {
+            config.game_mode.remaps.extend(game_mode.remaps.clone());
+            config.game_mode.overlays.extend(game_mode.overlays.clone());
+        }
+
+        config
+    }
+
+    /// Save only the enabled_keyboards field, preserving the rest of the file
     #[allow(clippy::missing_errors_doc)]
-    /// Save only the enabled_keyboards field, preserving all other formatting
     pub fn save_enabled_keyboards_only(&self, path: &std::path::Path) -> anyhow::Result<()> {
         self.save_enabled_keyboards_only_with_comments(path, None)
     }
 
     /// Save enabled_keyboards with optional comments for each pattern
+    ///
+    /// Only the field's value is rewritten; comments and formatting elsewhere in the
+    /// file are kept, as are trailing comments on entries that survive the rewrite.
     #[allow(clippy::missing_errors_doc)]
     pub fn save_enabled_keyboards_only_with_comments(
         &self,
         path: &std::path::Path,
         comments: Option<&std::collections::HashMap<String, String>>,
     ) -> anyhow::Result<()> {
-        // Read the original file
         let content = std::fs::read_to_string(path)?;
-
-        // Find the enabled_keyboards field
-        let start_marker = "enabled_keyboards:";
-
-        if let Some(start_pos) = content.find(start_marker) {
-            // Find where the field starts (after the colon)
-            let field_start = start_pos + start_marker.len();
-
-            // Find the end of this field (next field or closing paren)
-            let remaining = &content[field_start..];
-
-            // Find the end by looking for comma at the end of the value
-            let mut end_pos = field_start;
-            let mut depth = 0;
-            let mut in_string = false;
-            let mut found_value_start = false;
-
-            for (i, ch) in remaining.char_indices() {
-                match ch {
-                    '"' if i == 0
-                        || remaining.as_bytes().get(i.wrapping_sub(1)) != Some(&b'\\') =>
-                    {
-                        in_string = !in_string;
-                    }
-                    '[' if !in_string => {
-                        depth += 1;
-                        found_value_start = true;
-                    }
-                    ']' if !in_string => {
-                        depth -= 1;
-                        if depth == 0 && found_value_start {
-                            // Found the end of the array
-                            end_pos = field_start + i + 1;
-                            break;
-                        }
-                    }
-                    'N' if !in_string
-                        && !found_value_start
-                        && remaining[i..].starts_with("None") =>
-                    {
-                        end_pos = field_start + i + 4;
-                        break;
-                    }
-                    'S' if !in_string
-                        && !found_value_start
-                        && remaining[i..].starts_with("Some") =>
-                    {
-                        found_value_start = true;
-                    }
-                    '(' if !in_string && found_value_start => depth += 1,
-                    ')' if !in_string && found_value_start => {
-                        depth -= 1;
-                        if depth == 0 {
-                            end_pos = field_start + i + 1;
-                            break;
-                        }
-                    }
-                    _ => {}
-                }
-            }
-
-            if end_pos == field_start {
-                // Couldn't parse, fall back to full save
-                return self.save(path);
-            }
-
-            // Generate the new value (normalize to handle legacy Some* variants)
-            let new_value = match self.enabled_keyboards.normalize() {
-                EnabledKeyboards::ExplicitNone | EnabledKeyboards::SomeNone => " None".to_string(),
-                EnabledKeyboards::List(keyboards) | EnabledKeyboards::SomeList(keyboards) => {
-                    if keyboards.is_empty() {
-                        " []".to_string()
-                    } else {
-                        let mut result = " [\n".to_string();
-                        for kbd in keyboards {
-                            match kbd {
-                                EnabledKeyboardEntry::Bare(ref pattern) => {
-                                    let comment = comments
-                                        .and_then(|c| c.get(pattern))
-                                        .map(|name| format!(" // {}", name))
-                                        .unwrap_or_default();
-                                    result.push_str(&format!(
-                                        "        \"{}\",{}\n",
-                                        pattern, comment
-                                    ));
-                                }
-                                EnabledKeyboardEntry::Explicit(ref pattern, action) => {
-                                    let action_str = match action {
-                                        EnableDisable::Enable => "Enable",
-                                        EnableDisable::Disable => "Disable",
-                                    };
-                                    let comment = comments
-                                        .and_then(|c| c.get(pattern))
-                                        .map(|name| format!(" // {}", name))
-                                        .unwrap_or_default();
-                                    result.push_str(&format!(
-                                        "        \"{}\": {},{}\n",
-                                        pattern, action_str, comment
-                                    ));
-                                }
+        let old_value = find_top_level_field(&content, "enabled_keyboards")
+            .map(|range| &content[range])
+            .unwrap_or_default();
+
+        // Normalize to handle legacy Some* variants
+        let new_value = match self.enabled_keyboards.normalize() {
+            EnabledKeyboards::ExplicitNone | EnabledKeyboards::SomeNone => " None".to_string(),
+            EnabledKeyboards::List(keyboards) | EnabledKeyboards::SomeList(keyboards) => {
+                if keyboards.is_empty() {
+                    " []".to_string()
+                } else {
+                    let mut result = " [\n".to_string();
+                    for kbd in keyboards {
+                        let pattern = kbd.pattern();
+                        let entry = match kbd {
+                            EnabledKeyboardEntry::Bare(_) => format!("{:?}", pattern),
+                            EnabledKeyboardEntry::Explicit(_, EnableDisable::Enable) => {
+                                format!("{:?}: Enable", pattern)
                             }
-                        }
-                        result.push_str("    ]");
-                        result
+                            EnabledKeyboardEntry::Explicit(_, EnableDisable::Disable) => {
+                                format!("{:?}: Disable", pattern)
+                            }
+                        };
+                        let comment = comments
+                            .and_then(|c| c.get(pattern))
+                            .map(|name| format!(" // {}", name))
+                            .or_else(|| entry_comment(old_value, pattern))
+                            .unwrap_or_default();
+                        result.push_str(&format!("        {},{}\n", entry, comment));
                     }
+                    result.push_str("    ]");
+                    result
                 }
-            };
-            let new_content = format!(
-                "{}{}{}",
-                &content[..field_start],
-                new_value,
-                &content[end_pos..]
-            );
+            }
+        };
 
-            // Write it back
-            std::fs::write(path, new_content)?;
-            Ok(())
-        } else {
-            // enabled_keyboards field not found, fall back to full save
-            self.save(path)
-        }
+        let new_content = replace_top_level_field(&content, "enabled_keyboards", &new_value)?;
+        std::fs::write(path, new_content)?;
+        Ok(())
     }
 
     /// Validate config without printing - returns errors as a Vec<String>
@@ -1035,7 +2548,7 @@ impl Config {
         let mut extract_socd = |remaps: &HashMap<KeyCode, KeyAction>| {
             let mut pairs = Vec::new();
             for (key, action) in remaps {
-                if let KeyAction::SOCD(this_action, opposing_actions) = action {
+                if let KeyAction::SOCD(this_action, opposing_actions) = action.without_mode() {
                     // Extract KeyCode from Action (only validate Key actions)
                     if let KeyAction::Key(this_key) = this_action.as_ref() {
                         if key != this_key {
@@ -1063,8 +2576,10 @@ impl Config {
                 socd_map.insert(key1, key2);
             }
         }
-        for (key1, key2) in extract_socd(&self.game_mode.remaps) {
-            socd_map.insert(key1, key2);
+        for remaps in self.game_mode.all_remaps() {
+            for (key1, key2) in extract_socd(remaps) {
+                socd_map.insert(key1, key2);
+            }
         }
 
         // Check symmetry
@@ -1128,12 +2643,28 @@ impl Config {
             }
         }
 
+        for layer in self.notifications.layers.keys() {
+            referenced_layers.insert(layer.0.clone());
+        }
+
         for layer_name in &referenced_layers {
             if layer_name != "base" && !self.layers.contains_key(&Layer(layer_name.clone())) {
                 errors.push(format!("Referenced layer not defined: \"{}\"", layer_name));
             }
         }
 
+        // Validation 4: Check schedule rules
+        errors.extend(self.schedule_errors());
+
+        // Validation 5: Check sequences
+        errors.extend(self.sequence_errors());
+
+        // Validation 6: Check hardware layouts
+        errors.extend(self.hardware_layout_errors());
+
+        // Validation 7: Check output keys are keys
+        errors.extend(self.button_output_errors());
+
         if !errors.is_empty() {
             Err(anyhow::anyhow!(
                 "Config validation failed: {}",
@@ -1145,6 +2676,347 @@ impl Config {
     }
 }
 
+/// Length of the comment or string literal starting at byte `i` of RON source, if any
+pub(super) fn ron_skip_len(bytes: &[u8], i: usize) -> Option<usize> {
+    match (bytes[i], bytes.get(i + 1)) {
+        (b'/', Some(b'/')) => Some(
+            bytes[i..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .unwrap_or(bytes.len() - i),
+        ),
+        (b'/', Some(b'*')) => {
+            // Block comments nest in RON
+            let mut depth = 0;
+            let mut j = i;
+            while j + 1 < bytes.len() {
+                match (bytes[j], bytes[j + 1]) {
+                    (b'/', b'*') => {
+                        depth += 1;
+                        j += 2;
+                    }
+                    (b'*', b'/') => {
+                        depth -= 1;
+                        j += 2;
+                        if depth == 0 {
+                            return Some(j - i);
+                        }
+                    }
+                    _ => j += 1,
+                }
+            }
+            Some(bytes.len() - i)
+        }
+        (b'"', _) => {
+            let mut j = i + 1;
+            while j < bytes.len() {
+                match bytes[j] {
+                    b'\\' => j += 2,
+                    b'"' => return Some(j + 1 - i),
+                    _ => j += 1,
+                }
+            }
+            Some(bytes.len() - i)
+        }
+        _ => None,
+    }
+}
+
+/// Byte range of a top-level struct field's value, from just after the colon to the
+/// end of its last token (comments and strings are skipped while scanning)
+fn find_top_level_field(content: &str, field: &str) -> Option<std::ops::Range<usize>> {
+    let bytes = content.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut depth = 0usize;
+    let mut expect_key = false;
+    let mut value: Option<std::ops::Range<usize>> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(len) = ron_skip_len(bytes, i) {
+            if let (Some(range), b'"') = (value.as_mut(), bytes[i]) {
+                range.end = i + len;
+            }
+            i += len;
+            continue;
+        }
+        let b = bytes[i];
+        if b.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if let Some(range) = value.as_mut() {
+            match b {
+                b',' | b')' if depth == 1 => return value,
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            range.end = i + 1;
+            i += 1;
+            continue;
+        }
+        match b {
+            b'(' | b'[' | b'{' => {
+                depth += 1;
+                expect_key = depth == 1;
+            }
+            b')' | b']' | b'}' => {
+                depth = depth.saturating_sub(1);
+                expect_key = false;
+            }
+            b',' => expect_key = depth == 1,
+            _ if expect_key && content[i..].starts_with(field) => {
+                let after = i + field.len();
+                let rest = content[after..].trim_start();
+                if bytes.get(after).is_some_and(|&b| !is_ident(b)) && rest.starts_with(':') {
+                    let value_start = content.len() - rest.len() + 1;
+                    value = Some(value_start..value_start);
+                    i = value_start;
+                    continue;
+                }
+                expect_key = false;
+            }
+            _ => expect_key = false,
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Byte offset of the closing paren of the top-level struct
+fn top_level_close(content: &str) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(len) = ron_skip_len(bytes, i) {
+            i += len;
+            continue;
+        }
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && bytes[i] == b')' {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Replace (or add) a top-level field's value without touching the rest of the file
+///
+/// `value` is inserted verbatim after the colon. A missing field is appended before
+/// the struct's closing paren.
+fn replace_top_level_field(content: &str, field: &str, value: &str) -> anyhow::Result<String> {
+    if let Some(range) = find_top_level_field(content, field) {
+        return Ok(format!(
+            "{}{}{}",
+            &content[..range.start],
+            value,
+            &content[range.end..]
+        ));
+    }
+
+    let close = top_level_close(content)
+        .ok_or_else(|| anyhow::anyhow!("Could not find the end of the config to add {}", field))?;
+    // Last token before the closing paren, ignoring whitespace and comments
+    let bytes = content.as_bytes();
+    let mut last: Option<(u8, usize)> = None;
+    let mut i = 0;
+    while i < close {
+        if let Some(len) = ron_skip_len(bytes, i) {
+            if bytes[i] == b'"' {
+                last = Some((b'"', i + len));
+            }
+            i += len;
+            continue;
+        }
+        if !bytes[i].is_ascii_whitespace() {
+            last = Some((bytes[i], i + 1));
+        }
+        i += 1;
+    }
+    let (separator, insert_at) = match last {
+        Some((b',' | b'(', end)) => ("", end),
+        Some((_, end)) => (",", end),
+        None => ("", close),
+    };
+    let body_end = content[..close].trim_end().len();
+    Ok(format!(
+        "{}{}{}\n    {}:{},\n{}",
+        &content[..insert_at],
+        separator,
+        &content[insert_at..body_end],
+        field,
+        value,
+        &content[close..]
+    ))
+}
+
+/// Whether the config text sets a top-level field
+pub(super) fn has_top_level_field(content: &str, field: &str) -> bool {
+    find_top_level_field(content, field).is_some()
+}
+
+/// Add a top-level field unless the config already sets it
+pub(super) fn add_top_level_field(
+    content: &str,
+    field: &str,
+    value: &str,
+) -> anyhow::Result<String> {
+    if has_top_level_field(content, field) {
+        return Ok(content.to_string());
+    }
+    replace_top_level_field(content, field, value)
+}
+
+/// Set entries of a top-level map field (e.g. `remaps`) without touching the rest of the file
+///
+/// Entries whose key is already in the map get the new value in place; the others are
+/// appended before the closing brace. Returns the new text and the keys that were replaced.
+pub(super) fn set_map_entries(
+    content: &str,
+    field: &str,
+    entries: &[(String, String)],
+) -> anyhow::Result<(String, Vec<String>)> {
+    let Some(range) = find_top_level_field(content, field) else {
+        let mut value = String::from(" {\n");
+        for (key, entry) in entries {
+            value.push_str(&format!("        {}: {},\n", key, entry));
+        }
+        value.push_str("    }");
+        return Ok((replace_top_level_field(content, field, &value)?, Vec::new()));
+    };
+    let bytes = content.as_bytes();
+    let open = range.start + content[range.clone()].find('{').unwrap_or(usize::MAX);
+    if open >= range.end || !content[range.start..open].trim().is_empty() {
+        anyhow::bail!("{} is not a map", field);
+    }
+    let close = range.end - 1;
+
+    // Top-level entries of the map: key text and the value's byte range
+    let mut existing: Vec<(String, std::ops::Range<usize>)> = Vec::new();
+    let mut depth = 0usize;
+    let mut entry_start: Option<usize> = None;
+    let mut value_start: Option<usize> = None;
+    let mut last_token = open;
+    let mut i = open + 1;
+    while i < close {
+        if let Some(len) = ron_skip_len(bytes, i) {
+            if bytes[i] == b'"' {
+                entry_start.get_or_insert(i);
+                last_token = i + len - 1;
+            }
+            i += len;
+            continue;
+        }
+        let b = bytes[i];
+        if !b.is_ascii_whitespace() {
+            if depth == 0 {
+                match b {
+                    b',' => {
+                        if let (Some(key), Some(value)) = (entry_start.take(), value_start.take()) {
+                            existing.push((content[key..value - 1].trim().to_string(), value..i));
+                        }
+                    }
+                    b':' if value_start.is_none() => value_start = Some(i + 1),
+                    _ => {
+                        entry_start.get_or_insert(i);
+                    }
+                }
+            }
+            match b {
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            last_token = i;
+        }
+        i += 1;
+    }
+    if let (Some(key), Some(value)) = (entry_start, value_start) {
+        existing.push((
+            content[key..value - 1].trim().to_string(),
+            value..last_token + 1,
+        ));
+    }
+
+    let mut replaced = Vec::new();
+    let mut edits: Vec<(std::ops::Range<usize>, String)> = Vec::new();
+    let mut added = String::new();
+    let line_start = content[..close].rfind('\n').map_or(0, |n| n + 1);
+    let close_indent: String = content[line_start..close]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+    for (key, value) in entries {
+        if let Some((_, range)) = existing.iter().find(|(k, _)| k == key) {
+            edits.push((range.clone(), format!(" {}", value)));
+            replaced.push(key.clone());
+        } else {
+            added.push_str(&format!("\n{}    {}: {},", close_indent, key, value));
+        }
+    }
+    if !added.is_empty() {
+        let separator = if matches!(bytes[last_token], b',' | b'{') {
+            ""
+        } else {
+            ","
+        };
+        // Keep comments after the last entry where they are
+        let body_end = content[..close].trim_end().len().max(last_token + 1);
+        if !content[body_end..close].contains('\n') {
+            added.push_str(&format!("\n{}", close_indent));
+        }
+        edits.push((
+            last_token + 1..body_end,
+            format!(
+                "{}{}{}",
+                separator,
+                &content[last_token + 1..body_end],
+                added
+            ),
+        ));
+    }
+
+    let mut result = content.to_string();
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    for (range, text) in edits {
+        result.replace_range(range, &text);
+    }
+    Ok((result, replaced))
+}
+
+/// Trailing `//` comment on the line of an existing enabled_keyboards entry
+fn entry_comment(old_value: &str, pattern: &str) -> Option<String> {
+    let quoted = format!("{:?}", pattern);
+    old_value.lines().find_map(|line| {
+        let bytes = line.as_bytes();
+        let mut has_entry = false;
+        let mut i = 0;
+        while i < bytes.len() {
+            match ron_skip_len(bytes, i) {
+                Some(len) if bytes[i] == b'"' => {
+                    has_entry |= line[i..i + len] == quoted;
+                    i += len;
+                }
+                Some(_) if bytes[i + 1] == b'/' => {
+                    return has_entry.then(|| format!(" {}", &line[i..]));
+                }
+                Some(len) => i += len,
+                None => i += 1,
+            }
+        }
+        None
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1193,7 +3065,7 @@ mod tests {
         assert_eq!(Config::preprocess_config(input), input);
 
         // SOCD action - KC inside function calls should be wrapped
-        let input = "KC_W: SOCD(KC_W, [KC_S]),";
+        let input = "KC_W: SOCD(Key(KC_W), [Key(KC_S)]),";
         let expected = "KC_W: SOCD(Key(KC_W), [Key(KC_S)]),";
         assert_eq!(Config::preprocess_config(input), expected);
 
@@ -1201,4 +3073,432 @@ mod tests {
         let input = r#"KC_F1: CMD("/usr/bin/test"),"#;
         assert_eq!(Config::preprocess_config(input), input);
     }
+
+    #[test]
+    fn test_hooks_parse() {
+        let input = r#"(
+            hooks: (
+                on_layer_change: Some("notify-send $KEYMUX_LAYER"),
+            ),
+        )"#;
+        let config: Config = ron::from_str(&Config::preprocess_config(input)).unwrap();
+        assert_eq!(
+            config.hooks.on_layer_change.as_deref(),
+            Some("notify-send $KEYMUX_LAYER")
+        );
+        assert!(config.hooks.on_game_mode.is_none());
+    }
+
+    #[test]
+    fn test_split_keyboards_parse() {
+        let config = Config::parse(r#"(split_keyboards: [("Ergo Left", "4653:0001")])"#).unwrap();
+        let right = KeyboardId::new("4653:0001@usb-2".to_string());
+        assert_eq!(config.split_primary_of(&right, "Ergo"), Some("Ergo Left"));
+        let left = KeyboardId::new("4653:0002".to_string());
+        assert_eq!(config.split_primary_of(&left, "Ergo Left"), None);
+    }
+
+    #[test]
+    fn test_keyboard_groups() {
+        let config = Config::parse(
+            r#"(keyboard_groups: {
+                "desk": ["Keychron", "1189:8890:0100:0003"],
+                "travel": ["Keychron"],
+            })"#,
+        )
+        .unwrap();
+        let macropad = KeyboardId::new("1189:8890:0100:0003@usb-3".to_string());
+        assert_eq!(config.keyboard_group(&macropad, "Macropad"), Some("desk"));
+        let main = KeyboardId::new("3434:0361".to_string());
+        assert_eq!(config.keyboard_group(&main, "Keychron Q1"), Some("desk"));
+        assert_eq!(config.keyboard_group(&main, "Laptop"), None);
+    }
+
+    #[test]
+    fn test_notification_preferences() {
+        let defaults = Config::parse("()").unwrap().notifications;
+        assert!(defaults.wants(NotificationKind::Reload));
+        assert!(defaults.wants(NotificationKind::Error));
+        assert!(!defaults.wants(NotificationKind::KeyboardConnect));
+        assert_eq!(defaults.urgency(NotificationKind::Error), Urgency::Critical);
+
+        let config = Config::parse(
+            r#"(notifications: (
+                reload: false,
+                keyboard_connect: true,
+                urgency: { error: normal, keyboard_connect: critical },
+                sounds: false,
+            ))"#,
+        )
+        .unwrap();
+        let notifications = config.notifications;
+        assert!(!notifications.wants(NotificationKind::Reload));
+        assert!(notifications.wants(NotificationKind::KeyboardConnect));
+        assert_eq!(
+            notifications.urgency(NotificationKind::Error),
+            Urgency::Normal
+        );
+        assert_eq!(
+            notifications.urgency(NotificationKind::Reload),
+            Urgency::Normal
+        );
+        assert!(!notifications.sounds);
+    }
+
+    #[test]
+    fn test_per_keyboard_connect_hooks() {
+        let input = r#"(
+            per_keyboard_overrides: {
+                "3434:0361": (on_connect: Some("keymux disable AT --quiet")),
+            },
+        )"#;
+        let config = Config::parse(input).unwrap();
+        let keyboard = config.keyboard_override("3434:0361@usb-1").unwrap();
+        assert_eq!(
+            keyboard.on_connect.as_deref(),
+            Some("keymux disable AT --quiet")
+        );
+        assert!(keyboard.on_disconnect.is_none());
+        assert!(config.keyboard_override("1234:5678").is_none());
+    }
+
+    #[test]
+    fn test_per_keyboard_timing_overrides() {
+        let input = r#"(
+            tapping_term_ms: 130,
+            double_tap_window_ms: Some(250),
+            mt_config: (chord_detection_window_ms: 50),
+            output_pacing: (tap_hold_ms: 10),
+            per_keyboard_overrides: {
+                "1234:5678": (
+                    tapping_term_ms: Some(180),
+                    double_tap_window_ms: Some(300),
+                    roll_detection_window_ms: Some(90),
+                    output_pacing: Some((tap_hold_ms: 0, event_gap_ms: 0)),
+                ),
+            },
+        )"#;
+        let config: Config = ron::from_str(&Config::preprocess_config(input)).unwrap();
+
+        let low_profile = config.for_keyboard("1234:5678@1-2");
+        assert_eq!(low_profile.tapping_term_ms, 180);
+        assert_eq!(low_profile.double_tap_window_ms, Some(300));
+        assert_eq!(low_profile.mt_config.roll_detection_window_ms, 90);
+        // Fields not overridden keep the global values
+        assert_eq!(low_profile.mt_config.chord_detection_window_ms, 50);
+        assert_eq!(low_profile.output_pacing.tap_hold_ms, 0);
+
+        let laptop = config.for_keyboard("abcd:ef01");
+        assert_eq!(laptop.tapping_term_ms, 130);
+        assert_eq!(laptop.double_tap_window_ms, Some(250));
+        assert_eq!(laptop.output_pacing.tap_hold_ms, 10);
+        assert_eq!(laptop.output_pacing.event_gap_ms, 2);
+    }
+
+    #[test]
+    fn test_window_layer_rules() {
+        let input = r#"(
+            window_layers: [
+                (app_id: Some("^(kitty|foot)$"), layer: "vim"),
+                (title: Some("(?i)spreadsheet"), layer: "numpad"),
+            ],
+        )"#;
+        let config: Config = ron::from_str(&Config::preprocess_config(input)).unwrap();
+        let layer_for = |app_id, title| {
+            config
+                .window_layers
+                .iter()
+                .find(|rule| rule.matches(app_id, title))
+                .map(|rule| rule.layer.0.as_str())
+        };
+        assert_eq!(layer_for(Some("kitty"), Some("zsh")), Some("vim"));
+        assert_eq!(layer_for(Some("kitty-old"), None), None);
+        assert_eq!(
+            layer_for(Some("libreoffice"), Some("Budget Spreadsheet")),
+            Some("numpad")
+        );
+        assert_eq!(layer_for(None, None), None);
+    }
+
+    #[test]
+    fn test_output_keys() {
+        let input = r#"(
+            remaps: {
+                KC_CAPS: MT(KC_ESC, KC_LCTL),
+                KC_F: Turbo(KC_X, 20),
+            },
+            layers: {
+                "nav": (remaps: { KC_H: KC_LEFT, KC_J: Transparent }),
+                "symbols": (remaps: { KC_A: KC_1 }, with_mods: [KC_RALT]),
+            },
+        )"#;
+        let config: Config = ron::from_str(&Config::preprocess_config(input)).unwrap();
+        let keys = config.output_keys();
+        for key in [
+            KeyCode::KC_ESC,
+            KeyCode::KC_LCTL,
+            KeyCode::KC_X,
+            KeyCode::KC_LEFT,
+            KeyCode::KC_J,
+            KeyCode::KC_RALT,
+        ] {
+            assert!(keys.contains(&key), "{:?} missing", key);
+        }
+        assert!(!keys.contains(&KeyCode::KC_CAPS));
+        assert!(!config.has_scripts());
+    }
+
+    #[test]
+    fn test_schedule_rules() {
+        let input = r#"(
+            profiles: {
+                "work": (remaps: Some({ KC_CAPS: KC_ESC })),
+            },
+            schedule: [
+                (profile: "work", from: "09:00", to: "17:30", days: ["Mon", "Tue", "Wed", "Thu", "Fri"]),
+                (profile: "night", from: "22:00", to: "06:00"),
+            ],
+        )"#;
+        let config: Config = ron::from_str(&Config::preprocess_config(input)).unwrap();
+        for rule in &config.schedule {
+            rule.validate().unwrap();
+        }
+
+        // Monday 10:00 is work, Saturday 10:00 isn't
+        assert_eq!(config.scheduled_profile(0, 600), Some("work"));
+        assert_eq!(config.scheduled_profile(5, 600), None);
+        assert_eq!(config.scheduled_profile(0, 17 * 60 + 30), None);
+        // Wrapping range covers both sides of midnight
+        assert_eq!(config.scheduled_profile(6, 23 * 60), Some("night"));
+        assert_eq!(config.scheduled_profile(0, 5 * 60), Some("night"));
+
+        let work = config.with_profile("work");
+        assert_eq!(
+            work.remaps.get(&KeyCode::KC_CAPS),
+            Some(&KeyAction::Key(KeyCode::KC_ESC))
+        );
+        assert_eq!(config.with_profile("default"), config);
+    }
+
+    #[test]
+    fn test_quick_tap_term_parse() {
+        let input = r#"(
+            mt_config: (
+                quick_tap_term_ms: 120,
+                quick_tap_term_per_key: { KC_SPC: 0, KC_F: 200 },
+            ),
+        )"#;
+        let config: Config = ron::from_str(&Config::preprocess_config(input)).unwrap();
+        assert_eq!(config.mt_config.quick_tap_term_ms, 120);
+        assert_eq!(
+            config.mt_config.quick_tap_term_per_key.get(&KeyCode::KC_F),
+            Some(&200)
+        );
+        assert_eq!(
+            config
+                .mt_config
+                .quick_tap_term_per_key
+                .get(&KeyCode::KC_SPC),
+            Some(&0)
+        );
+    }
+
+    #[test]
+    fn test_ignored_keys_parse() {
+        let input = r#"(
+            ignored_keys: [KC_F13],
+            per_keyboard_overrides: {
+                "1234:5678": (ignored_keys: Some([KC_SCRL, Key(KC_INS)])),
+            },
+        )"#;
+        let config = Config::parse(input).unwrap();
+        assert_eq!(config.ignored_keys, vec![KeyCode::KC_F13]);
+        assert_eq!(
+            config.for_keyboard("1234:5678").ignored_keys,
+            vec![KeyCode::KC_F13, KeyCode::KC_SCRL, KeyCode::KC_INS]
+        );
+        assert!(Config::parse("(ignored_keys: [MO(\"nav\")])").is_err());
+    }
+
+    #[test]
+    fn test_scancodes_parse() {
+        let input = r#"(
+            scancodes: { 0xc0223: KC_F20 },
+            remaps: { KC_F20: KC_MUTE },
+        )"#;
+        let config = Config::parse(input).unwrap();
+        assert_eq!(config.scancodes.get(&0xc0223), Some(&KeyCode::KC_F20));
+
+        // Keys without a name are written by code, as remap keys and as values
+        let config =
+            Config::parse("(remaps: { KC_RAW(0x1d2): KC_RAW(0x1d3), KC_A: KC_RAW(30) })").unwrap();
+        assert_eq!(
+            config.remaps.get(&KeyCode::KC_RAW(0x1d2)),
+            Some(&KeyAction::Key(KeyCode::KC_RAW(0x1d3)))
+        );
+        assert_eq!(
+            config.remaps.get(&KeyCode::KC_A),
+            Some(&KeyAction::Key(KeyCode::KC_A))
+        );
+
+        // `Raw(code)` sends the same, and buttons can't come out of a keyboard
+        let config =
+            Config::parse("(remaps: { KC_F13: Raw(0x1d3), KC_F14: MT(Raw(0x2c0), KC_LCTL) })")
+                .unwrap();
+        assert_eq!(
+            config.remaps.get(&KeyCode::KC_F13),
+            Some(&KeyAction::Key(KeyCode::KC_RAW(0x1d3)))
+        );
+        assert_eq!(config.button_output_errors().len(), 1);
+        assert!(Config::parse("(remaps: { KC_F13: Raw(0x300) })").is_err());
+    }
+
+    #[test]
+    fn test_replace_top_level_field_keeps_comments() {
+        let input = r#"(
+    // enabled_keyboards: None, (commented out, not the real field)
+    keyboard_overrides: { "x": ( enabled_keyboards: [] ) },
+    enabled_keyboards: [
+        "*",
+        "1234": Disable, // Laptop ] keyboard
+    ], /* keep me */
+    tapping_term_ms: 200, // trailing
+)"#;
+        let range = find_top_level_field(input, "enabled_keyboards").unwrap();
+        assert!(input[range.clone()].trim_start().starts_with('['));
+        assert!(input[range.clone()].ends_with(']'));
+        assert_eq!(
+            entry_comment(&input[range], "1234").as_deref(),
+            Some(" // Laptop ] keyboard")
+        );
+
+        let output = replace_top_level_field(input, "enabled_keyboards", " None").unwrap();
+        assert!(output.contains("    enabled_keyboards: None, /* keep me */"));
+        assert!(output.contains("// enabled_keyboards: None, (commented out"));
+        assert!(output.contains("tapping_term_ms: 200, // trailing"));
+
+        let input = "(\n    // settings\n    tapping_term_ms: 200 // no comma\n)";
+        let output = replace_top_level_field(input, "enabled_keyboards", " [\"*\"]").unwrap();
+        assert_eq!(
+            output,
+            "(\n    // settings\n    tapping_term_ms: 200, // no comma\n    enabled_keyboards: [\"*\"],\n)"
+        );
+    }
+
+    #[test]
+    fn test_hotplug_filters() {
+        let config =
+            Config::parse(r#"(hotplug: (debounce_ms: 1000, ignore_paths: ["/usb3/3-2/"]))"#)
+                .unwrap();
+        let event = |action: &str, devpath: &str| {
+            format!("UDEV  [1234.567890] {action:<8} {devpath} (input)")
+        };
+        let keyboard = "/devices/pci0000:00/0000:00:14.0/usb1/1-4/1-4:1.0/input/input27/event5";
+        let hub = "/devices/pci0000:00/0000:00:14.0/usb3/3-2/3-2.1/input/input30/event9";
+
+        assert!(config.hotplug.triggers(&event("add", keyboard)));
+        assert!(config.hotplug.triggers(&event("remove", keyboard)));
+        assert!(!config.hotplug.triggers(&event("change", keyboard)));
+        assert!(!config.hotplug.triggers(&event("add", hub)));
+
+        let mut merged = HotplugConfig::default();
+        merged.merge(&config.hotplug);
+        assert_eq!(merged.debounce_ms, 1000);
+        assert_eq!(merged.ignore_paths, vec!["/usb3/3-2/".to_string()]);
+    }
+
+    #[test]
+    fn test_hardware_layout_moves_socd_to_positions() {
+        let config = Config::parse(
+            r#"(
+                game_mode: (remaps: {
+                    KC_W: SOCD(Key(KC_W), [Key(KC_S)]),
+                    KC_S: SOCD(Key(KC_S), [Key(KC_W)]),
+                }),
+                per_keyboard_overrides: {
+                    "aaaa:bbbb:0001:0003": (
+                        hardware_layout: Some("dvorak"),
+                        hardware_swaps: Some({ KC_LCTL: KC_CAPS }),
+                    ),
+                },
+            )"#,
+        )
+        .unwrap();
+        assert!(config.hardware_layout_errors().is_empty());
+
+        // Dvorak boards send , and O from the W and S positions
+        let dvorak = config.for_keyboard("aaaa:bbbb:0001:0003");
+        let positions = dvorak.key_positions();
+        assert_eq!(positions.get(&KeyCode::KC_COMM), Some(&KeyCode::KC_W));
+        assert_eq!(positions.get(&KeyCode::KC_LCTL), Some(&KeyCode::KC_CAPS));
+        assert_eq!(
+            dvorak.game_mode.remaps.get(&KeyCode::KC_COMM),
+            Some(&KeyAction::SOCD(
+                Box::new(KeyAction::Key(KeyCode::KC_COMM)),
+                vec![Box::new(KeyAction::Key(KeyCode::KC_O))]
+            ))
+        );
+        assert!(!dvorak.game_mode.remaps.contains_key(&KeyCode::KC_W));
+        assert!(dvorak.validate_silent().is_ok());
+
+        // Other keyboards keep WASD
+        let other = config.for_keyboard("cccc:dddd:0001:0003");
+        assert!(other.key_positions().is_empty());
+        assert!(other.game_mode.remaps.contains_key(&KeyCode::KC_W));
+
+        let typo = Config::parse(r#"(hardware_layout: Some("dvrak"))"#).unwrap();
+        assert_eq!(typo.hardware_layout_errors().len(), 1);
+    }
+
+    #[test]
+    fn test_game_mode_overlays() {
+        let config = Config::parse(
+            r#"(
+                game_mode: (
+                    remaps: { KC_LGUI: KC_NO, KC_Q: KC_ESC },
+                    overlays: {
+                        "fps": (
+                            app_ids: ["^cs2$", "^steam_app_730$"],
+                            remaps: { KC_Q: Turbo(Key(KC_Q), 20) },
+                        ),
+                        "racing": (app_ids: ["^steam_app_"], remaps: { KC_A: KC_LEFT }),
+                    },
+                ),
+            )"#,
+        )
+        .unwrap();
+        let game_mode = &config.game_mode;
+
+        // The first overlay by name wins when several match
+        assert_eq!(game_mode.overlay_for_app(Some("cs2")), Some("fps"));
+        assert_eq!(
+            game_mode.overlay_for_app(Some("steam_app_730")),
+            Some("fps")
+        );
+        assert_eq!(
+            game_mode.overlay_for_app(Some("steam_app_1")),
+            Some("racing")
+        );
+        assert_eq!(game_mode.overlay_for_app(Some("firefox")), None);
+        assert_eq!(game_mode.overlay_for_app(None), None);
+
+        let fps = game_mode.remaps_with_overlay(Some("fps"));
+        assert_eq!(
+            fps.get(&KeyCode::KC_Q),
+            Some(&KeyAction::Turbo(
+                Box::new(KeyAction::Key(KeyCode::KC_Q)),
+                20
+            ))
+        );
+        assert_eq!(
+            fps.get(&KeyCode::KC_LGUI),
+            Some(&KeyAction::Key(KeyCode::KC_NO))
+        );
+        assert!(!fps.contains_key(&KeyCode::KC_A));
+        assert_eq!(
+            game_mode.remaps_with_overlay(None),
+            game_mode.remaps_with_overlay(Some("missing"))
+        );
+        assert!(config.validate_silent().is_ok());
+    }
 }
@@ -20,7 +20,7 @@ pub struct ConfigManager {
 impl ConfigManager {
     /// Create a new config manager
     pub fn new(config_path: PathBuf) -> Result<Self> {
-        let config = Config::load(&config_path)
+        let config = Config::load_with_state(&config_path)
             .with_context(|| format!("Failed to load config from {:?}", config_path))?;
 
         Ok(Self {
@@ -34,6 +34,14 @@ impl ConfigManager {
         self.config.read().await.clone()
     }
 
+    /// Re-read the config from disk, keeping the current one if loading fails
+    pub async fn reload(&self) -> Result<()> {
+        let config = Config::load_with_state(&self.config_path)
+            .with_context(|| format!("Failed to load config from {:?}", self.config_path))?;
+        *self.config.write().await = config;
+        Ok(())
+    }
+
     /// Get the config file path
     pub fn get_config_path(&self) -> PathBuf {
         self.config_path.clone()
@@ -0,0 +1,196 @@
+/// Human-readable summary of what changed between two configs
+///
+/// Logged by the daemon on reload so an edit can be confirmed at a glance. Remaps
+/// are listed key by key in config notation; other settings are compared field by
+/// field, going one level into blocks like `mt_config`.
+use super::config::{Config, KeyAction};
+use crate::keycode::KeyCode;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+/// Fields listed key by key instead of compared as values
+const REMAP_FIELDS: [&str; 3] = ["remaps", "layers", "game_mode"];
+
+/// One line per change: `+` added, `-` removed, `~` changed
+pub fn diff_configs(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    diff_remaps("remap", &old.remaps, &new.remaps, &mut changes);
+
+    let mut layers: Vec<_> = old.layers.keys().chain(new.layers.keys()).collect();
+    layers.sort_by_key(|layer| &layer.0);
+    layers.dedup();
+    for layer in layers {
+        match (old.layers.get(layer), new.layers.get(layer)) {
+            (None, Some(_)) => changes.push(format!("+ layer \"{}\"", layer.0)),
+            (Some(_), None) => changes.push(format!("- layer \"{}\"", layer.0)),
+            (Some(before), Some(after)) => {
+                diff_remaps(
+                    &format!("layer \"{}\"", layer.0),
+                    &before.remaps,
+                    &after.remaps,
+                    &mut changes,
+                );
+                if before.with_mods != after.with_mods {
+                    changes.push(format!(
+                        "~ layer \"{}\" with_mods: {:?} → {:?}",
+                        layer.0, before.with_mods, after.with_mods
+                    ));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    diff_remaps(
+        "game_mode remap",
+        &old.game_mode.remaps,
+        &new.game_mode.remaps,
+        &mut changes,
+    );
+    if let (Ok(before), Ok(after)) = (
+        serde_json::to_value(&old.game_mode),
+        serde_json::to_value(&new.game_mode),
+    ) {
+        diff_values("game_mode", &before, &after, 1, &mut changes);
+    }
+
+    // Everything else, generically
+    if let (Ok(Value::Object(before)), Ok(Value::Object(after))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    {
+        let fields: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+        for field in fields {
+            if REMAP_FIELDS.contains(&field.as_str()) {
+                continue;
+            }
+            let null = Value::Null;
+            let before = before.get(field).unwrap_or(&null);
+            let after = after.get(field).unwrap_or(&null);
+            diff_values(field, before, after, 1, &mut changes);
+        }
+    }
+
+    changes
+}
+
+fn diff_remaps(
+    label: &str,
+    old: &HashMap<KeyCode, KeyAction>,
+    new: &HashMap<KeyCode, KeyAction>,
+    changes: &mut Vec<String>,
+) {
+    let mut keys: Vec<_> = old.keys().chain(new.keys()).collect();
+    keys.sort_by_key(|key| key.to_string());
+    keys.dedup();
+    for key in keys {
+        match (old.get(key), new.get(key)) {
+            (None, Some(action)) => {
+                changes.push(format!("+ {} {}: {}", label, key, describe_action(action)))
+            }
+            (Some(_), None) => changes.push(format!("- {} {}", label, key)),
+            (Some(before), Some(after)) if before != after => changes.push(format!(
+                "~ {} {}: {} → {}",
+                label,
+                key,
+                describe_action(before),
+                describe_action(after)
+            )),
+            _ => {}
+        }
+    }
+}
+
+/// An action roughly as written in the config (`MT(KC_F, KC_LSFT)`, `TG("nav")`)
+fn describe_action(action: &KeyAction) -> String {
+    if let KeyAction::Key(key) = action {
+        return key.to_string();
+    }
+    let debug = format!("{:?}", action);
+    regex::Regex::new(r#"(?:Layer|Key)\(("[^"]*"|KC_RAW\(\d+\)|KC_\w+)\)"#)
+        .map(|re| re.replace_all(&debug, "$1").into_owned())
+        .unwrap_or(debug)
+}
+
+/// Compare two values, descending `depth` more levels into objects
+fn diff_values(path: &str, old: &Value, new: &Value, depth: usize, changes: &mut Vec<String>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (Value::Object(before), Value::Object(after)) if depth > 0 => {
+            let fields: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+            for field in fields {
+                let path = format!("{}.{}", path, field);
+                match (before.get(field), after.get(field)) {
+                    (Some(before), Some(after)) => {
+                        diff_values(&path, before, after, depth - 1, changes);
+                    }
+                    (None, Some(_)) => changes.push(format!("+ {}", path)),
+                    (Some(_), None) => changes.push(format!("- {}", path)),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if is_scalar(old) && is_scalar(new) => changes.push(format!(
+            "~ {}: {} → {}",
+            path,
+            describe_value(old),
+            describe_value(new)
+        )),
+        _ => changes.push(format!("~ {} changed", path)),
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+fn describe_value(value: &Value) -> String {
+    match value {
+        Value::Null => "none".to_string(),
+        Value::String(s) => format!("\"{}\"", s),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lists_remaps_layers_and_timings() {
+        let old = Config::parse(
+            r#"(
+                tapping_term_ms: 200,
+                remaps: { KC_CAPS: KC_ESC, KC_F: MT(KC_F, KC_LSFT) },
+                layers: { "nav": (remaps: { KC_H: KC_LEFT }) },
+            )"#,
+        )
+        .unwrap();
+        let new = Config::parse(
+            r#"(
+                tapping_term_ms: 180,
+                remaps: { KC_F: MT(KC_F, KC_LCTL), KC_SCRL: TG("nav") },
+                layers: {
+                    "nav": (remaps: { KC_H: KC_LEFT, KC_L: KC_RGHT }),
+                    "num": (remaps: {}),
+                },
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            diff_configs(&old, &new),
+            vec![
+                "- remap KC_CAPS",
+                "~ remap KC_F: MT(KC_F, KC_LSFT) → MT(KC_F, KC_LCTL)",
+                "+ remap KC_SCRL: TG(\"nav\")",
+                "+ layer \"nav\" KC_L: KC_RGHT",
+                "+ layer \"num\"",
+                "~ tapping_term_ms: 200 → 180",
+            ]
+        );
+        assert!(diff_configs(&new, &new).is_empty());
+    }
+}
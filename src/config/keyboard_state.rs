@@ -0,0 +1,117 @@
+/// Enabled-keyboard state managed by `keymux toggle/enable/disable`
+///
+/// Toggling used to rewrite `enabled_keyboards` in config.ron, which clobbered hand
+/// edits and triggered a full hot reload. The toggle commands now write this file
+/// (through the daemon when it's running) next to config.ron instead; while it
+/// exists it takes precedence over the config's `enabled_keyboards`. Delete it to
+/// go back to the config's list.
+use crate::config::{Config, EnableDisable, EnabledKeyboardEntry, EnabledKeyboards};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// State file living next to a config file
+pub fn path_for_config(config_path: &Path) -> PathBuf {
+    config_path.with_file_name("enabled_keyboards.json")
+}
+
+/// Flatten enabled_keyboards into (pattern, action) pairs; an empty list disables all
+pub fn to_entries(enabled: &EnabledKeyboards) -> Vec<(String, EnableDisable)> {
+    enabled
+        .entries()
+        .unwrap_or_default()
+        .iter()
+        .map(|entry| (entry.pattern().to_string(), entry.action()))
+        .collect()
+}
+
+/// Rebuild enabled_keyboards from (pattern, action) pairs
+pub fn from_entries(entries: &[(String, EnableDisable)]) -> EnabledKeyboards {
+    if entries.is_empty() {
+        return EnabledKeyboards::ExplicitNone;
+    }
+    EnabledKeyboards::List(
+        entries
+            .iter()
+            .map(|(pattern, action)| match action {
+                EnableDisable::Enable => EnabledKeyboardEntry::Bare(pattern.clone()),
+                EnableDisable::Disable => {
+                    EnabledKeyboardEntry::Explicit(pattern.clone(), EnableDisable::Disable)
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Entries with `pattern` set to `action`, replacing an earlier entry for it (last match wins)
+pub fn with_entry(
+    entries: &[(String, EnableDisable)],
+    pattern: &str,
+    action: EnableDisable,
+) -> Vec<(String, EnableDisable)> {
+    entries
+        .iter()
+        .filter(|(existing, _)| existing != pattern)
+        .cloned()
+        .chain(std::iter::once((pattern.to_string(), action)))
+        .collect()
+}
+
+/// Read the state for a config, if any has been saved
+pub fn load(config_path: &Path) -> Option<EnabledKeyboards> {
+    let path = path_for_config(config_path);
+    let content = std::fs::read_to_string(&path).ok()?;
+    match serde_json::from_str::<Vec<(String, EnableDisable)>>(&content) {
+        Ok(entries) => Some(from_entries(&entries)),
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable keyboard state {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Write the state for a config as `uid`, so the user can still edit or remove it
+///
+/// The daemon calls this as root on a path inside the user's home, so the write goes
+/// through `user_fs` rather than following whatever the user put there.
+pub fn save(config_path: &Path, uid: u32, entries: &[(String, EnableDisable)]) -> Result<()> {
+    let path = path_for_config(config_path);
+    if let Some(dir) = path.parent() {
+        crate::user_fs::create_dir_all(uid, dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let content = serde_json::to_string_pretty(entries)?;
+    crate::user_fs::replace(uid, &path, content.as_bytes(), None)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+impl Config {
+    /// Load a config with the saved enabled-keyboard state applied on top
+    #[allow(clippy::missing_errors_doc)]
+    pub fn load_with_state(path: &Path) -> Result<Self> {
+        let mut config = Self::load(path)?;
+        if let Some(enabled) = load(path) {
+            config.enabled_keyboards = enabled;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_entry_replaces_pattern_at_the_end() {
+        let entries = vec![
+            ("Laptop".to_string(), EnableDisable::Disable),
+            ("*".to_string(), EnableDisable::Enable),
+        ];
+        assert_eq!(
+            with_entry(&entries, "Laptop", EnableDisable::Enable),
+            vec![
+                ("*".to_string(), EnableDisable::Enable),
+                ("Laptop".to_string(), EnableDisable::Enable),
+            ]
+        );
+    }
+}
@@ -1,10 +1,15 @@
 pub mod config;
 pub mod config_manager;
+pub mod diff;
+pub mod keyboard_state;
+pub mod preset;
 pub mod validator;
 
 pub use config::{
-    Config, EnableDisable, EnabledKeyboardEntry, EnabledKeyboards, GameMode, KeyAction, Layer,
-    LayerConfig, MtConfig,
+    BehaviorTest, Condition, Config, EnableDisable, EnabledKeyboardEntry, EnabledKeyboards,
+    GameMode, GameModeOverlay, HooksConfig, HotplugConfig, KeyAction, Layer, LayerConfig, MtConfig,
+    NotificationKind, NotificationsConfig, OutputPacing, SequenceRule, SnippetsConfig, SocdMode,
+    SocdPreset, Unmapped, Urgency, WindowLayerRule,
 };
 pub use config_manager::ConfigManager;
 pub use validator::validate_config;
@@ -0,0 +1,381 @@
+//! Generated config blocks added by `keymux config preset ...`
+//!
+//! Presets are written into the user's config.ron as text, so comments and formatting
+//! around them survive and the result reads like something written by hand.
+
+use super::config::{add_top_level_field, set_map_entries, Config, KeyAction};
+use crate::keycode::KeyCode;
+use anyhow::Result;
+
+/// Home row of the left and right hand, pinky to index (physical QWERTY positions)
+const LEFT_HOME_ROW: [KeyCode; 4] = [KeyCode::KC_A, KeyCode::KC_S, KeyCode::KC_D, KeyCode::KC_F];
+const RIGHT_HOME_ROW: [KeyCode; 4] = [
+    KeyCode::KC_SCLN,
+    KeyCode::KC_L,
+    KeyCode::KC_K,
+    KeyCode::KC_J,
+];
+
+/// Letters the home row types when keymux itself remaps to Colemak (or Colemak-DH)
+const COLEMAK_LEFT: [KeyCode; 4] = [KeyCode::KC_A, KeyCode::KC_R, KeyCode::KC_S, KeyCode::KC_T];
+const COLEMAK_RIGHT: [KeyCode; 4] = [KeyCode::KC_O, KeyCode::KC_I, KeyCode::KC_E, KeyCode::KC_N];
+
+/// MT options that suit home-row mods, added when the config has no mt_config yet
+const HRM_MT_CONFIG: &str = " (
+        permissive_hold: true,
+        enable_roll_detection: true,
+        enable_chord_detection: true,
+        enable_cross_hand_unwrap: true,
+        quick_tap_term_ms: 150,
+    )";
+
+/// Left- and right-hand modifiers for an order like "gacs" (pinky to index)
+fn parse_mod_order(mods: &str) -> Result<[(KeyCode, KeyCode); 4]> {
+    let mods = mods.to_lowercase();
+    let mut sorted: Vec<char> = mods.chars().collect();
+    sorted.sort_unstable();
+    if sorted != ['a', 'c', 'g', 's'] {
+        anyhow::bail!(
+            "--mods must use each of g, a, c, s once (e.g. gacs or scag), got {:?}",
+            mods
+        );
+    }
+    let mut order = [(KeyCode::KC_NO, KeyCode::KC_NO); 4];
+    for (slot, ch) in order.iter_mut().zip(mods.chars()) {
+        *slot = match ch {
+            'g' => (KeyCode::KC_LGUI, KeyCode::KC_RGUI),
+            'a' => (KeyCode::KC_LALT, KeyCode::KC_RALT),
+            'c' => (KeyCode::KC_LCTL, KeyCode::KC_RCTL),
+            _ => (KeyCode::KC_LSFT, KeyCode::KC_RSFT),
+        };
+    }
+    Ok(order)
+}
+
+/// Home-row-mod remaps: (physical key, MT action) as config text
+///
+/// `layout` names the letters the home row should type: "qwerty" (also right for an
+/// OS-level Colemak layout, which keymux never sees) or "colemak"/"colemak-dh" when the
+/// layout is remapped by keymux itself.
+pub fn home_row_mods(mods: &str, layout: &str) -> Result<Vec<(KeyCode, String)>> {
+    let order = parse_mod_order(mods)?;
+    let (left_taps, right_taps) = match layout.to_lowercase().as_str() {
+        "qwerty" => (LEFT_HOME_ROW, RIGHT_HOME_ROW),
+        "colemak" | "colemak-dh" => (COLEMAK_LEFT, COLEMAK_RIGHT),
+        other => anyhow::bail!("Unknown layout {:?} (expected qwerty or colemak)", other),
+    };
+    let mut remaps = Vec::with_capacity(8);
+    for (i, (left_mod, _)) in order.iter().enumerate() {
+        remaps.push((
+            LEFT_HOME_ROW[i],
+            format!("MT({:?}, {:?})", left_taps[i], left_mod),
+        ));
+    }
+    // Mirrored: the right pinky gets the left pinky's modifier
+    for (i, (_, right_mod)) in order.iter().enumerate().rev() {
+        remaps.push((
+            RIGHT_HOME_ROW[i],
+            format!("MT({:?}, {:?})", right_taps[i], right_mod),
+        ));
+    }
+    Ok(remaps)
+}
+
+/// Add home-row mods to config text, returning the new text and the keys whose remaps
+/// were replaced
+pub fn apply_home_row_mods(
+    content: &str,
+    mods: &str,
+    layout: &str,
+) -> Result<(String, Vec<String>)> {
+    let entries: Vec<(String, String)> = home_row_mods(mods, layout)?
+        .into_iter()
+        .map(|(key, action)| (format!("{:?}", key), action))
+        .collect();
+    let (content, replaced) = set_map_entries(content, "remaps", &entries)?;
+    let content = add_top_level_field(&content, "mt_config", HRM_MT_CONFIG)?;
+    Ok((content, replaced))
+}
+
+/// Physical keys in QWERTY order, one row per string
+const QWERTY_ROWS: [&str; 4] = ["-=", "qwertyuiop[]", "asdfghjkl;'", "zxcvbnm,./"];
+
+/// Alternative layouts: what each key of `QWERTY_ROWS` types
+const LAYOUTS: [(&str, [&str; 4]); 5] = [
+    (
+        "colemak",
+        ["-=", "qwfpgjluy;[]", "arstdhneio'", "zxcvbkm,./"],
+    ),
+    // ANSI angle mod for row-staggered keyboards
+    (
+        "colemak-dh",
+        ["-=", "qwfpbjluy;[]", "arstgmneio'", "xcdvzkh,./"],
+    ),
+    (
+        "colemak-dh-matrix",
+        ["-=", "qwfpbjluy;[]", "arstgmneio'", "zxcdvkh,./"],
+    ),
+    (
+        "dvorak",
+        ["[]", "',.pyfgcrl/=", "aoeuidhtns-", ";qjkxbmwvz"],
+    ),
+    (
+        "workman",
+        ["-=", "qdrwbjfup;[]", "ashtgyneoi'", "zxmcvkl,./"],
+    ),
+];
+
+/// Names accepted by `keymux config preset layout`
+pub fn layout_names() -> Vec<&'static str> {
+    LAYOUTS.iter().map(|(name, _)| *name).collect()
+}
+
+/// Key that types a character on a QWERTY system layout
+fn qwerty_key(ch: char) -> Option<KeyCode> {
+    let key = match ch {
+        'a' => KeyCode::KC_A,
+        'b' => KeyCode::KC_B,
+        'c' => KeyCode::KC_C,
+        'd' => KeyCode::KC_D,
+        'e' => KeyCode::KC_E,
+        'f' => KeyCode::KC_F,
+        'g' => KeyCode::KC_G,
+        'h' => KeyCode::KC_H,
+        'i' => KeyCode::KC_I,
+        'j' => KeyCode::KC_J,
+        'k' => KeyCode::KC_K,
+        'l' => KeyCode::KC_L,
+        'm' => KeyCode::KC_M,
+        'n' => KeyCode::KC_N,
+        'o' => KeyCode::KC_O,
+        'p' => KeyCode::KC_P,
+        'q' => KeyCode::KC_Q,
+        'r' => KeyCode::KC_R,
+        's' => KeyCode::KC_S,
+        't' => KeyCode::KC_T,
+        'u' => KeyCode::KC_U,
+        'v' => KeyCode::KC_V,
+        'w' => KeyCode::KC_W,
+        'x' => KeyCode::KC_X,
+        'y' => KeyCode::KC_Y,
+        'z' => KeyCode::KC_Z,
+        ';' => KeyCode::KC_SCLN,
+        '\'' => KeyCode::KC_QUOT,
+        ',' => KeyCode::KC_COMM,
+        '.' => KeyCode::KC_DOT,
+        '/' => KeyCode::KC_SLSH,
+        '[' => KeyCode::KC_LBRC,
+        ']' => KeyCode::KC_RBRC,
+        '-' => KeyCode::KC_MINS,
+        '=' => KeyCode::KC_EQL,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Remaps that make a QWERTY system type an alternative layout: (physical key, output key)
+pub fn layout_remaps(layout: &str) -> Result<Vec<(KeyCode, KeyCode)>> {
+    let Some((_, rows)) = LAYOUTS.iter().find(|(name, _)| *name == layout) else {
+        anyhow::bail!(
+            "Unknown layout {:?} (expected one of: {})",
+            layout,
+            layout_names().join(", ")
+        );
+    };
+    let mut remaps = Vec::new();
+    for (physical, typed) in QWERTY_ROWS.iter().zip(rows) {
+        for (from, to) in physical.chars().zip(typed.chars()) {
+            if from != to {
+                if let (Some(from), Some(to)) = (qwerty_key(from), qwerty_key(to)) {
+                    remaps.push((from, to));
+                }
+            }
+        }
+    }
+    Ok(remaps)
+}
+
+/// Add an alternative-layout layer to config text, optionally with a base-layer key that
+/// toggles it. Returns the new text and the keys whose remaps were replaced.
+///
+/// Keys the base layer turns into a mod-tap of themselves (home-row mods) stay mod-taps
+/// with the layout's letter as the tap, so the layer doesn't switch them off.
+pub fn apply_layout_layer(
+    content: &str,
+    layout: &str,
+    toggle: Option<KeyCode>,
+) -> Result<(String, Vec<String>)> {
+    let config = Config::parse(content)?;
+    let mut layer = String::from("(\n            remaps: {\n");
+    for (from, to) in layout_remaps(layout)? {
+        let action = match config.remaps.get(&from) {
+            Some(KeyAction::MT(tap, hold)) if **tap == KeyAction::Key(from) => match **hold {
+                KeyAction::Key(hold) => format!("MT({:?}, {:?})", to, hold),
+                _ => format!("{:?}", to),
+            },
+            _ => format!("{:?}", to),
+        };
+        layer.push_str(&format!("                {:?}: {},\n", from, action));
+    }
+    layer.push_str("            },\n        )");
+
+    let name = format!("{:?}", layout);
+    let (content, _) = set_map_entries(content, "layers", &[(name.clone(), layer)])?;
+    match toggle {
+        Some(key) => set_map_entries(
+            &content,
+            "remaps",
+            &[(format!("{:?}", key), format!("TG({})", name))],
+        ),
+        None => Ok((content, Vec::new())),
+    }
+}
+
+/// Name of the layer `keymux config preset numpad` adds
+pub const NUMPAD_LAYER: &str = "numpad";
+
+/// Numpad on the right hand: 789 on UIO, 456 on JKL, 123 on M,. and 0 on /, with the
+/// operators and Enter beside them
+const NUMPAD_REMAPS: [(KeyCode, KeyCode); 14] = [
+    (KeyCode::KC_U, KeyCode::KC_P7),
+    (KeyCode::KC_I, KeyCode::KC_P8),
+    (KeyCode::KC_O, KeyCode::KC_P9),
+    (KeyCode::KC_J, KeyCode::KC_P4),
+    (KeyCode::KC_K, KeyCode::KC_P5),
+    (KeyCode::KC_L, KeyCode::KC_P6),
+    (KeyCode::KC_M, KeyCode::KC_P1),
+    (KeyCode::KC_COMM, KeyCode::KC_P2),
+    (KeyCode::KC_DOT, KeyCode::KC_P3),
+    (KeyCode::KC_SLSH, KeyCode::KC_P0),
+    (KeyCode::KC_P, KeyCode::KC_PMNS),
+    (KeyCode::KC_SCLN, KeyCode::KC_PPLS),
+    (KeyCode::KC_QUOT, KeyCode::KC_PENT),
+    (KeyCode::KC_N, KeyCode::KC_PDOT),
+];
+
+/// Add the numpad layer to config text, optionally with base-layer keys that hold
+/// (`MO`) or toggle (`TG`) it. Returns the new text and the keys whose remaps were
+/// replaced.
+pub fn apply_numpad_layer(
+    content: &str,
+    hold: Option<KeyCode>,
+    toggle: Option<KeyCode>,
+) -> Result<(String, Vec<String>)> {
+    let mut layer = String::from("(\n            remaps: {\n");
+    for (from, to) in NUMPAD_REMAPS {
+        layer.push_str(&format!("                {:?}: {:?},\n", from, to));
+    }
+    layer.push_str("            },\n        )");
+
+    let name = format!("{:?}", NUMPAD_LAYER);
+    let (content, _) = set_map_entries(content, "layers", &[(name.clone(), layer)])?;
+    let mut entries = Vec::new();
+    if let Some(key) = hold {
+        entries.push((format!("{:?}", key), format!("MO({})", name)));
+    }
+    if let Some(key) = toggle {
+        entries.push((format!("{:?}", key), format!("TG({})", name)));
+    }
+    if entries.is_empty() {
+        return Ok((content, Vec::new()));
+    }
+    set_map_entries(&content, "remaps", &entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_home_row_mods_merge_into_remaps() {
+        let input =
+            "(\n    remaps: {\n        KC_CAPS: KC_ESC, // escape\n        KC_A: KC_B,\n    },\n)";
+        let (output, replaced) = apply_home_row_mods(input, "scag", "qwerty").unwrap();
+        assert_eq!(replaced, vec!["KC_A".to_string()]);
+        assert!(output.contains("KC_CAPS: KC_ESC, // escape"));
+
+        let config = Config::parse(&output).unwrap();
+        assert_eq!(config.remaps.len(), 9);
+        let mt = |tap, hold| {
+            KeyAction::MT(
+                Box::new(KeyAction::Key(tap)),
+                Box::new(KeyAction::Key(hold)),
+            )
+        };
+        assert_eq!(
+            config.remaps[&KeyCode::KC_A],
+            mt(KeyCode::KC_A, KeyCode::KC_LSFT)
+        );
+        assert_eq!(
+            config.remaps[&KeyCode::KC_SCLN],
+            mt(KeyCode::KC_SCLN, KeyCode::KC_RSFT)
+        );
+        assert_eq!(
+            config.remaps[&KeyCode::KC_J],
+            mt(KeyCode::KC_J, KeyCode::KC_RGUI)
+        );
+        assert_eq!(config.mt_config.quick_tap_term_ms, 150);
+
+        // A config without remaps gets the field
+        let (output, _) =
+            apply_home_row_mods("(\n    tapping_term_ms: 180,\n)", "gacs", "colemak").unwrap();
+        let config = Config::parse(&output).unwrap();
+        assert_eq!(
+            config.remaps[&KeyCode::KC_D],
+            mt(KeyCode::KC_S, KeyCode::KC_LCTL)
+        );
+
+        assert!(home_row_mods("gacx", "qwerty").is_err());
+    }
+
+    #[test]
+    fn test_layout_layer_keeps_home_row_mods() {
+        let input = "(\n    remaps: {\n        KC_S: MT(KC_S, KC_LALT),\n    },\n)";
+        let (output, _) = apply_layout_layer(input, "colemak-dh", Some(KeyCode::KC_SCRL)).unwrap();
+        let config = Config::parse(&output).unwrap();
+        let layer = &config.layers[&crate::config::Layer("colemak-dh".to_string())];
+        assert_eq!(
+            layer.remaps[&KeyCode::KC_S],
+            KeyAction::MT(
+                Box::new(KeyAction::Key(KeyCode::KC_R)),
+                Box::new(KeyAction::Key(KeyCode::KC_LALT))
+            )
+        );
+        assert_eq!(layer.remaps[&KeyCode::KC_B], KeyAction::Key(KeyCode::KC_Z));
+        assert!(!layer.remaps.contains_key(&KeyCode::KC_A));
+        assert_eq!(
+            config.remaps[&KeyCode::KC_SCRL],
+            KeyAction::TG(crate::config::Layer("colemak-dh".to_string()))
+        );
+
+        // Every layout is a permutation of the keys it moves
+        for name in layout_names() {
+            let remaps = layout_remaps(name).unwrap();
+            let mut from: Vec<KeyCode> = remaps.iter().map(|(f, _)| *f).collect();
+            let mut to: Vec<KeyCode> = remaps.iter().map(|(_, t)| *t).collect();
+            from.sort_by_key(|k| k.code());
+            to.sort_by_key(|k| k.code());
+            assert_eq!(from, to, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_numpad_layer_with_hold_key() {
+        let input = "(\n    remaps: {\n        KC_CAPS: KC_ESC,\n    },\n)";
+        let (output, replaced) = apply_numpad_layer(input, Some(KeyCode::KC_RALT), None).unwrap();
+        assert!(replaced.is_empty());
+        let config = Config::parse(&output).unwrap();
+        let numpad = crate::config::Layer(NUMPAD_LAYER.to_string());
+        let layer = &config.layers[&numpad];
+        assert_eq!(layer.remaps[&KeyCode::KC_U], KeyAction::Key(KeyCode::KC_P7));
+        assert_eq!(
+            layer.remaps[&KeyCode::KC_COMM],
+            KeyAction::Key(KeyCode::KC_P2)
+        );
+        assert_eq!(config.remaps[&KeyCode::KC_RALT], KeyAction::MO(numpad));
+        assert_eq!(
+            config.remaps[&KeyCode::KC_CAPS],
+            KeyAction::Key(KeyCode::KC_ESC)
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use crate::config::{Config, KeyAction, Layer};
+use crate::config::config::ron_skip_len;
+use crate::config::{Condition, Config, GameMode, GameModeOverlay, KeyAction, Layer, LayerConfig};
 use crate::keycode::KeyCode;
 use anyhow::Result;
 use std::collections::{HashMap, HashSet};
@@ -55,7 +56,7 @@ pub fn validate_config(config_path: Option<&std::path::Path>) -> Result<()> {
     let mut extract_socd = |remaps: &HashMap<KeyCode, KeyAction>| {
         let mut pairs = Vec::new();
         for (key, action) in remaps {
-            if let KeyAction::SOCD(this_action, opposing_actions) = action {
+            if let KeyAction::SOCD(this_action, opposing_actions) = action.without_mode() {
                 if let KeyAction::Key(this_key) = this_action.as_ref() {
                     if key != this_key {
                         warnings.push(format!(
@@ -82,8 +83,10 @@ pub fn validate_config(config_path: Option<&std::path::Path>) -> Result<()> {
             socd_map.insert(key1, key2);
         }
     }
-    for (key1, key2) in extract_socd(&config.game_mode.remaps) {
-        socd_map.insert(key1, key2);
+    for remaps in config.game_mode.all_remaps() {
+        for (key1, key2) in extract_socd(remaps) {
+            socd_map.insert(key1, key2);
+        }
     }
 
     let mut socd_checked = HashSet::new();
@@ -130,46 +133,70 @@ pub fn validate_config(config_path: Option<&std::path::Path>) -> Result<()> {
     }
     println!("{}", "✓".bright_green().bold());
 
-    print!("  {} Checking layer references... ", "→".bright_blue());
-    let mut referenced_layers = HashSet::new();
+    print!("  {} Checking keymap semantics... ", "→".bright_blue());
+    let mut semantic = duplicate_key_errors(&std::fs::read_to_string(&config_path)?);
+    semantic.extend(semantic_errors(&config));
+    let no_way_back = no_way_back_warnings(&config);
+    if semantic.is_empty() {
+        println!(
+            "{} {} layers",
+            "✓".bright_green().bold(),
+            config.layers.len()
+        );
+    } else {
+        println!("{}", "✗".bright_red().bold());
+        errors.extend(semantic);
+    }
+    warnings.extend(no_way_back);
 
-    let extract_layer_refs = |remaps: &HashMap<KeyCode, KeyAction>| {
-        let mut refs = Vec::new();
-        for action in remaps.values() {
-            if let KeyAction::TO(layer) = action {
-                refs.push(layer.0.clone());
-            }
+    if !config.schedule.is_empty() {
+        print!("  {} Checking schedule... ", "→".bright_blue());
+        let schedule_errors = config.schedule_errors();
+        if schedule_errors.is_empty() {
+            println!(
+                "{} {} rules",
+                "✓".bright_green().bold(),
+                config.schedule.len()
+            );
+        } else {
+            println!("{}", "✗".bright_red().bold());
+            errors.extend(schedule_errors);
         }
-        refs
-    };
-
-    for layer_name in extract_layer_refs(&config.remaps) {
-        referenced_layers.insert(layer_name);
     }
-    for layer_config in config.layers.values() {
-        for layer_name in extract_layer_refs(&layer_config.remaps) {
-            referenced_layers.insert(layer_name);
+
+    if !config.sequences.is_empty() {
+        print!("  {} Checking sequences... ", "→".bright_blue());
+        let sequence_errors = config.sequence_errors();
+        if sequence_errors.is_empty() {
+            println!(
+                "{} {} sequences",
+                "✓".bright_green().bold(),
+                config.sequences.len()
+            );
+        } else {
+            println!("{}", "✗".bright_red().bold());
+            errors.extend(sequence_errors);
         }
     }
 
-    let mut missing_layers = Vec::new();
-    for layer_name in &referenced_layers {
-        if layer_name != "base" && !config.layers.contains_key(&Layer(layer_name.clone())) {
-            missing_layers.push(layer_name.clone());
-        }
+    let hardware_layout_errors = config.hardware_layout_errors();
+    if !hardware_layout_errors.is_empty() {
+        println!(
+            "  {} Checking hardware layouts... {}",
+            "→".bright_blue(),
+            "✗".bright_red().bold()
+        );
+        errors.extend(hardware_layout_errors);
     }
 
-    if missing_layers.is_empty() {
+    let button_output_errors = config.button_output_errors();
+    if !button_output_errors.is_empty() {
         println!(
-            "{} {} layers",
-            "✓".bright_green().bold(),
-            config.layers.len()
+            "  {} Checking output keys... {}",
+            "→".bright_blue(),
+            "✗".bright_red().bold()
         );
-    } else {
-        println!("{}", "✗".bright_red().bold());
-        for layer_name in missing_layers {
-            errors.push(format!("Referenced layer not defined: \"{}\"", layer_name));
-        }
+        errors.extend(button_output_errors);
     }
 
     println!();
@@ -222,3 +249,539 @@ pub fn validate_config(config_path: Option<&std::path::Path>) -> Result<()> {
         Ok(())
     }
 }
+
+/// Duplicate keys in any map or struct of the config source, which RON would
+/// otherwise resolve silently by keeping the last one
+pub fn duplicate_key_errors(content: &str) -> Vec<String> {
+    struct Frame {
+        path: String,
+        is_list: bool,
+        index: usize,
+        last_key: Option<String>,
+        keys: HashMap<String, usize>,
+    }
+
+    let bytes = content.as_bytes();
+    let line_of = |pos: usize| content[..pos].matches('\n').count() + 1;
+    let is_ident_start = |b: u8| b.is_ascii_alphabetic() || b == b'_';
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    // Position of the next character that isn't whitespace or a comment
+    let next_significant = |mut i: usize| {
+        while i < bytes.len() {
+            if bytes[i].is_ascii_whitespace() {
+                i += 1;
+            } else if let Some(len) = ron_skip_len(bytes, i).filter(|_| bytes[i] == b'/') {
+                i += len;
+            } else {
+                break;
+            }
+        }
+        i
+    };
+
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut errors = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let token_end = match ron_skip_len(bytes, i) {
+            Some(len) if bytes[i] == b'"' => Some(i + len),
+            Some(len) => {
+                i += len;
+                continue;
+            }
+            None if is_ident_start(bytes[i]) && (i == 0 || !is_ident(bytes[i - 1])) => {
+                let len = bytes[i..].iter().take_while(|&&b| is_ident(b)).count();
+                Some(i + len)
+            }
+            None => None,
+        };
+
+        if let Some(end) = token_end {
+            let after = next_significant(end);
+            let is_key = bytes.get(after) == Some(&b':') && bytes.get(after + 1) != Some(&b':');
+            if let (true, Some(frame)) = (is_key, stack.last_mut()) {
+                let key = content[i..end].trim_matches('"').to_string();
+                let line = line_of(i);
+                if let Some(first) = frame.keys.insert(key.clone(), line) {
+                    let location = if frame.path.is_empty() {
+                        "config".to_string()
+                    } else {
+                        frame.path.clone()
+                    };
+                    errors.push(format!(
+                        "{}: {} is defined twice (lines {} and {})",
+                        location, key, first, line
+                    ));
+                }
+                frame.last_key = Some(key);
+            }
+            i = end;
+            continue;
+        }
+
+        match bytes[i] {
+            b'(' | b'[' | b'{' => {
+                let path = match stack.last() {
+                    None => String::new(),
+                    Some(parent) if parent.is_list => format!("{}[{}]", parent.path, parent.index),
+                    Some(parent) => match &parent.last_key {
+                        Some(key) if parent.path.is_empty() => key.clone(),
+                        Some(key) => format!("{}.{}", parent.path, key),
+                        None => parent.path.clone(),
+                    },
+                };
+                stack.push(Frame {
+                    path,
+                    is_list: bytes[i] == b'[',
+                    index: 0,
+                    last_key: None,
+                    keys: HashMap::new(),
+                });
+            }
+            b')' | b']' | b'}' => {
+                stack.pop();
+            }
+            b',' => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.index += 1;
+                    frame.last_key = None;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    errors
+}
+
+/// A set of remap tables sharing one layer namespace: the global config, a
+/// per-keyboard override or a profile
+struct Scope<'a> {
+    prefix: String,
+    remaps: Option<&'a HashMap<KeyCode, KeyAction>>,
+    /// Layers defined in this scope
+    layers: Vec<(&'a Layer, &'a LayerConfig)>,
+    game_mode_remaps: Option<&'a HashMap<KeyCode, KeyAction>>,
+    /// Game mode overlays defined in this scope
+    game_mode_overlays: Vec<(&'a String, &'a GameModeOverlay)>,
+    /// Layers this scope's actions can refer to (its own plus the global ones)
+    visible: HashMap<&'a Layer, &'a LayerConfig>,
+}
+
+impl<'a> Scope<'a> {
+    /// Remap tables with their config paths
+    fn tables(&self) -> Vec<(String, &'a HashMap<KeyCode, KeyAction>)> {
+        let mut tables = Vec::new();
+        if let Some(remaps) = self.remaps {
+            tables.push((format!("{}remaps", self.prefix), remaps));
+        }
+        for (layer, layer_config) in &self.layers {
+            tables.push((
+                format!("{}layers.{}.remaps", self.prefix, layer.0),
+                &layer_config.remaps,
+            ));
+        }
+        if let Some(remaps) = self.game_mode_remaps {
+            tables.push((format!("{}game_mode.remaps", self.prefix), remaps));
+        }
+        for (name, overlay) in &self.game_mode_overlays {
+            tables.push((
+                format!("{}game_mode.overlays.{}.remaps", self.prefix, name),
+                &overlay.remaps,
+            ));
+        }
+        tables
+    }
+}
+
+/// Game mode overlays in a stable order
+fn sorted_overlays(game_mode: &GameMode) -> Vec<(&String, &GameModeOverlay)> {
+    let mut overlays: Vec<_> = game_mode.overlays.iter().collect();
+    overlays.sort_by(|a, b| a.0.cmp(b.0));
+    overlays
+}
+
+/// Layers of a table in a stable order
+fn sorted_layers(layers: &HashMap<Layer, LayerConfig>) -> Vec<(&Layer, &LayerConfig)> {
+    let mut layers: Vec<_> = layers.iter().collect();
+    layers.sort_by(|a, b| a.0 .0.cmp(&b.0 .0));
+    layers
+}
+
+fn scopes(config: &Config) -> Vec<Scope<'_>> {
+    let global: HashMap<&Layer, &LayerConfig> = config.layers.iter().collect();
+    let mut scopes = vec![Scope {
+        prefix: String::new(),
+        remaps: Some(&config.remaps),
+        layers: sorted_layers(&config.layers),
+        game_mode_remaps: Some(&config.game_mode.remaps),
+        game_mode_overlays: sorted_overlays(&config.game_mode),
+        visible: global.clone(),
+    }];
+
+    let mut overlays: Vec<_> = config
+        .per_keyboard_overrides
+        .iter()
+        .map(|(id, cfg)| (format!("per_keyboard_overrides.{}.", id), cfg))
+        .chain(
+            config
+                .profiles
+                .iter()
+                .map(|(name, cfg)| (format!("profiles.{}.", name), cfg)),
+        )
+        .collect();
+    overlays.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (prefix, overlay) in overlays {
+        let layers = overlay
+            .layers
+            .as_ref()
+            .map(sorted_layers)
+            .unwrap_or_default();
+        let mut visible = global.clone();
+        visible.extend(layers.iter().copied());
+        scopes.push(Scope {
+            prefix,
+            remaps: overlay.remaps.as_ref(),
+            layers,
+            game_mode_remaps: overlay.game_mode.as_ref().map(|g| &g.remaps),
+            game_mode_overlays: overlay
+                .game_mode
+                .as_ref()
+                .map(sorted_overlays)
+                .unwrap_or_default(),
+            visible,
+        });
+    }
+    scopes
+}
+
+/// Bindings of a remap table in a stable order
+fn sorted_remaps(remaps: &HashMap<KeyCode, KeyAction>) -> Vec<(&KeyCode, &KeyAction)> {
+    let mut remaps: Vec<_> = remaps.iter().collect();
+    remaps.sort_by_key(|(key, _)| format!("{:?}", key));
+    remaps
+}
+
+/// Check one bound action: layer references and nesting the processor supports
+///
+/// MT, OSM, SOCD and Turbo only act on plain keys, TapLock on a (one-shot) modifier
+/// and FixedTerm on an MT; DT runs any single action, and so does either branch of an
+/// If and the action an OnlyGameMode/OnlyTyping wraps.
+fn check_action(
+    action: &KeyAction,
+    path: &str,
+    layers: &HashMap<&Layer, &LayerConfig>,
+    in_dt: bool,
+    errors: &mut Vec<String>,
+) {
+    let mut plain_key = |inner: &KeyAction, role: &str| {
+        if !matches!(inner, KeyAction::Key(_)) {
+            errors.push(format!(
+                "{}: {} must be a plain key, got {:?}",
+                path, role, inner
+            ));
+        }
+    };
+    match action {
+        KeyAction::TO(layer) | KeyAction::TG(layer) | KeyAction::MO(layer)
+            if !layer.is_base() && !layers.contains_key(layer) =>
+        {
+            errors.push(format!("{}: layer \"{}\" is not defined", path, layer.0));
+        }
+        KeyAction::MT(tap, hold) => {
+            plain_key(tap, "MT tap");
+            plain_key(hold, "MT hold");
+        }
+        KeyAction::OSM(modifier) => plain_key(modifier, "OSM modifier"),
+        KeyAction::FixedTerm(inner) => {
+            if matches!(**inner, KeyAction::MT(..)) {
+                check_action(
+                    inner,
+                    &format!("{} (FixedTerm)", path),
+                    layers,
+                    in_dt,
+                    errors,
+                );
+            } else {
+                errors.push(format!(
+                    "{}: FixedTerm must wrap an MT, got {:?}",
+                    path, inner
+                ));
+            }
+        }
+        KeyAction::TapLock(inner) => {
+            match crate::event_processor::actions::taplock::locked_modifier(inner) {
+                Some(key) if key.is_modifier() => {}
+                _ => errors.push(format!(
+                    "{}: TapLock must wrap a modifier key or OSM of one, got {:?}",
+                    path, inner
+                )),
+            }
+        }
+        KeyAction::Turbo(key, _) => plain_key(key, "Turbo key"),
+        KeyAction::SOCD(this, opposing) => {
+            plain_key(this, "SOCD key");
+            for action in opposing {
+                plain_key(action, "SOCD opposing key");
+            }
+        }
+        KeyAction::DT(tap, double_tap) => {
+            if in_dt {
+                errors.push(format!("{}: DT cannot be nested inside DT", path));
+            } else {
+                check_action(tap, &format!("{} (DT tap)", path), layers, true, errors);
+                check_action(
+                    double_tap,
+                    &format!("{} (DT double tap)", path),
+                    layers,
+                    true,
+                    errors,
+                );
+            }
+        }
+        KeyAction::OnlyGameMode(inner) => {
+            check_action(
+                inner,
+                &format!("{} (OnlyGameMode)", path),
+                layers,
+                in_dt,
+                errors,
+            );
+        }
+        KeyAction::OnlyTyping(inner) => {
+            check_action(
+                inner,
+                &format!("{} (OnlyTyping)", path),
+                layers,
+                in_dt,
+                errors,
+            );
+        }
+        KeyAction::If(condition, then_action, else_action) => {
+            match condition {
+                Condition::LayerActive(layer)
+                    if !layer.is_base() && !layers.contains_key(layer) =>
+                {
+                    errors.push(format!(
+                        "{}: If checks layer \"{}\", which is not defined",
+                        path, layer.0
+                    ));
+                }
+                Condition::AppMatches(pattern) => {
+                    if let Err(e) = regex::Regex::new(pattern) {
+                        errors.push(format!(
+                            "{}: invalid app_matches pattern {:?}: {}",
+                            path, pattern, e
+                        ));
+                    }
+                }
+                _ => {}
+            }
+            check_action(
+                then_action,
+                &format!("{} (If then)", path),
+                layers,
+                in_dt,
+                errors,
+            );
+            check_action(
+                else_action,
+                &format!("{} (If else)", path),
+                layers,
+                in_dt,
+                errors,
+            );
+        }
+        _ => {}
+    }
+}
+
+/// SOCD groups in one table that share keys without one containing the other
+fn socd_overlap_errors(path: &str, remaps: &HashMap<KeyCode, KeyAction>) -> Vec<String> {
+    let groups: Vec<(KeyCode, HashSet<KeyCode>)> = sorted_remaps(remaps)
+        .into_iter()
+        .filter_map(|(key, action)| match action.without_mode() {
+            KeyAction::SOCD(this, opposing) => {
+                let mut group: HashSet<KeyCode> = opposing
+                    .iter()
+                    .filter_map(|action| match action.as_ref() {
+                        KeyAction::Key(key) => Some(*key),
+                        _ => None,
+                    })
+                    .collect();
+                if let KeyAction::Key(this) = this.as_ref() {
+                    group.insert(*this);
+                }
+                Some((*key, group))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut errors = Vec::new();
+    for (i, (key_a, group_a)) in groups.iter().enumerate() {
+        for (key_b, group_b) in &groups[i + 1..] {
+            let shares = !group_a.is_disjoint(group_b);
+            if shares && !group_a.is_subset(group_b) && !group_b.is_subset(group_a) {
+                errors.push(format!(
+                    "{}: SOCD groups of {:?} and {:?} overlap without matching",
+                    path, key_a, key_b
+                ));
+            }
+        }
+    }
+    errors
+}
+
+/// Problems beyond parsing: undefined layers, unsupported nesting and
+/// overlapping SOCD groups, each with the config path it was found at
+pub fn semantic_errors(config: &Config) -> Vec<String> {
+    let mut errors = Vec::new();
+    for scope in scopes(config) {
+        for (path, remaps) in scope.tables() {
+            for (key, action) in sorted_remaps(remaps) {
+                let key_path = format!("{}.{:?}", path, key);
+                check_action(action, &key_path, &scope.visible, false, &mut errors);
+            }
+            errors.extend(socd_overlap_errors(&path, remaps));
+        }
+    }
+    for (i, rule) in config.window_layers.iter().enumerate() {
+        if !rule.layer.is_base() && !config.layers.contains_key(&rule.layer) {
+            errors.push(format!(
+                "window_layers[{}]: layer \"{}\" is not defined",
+                i, rule.layer.0
+            ));
+        }
+        for error in rule.pattern_errors() {
+            errors.push(format!("window_layers[{}]: invalid pattern {}", i, error));
+        }
+    }
+    let mut groups: Vec<_> = config.layer_groups.iter().collect();
+    groups.sort_by_key(|(name, _)| *name);
+    for (name, layers) in groups {
+        for layer in layers {
+            if layer.is_base() {
+                errors.push(format!(
+                    "layer_groups.{}: the base layer is always active and can't be grouped",
+                    name
+                ));
+            } else if !config.layers.contains_key(layer) {
+                errors.push(format!(
+                    "layer_groups.{}: layer \"{}\" is not defined",
+                    name, layer.0
+                ));
+            }
+        }
+    }
+    errors
+}
+
+/// Layers entered with TO or TG that have no key leading back out
+///
+/// MO layers always end when the key is released, so only sticky switches can
+/// strand the keyboard in a layer.
+pub fn no_way_back_warnings(config: &Config) -> Vec<String> {
+    // Whether an action (or a DT branch of it) switches layers other than into `layer`
+    fn leaves(action: &KeyAction, layer: &Layer) -> bool {
+        match action {
+            KeyAction::TO(target) => target != layer,
+            KeyAction::TG(_) => true,
+            KeyAction::DT(tap, double_tap) => leaves(tap, layer) || leaves(double_tap, layer),
+            _ => false,
+        }
+    }
+
+    let mut warnings = Vec::new();
+    let mut reported = HashSet::new();
+    for scope in scopes(config) {
+        for (path, remaps) in scope.tables() {
+            for (key, action) in sorted_remaps(remaps) {
+                let (kind, layer) = match action {
+                    KeyAction::TO(layer) => ("TO", layer),
+                    KeyAction::TG(layer) => ("TG", layer),
+                    _ => continue,
+                };
+                let Some(target) = scope.visible.get(layer) else {
+                    continue;
+                };
+                let has_exit = target.remaps.values().any(|a| leaves(a, layer))
+                    // A TG key left unbound (or transparent) in its layer toggles it off again
+                    || (kind == "TG"
+                        && target
+                            .remaps
+                            .get(key)
+                            .is_none_or(KeyAction::is_transparent));
+                if !has_exit && reported.insert((scope.prefix.clone(), layer.clone())) {
+                    warnings.push(format!(
+                        "{}.{:?}: {}(\"{}\") enters a layer with no key leading back",
+                        path, key, kind, layer.0
+                    ));
+                }
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Config {
+        ron::from_str(&Config::preprocess_config(input)).unwrap()
+    }
+
+    #[test]
+    fn test_semantic_checks() {
+        let input = r#"(
+            remaps: {
+                KC_CAPS: KC_ESC,
+                KC_CAPS: KC_GRV, // duplicate
+                KC_A: MT(KC_A, TO("nav")),
+                KC_B: TG("gaming"),
+                KC_C: MO("missing"),
+                KC_D: DT(KC_D, DT(KC_E, KC_F)),
+                KC_W: SOCD(KC_W, [KC_S]),
+                KC_S: SOCD(KC_S, [KC_W]),
+                KC_X: SOCD(KC_X, [KC_S]),
+                KC_SPC: FixedTerm(MT(KC_SPC, KC_LSFT)),
+                KC_ENT: FixedTerm(KC_ENT),
+            },
+            layers: {
+                "nav": (remaps: { KC_H: KC_LEFT }),
+                "gaming": (remaps: { KC_B: KC_B }),
+            },
+            window_layers: [(app_id: Some("(kitty"), layer: "nav")],
+        )"#;
+
+        let duplicates = duplicate_key_errors(input);
+        assert_eq!(
+            duplicates,
+            vec!["remaps: KC_CAPS is defined twice (lines 3 and 4)".to_string()]
+        );
+
+        let errors = semantic_errors(&parse(input));
+        assert!(errors.contains(
+            &"remaps.KC_A: MT hold must be a plain key, got TO(Layer(\"nav\"))".to_string()
+        ));
+        assert!(errors.contains(&"remaps.KC_C: layer \"missing\" is not defined".to_string()));
+        assert!(errors
+            .contains(&"remaps.KC_D (DT double tap): DT cannot be nested inside DT".to_string()));
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("remaps: SOCD groups of KC_S and KC_X")));
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("window_layers[0]: invalid pattern \"(kitty\"")));
+        assert!(errors
+            .contains(&"remaps.KC_ENT: FixedTerm must wrap an MT, got Key(KC_ENT)".to_string()));
+        assert_eq!(errors.len(), 7, "{:?}", errors);
+
+        assert_eq!(
+            no_way_back_warnings(&parse(input)),
+            vec!["remaps.KC_B: TG(\"gaming\") enters a layer with no key leading back".to_string()]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+/// Dry run of daemon startup (`keymux daemon --check`)
+///
+/// Discovers keyboards, loads and validates every user's config, and checks uinput
+/// and the IPC socket without grabbing or creating anything, so it can run as the
+/// service's ExecStartPre and stop a broken setup before the daemon takes keyboards.
+use crate::config::validator::{duplicate_key_errors, no_way_back_warnings, semantic_errors};
+use crate::config::Config;
+use crate::ipc::{get_root_socket_path, send_request, IpcRequest};
+use crate::keyboard_id::find_all_keyboards;
+use crate::session_manager::SessionBackend;
+use anyhow::Result;
+use colored::Colorize;
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+
+/// Every user config the daemon could load, with the owning user's name
+fn user_configs() -> Vec<(String, PathBuf)> {
+    let mut configs: Vec<(String, PathBuf)> = std::fs::read_dir("/home")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().into_owned(),
+                crate::paths::config_path_in(&entry.path()),
+            )
+        })
+        .filter(|(_, path)| path.exists())
+        .collect();
+    configs.sort();
+    configs
+}
+
+fn check_uinput() -> Result<()> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/uinput")?;
+    Ok(())
+}
+
+/// Bind a throwaway socket next to the real one, or confirm a daemon already owns it
+fn check_socket() -> Result<Option<String>> {
+    let socket_path = get_root_socket_path();
+    if socket_path.exists() && send_request(&IpcRequest::Ping).is_ok() {
+        return Ok(Some("in use by a running daemon".to_string()));
+    }
+    if let Some(parent) = socket_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let probe = socket_path.with_extension("sock.check");
+    let _ = std::fs::remove_file(&probe);
+    let listener = UnixListener::bind(&probe);
+    let _ = std::fs::remove_file(&probe);
+    listener?;
+    Ok(None)
+}
+
+/// Print the startup report; fails if the daemon would not be able to start cleanly
+pub fn run_check() -> Result<()> {
+    let mut failures = 0;
+    let fail = |failures: &mut usize, what: &str, detail: String| {
+        *failures += 1;
+        println!("  {} {}: {}", "✗".bright_red(), what, detail.red());
+    };
+
+    println!();
+    println!(
+        "{}",
+        "═══════════════════════════════════════".bright_cyan()
+    );
+    println!("  {}", "Daemon Startup Check".bright_cyan().bold());
+    println!(
+        "{}",
+        "═══════════════════════════════════════".bright_cyan()
+    );
+    println!();
+
+    if unsafe { libc::getuid() } == 0 {
+        println!("  {} Running as root", "✓".bright_green().bold());
+    } else {
+        fail(
+            &mut failures,
+            "Privileges",
+            "the daemon must run as root for device access".to_string(),
+        );
+    }
+
+    match check_uinput() {
+        Ok(()) => println!("  {} /dev/uinput is writable", "✓".bright_green().bold()),
+        Err(e) => fail(&mut failures, "/dev/uinput", e.to_string()),
+    }
+
+    let socket_path = get_root_socket_path();
+    match check_socket() {
+        Ok(None) => println!(
+            "  {} IPC socket can be bound at {}",
+            "✓".bright_green().bold(),
+            socket_path.display()
+        ),
+        Ok(Some(note)) => println!(
+            "  {} IPC socket {} is {}",
+            "⚠".bright_yellow(),
+            socket_path.display(),
+            note.yellow()
+        ),
+        Err(e) => fail(&mut failures, "IPC socket", e.to_string()),
+    }
+
+    match SessionBackend::detect() {
+        SessionBackend::Logind => {
+            println!(
+                "  {} Sessions tracked through loginctl",
+                "✓".bright_green().bold()
+            );
+        }
+        SessionBackend::Console => println!(
+            "  {} loginctl unavailable: sessions come from utmp and the active VT",
+            "ℹ".bright_blue()
+        ),
+    }
+
+    println!();
+    let mut keyboards: Vec<_> = find_all_keyboards().into_iter().collect();
+    keyboards.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+    if keyboards.is_empty() {
+        println!("  {} No keyboards found", "⚠".bright_yellow());
+    } else {
+        println!(
+            "  {} {} keyboard(s) found",
+            "✓".bright_green().bold(),
+            keyboards.len()
+        );
+        for (id, keyboard) in &keyboards {
+            let note = if keyboard.remapper_output {
+                " (remapper output)".dimmed().to_string()
+            } else {
+                String::new()
+            };
+            println!(
+                "    {} {} {}{}",
+                "→".bright_blue(),
+                keyboard.name.bright_white(),
+                id.to_string().dimmed(),
+                note
+            );
+        }
+    }
+
+    println!();
+    let configs = user_configs();
+    if configs.is_empty() {
+        println!(
+            "  {} No user configs found under /home/*/.config/keymux",
+            "⚠".bright_yellow()
+        );
+    }
+    for (user, path) in &configs {
+        let config = match Config::load_with_state(path) {
+            Ok(config) => config,
+            Err(e) => {
+                fail(
+                    &mut failures,
+                    &format!("Config for {}", user),
+                    e.to_string(),
+                );
+                continue;
+            }
+        };
+        if let Err(e) = config.validate_silent() {
+            fail(
+                &mut failures,
+                &format!("Config for {}", user),
+                e.to_string(),
+            );
+            continue;
+        }
+
+        let enabled = keyboards
+            .iter()
+            .filter(|(id, keyboard)| {
+                let event_path = keyboard
+                    .devices
+                    .first()
+                    .and_then(|(p, _)| p.file_name().and_then(|n| n.to_str()));
+                config.is_keyboard_enabled(&id.to_string(), Some(&keyboard.name), event_path)
+                    && (!keyboard.remapper_output
+                        || config.allows_remapper_output(id, &keyboard.name))
+            })
+            .count();
+        println!(
+            "  {} Config for {} is valid ({} of {} keyboard(s) enabled)",
+            "✓".bright_green().bold(),
+            user.bright_white(),
+            enabled,
+            keyboards.len()
+        );
+
+        // Problems the daemon tolerates but that likely don't do what was meant
+        let mut warnings = std::fs::read_to_string(path)
+            .map(|content| duplicate_key_errors(&content))
+            .unwrap_or_default();
+        warnings.extend(semantic_errors(&config));
+        warnings.extend(no_way_back_warnings(&config));
+        for warning in warnings {
+            println!("    {} {}", "⚠".bright_yellow(), warning.yellow());
+        }
+    }
+
+    println!();
+    println!(
+        "{}",
+        "═══════════════════════════════════════".bright_cyan()
+    );
+    if failures == 0 {
+        println!(
+            "  {} {}",
+            "✓".bright_green().bold(),
+            "Daemon is ready to start".bright_green()
+        );
+    } else {
+        println!(
+            "  {} {}",
+            "✗".bright_red().bold(),
+            format!("{} problem(s) found", failures).bright_red()
+        );
+    }
+    println!(
+        "{}",
+        "═══════════════════════════════════════".bright_cyan()
+    );
+    println!();
+
+    if failures == 0 {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Daemon check failed with {} problem(s)",
+            failures
+        ))
+    }
+}
@@ -0,0 +1,266 @@
+//! Config file watcher for hot reload
+//!
+//! Watches the config (`~/.config/keymux/config.ron`) of every logged-in user. The daemon passes the
+//! current users whenever sessions change, so users who log in later get hot reload
+//! without a restart. A config that doesn't exist yet is watched through its nearest
+//! existing parent directory, so creating it is noticed as well.
+
+use anyhow::Result;
+use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc as tokio_mpsc;
+use tracing::{debug, error, info, warn};
+
+/// Quiet time after a change before reloading, so editors' write bursts count once
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+enum Message {
+    Fs(notify::Result<Event>),
+    SetConfigs(Vec<PathBuf>),
+}
+
+/// Handle to the watcher thread
+pub struct ConfigWatcher {
+    tx: mpsc::Sender<Message>,
+    uids: BTreeSet<u32>,
+}
+
+impl ConfigWatcher {
+    /// Start the watcher thread; the receiver gets the path of each changed config
+    pub fn start() -> (Self, tokio_mpsc::UnboundedReceiver<PathBuf>) {
+        let (changed_tx, changed_rx) = tokio_mpsc::unbounded_channel();
+        let (tx, rx) = mpsc::channel();
+
+        let fs_tx = tx.clone();
+        match recommended_watcher(move |event| {
+            let _ = fs_tx.send(Message::Fs(event));
+        }) {
+            Ok(watcher) => {
+                thread::spawn(move || run(Box::new(watcher), &rx, &changed_tx));
+            }
+            Err(e) => error!("Failed to create config file watcher: {}", e),
+        }
+
+        let watcher = Self {
+            tx,
+            uids: BTreeSet::new(),
+        };
+        (watcher, changed_rx)
+    }
+
+    /// Watch the configs of exactly these users (no-op if they haven't changed)
+    pub fn sync_users(&mut self, uids: &[u32], home_dir: impl Fn(u32) -> Result<PathBuf>) {
+        let uids: BTreeSet<u32> = uids.iter().copied().collect();
+        if uids == self.uids {
+            return;
+        }
+        let configs = uids
+            .iter()
+            .filter_map(|&uid| match home_dir(uid) {
+                Ok(home) => Some(crate::paths::config_path_in(&home)),
+                Err(e) => {
+                    warn!("Not watching config of user {}: {}", uid, e);
+                    None
+                }
+            })
+            .collect();
+        self.uids = uids;
+        let _ = self.tx.send(Message::SetConfigs(configs));
+    }
+}
+
+/// Resolve a symlink one level, relative to its parent (the path itself if it isn't one)
+fn resolve_symlink(path: &Path) -> Option<PathBuf> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    if !metadata.file_type().is_symlink() {
+        return Some(path.to_path_buf());
+    }
+    let target = std::fs::read_link(path).ok()?;
+    if target.is_absolute() {
+        return Some(target);
+    }
+    let resolved = path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(&target);
+    Some(resolved.canonicalize().unwrap_or(resolved))
+}
+
+/// Directory to watch for a config: its own directory, or the nearest existing parent
+fn watch_dir(config_path: &Path) -> Option<PathBuf> {
+    config_path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.is_dir())
+        .map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()))
+}
+
+struct Watches {
+    watcher: Box<dyn Watcher>,
+    configs: Vec<PathBuf>,
+    dirs: HashSet<PathBuf>,
+    /// Configs that existed at the last update
+    existing: HashSet<PathBuf>,
+}
+
+impl Watches {
+    /// Watch what `configs` need now and drop the rest; returns configs that appeared
+    fn update(&mut self, configs: Vec<PathBuf>) -> Vec<PathBuf> {
+        let mut dirs = HashSet::new();
+        for config in &configs {
+            dirs.extend(watch_dir(config));
+            // A symlinked config changes where its target lives
+            if let Some(target) = resolve_symlink(config).filter(|target| target != config) {
+                dirs.extend(watch_dir(&target));
+            }
+        }
+
+        for dir in self.dirs.difference(&dirs) {
+            let _ = self.watcher.unwatch(dir);
+            debug!("Stopped watching {:?}", dir);
+        }
+        for dir in dirs.difference(&self.dirs) {
+            if let Err(e) = self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+                warn!("Failed to watch directory {:?}: {}", dir, e);
+            }
+        }
+        for config in configs
+            .iter()
+            .filter(|config| !self.configs.contains(config))
+        {
+            info!("Watching config at {:?}", config);
+        }
+
+        let existing: HashSet<PathBuf> = configs
+            .iter()
+            .filter(|config| config.exists())
+            .cloned()
+            .collect();
+        let appeared = existing
+            .iter()
+            .filter(|config| self.configs.contains(config) && !self.existing.contains(*config))
+            .cloned()
+            .collect();
+
+        self.configs = configs;
+        self.dirs = dirs;
+        self.existing = existing;
+        appeared
+    }
+
+    /// Config an event path belongs to, if any
+    fn config_for(&self, path: &Path) -> Option<&PathBuf> {
+        self.configs.iter().find(|config| {
+            config.as_path() == path || resolve_symlink(config).is_some_and(|target| target == path)
+        })
+    }
+
+    /// A directory on the way to a config that doesn't exist yet was created
+    fn opens_path_to_config(&self, path: &Path) -> bool {
+        self.configs
+            .iter()
+            .any(|config| config != path && config.starts_with(path))
+    }
+}
+
+fn run(
+    watcher: Box<dyn Watcher>,
+    rx: &mpsc::Receiver<Message>,
+    changed_tx: &tokio_mpsc::UnboundedSender<PathBuf>,
+) {
+    let mut watches = Watches {
+        watcher,
+        configs: Vec::new(),
+        dirs: HashSet::new(),
+        existing: HashSet::new(),
+    };
+    // Changed configs, reported together once DEBOUNCE has passed since the first
+    let mut pending: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut settle_at: Option<Instant> = None;
+
+    loop {
+        if pending.is_empty() {
+            settle_at = None;
+        } else if settle_at.is_none() {
+            settle_at = Some(Instant::now() + DEBOUNCE);
+        }
+        let message = match settle_at {
+            None => rx.recv().ok(),
+            Some(deadline) => {
+                match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(message) => Some(message),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        info!("Config changes settled, triggering reload");
+                        for config in std::mem::take(&mut pending) {
+                            let _ = changed_tx.send(config);
+                        }
+                        continue;
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => None,
+                }
+            }
+        };
+        let Some(message) = message else {
+            error!("Config watch channel closed");
+            return;
+        };
+
+        match message {
+            Message::SetConfigs(configs) => {
+                pending.extend(watches.update(configs));
+                info!(
+                    "Config file watcher covers {} config(s) in {} director(y/ies)",
+                    watches.configs.len(),
+                    watches.dirs.len()
+                );
+            }
+            Message::Fs(Ok(Event {
+                kind: kind @ (EventKind::Modify(_) | EventKind::Create(_)),
+                paths,
+                ..
+            })) => {
+                for path in paths {
+                    if let Some(config) = watches.config_for(&path).cloned() {
+                        info!("Config file changed: {:?}", path);
+                        watches.existing.insert(config.clone());
+                        pending.insert(config);
+                    } else if matches!(kind, EventKind::Create(_))
+                        && watches.opens_path_to_config(&path)
+                    {
+                        // Watch deeper now that the directory exists
+                        let configs = watches.configs.clone();
+                        pending.extend(watches.update(configs));
+                    }
+                }
+            }
+            Message::Fs(Ok(_)) => {} // Ignore other event types
+            Message::Fs(Err(e)) => error!("Config watch error: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_config_is_watched_through_nearest_parent() {
+        let home = std::env::temp_dir().join(format!("keymux-watch-{}", std::process::id()));
+        std::fs::create_dir_all(home.join(".config")).unwrap();
+        let config = home.join(".config/keymux/config.ron");
+        let canonical = |path: PathBuf| path.canonicalize().unwrap();
+
+        assert_eq!(watch_dir(&config), Some(canonical(home.join(".config"))));
+        std::fs::create_dir_all(config.parent().unwrap()).unwrap();
+        assert_eq!(
+            watch_dir(&config),
+            Some(canonical(home.join(".config/keymux")))
+        );
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
@@ -2,11 +2,26 @@
 ///
 /// Provides async event handling for hotplug, IPC, config changes, and session management
 /// while maintaining synchronous event processors for zero-latency key processing.
-use crate::config::ConfigManager;
-use crate::event_processor;
-use crate::ipc::{get_root_socket_path, IpcRequest, IpcResponse};
-use crate::keyboard_id::{find_all_keyboards, KeyboardId};
+use crate::battery::battery_for_node;
+use crate::config::diff::diff_configs;
+use crate::config::{
+    Config, ConfigManager, EnableDisable, HotplugConfig, Layer, NotificationKind,
+    NotificationsConfig,
+};
+use crate::daemon::config_watch::ConfigWatcher;
+use crate::daemon::rate_limit::RateLimiter;
+use crate::daemon::restart_backoff::{CrashResponse, RestartBackoff};
+use crate::daemon::state::{DaemonState, QuarantineNotes};
+use crate::event_processor::{
+    self, actions::TapLocks, dedupe::NodeEvents, shared::SharedKeyboardState, EventSender,
+    ProcessorCommand, ProcessorEvent, ProcessorStatus,
+};
+use crate::ipc::{get_root_socket_path, IpcRequest, IpcResponse, PeerCred, QuarantineInfo};
+use crate::keyboard_id::{find_all_keyboards, is_pointer_node, KeyboardId};
+use crate::keycode::KeyCode;
+use crate::niri::{detect_game_mode, GameModeState};
 use crate::session_manager::SessionManager;
+use crate::window_manager::WindowInfo;
 use anyhow::{Context, Result};
 
 use evdev::Device;
@@ -14,12 +29,225 @@ use std::collections::{HashMap, HashSet};
 use std::os::unix::net::UnixListener;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::mpsc as tokio_mpsc;
 use tracing::{debug, error, info, warn};
 
+/// How long an IPC client may take to send its request or read the response
+const IPC_IO_TIMEOUT: Duration = Duration::from_secs(2);
+/// Largest IPC request accepted (typed strings are the only big payload)
+const MAX_IPC_REQUEST_LEN: usize = 1024 * 1024;
+
+/// Request from an IPC client, who sent it, and where to send the response
+type IpcMessage = (IpcRequest, PeerCred, mpsc::Sender<IpcResponse>);
+
+/// A user's private IPC socket and the flag that stops its listener thread
+struct UserSocket {
+    uid: u32,
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+}
+
+impl UserSocket {
+    fn close(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the blocking accept so the thread sees the flag
+        let _ = std::os::unix::net::UnixStream::connect(&self.path);
+        let _ = crate::user_fs::as_user(self.uid, || std::fs::remove_file(&self.path));
+    }
+}
+
+/// Bind a listening socket at `path` inside `uid`'s runtime directory
+///
+/// The directory belongs to the user, who can swap anything in it for a symlink, so
+/// nothing here touches the path as root: the old socket is removed and the new one
+/// bound with the user's filesystem IDs (which also makes it theirs), and its mode is
+/// set on the socket before bind, since bind creates the file with the socket's mode.
+fn bind_user_socket(path: &std::path::Path, uid: u32) -> std::io::Result<UnixListener> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    let bytes = path.as_os_str().as_bytes();
+    if bytes.len() >= addr.sun_path.len() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "socket path too long",
+        ));
+    }
+    for (dst, src) in addr.sun_path.iter_mut().zip(bytes) {
+        *dst = *src as libc::c_char;
+    }
+
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    if unsafe { libc::fchmod(fd.as_raw_fd(), 0o600) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    crate::user_fs::as_user(uid, || {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let bound = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_un as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t,
+            )
+        };
+        if bound != 0 || unsafe { libc::listen(fd.as_raw_fd(), 128) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    })?;
+    Ok(UnixListener::from(fd))
+}
+
+/// Accept IPC connections until the listener fails or `stop` is set
+///
+/// With a `scope` uid (a user's private socket) every request is handled as that
+/// user's, whoever connected.
+fn serve_ipc(
+    listener: UnixListener,
+    tx: &tokio_mpsc::UnboundedSender<IpcMessage>,
+    rate_limiter: &std::sync::Mutex<RateLimiter>,
+    scope: Option<u32>,
+    stop: Option<&AtomicBool>,
+) {
+    use std::io::{Read, Write};
+
+    let write_response = |stream: &mut std::os::unix::net::UnixStream, response: &IpcResponse| {
+        if let Ok(resp_bytes) = bincode::serialize(response) {
+            // Send length prefix
+            let resp_len = (resp_bytes.len() as u32).to_le_bytes();
+            let _ = stream.write_all(&resp_len);
+            // Send response data
+            let _ = stream.write_all(&resp_bytes);
+        }
+    };
+
+    for stream in listener.incoming() {
+        if stop.is_some_and(|stop| stop.load(Ordering::SeqCst)) {
+            break;
+        }
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("Failed to accept IPC connection: {}", e);
+                continue;
+            }
+        };
+
+        // Connections are served one at a time; don't let a stalled client block the rest
+        let _ = stream.set_read_timeout(Some(IPC_IO_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(IPC_IO_TIMEOUT));
+
+        let Some(mut peer) = crate::ipc::peer_cred(&stream) else {
+            error!("Rejecting IPC connection with unknown peer credentials");
+            continue;
+        };
+        if let Some(uid) = scope {
+            peer.uid = uid;
+        }
+
+        // Read length prefix (4 bytes)
+        let mut len_buf = [0u8; 4];
+        if let Err(e) = stream.read_exact(&mut len_buf) {
+            error!("Failed to read IPC length: {}", e);
+            continue;
+        }
+        let len = u32::from_le_bytes(len_buf) as usize;
+        if len > MAX_IPC_REQUEST_LEN {
+            warn!(
+                "Rejecting {} byte IPC request from uid {} (pid {})",
+                len, peer.uid, peer.pid
+            );
+            continue;
+        }
+
+        // Read request data
+        let mut buffer = vec![0u8; len];
+        if let Err(e) = stream.read_exact(&mut buffer) {
+            error!("Failed to read IPC request: {}", e);
+            continue;
+        }
+        let Ok(request) = bincode::deserialize::<IpcRequest>(&buffer) else {
+            continue;
+        };
+
+        let allowed = rate_limiter
+            .lock()
+            .map(|mut limiter| limiter.allow(peer.uid, request.is_expensive()))
+            .unwrap_or(true);
+        if !allowed {
+            warn!(
+                "audit: rate limited {} from uid {} (pid {})",
+                request.name(),
+                peer.uid,
+                peer.pid
+            );
+            write_response(
+                &mut stream,
+                &IpcResponse::Error("Too many requests, try again shortly".to_string()),
+            );
+            continue;
+        }
+        if request.is_audited() {
+            info!(
+                "audit: {} from uid {} (pid {})",
+                request.name(),
+                peer.uid,
+                peer.pid
+            );
+        }
+
+        // Create response channel
+        let (resp_tx, resp_rx) = mpsc::channel();
+
+        // Send to main loop and wait for the response
+        if tx.send((request, peer, resp_tx)).is_ok() {
+            if let Ok(response) = resp_rx.recv_timeout(Duration::from_secs(5)) {
+                write_response(&mut stream, &response);
+            }
+        }
+    }
+}
+
+/// Polkit action that lets non-root users run privileged IPC requests
+const POLKIT_ACTION_MANAGE: &str = "io.github.fibsussy.keymux.manage";
+
+/// How long pkcheck may take before the request is refused
+const POLKIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Ask polkit (without prompting) whether a process may perform an action
+///
+/// Returns false when pkcheck isn't installed or doesn't answer within
+/// `POLKIT_TIMEOUT`, so polkit only ever widens access and can't stall the daemon.
+async fn polkit_allows(action_id: &str, pid: i32) -> bool {
+    let status = tokio::process::Command::new("pkcheck")
+        .args(["--action-id", action_id, "--process", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .status();
+    match tokio::time::timeout(POLKIT_TIMEOUT, status).await {
+        Ok(status) => status.is_ok_and(|status| status.success()),
+        Err(_) => {
+            warn!("pkcheck for {} took too long, denying", action_id);
+            false
+        }
+    }
+}
+
 /// Metadata about a keyboard
 #[derive(Debug, Clone)]
 struct KeyboardMeta {
@@ -27,6 +255,8 @@ struct KeyboardMeta {
     /// ALL event file paths for this logical keyboard
     paths: Vec<PathBuf>,
     connected: bool,
+    /// Another remapper's virtual output (see `grab_remapper_outputs`)
+    remapper_output: bool,
 }
 
 /// Active event processor thread handle
@@ -34,7 +264,14 @@ struct ProcessorHandle {
     shutdown_tx: crossbeam_channel::Sender<()>,
     game_mode_tx: mpsc::Sender<bool>,
     save_stats_tx: mpsc::Sender<()>,
+    command_tx: crossbeam_channel::Sender<ProcessorCommand>,
     thread_handle: Option<thread::JoinHandle<()>>,
+    /// Position of the event file among the keyboard's paths (to match them up on reconnect)
+    index: usize,
+    /// Event file the processor reads, reported back when the thread exits
+    event_path: Arc<std::sync::Mutex<PathBuf>>,
+    /// Layer and event counters, updated by the thread
+    status: Arc<ProcessorStatus>,
 }
 
 /// Async daemon orchestrator
@@ -51,10 +288,71 @@ pub struct AsyncDaemon {
     keyboard_owners: HashMap<KeyboardId, u32>,
     /// Current game mode state (preserved across thread restarts)
     game_mode_active: bool,
-    /// Receiver for processor thread death notifications (path of the dead processor)
-    processor_dead_rx: tokio_mpsc::UnboundedReceiver<PathBuf>,
+    /// What last set the game mode state, for GetGameModeStatus
+    game_mode_reason: String,
+    /// Game mode was set over IPC, so window detection leaves it alone until
+    /// `keymux gamemode auto`
+    game_mode_manual: bool,
+    /// Per-keyboard game mode overrides that ignore the global state
+    keyboard_game_mode: HashMap<KeyboardId, bool>,
+    /// Game mode overlay forced over IPC (None = each config's window rules pick it)
+    forced_overlay: Option<String>,
+    /// State buses linking processors: one per user when `share_state_across_keyboards`
+    /// is on (group None), otherwise one per `keyboard_groups` group
+    shared_states: HashMap<(u32, Option<String>), Arc<SharedKeyboardState>>,
+    /// Key presses seen on each keyboard's event nodes, for `dedupe_nodes_ms`
+    node_events: HashMap<KeyboardId, Arc<NodeEvents>>,
+    /// Positions among a keyboard's paths of the nodes left to the OS (`skip_pointer_nodes`)
+    skipped_nodes: HashMap<KeyboardId, HashSet<usize>>,
+    /// Owner and `keyboard_groups` group of running keyboards that are in one
+    keyboard_groups: HashMap<KeyboardId, (u32, String)>,
+    /// Secondary split halves forwarding to a primary, by event path, with the status of
+    /// both processors at linking time to notice restarts (`split_keyboards`)
+    split_links: HashMap<PathBuf, (Arc<ProcessorStatus>, Arc<ProcessorStatus>)>,
+    /// Last focused window reported by the window manager (for window_layers rules)
+    focused_window: Option<WindowInfo>,
+    /// Layers and `TapLock` locks saved by the previous daemon, applied when each
+    /// keyboard's processors start
+    restored_layers: HashMap<KeyboardId, (Vec<Layer>, TapLocks)>,
+    /// Disconnected keyboards whose processors are paused waiting for a reconnect
+    paused_keyboards: HashSet<KeyboardId>,
+    /// Keyboards whose owner was told the battery is low, until it's charged again
+    low_battery_warned: HashSet<KeyboardId>,
+    /// Profile each user's processors currently run with (absent = default)
+    active_profiles: HashMap<u32, String>,
+    /// Profiles forced over IPC, taking precedence over the schedule ("default" = none)
+    profile_overrides: HashMap<u32, String>,
+    /// Receiver for processor thread death notifications (path of the dead processor, error)
+    processor_dead_rx: tokio_mpsc::UnboundedReceiver<(PathBuf, Option<String>)>,
     /// Sender side kept on the daemon to clone into each new ProcessorHandle
-    processor_dead_tx: tokio_mpsc::UnboundedSender<PathBuf>,
+    processor_dead_tx: tokio_mpsc::UnboundedSender<(PathBuf, Option<String>)>,
+    /// Backoff state of keyboards whose processors crashed
+    processor_restarts: HashMap<KeyboardId, RestartBackoff>,
+    /// Keyboards left alone after crashing `QUARANTINE_AFTER` times in a row
+    quarantined: HashMap<KeyboardId, QuarantineInfo>,
+    /// Keyboards whose restart delay has passed
+    processor_restart_rx: tokio_mpsc::UnboundedReceiver<KeyboardId>,
+    processor_restart_tx: tokio_mpsc::UnboundedSender<KeyboardId>,
+    /// Events processors send up: layer changes, heartbeats and requests such as a
+    /// `KeyboardToggle` key being pressed
+    processor_event_rx: tokio_mpsc::UnboundedReceiver<ProcessorEvent>,
+    processor_event_tx: EventSender,
+    /// Last heartbeat of each processor, by event path
+    processor_heartbeats: HashMap<PathBuf, std::time::Instant>,
+    /// Processors already reported as not sending heartbeats
+    stalled_processors: HashSet<PathBuf>,
+    /// Processors whose owner was told they were throttled for spinning
+    busy_processors: HashSet<PathBuf>,
+    /// Queue into the main loop shared by every IPC socket
+    ipc_tx: Option<tokio_mpsc::UnboundedSender<IpcMessage>>,
+    /// Per-uid limits shared by every IPC socket
+    ipc_rate_limiter: Arc<std::sync::Mutex<RateLimiter>>,
+    /// Private IPC sockets of active users
+    user_sockets: HashMap<u32, UserSocket>,
+    /// Hot reload watcher, told which users' configs to watch as sessions change
+    config_watcher: Option<ConfigWatcher>,
+    /// When the daemon started, for `keymux status`
+    started_at: std::time::Instant,
 }
 
 impl AsyncDaemon {
@@ -70,19 +368,58 @@ impl AsyncDaemon {
             ));
         }
 
+        Ok(Self::empty())
+    }
+
+    /// Daemon that hasn't found any keyboards or users yet
+    fn empty() -> Self {
         let session_manager = SessionManager::new();
         let (processor_dead_tx, processor_dead_rx) = tokio_mpsc::unbounded_channel();
+        let (processor_restart_tx, processor_restart_rx) = tokio_mpsc::unbounded_channel();
+        let (processor_event_tx, processor_event_rx) = tokio_mpsc::unbounded_channel();
 
-        Ok(Self {
+        Self {
             user_configs: HashMap::new(),
             session_manager,
             all_keyboards: HashMap::new(),
             active_processors: HashMap::new(),
             keyboard_owners: HashMap::new(),
             game_mode_active: false,
+            game_mode_reason: "nothing has turned it on yet".to_string(),
+            game_mode_manual: false,
+            keyboard_game_mode: HashMap::new(),
+            forced_overlay: None,
+            shared_states: HashMap::new(),
+            node_events: HashMap::new(),
+            skipped_nodes: HashMap::new(),
+            keyboard_groups: HashMap::new(),
+            split_links: HashMap::new(),
+            focused_window: None,
+            restored_layers: HashMap::new(),
+            paused_keyboards: HashSet::new(),
+            low_battery_warned: HashSet::new(),
+            active_profiles: HashMap::new(),
+            profile_overrides: HashMap::new(),
             processor_dead_rx,
             processor_dead_tx,
-        })
+            processor_restarts: HashMap::new(),
+            quarantined: QuarantineNotes::load()
+                .into_iter()
+                .map(|(id, info)| (KeyboardId::new(id), info))
+                .collect(),
+            processor_restart_rx,
+            processor_restart_tx,
+            processor_event_rx,
+            processor_event_tx,
+            processor_heartbeats: HashMap::new(),
+            stalled_processors: HashSet::new(),
+            busy_processors: HashSet::new(),
+            ipc_tx: None,
+            ipc_rate_limiter: Arc::new(std::sync::Mutex::new(RateLimiter::new())),
+            user_sockets: HashMap::new(),
+            config_watcher: None,
+            started_at: std::time::Instant::now(),
+        }
     }
 
     /// Run the async daemon event loop
@@ -94,7 +431,9 @@ impl AsyncDaemon {
         let mut hotplug_rx = self.start_hotplug_monitor();
         let mut ipc_rx = self.start_ipc_server()?;
         let mut niri_rx = self.start_niri_monitor();
-        let mut config_watch_rx = self.start_config_watcher();
+        let mut system_shutdown_rx = crate::daemon::shutdown_inhibit::start();
+        let (config_watcher, mut config_watch_rx) = ConfigWatcher::start();
+        self.config_watcher = Some(config_watcher);
 
         // Initial session and keyboard discovery
         info!("Refreshing user sessions...");
@@ -106,20 +445,30 @@ impl AsyncDaemon {
         // Load user configs and sync keyboards
         info!("Loading user configs...");
         self.load_user_configs().await;
+        self.restore_state();
 
         info!("Syncing keyboards to users...");
+        self.apply_profiles().await;
         self.sync_keyboards_to_users().await;
 
+        // Save runtime state and release keyboards when asked to stop
+        let mut sigterm = signal(SignalKind::terminate())?;
+        let mut sigint = signal(SignalKind::interrupt())?;
+
         // Main event loop - use async recv for zero CPU usage when idle
         let mut session_check = tokio::time::interval(Duration::from_secs(5));
+        // Schedule rules have minute resolution
+        let mut schedule_check = tokio::time::interval(Duration::from_secs(30));
+        // Batteries drain slowly; the kernel only updates them every few minutes anyway
+        let mut battery_check = tokio::time::interval(Duration::from_secs(120));
         // Pending hotplug debounce: armed when we receive an add/remove event, fires after settling
         let mut hotplug_debounce: Option<tokio::time::Instant> = None;
-        const HOTPLUG_DEBOUNCE_MS: u64 = 300;
+        let mut hotplug_settle = Duration::ZERO;
 
         loop {
             // Compute how long until the debounce timer fires (if armed)
             let debounce_deadline = hotplug_debounce.map(|t| {
-                let settle = t + Duration::from_millis(HOTPLUG_DEBOUNCE_MS);
+                let settle = t + hotplug_settle;
                 let now = tokio::time::Instant::now();
                 if settle > now {
                     settle - now
@@ -130,13 +479,16 @@ impl AsyncDaemon {
 
             tokio::select! {
                 Some(event) = hotplug_rx.recv() => {
-                    // Only react to "add" and "remove" events. We use --udev so events only
-                    // fire after udev rule processing is complete (device node fully ready).
-                    // However, a single physical replug fires many udev events in rapid
-                    // succession — arm a debounce timer so we act once after things settle.
-                    if event.contains(" add ") || event.contains(" remove ") {
-                        debug!("Hotplug event (add/remove): {}", event);
+                    // Only react to the configured actions ("add" and "remove" by default).
+                    // We use --udev so events only fire after udev rule processing is
+                    // complete (device node fully ready). However, a single physical replug
+                    // fires many udev events in rapid succession — arm a debounce timer so
+                    // we act once after things settle.
+                    let hotplug = self.hotplug_config().await;
+                    if hotplug.triggers(&event) {
+                        debug!("Hotplug event: {}", event);
                         hotplug_debounce = Some(tokio::time::Instant::now());
+                        hotplug_settle = Duration::from_millis(u64::from(hotplug.debounce_ms));
                     }
                 }
                 // Debounce timer fired — drain any remaining queued events then resync
@@ -162,16 +514,52 @@ impl AsyncDaemon {
                         self.sync_keyboards_to_users().await;
                     }
                 }
-                Some((request, resp_tx)) = ipc_rx.recv() => {
-                    debug!("IPC request: {:?}", request);
+                Some((mut request, peer, resp_tx)) = ipc_rx.recv() => {
+                    debug!("IPC request from uid {}: {:?}", peer.uid, request);
+                    if let Err(reason) = self.authorize(&peer, &mut request).await {
+                        warn!(
+                            "audit: denied {} from uid {} (pid {}): {}",
+                            request.name(),
+                            peer.uid,
+                            peer.pid,
+                            reason
+                        );
+                        let _ = resp_tx.send(IpcResponse::Error(format!("Permission denied: {}", reason)));
+                        continue;
+                    }
+                    let shutdown = matches!(request, IpcRequest::Shutdown);
                     let response = self.handle_ipc_request(request).await;
                     let _ = resp_tx.send(response);
+                    if shutdown {
+                        self.shutdown().await;
+                        return Ok(());
+                    }
+                }
+                _ = sigterm.recv() => {
+                    info!("SIGTERM received, shutting down");
+                    self.shutdown().await;
+                    return Ok(());
+                }
+                Some(done_tx) = system_shutdown_rx.recv() => {
+                    self.flush_adaptive_stats_all().await;
+                    let _ = done_tx.send(());
+                }
+                _ = sigint.recv() => {
+                    info!("SIGINT received, shutting down");
+                    self.shutdown().await;
+                    return Ok(());
                 }
                 Some(event) = niri_rx.recv() => {
                     self.process_niri_event(event).await;
                 }
-                Some(()) = config_watch_rx.recv() => {
-                    // Check if hot config reload is enabled for ANY user
+                Some(config_path) = config_watch_rx.recv() => {
+                    // A user's first config is picked up even without hot reload
+                    let first_config = !self
+                        .user_configs
+                        .values()
+                        .any(|mgr| mgr.get_config_path() == config_path);
+
+                    // Otherwise reload if hot config reload is enabled for ANY user
                     let mut hot_reload_enabled = false;
                     for mgr in self.user_configs.values() {
                         let config = mgr.get_config().await;
@@ -181,7 +569,10 @@ impl AsyncDaemon {
                         }
                     }
 
-                    if hot_reload_enabled {
+                    if first_config {
+                        info!("New config at {:?}, loading it", config_path);
+                        self.sync_keyboards_to_users().await;
+                    } else if hot_reload_enabled {
                         info!("Config file changed, reloading...");
                         if let Err(e) = self.reload_all_configs().await {
                             error!("Config reload failed: {}", e);
@@ -191,24 +582,22 @@ impl AsyncDaemon {
                 _ = session_check.tick() => {
                     self.refresh_sessions().await;
                     self.sync_keyboards_to_users().await;
+                    self.check_processor_heartbeats();
                 }
-                Some(dead_path) = self.processor_dead_rx.recv() => {
-                    // A processor thread died (ENODEV or error) — clean up immediately
-                    // without waiting for a udev event to trigger rediscovery.
-                    if let Some((kbd_id, _, _)) = self.active_processors.remove(&dead_path) {
-                        info!("Processor thread died for: {} ({})", dead_path.display(), kbd_id);
-                        // If this was the last processor for this keyboard, mark it disconnected
-                        let any_remaining = self.active_processors
-                            .values()
-                            .any(|(k, _, _)| k == &kbd_id);
-                        if !any_remaining {
-                            info!("All processors dead for {}, marking disconnected", kbd_id);
-                            if let Some(meta) = self.all_keyboards.get_mut(&kbd_id) {
-                                meta.connected = false;
-                            }
-                            self.keyboard_owners.remove(&kbd_id);
-                        }
-                    }
+                _ = schedule_check.tick() => {
+                    self.apply_profiles().await;
+                }
+                _ = battery_check.tick() => {
+                    self.check_batteries().await;
+                }
+                Some((dead_path, cause)) = self.processor_dead_rx.recv() => {
+                    self.handle_dead_processor(&dead_path, cause).await;
+                }
+                Some(kbd_id) = self.processor_restart_rx.recv() => {
+                    self.restart_processors(&kbd_id).await;
+                }
+                Some(event) = self.processor_event_rx.recv() => {
+                    self.handle_processor_event(event).await;
                 }
             }
         }
@@ -254,6 +643,7 @@ impl AsyncDaemon {
                     name: kbd_name,
                     paths,
                     connected: true,
+                    remapper_output: logical_kbd.remapper_output,
                 },
             );
         }
@@ -282,9 +672,21 @@ impl AsyncDaemon {
             .collect();
 
         for kbd_id in disconnected_keyboards {
+            // Paused processors stop themselves once their grace period runs out (never
+            // with disconnect_grace_forever)
+            let has_processors = self
+                .active_processors
+                .values()
+                .any(|(k, _, _)| k == &kbd_id);
+            if has_processors && self.keeps_processors_paused(&kbd_id).await {
+                self.paused_keyboards.insert(kbd_id);
+                continue;
+            }
             info!("Stopping processors for disconnected keyboard: {}", kbd_id);
             let _ = self.stop_processors_for_keyboard(&kbd_id).await;
-            self.keyboard_owners.remove(&kbd_id);
+            if let Some(uid) = self.keyboard_owners.remove(&kbd_id) {
+                self.run_keyboard_hook(uid, &kbd_id, false).await;
+            }
         }
 
         // Collect keyboard data first to avoid borrow checker issues
@@ -297,6 +699,9 @@ impl AsyncDaemon {
 
         // For each keyboard, check if any active user wants it
         for (kbd_id, meta) in keyboards {
+            if self.quarantined.contains_key(&kbd_id) {
+                continue;
+            }
             let mut assigned_uid = None;
 
             // Check existing ownership first
@@ -314,7 +719,8 @@ impl AsyncDaemon {
                             &kbd_id.to_string(),
                             Some(&meta.name),
                             event_path,
-                        );
+                        ) && (!meta.remapper_output
+                            || config.allows_remapper_output(&kbd_id, &meta.name));
 
                         if enabled {
                             assigned_uid = Some(owner_uid);
@@ -351,7 +757,8 @@ impl AsyncDaemon {
                         &kbd_id.to_string(),
                         Some(&meta.name),
                         event_path,
-                    );
+                    ) && (!meta.remapper_output
+                        || config.allows_remapper_output(&kbd_id, &meta.name));
 
                     if wants_keyboard {
                         info!("Assigning keyboard {} to user {}", meta.name, uid);
@@ -365,13 +772,22 @@ impl AsyncDaemon {
             match assigned_uid {
                 Some(uid) => {
                     // Check if already running for this user by checking if ANY event path for this keyboard is active
-                    let has_active_processors = meta.paths.iter().any(|path| {
+                    let mut has_active_processors = meta.paths.iter().any(|path| {
                         self.active_processors
                             .get(path)
                             .map(|(_, owner_uid, _)| *owner_uid == uid)
                             .unwrap_or(false)
                     });
 
+                    // A board back within its grace period resumes its paused processors;
+                    // if that fails they're restarted, since the event paths may be reused
+                    if self.paused_keyboards.remove(&kbd_id) {
+                        if self.reattach_processors(&kbd_id, &meta, uid) {
+                            continue;
+                        }
+                        has_active_processors = false;
+                    }
+
                     if !has_active_processors {
                         // Stop any existing processors for this keyboard (might be owned by different user)
                         let _ = self.stop_processors_for_keyboard(&kbd_id).await;
@@ -384,6 +800,24 @@ impl AsyncDaemon {
                             error!("Failed to start processors for user {}: {}", uid, e);
                         } else {
                             self.keyboard_owners.insert(kbd_id.clone(), uid);
+                            self.run_user_hook(
+                                uid,
+                                |hooks| &hooks.on_keyboard_connect,
+                                "keyboard_connect",
+                                vec![
+                                    ("KEYMUX_KEYBOARD_ID", kbd_id.to_string()),
+                                    ("KEYMUX_KEYBOARD_NAME", meta.name.clone()),
+                                ],
+                            )
+                            .await;
+                            self.run_keyboard_hook(uid, &kbd_id, true).await;
+                            self.notify_user(
+                                uid,
+                                NotificationKind::KeyboardConnect,
+                                "Keyboard connected",
+                                &format!("keymux is handling {}", meta.name),
+                            )
+                            .await;
                         }
                     }
                 }
@@ -405,6 +839,96 @@ impl AsyncDaemon {
                 }
             }
         }
+
+        self.link_split_halves().await;
+    }
+
+    /// Point secondary split halves at their primary's processor (`split_keyboards`)
+    ///
+    /// Links are sent again when either processor was restarted. A secondary whose
+    /// primary is gone is told to type alone (it also does so by itself as soon as
+    /// forwarding fails).
+    async fn link_split_halves(&mut self) {
+        let mut configs: HashMap<u32, Config> = HashMap::new();
+        for (_, uid, _) in self.active_processors.values() {
+            if let (false, Some(config_mgr)) =
+                (configs.contains_key(uid), self.user_configs.get(uid))
+            {
+                configs.insert(*uid, config_mgr.get_config().await);
+            }
+        }
+        let name_of = |kbd_id: &KeyboardId| {
+            self.all_keyboards
+                .get(kbd_id)
+                .map_or("", |meta| meta.name.as_str())
+        };
+
+        // Secondary event path -> primary event path
+        let mut wanted: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for (path, (kbd_id, uid, _)) in &self.active_processors {
+            let Some(primary) = configs
+                .get(uid)
+                .and_then(|config| config.split_primary_of(kbd_id, name_of(kbd_id)))
+            else {
+                continue;
+            };
+            // The primary's lowest event path, so the choice is stable
+            let primary_path = self
+                .active_processors
+                .iter()
+                .filter(|(_, (other, owner, _))| {
+                    owner == uid
+                        && other != kbd_id
+                        && (other.matches_config_entry(primary) || name_of(other).contains(primary))
+                })
+                .map(|(path, _)| path)
+                .min();
+            if let Some(primary_path) = primary_path {
+                wanted.insert(path.clone(), primary_path.clone());
+            }
+        }
+
+        let stale: Vec<PathBuf> = self
+            .split_links
+            .keys()
+            .filter(|path| !wanted.contains_key(*path))
+            .cloned()
+            .collect();
+        for path in stale {
+            self.split_links.remove(&path);
+            if let Some((_, _, handle)) = self.active_processors.get(&path) {
+                let _ = handle.command_tx.send(ProcessorCommand::ForwardKeys(None));
+            }
+        }
+
+        for (secondary_path, primary_path) in wanted {
+            let (Some((_, _, secondary)), Some((primary_id, _, primary))) = (
+                self.active_processors.get(&secondary_path),
+                self.active_processors.get(&primary_path),
+            ) else {
+                continue;
+            };
+            let linked = self.split_links.get(&secondary_path).is_some_and(|(s, p)| {
+                Arc::ptr_eq(s, &secondary.status) && Arc::ptr_eq(p, &primary.status)
+            });
+            if linked {
+                continue;
+            }
+            info!(
+                "Linking split half {} to {}",
+                secondary_path.display(),
+                primary_id
+            );
+            let _ = secondary
+                .command_tx
+                .send(ProcessorCommand::ForwardKeys(Some(
+                    primary.command_tx.clone(),
+                )));
+            self.split_links.insert(
+                secondary_path,
+                (Arc::clone(&secondary.status), Arc::clone(&primary.status)),
+            );
+        }
     }
 
     /// Load configs for all active users
@@ -428,7 +952,7 @@ impl AsyncDaemon {
                 }
             };
 
-            let config_path = home_dir.join(".config/keymux/config.ron");
+            let config_path = crate::paths::config_path_in(&home_dir);
 
             // Load user's config
             match ConfigManager::new(config_path.clone()) {
@@ -444,6 +968,10 @@ impl AsyncDaemon {
 
         // Remove configs for inactive users
         self.user_configs.retain(|uid, _| active_uids.contains(uid));
+        self.sync_user_sockets(&active_uids);
+        if let Some(watcher) = &mut self.config_watcher {
+            watcher.sync_users(&active_uids, crate::get_user_home_dir);
+        }
     }
 
     /// Get list of active user UIDs
@@ -451,26 +979,6 @@ impl AsyncDaemon {
         self.session_manager.get_active_uids().await
     }
 
-    /// Get username from UID
-    fn get_username(&self, uid: u32) -> Result<String> {
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(format!("getent passwd {} | cut -d: -f1", uid))
-            .output()?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("Failed to get username for UID {}", uid));
-        }
-
-        let username = String::from_utf8(output.stdout)?.trim().to_string();
-
-        if username.is_empty() {
-            return Err(anyhow::anyhow!("Empty username for UID {}", uid));
-        }
-
-        Ok(username)
-    }
-
     /// Get user's home directory
     fn get_user_home_dir(&self, uid: u32) -> Result<PathBuf> {
         let output = Command::new("sh")
@@ -494,45 +1002,6 @@ impl AsyncDaemon {
         Ok(PathBuf::from(home))
     }
 
-    /// Send desktop notification to a user
-    fn send_notification(&self, uid: u32, title: &str, message: &str, urgency: &str) {
-        info!("Attempting to send notification to user {}: {}", uid, title);
-
-        // Get username from UID
-        let username = match self.get_username(uid) {
-            Ok(name) => name,
-            Err(e) => {
-                error!("Failed to get username for UID {}: {}", uid, e);
-                return;
-            }
-        };
-
-        info!("Resolved UID {} to username: {}", uid, username);
-
-        match Command::new("runuser")
-            .args([
-                "-u",
-                &username,
-                "--",
-                "/usr/bin/notify-send",
-                "-u",
-                urgency,
-                title,
-                message,
-            ])
-            .spawn()
-        {
-            Ok(_) => info!(
-                "Sent {} notification to user {} ({}): {}",
-                urgency, username, uid, title
-            ),
-            Err(e) => error!(
-                "Failed to send notification to user {} ({}): {}",
-                username, uid, e
-            ),
-        }
-    }
-
     /// Start event processors for ALL event files of a keyboard - ONE THREAD PER EVENT FILE!
     async fn start_processors_for_keyboard(
         &mut self,
@@ -556,9 +1025,35 @@ impl AsyncDaemon {
             .context("User config not loaded")?
             .get_config_path();
 
-        // Apply per-keyboard config overrides
+        // Apply the active profile, then per-keyboard config overrides
+        let base_config = match self.active_profiles.get(&uid) {
+            Some(profile) => base_config.with_profile(profile),
+            None => base_config,
+        };
         let config = base_config.for_keyboard(&kbd_id.to_string());
 
+        // Join the keyboard's group, taking on a game mode override the group has
+        let group = base_config
+            .keyboard_group(kbd_id, kbd_name)
+            .map(str::to_string);
+        match &group {
+            Some(group) => {
+                let member = (uid, group.clone());
+                let group_override = self
+                    .keyboard_groups
+                    .iter()
+                    .filter(|(id, other)| *id != kbd_id && **other == member)
+                    .find_map(|(id, _)| self.keyboard_game_mode.get(id).copied());
+                if let Some(enabled) = group_override {
+                    self.keyboard_game_mode.insert(kbd_id.clone(), enabled);
+                }
+                self.keyboard_groups.insert(kbd_id.clone(), member);
+            }
+            None => {
+                self.keyboard_groups.remove(kbd_id);
+            }
+        }
+
         info!(
             "Starting {} event processor thread(s) for: {} (user: {})",
             event_paths.len(),
@@ -568,6 +1063,7 @@ impl AsyncDaemon {
 
         // Track which paths we successfully started so we can roll back on partial failure
         let mut started_paths: Vec<PathBuf> = Vec::new();
+        let mut skipped = HashSet::new();
 
         // Spawn ONE THREAD PER EVENT FILE
         for (idx, event_path) in event_paths.iter().enumerate() {
@@ -595,11 +1091,21 @@ impl AsyncDaemon {
                     return Err(e);
                 }
             };
+            if config.skip_pointer_nodes && is_pointer_node(&device) {
+                info!(
+                    "Leaving pointer node {} of {} to the OS (skip_pointer_nodes)",
+                    event_path.display(),
+                    kbd_name
+                );
+                skipped.insert(idx);
+                continue;
+            }
 
             // Create channels
             let (shutdown_tx, shutdown_rx) = crossbeam_channel::bounded(1);
             let (game_mode_tx, game_mode_rx) = mpsc::channel();
             let (save_stats_tx, save_stats_rx) = mpsc::channel();
+            let (command_tx, command_rx) = crossbeam_channel::unbounded();
 
             // Spawn ONE real thread per event file — no wrapper, the JoinHandle
             // tracks the actual processor loop.  A clone of dead_tx is moved into
@@ -608,9 +1114,14 @@ impl AsyncDaemon {
             let kbd_id_clone = kbd_id.clone();
             let kbd_name_clone = kbd_name.to_string();
             let event_path_clone = event_path.clone();
+            let current_path = Arc::new(std::sync::Mutex::new(event_path.clone()));
+            let thread_path = Arc::clone(&current_path);
             let config_clone = config.clone();
             let config_path_clone = config_path.clone();
             let dead_tx = self.processor_dead_tx.clone();
+            let daemon_tx = self.processor_event_tx.clone();
+            let status = Arc::new(ProcessorStatus::default());
+            let thread_status = Arc::clone(&status);
 
             let handle = thread::spawn(move || {
                 info!(
@@ -618,21 +1129,45 @@ impl AsyncDaemon {
                     kbd_name_clone,
                     event_path_clone.display()
                 );
-                event_processor::run_processor(
-                    kbd_id_clone,
-                    device,
-                    kbd_name_clone,
-                    config_clone,
-                    config_path_clone,
-                    uid,
-                    shutdown_rx,
-                    game_mode_rx,
-                    save_stats_rx,
-                );
-                // Notify daemon that this processor is gone
-                let _ = dead_tx.send(event_path_clone);
+                // A panic is reported like an error, so a keyboard that trips one gets
+                // the same restarts and quarantine instead of silently going dead
+                let processor_path = Arc::clone(&thread_path);
+                let cause = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+                    event_processor::run_processor(
+                        kbd_id_clone,
+                        device,
+                        processor_path,
+                        kbd_name_clone,
+                        config_clone,
+                        config_path_clone,
+                        uid,
+                        shutdown_rx,
+                        game_mode_rx,
+                        save_stats_rx,
+                        command_rx,
+                        daemon_tx,
+                        thread_status,
+                    )
+                }))
+                .unwrap_or_else(|panic| {
+                    let message = panic
+                        .downcast_ref::<&str>()
+                        .map(|s| (*s).to_string())
+                        .or_else(|| panic.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "unknown panic".to_string());
+                    Some(format!("panicked: {}", message))
+                });
+                // Notify daemon that this processor is gone (under its latest path)
+                let path = thread_path
+                    .lock()
+                    .map_or_else(|e| e.into_inner().clone(), |p| p.clone());
+                let _ = dead_tx.send((path, cause));
             });
 
+            // Counts as alive until its first heartbeat is due
+            self.processor_heartbeats
+                .insert(event_path.clone(), std::time::Instant::now());
+
             // Store processor handle indexed by EVENT PATH
             self.active_processors.insert(
                 event_path.clone(),
@@ -643,7 +1178,11 @@ impl AsyncDaemon {
                         shutdown_tx,
                         game_mode_tx: game_mode_tx.clone(),
                         save_stats_tx: save_stats_tx.clone(),
+                        command_tx,
                         thread_handle: Some(handle),
+                        index: idx,
+                        event_path: current_path,
+                        status,
                     },
                 ),
             );
@@ -652,7 +1191,64 @@ impl AsyncDaemon {
             started_paths.push(event_path.clone());
 
             // Send current game mode state to the new thread to preserve state across restarts
-            let _ = game_mode_tx.send(self.game_mode_active);
+            let game_mode = self.game_mode_for(kbd_id);
+            let _ = game_mode_tx.send(game_mode);
+
+            // Link this processor with the user's other keyboards if they opted in, or
+            // with the rest of its group
+            let bus = if base_config.share_state_across_keyboards {
+                Some((uid, None))
+            } else {
+                group.clone().map(|group| (uid, Some(group)))
+            };
+            if let Some(bus) = bus {
+                let shared = self.shared_states.entry(bus).or_default();
+                if let Some((_, _, handle)) = self.active_processors.get(event_path) {
+                    let _ = handle
+                        .command_tx
+                        .send(ProcessorCommand::AttachSharedState(Arc::clone(shared)));
+                }
+            }
+
+            // Let the keyboard's event nodes drop the keystrokes they all report
+            if config.dedupe_nodes_ms > 0 {
+                let events = self.node_events.entry(kbd_id.clone()).or_default();
+                if let Some((_, _, handle)) = self.active_processors.get(event_path) {
+                    let _ = handle
+                        .command_tx
+                        .send(ProcessorCommand::AttachNodeEvents(Arc::clone(events)));
+                }
+            }
+
+            // Start on the layer the focused window asks for
+            if let Some(layer) = self
+                .focused_window
+                .as_ref()
+                .and_then(|window| window_layer(&base_config, window))
+            {
+                if let Some((_, _, handle)) = self.active_processors.get(event_path) {
+                    let _ = handle
+                        .command_tx
+                        .send(ProcessorCommand::SetWindowLayer(Some(layer)));
+                }
+            }
+            if self.forced_overlay.is_some() {
+                if let Some((_, _, handle)) = self.active_processors.get(event_path) {
+                    let _ = handle.command_tx.send(ProcessorCommand::SetGameModeOverlay(
+                        self.forced_overlay.clone(),
+                    ));
+                }
+            }
+            if let Some(window) = &self.focused_window {
+                if let Some((_, _, handle)) = self.active_processors.get(event_path) {
+                    let _ = handle.command_tx.send(ProcessorCommand::SetSnippetsEnabled(
+                        base_config.snippets.enabled_for(window.app_id.as_deref()),
+                    ));
+                    let _ = handle
+                        .command_tx
+                        .send(ProcessorCommand::SetFocusedApp(window.app_id.clone()));
+                }
+            }
 
             info!(
                 "Started thread {}/{} for {} at {} (game_mode: {})",
@@ -660,67 +1256,328 @@ impl AsyncDaemon {
                 event_paths.len(),
                 kbd_name,
                 event_path.display(),
-                self.game_mode_active
+                game_mode
             );
         }
 
+        self.skipped_nodes.insert(kbd_id.clone(), skipped);
+
+        // Bring back layers that were active before the daemon restarted
+        if let Some((layers, locks)) = self.restored_layers.remove(kbd_id) {
+            for path in &started_paths {
+                if let Some((_, _, handle)) = self.active_processors.get(path) {
+                    let _ = handle.command_tx.send(ProcessorCommand::RestoreLayers(
+                        layers.clone(),
+                        locks.clone(),
+                    ));
+                }
+            }
+        }
+
         Ok(())
     }
 
-    /// Stop ALL event processors for a keyboard
-    async fn stop_processors_for_keyboard(&mut self, kbd_id: &KeyboardId) -> Result<()> {
-        // Find all event paths for this keyboard
-        let paths_to_stop: Vec<PathBuf> = self
+    /// Clean up after a processor thread exited on its own
+    ///
+    /// Runs as soon as the thread is gone, without waiting for a udev event. A
+    /// processor that failed while its event file is still there crashed rather
+    /// than lost its device, so the keyboard gets restarted after a backoff.
+    async fn handle_dead_processor(&mut self, dead_path: &std::path::Path, cause: Option<String>) {
+        // Processors stopped on purpose were already removed
+        let Some((kbd_id, uid, _)) = self.active_processors.remove(dead_path) else {
+            return;
+        };
+        match &cause {
+            Some(cause) => warn!(
+                "Processor thread died for: {} ({}): {}",
+                dead_path.display(),
+                kbd_id,
+                cause
+            ),
+            None => info!(
+                "Processor thread exited for: {} ({})",
+                dead_path.display(),
+                kbd_id
+            ),
+        }
+
+        if let Some(cause) = cause {
+            if dead_path.exists() && self.keyboard_owners.get(&kbd_id) == Some(&uid) {
+                self.schedule_processor_restart(kbd_id, cause).await;
+                return;
+            }
+        }
+
+        // If this was the last processor for this keyboard, mark it disconnected
+        let any_remaining = self
             .active_processors
-            .iter()
-            .filter(|(_, (k_id, _, _))| k_id == kbd_id)
-            .map(|(path, _)| path.clone())
-            .collect();
+            .values()
+            .any(|(k, _, _)| k == &kbd_id);
+        if !any_remaining {
+            info!("All processors dead for {}, marking disconnected", kbd_id);
+            self.mark_disconnected(&kbd_id).await;
+        }
+    }
 
-        if paths_to_stop.is_empty() {
-            return Ok(());
+    async fn mark_disconnected(&mut self, kbd_id: &KeyboardId) {
+        self.paused_keyboards.remove(kbd_id);
+        if let Some(meta) = self.all_keyboards.get_mut(kbd_id) {
+            meta.connected = false;
+        }
+        if let Some(uid) = self.keyboard_owners.remove(kbd_id) {
+            self.run_keyboard_hook(uid, kbd_id, false).await;
         }
+    }
 
+    /// Restart a keyboard's processors after a delay that doubles with each crash, or
+    /// quarantine it once it has crashed too often in a row
+    async fn schedule_processor_restart(&mut self, kbd_id: KeyboardId, error: String) {
+        let now = std::time::Instant::now();
+        let backoff = self
+            .processor_restarts
+            .entry(kbd_id.clone())
+            .or_insert_with(|| RestartBackoff::new(now));
+        if backoff.is_pending() {
+            return;
+        }
+        let delay = match backoff.on_crash(now) {
+            CrashResponse::Restart(delay) => delay,
+            CrashResponse::Quarantine(crashes) => {
+                self.quarantine(&kbd_id, error, crashes).await;
+                return;
+            }
+        };
         info!(
-            "Stopping {} processor thread(s) for: {}",
-            paths_to_stop.len(),
-            kbd_id
+            "Restarting processors for {} in {}ms (attempt {})",
+            kbd_id,
+            delay.as_millis(),
+            backoff.attempts()
         );
 
-        // Collect thread handles after sending all shutdown signals, then await them.
-        // This ensures the old processor has fully released its device grab before we
-        // attempt to open and re-grab the device for the new processor on reconnect.
-        let mut join_tasks = Vec::new();
-
-        for path in paths_to_stop {
-            if let Some((_, _, mut handle)) = self.active_processors.remove(&path) {
-                // Send shutdown signal
-                let _ = handle.shutdown_tx.send(());
+        let restart_tx = self.processor_restart_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = restart_tx.send(kbd_id);
+        });
+    }
 
-                if let Some(thread_handle) = handle.thread_handle.take() {
-                    // Await the thread with a generous timeout so we don't block forever
-                    // on a stuck thread, but DO wait long enough for the ungrab to complete.
-                    let path_str = path.display().to_string();
-                    join_tasks.push((path_str, thread_handle));
-                } else {
-                    info!("Stopped processor for: {}", path.display());
-                }
-            }
+    /// Stop a keyboard's processors for good and tell its owner why
+    ///
+    /// The rest of the daemon carries on; the keyboard types as it would without keymux
+    /// until `keymux unquarantine` lets it run again.
+    async fn quarantine(&mut self, kbd_id: &KeyboardId, error: String, crashes: u32) {
+        let name = self
+            .all_keyboards
+            .get(kbd_id)
+            .map_or_else(|| kbd_id.to_string(), |meta| meta.name.clone());
+        error!(
+            "Quarantining {} after {} crashes in a row: {}",
+            name, crashes, error
+        );
+        let _ = self.stop_processors_for_keyboard(kbd_id).await;
+        self.processor_restarts.remove(kbd_id);
+        if let Some(&uid) = self.keyboard_owners.get(kbd_id) {
+            self.notify_user(
+                uid,
+                NotificationKind::Error,
+                "Keyboard quarantined",
+                &format!(
+                    "{} crashed {} times in a row and is left to the OS: {}\n\
+                     Run `keymux unquarantine \"{}\"` to try again.",
+                    name, crashes, error, name
+                ),
+            )
+            .await;
         }
+        let since = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.quarantined.insert(
+            kbd_id.clone(),
+            QuarantineInfo {
+                error,
+                crashes,
+                since,
+            },
+        );
+        self.save_quarantine();
+    }
 
-        // Spawn all join tasks concurrently so N threads shut down in parallel
-        // (worst-case time = slowest thread, not sum of all threads).
-        let join_futures: Vec<_> = join_tasks
-            .into_iter()
-            .map(|(path_str, thread_handle)| {
-                tokio::task::spawn_blocking(move || {
-                    let _ = thread_handle.join();
-                    path_str
-                })
-            })
+    fn save_quarantine(&self) {
+        let notes: HashMap<String, QuarantineInfo> = self
+            .quarantined
+            .iter()
+            .map(|(id, info)| (id.to_string(), info.clone()))
             .collect();
+        if let Err(e) = QuarantineNotes::save(&notes) {
+            warn!("Failed to save quarantined keyboards: {}", e);
+        }
+    }
 
-        for fut in join_futures {
+    /// Restart a crashed keyboard's processors if it is still plugged in and owned
+    async fn restart_processors(&mut self, kbd_id: &KeyboardId) {
+        let Some(backoff) = self.processor_restarts.get_mut(kbd_id) else {
+            return;
+        };
+        backoff.restarted(std::time::Instant::now());
+
+        // Released or reassigned while waiting
+        let Some(&uid) = self.keyboard_owners.get(kbd_id) else {
+            return;
+        };
+        let Some(meta) = self.all_keyboards.get(kbd_id).cloned() else {
+            return;
+        };
+        if !meta.connected {
+            return;
+        }
+        // A hotplug resync may have restarted it already
+        let skipped = self.skipped_nodes.get(kbd_id);
+        if meta.paths.iter().enumerate().all(|(index, path)| {
+            self.active_processors.contains_key(path)
+                || skipped.is_some_and(|skipped| skipped.contains(&index))
+        }) {
+            return;
+        }
+
+        let _ = self.stop_processors_for_keyboard(kbd_id).await;
+        if !meta.paths.iter().all(|path| path.exists()) {
+            info!("{} is gone, not restarting its processors", meta.name);
+            self.mark_disconnected(kbd_id).await;
+            return;
+        }
+        match self
+            .start_processors_for_keyboard(kbd_id, &meta.name, &meta.paths, uid)
+            .await
+        {
+            Ok(()) => {
+                info!("Restarted processors for {}", meta.name);
+                self.link_split_halves().await;
+            }
+            Err(e) => {
+                warn!("Failed to restart processors for {}: {}", meta.name, e);
+                self.schedule_processor_restart(kbd_id.clone(), e.to_string())
+                    .await;
+            }
+        }
+    }
+
+    /// Hand a reconnected keyboard's event files to its paused processors
+    ///
+    /// Returns false (leaving everything as is) unless every paused processor
+    /// gets its device back.
+    fn reattach_processors(&mut self, kbd_id: &KeyboardId, meta: &KeyboardMeta, uid: u32) -> bool {
+        let paused: Vec<(PathBuf, usize)> = self
+            .active_processors
+            .iter()
+            .filter(|(_, (k_id, owner, _))| k_id == kbd_id && *owner == uid)
+            .map(|(path, (_, _, handle))| (path.clone(), handle.index))
+            .collect();
+        let skipped = self.skipped_nodes.get(kbd_id).map_or(0, HashSet::len);
+        if paused.is_empty() || paused.len() + skipped != meta.paths.len() {
+            return false;
+        }
+
+        // Open everything first so a failure leaves the processors untouched
+        let mut devices = Vec::with_capacity(paused.len());
+        for (old_path, index) in paused {
+            let Some(new_path) = meta.paths.get(index) else {
+                return false;
+            };
+            match Device::open(new_path) {
+                Ok(device) => devices.push((old_path, new_path.clone(), device)),
+                Err(e) => {
+                    warn!("Failed to reopen {}: {}", new_path.display(), e);
+                    return false;
+                }
+            }
+        }
+
+        for (old_path, new_path, device) in devices {
+            if let Some(entry) = self.active_processors.remove(&old_path) {
+                if let Ok(mut path) = entry.2.event_path.lock() {
+                    *path = new_path.clone();
+                }
+                let _ = entry
+                    .2
+                    .command_tx
+                    .send(ProcessorCommand::Reattach(Box::new(device)));
+                self.active_processors.insert(new_path, entry);
+            }
+        }
+        info!("Reattached {} to its paused processors", meta.name);
+        true
+    }
+
+    /// Whether the owner of a keyboard has its processors wait for it to reconnect,
+    /// for a grace period or for good
+    async fn keeps_processors_paused(&self, kbd_id: &KeyboardId) -> bool {
+        let Some(uid) = self.keyboard_owners.get(kbd_id) else {
+            return false;
+        };
+        match self.user_configs.get(uid) {
+            Some(config_mgr) => {
+                let config = config_mgr.get_config().await;
+                config.disconnect_grace_ms > 0 || config.disconnect_grace_forever
+            }
+            None => false,
+        }
+    }
+
+    /// Stop ALL event processors for a keyboard
+    async fn stop_processors_for_keyboard(&mut self, kbd_id: &KeyboardId) -> Result<()> {
+        // Find all event paths for this keyboard
+        let paths_to_stop: Vec<PathBuf> = self
+            .active_processors
+            .iter()
+            .filter(|(_, (k_id, _, _))| k_id == kbd_id)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        if paths_to_stop.is_empty() {
+            return Ok(());
+        }
+
+        info!(
+            "Stopping {} processor thread(s) for: {}",
+            paths_to_stop.len(),
+            kbd_id
+        );
+
+        // Collect thread handles after sending all shutdown signals, then await them.
+        // This ensures the old processor has fully released its device grab before we
+        // attempt to open and re-grab the device for the new processor on reconnect.
+        let mut join_tasks = Vec::new();
+
+        for path in paths_to_stop {
+            if let Some((_, _, mut handle)) = self.active_processors.remove(&path) {
+                // Send shutdown signal
+                let _ = handle.shutdown_tx.send(());
+
+                if let Some(thread_handle) = handle.thread_handle.take() {
+                    // Await the thread with a generous timeout so we don't block forever
+                    // on a stuck thread, but DO wait long enough for the ungrab to complete.
+                    let path_str = path.display().to_string();
+                    join_tasks.push((path_str, thread_handle));
+                } else {
+                    info!("Stopped processor for: {}", path.display());
+                }
+            }
+        }
+
+        // Spawn all join tasks concurrently so N threads shut down in parallel
+        // (worst-case time = slowest thread, not sum of all threads).
+        let join_futures: Vec<_> = join_tasks
+            .into_iter()
+            .map(|(path_str, thread_handle)| {
+                tokio::task::spawn_blocking(move || {
+                    let _ = thread_handle.join();
+                    path_str
+                })
+            })
+            .collect();
+
+        for fut in join_futures {
             match fut.await {
                 Ok(path_str) => info!("Stopped processor for: {}", path_str),
                 Err(e) => warn!("Thread join task panicked: {}", e),
@@ -730,6 +1587,19 @@ impl AsyncDaemon {
         Ok(())
     }
 
+    /// Hotplug settings of all loaded user configs combined
+    async fn hotplug_config(&self) -> HotplugConfig {
+        let mut merged: Option<HotplugConfig> = None;
+        for config_mgr in self.user_configs.values() {
+            let hotplug = &config_mgr.get_config().await.hotplug;
+            match merged.as_mut() {
+                Some(merged) => merged.merge(hotplug),
+                None => merged = Some(hotplug.clone()),
+            }
+        }
+        merged.unwrap_or_default()
+    }
+
     /// Start hotplug monitor (udev)
     fn start_hotplug_monitor(&self) -> tokio_mpsc::UnboundedReceiver<String> {
         let (tx, rx) = tokio_mpsc::unbounded_channel();
@@ -769,9 +1639,7 @@ impl AsyncDaemon {
     }
 
     /// Start IPC server
-    fn start_ipc_server(
-        &self,
-    ) -> Result<tokio_mpsc::UnboundedReceiver<(IpcRequest, mpsc::Sender<IpcResponse>)>> {
+    fn start_ipc_server(&mut self) -> Result<tokio_mpsc::UnboundedReceiver<IpcMessage>> {
         let (tx, rx) = tokio_mpsc::unbounded_channel();
         let socket_path = get_root_socket_path();
 
@@ -799,57 +1667,74 @@ impl AsyncDaemon {
 
         info!("IPC server listening on: {:?}", socket_path);
 
+        let rate_limiter = Arc::clone(&self.ipc_rate_limiter);
+        let server_tx = tx.clone();
         thread::spawn(move || {
-            for stream in listener.incoming() {
-                match stream {
-                    Ok(mut stream) => {
-                        use std::io::{Read, Write};
-
-                        // Read length prefix (4 bytes)
-                        let mut len_buf = [0u8; 4];
-                        if let Err(e) = stream.read_exact(&mut len_buf) {
-                            error!("Failed to read IPC length: {}", e);
-                            continue;
-                        }
-                        let len = u32::from_le_bytes(len_buf) as usize;
-
-                        // Read request data
-                        let mut buffer = vec![0u8; len];
-                        match stream.read_exact(&mut buffer) {
-                            Ok(()) => {
-                                if let Ok(request) = bincode::deserialize::<IpcRequest>(&buffer) {
-                                    // Create response channel
-                                    let (resp_tx, resp_rx) = mpsc::channel();
-
-                                    // Send to main loop
-                                    if tx.send((request, resp_tx)).is_ok() {
-                                        // Wait for response
-                                        if let Ok(response) =
-                                            resp_rx.recv_timeout(Duration::from_secs(5))
-                                        {
-                                            if let Ok(resp_bytes) = bincode::serialize(&response) {
-                                                // Send length prefix
-                                                let resp_len =
-                                                    (resp_bytes.len() as u32).to_le_bytes();
-                                                let _ = stream.write_all(&resp_len);
-                                                // Send response data
-                                                let _ = stream.write_all(&resp_bytes);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => error!("Failed to read IPC request: {}", e),
-                        }
-                    }
-                    Err(e) => error!("Failed to accept IPC connection: {}", e),
-                }
-            }
+            serve_ipc(listener, &server_tx, &rate_limiter, None, None);
         });
+        self.ipc_tx = Some(tx);
 
         Ok(rx)
     }
 
+    /// Open a private socket for each active user and close those of users who left
+    ///
+    /// `$XDG_RUNTIME_DIR/keymux.sock` is owned by the user with mode 0600, and every
+    /// request on it is handled as that user's, so everyday commands don't need the
+    /// world-writable system socket.
+    fn sync_user_sockets(&mut self, active_uids: &[u32]) {
+        let gone: Vec<u32> = self
+            .user_sockets
+            .keys()
+            .filter(|uid| !active_uids.contains(uid))
+            .copied()
+            .collect();
+        for uid in gone {
+            if let Some(socket) = self.user_sockets.remove(&uid) {
+                socket.close();
+                info!("Closed IPC socket for user {}", uid);
+            }
+        }
+
+        let Some(tx) = self.ipc_tx.clone() else {
+            return;
+        };
+        for &uid in active_uids {
+            if self.user_sockets.contains_key(&uid) {
+                continue;
+            }
+            let runtime_dir = PathBuf::from(format!("/run/user/{}", uid));
+            if !runtime_dir.is_dir() {
+                continue;
+            }
+            let socket_path = runtime_dir.join("keymux.sock");
+            let listener = match bind_user_socket(&socket_path, uid) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!("Failed to bind IPC socket for user {}: {}", uid, e);
+                    continue;
+                }
+            };
+            info!("IPC server listening on {:?} for user {}", socket_path, uid);
+
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread_stop = Arc::clone(&stop);
+            let rate_limiter = Arc::clone(&self.ipc_rate_limiter);
+            let tx = tx.clone();
+            thread::spawn(move || {
+                serve_ipc(listener, &tx, &rate_limiter, Some(uid), Some(&thread_stop));
+            });
+            self.user_sockets.insert(
+                uid,
+                UserSocket {
+                    uid,
+                    path: socket_path,
+                    stop,
+                },
+            );
+        }
+    }
+
     /// Start niri window monitor
     fn start_niri_monitor(
         &self,
@@ -866,210 +1751,6 @@ impl AsyncDaemon {
         rx
     }
 
-    /// Start config file watcher for automatic reload
-    /// Returns: Receiver<()> that signals when any config changed
-    fn start_config_watcher(&self) -> tokio_mpsc::UnboundedReceiver<()> {
-        use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Watcher};
-        use std::path::Path;
-
-        let (tx, rx) = tokio_mpsc::unbounded_channel();
-
-        thread::spawn(move || {
-            let (watch_tx, watch_rx) = std::sync::mpsc::channel();
-
-            let mut watcher: Box<dyn Watcher> = match recommended_watcher(watch_tx) {
-                Ok(w) => Box::new(w),
-                Err(e) => {
-                    error!("Failed to create config file watcher: {}", e);
-                    return;
-                }
-            };
-
-            // Track both original paths and resolved targets for symlinks
-            let mut watched_paths: HashSet<PathBuf> = HashSet::new();
-            let mut watched_dirs: HashSet<PathBuf> = HashSet::new();
-
-            /// Resolve symlinks to get the final target path
-            #[allow(clippy::option_if_let_else)]
-            fn resolve_symlink(path: &Path) -> Option<PathBuf> {
-                match std::fs::symlink_metadata(path) {
-                    Ok(metadata) => {
-                        if metadata.file_type().is_symlink() {
-                            match std::fs::read_link(path) {
-                                Ok(link_target) => {
-                                    // If the symlink target is relative, resolve it relative to the symlink's parent
-                                    let resolved = if link_target.is_absolute() {
-                                        link_target
-                                    } else {
-                                        path.parent()
-                                            .unwrap_or_else(|| Path::new("."))
-                                            .join(&link_target)
-                                            .canonicalize()
-                                            .unwrap_or(link_target)
-                                    };
-                                    Some(resolved)
-                                }
-                                Err(_) => None,
-                            }
-                        } else {
-                            Some(path.to_path_buf())
-                        }
-                    }
-                    Err(_) => None,
-                }
-            }
-
-            /// Add a config to be watched, handling symlinks properly
-            fn add_config_watch(
-                config_path: PathBuf,
-                watcher: &mut Box<dyn Watcher>,
-                watched_paths: &mut HashSet<PathBuf>,
-                watched_dirs: &mut HashSet<PathBuf>,
-            ) {
-                // Watch the directory containing the config
-                let config_dir = config_path.parent().unwrap_or_else(|| Path::new("."));
-
-                // Resolve any symlinks in the directory path
-                let resolved_dir = match resolve_symlink(config_dir) {
-                    Some(dir) => dir,
-                    None => {
-                        warn!("Failed to resolve config directory: {:?}", config_dir);
-                        return;
-                    }
-                };
-
-                // Watch the resolved directory
-                if let Err(e) = watcher.watch(&resolved_dir, RecursiveMode::NonRecursive) {
-                    warn!("Failed to watch directory {:?}: {}", resolved_dir, e);
-                    return;
-                }
-
-                // Track both original and resolved paths
-                watched_paths.insert(config_path.clone());
-                watched_dirs.insert(resolved_dir.clone());
-
-                // If config path itself is a symlink, also watch its resolved target
-                if let Some(resolved_config) = resolve_symlink(&config_path) {
-                    if resolved_config != config_path {
-                        let resolved_config_dir =
-                            resolved_config.parent().unwrap_or_else(|| Path::new("."));
-                        if resolved_config_dir != resolved_dir {
-                            if let Err(e) =
-                                watcher.watch(resolved_config_dir, RecursiveMode::NonRecursive)
-                            {
-                                warn!(
-                                    "Failed to watch symlink target directory {:?}: {}",
-                                    resolved_config_dir, e
-                                );
-                            } else {
-                                watched_dirs.insert(resolved_config_dir.to_path_buf());
-                            }
-                        }
-                        watched_paths.insert(resolved_config);
-                    }
-                }
-
-                let symlink_info = std::fs::symlink_metadata(&config_path)
-                    .map(|metadata| {
-                        if metadata.file_type().is_symlink() {
-                            format!(
-                                " (symlink -> {:?})",
-                                resolve_symlink(&config_path).unwrap_or_default()
-                            )
-                        } else {
-                            String::new()
-                        }
-                    })
-                    .unwrap_or_default();
-
-                info!("Watching config at {:?}{}", config_path, symlink_info);
-            }
-
-            // Scan for users with keymux configs
-            if let Ok(entries) = std::fs::read_dir("/home") {
-                for entry in entries.flatten() {
-                    let home_dir = entry.path();
-                    let config_dir = home_dir.join(".config/keymux");
-                    let config_path = config_dir.join("config.ron");
-
-                    if config_path.exists() {
-                        add_config_watch(
-                            config_path,
-                            &mut watcher,
-                            &mut watched_paths,
-                            &mut watched_dirs,
-                        );
-                    }
-                }
-            }
-
-            info!(
-                "Config file watcher started for {} config(s) in {} director(y/ies)",
-                watched_paths.len(),
-                watched_dirs.len()
-            );
-
-            loop {
-                match watch_rx.recv() {
-                    Ok(Ok(Event {
-                        kind: EventKind::Modify(_) | EventKind::Create(_),
-                        paths,
-                        ..
-                    })) => {
-                        // Check if any modified file relates to our watched configs
-                        let mut detected_change = false;
-                        for path in paths {
-                            // Check direct path match
-                            if watched_paths.contains(&path) {
-                                info!("Config file changed: {:?}", path);
-                                detected_change = true;
-                                break;
-                            }
-
-                            // Check if this path is a symlink target of any watched config
-                            for watched_path in &watched_paths {
-                                if let Some(resolved) = resolve_symlink(watched_path) {
-                                    if path == resolved {
-                                        info!("Config file changed via symlink target: {:?} (original: {:?})", path, watched_path);
-                                        detected_change = true;
-                                        break;
-                                    }
-                                }
-                            }
-
-                            if detected_change {
-                                break;
-                            }
-                        }
-
-                        if detected_change {
-                            // Debounce: drain all events for the next 300ms
-                            let debounce_start = std::time::Instant::now();
-                            while debounce_start.elapsed() < Duration::from_millis(300) {
-                                if watch_rx.recv_timeout(Duration::from_millis(50)).is_err() {
-                                    break;
-                                }
-                                // Drain event, continue debouncing
-                            }
-
-                            // Send single reload signal after debounce
-                            info!("Config changes settled, triggering reload");
-                            let _ = tx.send(());
-                        }
-                    }
-                    Ok(Ok(_)) => {} // Ignore other event types
-                    Ok(Err(e)) => error!("Config watch error: {}", e),
-                    Err(e) => {
-                        error!("Config watch channel error: {}", e);
-                        break;
-                    }
-                }
-            }
-        });
-
-        rx
-    }
-
     /// Reload all user configs and restart processors
     async fn reload_all_configs(&mut self) -> Result<()> {
         info!("Reloading all user configs...");
@@ -1087,7 +1768,7 @@ impl AsyncDaemon {
                 Err(_) => continue,
             };
 
-            let config_path = home_dir.join(".config/keymux/config.ron");
+            let config_path = crate::paths::config_path_in(&home_dir);
             if config_path.exists() {
                 // Try to load and validate
                 let new_config = match crate::config::Config::load(&config_path) {
@@ -1116,12 +1797,13 @@ impl AsyncDaemon {
 
             for (uid, error_msg) in &validation_errors {
                 info!("Sending error notification to user {}: {}", uid, error_msg);
-                self.send_notification(
+                self.notify_user(
                     *uid,
+                    NotificationKind::Error,
                     "Keyboard Middleware - Config Error",
                     error_msg,
-                    "critical",
-                );
+                )
+                .await;
             }
 
             // Return first error for logging purposes
@@ -1141,11 +1823,32 @@ impl AsyncDaemon {
         }
         self.keyboard_owners.clear();
 
-        // Step 3: Clear and reload configs
+        // Step 3: Clear and reload configs, keeping the old ones to report what changed
         info!("Reloading configs from disk...");
+        let mut previous_configs = HashMap::new();
+        for (&uid, config_mgr) in &self.user_configs {
+            previous_configs.insert(uid, config_mgr.get_config().await);
+        }
         self.user_configs.clear();
         self.load_user_configs().await;
 
+        let mut changes: HashMap<u32, Vec<String>> = HashMap::new();
+        for (&uid, config_mgr) in &self.user_configs {
+            let Some(previous) = previous_configs.get(&uid) else {
+                continue;
+            };
+            let user_changes = diff_configs(previous, &config_mgr.get_config().await);
+            if user_changes.is_empty() {
+                info!("Config for user {} unchanged", uid);
+            } else {
+                info!("Config changes for user {}:", uid);
+                for change in &user_changes {
+                    info!("  {}", change);
+                }
+            }
+            changes.insert(uid, user_changes);
+        }
+
         // Step 4: Restart all processors with new configs
         info!("Restarting processors with new configs...");
         self.sync_keyboards_to_users().await;
@@ -1163,28 +1866,224 @@ impl AsyncDaemon {
 
         for uid in owner_uids {
             info!("Sending notification to user {}", uid);
-            self.send_notification(
+            let notifications = self.user_notifications(uid).await;
+            let mut message = "Configuration reloaded successfully!".to_string();
+            if notifications.reload_changes {
+                message.push_str(&reload_changes_summary(changes.get(&uid)));
+            }
+            crate::notify::send(
                 uid,
+                &notifications,
+                NotificationKind::Reload,
                 "Keyboard Middleware",
-                "Configuration reloaded successfully!",
-                "normal",
+                &message,
             );
         }
 
         Ok(())
     }
 
+    /// Re-read enabled_keyboards from `uid`'s config (every user's for None) and
+    /// reassign keyboards
+    ///
+    /// Unlike a full reload this leaves processors for unaffected keyboards running,
+    /// so enabling or disabling one board doesn't interrupt typing on the others.
+    async fn refresh_enabled_keyboards(&mut self, uid: Option<u32>) {
+        self.refresh_sessions().await;
+        self.load_user_configs().await;
+
+        for (uid, config_mgr) in self
+            .user_configs
+            .iter()
+            .filter(|(owner, _)| uid.is_none_or(|uid| uid == **owner))
+        {
+            if let Err(e) = config_mgr.reload().await {
+                warn!("Keeping previous config for user {}: {}", uid, e);
+            }
+        }
+
+        self.sync_keyboards_to_users().await;
+    }
+
+    /// Check a request against the per-uid IPC policy
+    ///
+    /// Root may do anything. Other users may query freely and act on their own
+    /// keyboards, config and profile; requests that would touch another user's
+    /// keyboards are refused, and `uid: None` is narrowed to the caller. Shutdown is
+    /// root-only unless polkit grants `POLKIT_ACTION_MANAGE`.
+    async fn authorize(&self, peer: &PeerCred, request: &mut IpcRequest) -> Result<(), String> {
+        if peer.uid == 0 {
+            return Ok(());
+        }
+        let caller = peer.uid;
+        let owned_by_others = |kbd_ids: &[KeyboardId]| {
+            kbd_ids.iter().any(|kbd_id| {
+                self.keyboard_owners
+                    .get(kbd_id)
+                    .is_some_and(|&owner| owner != caller)
+            })
+        };
+        let own_uid = |uid: &mut Option<u32>| match uid {
+            Some(uid) if *uid != caller => Err(format!("cannot act for user {}", uid)),
+            _ => {
+                *uid = Some(caller);
+                Ok(())
+            }
+        };
+
+        match request {
+            IpcRequest::Ping
+            | IpcRequest::ListKeyboards
+            | IpcRequest::GetGameMode(_)
+            | IpcRequest::GetGameModeStatus
+            | IpcRequest::GetStatus
+            | IpcRequest::SaveAdaptiveStats => Ok(()),
+            IpcRequest::ToggleKeyboards | IpcRequest::Reload => {
+                // Both reload every user's config and restart their processors
+                if self.keyboard_owners.values().any(|&owner| owner != caller)
+                    && !polkit_allows(POLKIT_ACTION_MANAGE, peer.pid).await
+                {
+                    Err("reloading restarts other users' keyboards".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            IpcRequest::EnableKeyboard(pattern)
+            | IpcRequest::DisableKeyboard(pattern)
+            | IpcRequest::SetKeyboardGameMode(pattern, _)
+            | IpcRequest::Unquarantine(pattern)
+            | IpcRequest::GetEffectiveConfig {
+                keyboard: pattern, ..
+            } => {
+                if owned_by_others(&self.find_keyboards_matching(pattern)) {
+                    Err(format!("\"{}\" matches another user's keyboard", pattern))
+                } else {
+                    Ok(())
+                }
+            }
+            IpcRequest::SetGameMode(_) | IpcRequest::SetGameModeOverlay(_) => {
+                if self.keyboard_owners.values().any(|&owner| owner != caller) {
+                    Err("global game mode affects other users' keyboards".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            IpcRequest::GetActiveWindow => {
+                if self.keyboard_owners.values().any(|&owner| owner != caller) {
+                    Err("the focused window may belong to another user".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            IpcRequest::SetEnabledKeyboards { uid, .. } => {
+                if *uid == caller {
+                    Ok(())
+                } else {
+                    Err(format!("cannot change keyboards of user {}", uid))
+                }
+            }
+            IpcRequest::TypeString { uid, .. }
+            | IpcRequest::InjectKeys { uid, .. }
+            | IpcRequest::GetLayerHistory { uid, .. }
+            | IpcRequest::GetLockedModifiers { uid }
+            | IpcRequest::GetProfile { uid }
+            | IpcRequest::GetMtTrace { uid, .. }
+            | IpcRequest::SetProfile { uid, .. }
+            | IpcRequest::SetShadowConfig { uid, .. } => own_uid(uid),
+            IpcRequest::Shutdown => {
+                if polkit_allows(POLKIT_ACTION_MANAGE, peer.pid).await {
+                    Ok(())
+                } else {
+                    Err("only root can stop the daemon".to_string())
+                }
+            }
+        }
+    }
+
     /// Handle a single IPC request
     #[allow(clippy::future_not_send)]
     async fn handle_ipc_request(&mut self, request: IpcRequest) -> IpcResponse {
         match request {
             IpcRequest::Ping => IpcResponse::Pong,
             IpcRequest::SetGameMode(enabled) => {
-                self.set_game_mode_all(enabled).await;
+                let reason = format!(
+                    "turned {} over IPC (keymux gamemode or a window manager watcher)",
+                    if enabled { "on" } else { "off" }
+                );
+                self.game_mode_manual = true;
+                self.set_game_mode_all(enabled, reason).await;
                 IpcResponse::Ok
             }
-            IpcRequest::ListKeyboards => {
-                // Collect all enabled_keyboards entries from all user configs for annotation
+            IpcRequest::SetKeyboardGameMode(pattern, enabled) => {
+                // `keymux gamemode auto` hands the global state back to window detection
+                if pattern == "*" && enabled.is_none() && self.game_mode_manual {
+                    info!("Game mode follows the focused window again");
+                    self.game_mode_manual = false;
+                    if let Some(window) = self.focused_window.clone() {
+                        self.process_niri_event(
+                            crate::window_manager::WindowManagerEvent::WindowFocusChanged(window),
+                        )
+                        .await;
+                    }
+                }
+                if self.set_keyboard_game_mode(&pattern, enabled) == 0 {
+                    IpcResponse::Error(format!("No keyboards match: {}", pattern))
+                } else {
+                    IpcResponse::Ok
+                }
+            }
+            IpcRequest::SetGameModeOverlay(overlay) => {
+                if let Some(name) = &overlay {
+                    if !self.overlay_defined(name).await {
+                        return IpcResponse::Error(format!(
+                            "No config defines the game mode overlay \"{}\"",
+                            name
+                        ));
+                    }
+                }
+                info!(
+                    "Game mode overlay forced over IPC: {}",
+                    overlay
+                        .as_deref()
+                        .unwrap_or("none, following the focused window")
+                );
+                for (_, _, handle) in self.active_processors.values() {
+                    let _ = handle
+                        .command_tx
+                        .send(ProcessorCommand::SetGameModeOverlay(overlay.clone()));
+                }
+                self.forced_overlay = overlay;
+                IpcResponse::Ok
+            }
+            IpcRequest::GetGameModeStatus => {
+                let keyboard_overrides = self
+                    .keyboard_game_mode
+                    .iter()
+                    .map(|(kbd_id, &enabled)| {
+                        let name = self
+                            .all_keyboards
+                            .get(kbd_id)
+                            .map_or_else(|| kbd_id.to_string(), |meta| meta.name.clone());
+                        (name, enabled)
+                    })
+                    .collect();
+                IpcResponse::GameModeStatus(crate::ipc::GameModeStatus {
+                    active: self.game_mode_active,
+                    reason: self.game_mode_reason.clone(),
+                    keyboard_overrides,
+                    forced_overlay: self.forced_overlay.clone(),
+                })
+            }
+            IpcRequest::GetActiveWindow => IpcResponse::ActiveWindow(self.focused_window.clone()),
+            IpcRequest::GetGameMode(pattern) => match pattern {
+                None => IpcResponse::GameMode(self.game_mode_active),
+                Some(pattern) => match self.find_keyboards_matching(&pattern).first() {
+                    Some(kbd_id) => IpcResponse::GameMode(self.game_mode_for(kbd_id)),
+                    None => IpcResponse::Error(format!("No keyboards match: {}", pattern)),
+                },
+            },
+            IpcRequest::ListKeyboards => {
+                // Collect all enabled_keyboards entries from all user configs for annotation
                 let mut all_config_entries: Vec<(String, bool)> = Vec::new(); // (pattern, is_enable)
                 for config_mgr in self.user_configs.values() {
                     let config = config_mgr.get_config().await;
@@ -1285,6 +2184,18 @@ impl AsyncDaemon {
                             None
                         };
 
+                        // Live state from the processors reading the keyboard
+                        let statuses: Vec<&ProcessorStatus> = meta
+                            .paths
+                            .iter()
+                            .filter_map(|path| self.active_processors.get(path))
+                            .map(|(_, _, handle)| handle.status.as_ref())
+                            .collect();
+                        let active_layer = statuses
+                            .first()
+                            .and_then(|status| status.layer.lock().ok().map(|l| l.clone()));
+                        let owner_uid = self.keyboard_owners.get(id).copied();
+
                         crate::ipc::KeyboardInfo {
                             hardware_id: id.to_string(),
                             name: meta.name.clone(),
@@ -1293,6 +2204,37 @@ impl AsyncDaemon {
                             connected: meta.connected,
                             enabled_by_portless,
                             matched_rule,
+                            owner_uid,
+                            owner_name: owner_uid.and_then(|uid| {
+                                crate::event_processor::actions::cmd::get_user_info(uid)
+                                    .map(|(name, _)| name)
+                            }),
+                            event_nodes: meta
+                                .paths
+                                .iter()
+                                .map(|path| {
+                                    event_node_info(path, self.active_processors.contains_key(path))
+                                })
+                                .collect(),
+                            active_layer,
+                            game_mode: self.game_mode_for(id),
+                            events_in: statuses
+                                .iter()
+                                .map(|status| status.events_in.load(Ordering::Relaxed))
+                                .sum(),
+                            events_out: statuses
+                                .iter()
+                                .map(|status| status.events_out.load(Ordering::Relaxed))
+                                .sum(),
+                            evictions: statuses
+                                .iter()
+                                .map(|status| status.evictions.load(Ordering::Relaxed))
+                                .sum(),
+                            battery: meta
+                                .connected
+                                .then(|| meta.paths.iter().find_map(|path| battery_for_node(path)))
+                                .flatten(),
+                            quarantine: self.quarantined.get(id).cloned(),
                         }
                     })
                     .collect();
@@ -1308,35 +2250,274 @@ impl AsyncDaemon {
                     }
                 }
             }
-            IpcRequest::EnableKeyboard(hardware_id) => {
-                info!("Enable keyboard requested via IPC: {}", hardware_id);
-                // Create KeyboardId from string
-                let kbd_id = crate::keyboard_id::KeyboardId::new(hardware_id.clone());
-                // Check if keyboard exists
-                if self.all_keyboards.contains_key(&kbd_id) {
-                    // Find the first active user to assign to
-                    // In multi-user mode, we need to know which user's config to update
-                    // For now, we'll trigger a resync which will check all user configs
-                    info!("Keyboard {} found, triggering resync", hardware_id);
-                    self.sync_keyboards_to_users().await;
-                    IpcResponse::Ok
+            IpcRequest::Unquarantine(pattern) => {
+                let released: Vec<KeyboardId> = self
+                    .find_keyboards_matching(&pattern)
+                    .into_iter()
+                    .filter(|kbd_id| self.quarantined.remove(kbd_id).is_some())
+                    .collect();
+                if released.is_empty() {
+                    return IpcResponse::Error(format!(
+                        "No quarantined keyboards match: {}",
+                        pattern
+                    ));
+                }
+                for kbd_id in &released {
+                    info!("Unquarantined {} via IPC", kbd_id);
+                }
+                self.save_quarantine();
+                self.sync_keyboards_to_users().await;
+                IpcResponse::Ok
+            }
+            IpcRequest::EnableKeyboard(pattern) => {
+                info!("Enable keyboard requested via IPC: {}", pattern);
+                let matched = self.find_keyboards_matching(&pattern);
+                if matched.is_empty() {
+                    return IpcResponse::Error(format!("Keyboard not found: {}", pattern));
+                }
+                // Only the owners' configs can have changed; unowned keyboards may go
+                // to anyone
+                let owners: HashSet<u32> = matched
+                    .iter()
+                    .filter_map(|kbd_id| self.keyboard_owners.get(kbd_id).copied())
+                    .collect();
+                if owners.len() < matched.len() {
+                    self.refresh_enabled_keyboards(None).await;
                 } else {
-                    IpcResponse::Error(format!("Keyboard not found: {}", hardware_id))
+                    for uid in owners {
+                        self.refresh_enabled_keyboards(Some(uid)).await;
+                    }
+                }
+                IpcResponse::Ok
+            }
+            IpcRequest::DisableKeyboard(pattern) => {
+                info!("Disable keyboard requested via IPC: {}", pattern);
+                let matched = self.find_keyboards_matching(&pattern);
+                if matched.is_empty() {
+                    return IpcResponse::Error(format!("Keyboard not found: {}", pattern));
+                }
+                for kbd_id in &matched {
+                    if let Err(e) = self.stop_processors_for_keyboard(kbd_id).await {
+                        return IpcResponse::Error(format!(
+                            "Failed to disable keyboard {}: {}",
+                            kbd_id, e
+                        ));
+                    }
+                    self.keyboard_owners.remove(kbd_id);
+                }
+                IpcResponse::Ok
+            }
+            IpcRequest::SetEnabledKeyboards { entries, uid } => {
+                let config_path = match self.user_configs.get(&uid) {
+                    Some(config_mgr) => config_mgr.get_config_path(),
+                    None => match self.get_user_home_dir(uid) {
+                        Ok(home_dir) => crate::paths::config_path_in(&home_dir),
+                        Err(e) => return IpcResponse::Error(e.to_string()),
+                    },
+                };
+                info!(
+                    "Enabled keyboards for user {} set via IPC: {:?}",
+                    uid, entries
+                );
+                if let Err(e) = crate::config::keyboard_state::save(&config_path, uid, &entries) {
+                    error!("Failed to save keyboard state for user {}: {}", uid, e);
+                    return IpcResponse::Error(format!("Failed to save keyboard state: {}", e));
+                }
+                self.refresh_enabled_keyboards(Some(uid)).await;
+                IpcResponse::Ok
+            }
+            IpcRequest::TypeString {
+                text,
+                enter,
+                uid,
+                keyboard,
+            } => {
+                let Some((kbd_id, handle)) = self.pick_processor(uid, keyboard.as_deref()) else {
+                    return IpcResponse::Error(
+                        "No active keyboard to type through for that user".to_string(),
+                    );
+                };
+                info!(
+                    "Typing {} chars via IPC through {}",
+                    text.chars().count(),
+                    kbd_id
+                );
+                match handle
+                    .command_tx
+                    .send(ProcessorCommand::TypeString(text, enter))
+                {
+                    Ok(()) => IpcResponse::Ok,
+                    Err(_) => IpcResponse::Error(format!("Processor for {} is gone", kbd_id)),
                 }
             }
-            IpcRequest::DisableKeyboard(hardware_id) => {
-                info!("Disable keyboard requested via IPC: {}", hardware_id);
-                // Create KeyboardId from string
-                let kbd_id = crate::keyboard_id::KeyboardId::new(hardware_id.clone());
-                // Stop all processors for this keyboard
-                if let Err(e) = self.stop_processors_for_keyboard(&kbd_id).await {
-                    error!("Failed to stop processors: {}", e);
-                    IpcResponse::Error(format!("Failed to stop processors: {}", e))
+            IpcRequest::InjectKeys {
+                events,
+                uid,
+                keyboard,
+            } => {
+                let Some((kbd_id, handle)) = self.pick_processor(uid, keyboard.as_deref()) else {
+                    return IpcResponse::Error(
+                        "No active keyboard to inject into for that user".to_string(),
+                    );
+                };
+                info!(
+                    "Injecting {} key events via IPC into {}",
+                    events.len(),
+                    kbd_id
+                );
+                match handle.command_tx.send(ProcessorCommand::InjectKeys(events)) {
+                    Ok(()) => IpcResponse::Ok,
+                    Err(_) => IpcResponse::Error(format!("Processor for {} is gone", kbd_id)),
+                }
+            }
+            IpcRequest::SetProfile { profile, uid } => {
+                let uids: Vec<u32> = match uid {
+                    Some(uid) => vec![uid],
+                    None => self.user_configs.keys().copied().collect(),
+                };
+                info!("Profile override via IPC for {:?}: {:?}", uids, profile);
+                match self.set_profile_override(&uids, profile).await {
+                    Ok(()) => IpcResponse::Ok,
+                    Err(e) => IpcResponse::Error(e),
+                }
+            }
+            IpcRequest::SetShadowConfig { config, uid } => {
+                let shadow_config = match config.as_deref().map(Config::parse).transpose() {
+                    Ok(shadow_config) => shadow_config,
+                    Err(e) => return IpcResponse::Error(e.to_string()),
+                };
+                if let Some(Err(e)) = shadow_config.as_ref().map(Config::validate_silent) {
+                    return IpcResponse::Error(format!("Config validation failed: {}", e));
+                }
+
+                let mut compared = 0;
+                for (kbd_id, owner, handle) in self.active_processors.values() {
+                    if uid.is_some_and(|uid| uid != *owner) {
+                        continue;
+                    }
+                    // Same profile and per-keyboard overrides as the active config gets
+                    let keyboard_config = shadow_config.as_ref().map(|shadow_config| {
+                        let shadow_config = match self.active_profiles.get(owner) {
+                            Some(profile) => shadow_config.with_profile(profile),
+                            None => shadow_config.clone(),
+                        };
+                        Box::new(shadow_config.for_keyboard(&kbd_id.to_string()))
+                    });
+                    if handle
+                        .command_tx
+                        .send(ProcessorCommand::SetShadow(keyboard_config))
+                        .is_ok()
+                    {
+                        compared += 1;
+                    }
+                }
+                info!(
+                    "Shadow config {} for {} processor(s) via IPC",
+                    if shadow_config.is_some() {
+                        "set"
+                    } else {
+                        "cleared"
+                    },
+                    compared
+                );
+                if compared == 0 {
+                    IpcResponse::Error("No active keyboards for that user".to_string())
                 } else {
-                    self.keyboard_owners.remove(&kbd_id);
                     IpcResponse::Ok
                 }
             }
+            IpcRequest::GetProfile { uid } => {
+                let uid = uid.or_else(|| self.user_configs.keys().min().copied());
+                match uid {
+                    Some(uid) => IpcResponse::Profile {
+                        name: self.active_profiles.get(&uid).cloned(),
+                        overridden: self.profile_overrides.contains_key(&uid),
+                    },
+                    None => IpcResponse::Error("No active user".to_string()),
+                }
+            }
+            IpcRequest::GetLockedModifiers { uid } => {
+                let uid = uid.or_else(|| self.user_configs.keys().min().copied());
+                match uid {
+                    Some(uid) => {
+                        let mut locked: Vec<KeyCode> = Vec::new();
+                        for (_, owner, handle) in self.active_processors.values() {
+                            if *owner != uid {
+                                continue;
+                            }
+                            if let Ok(modifiers) = handle.status.locked_modifiers.lock() {
+                                for modifier in modifiers.iter() {
+                                    if !locked.contains(modifier) {
+                                        locked.push(*modifier);
+                                    }
+                                }
+                            }
+                        }
+                        IpcResponse::LockedModifiers(locked)
+                    }
+                    None => IpcResponse::Error("No active user".to_string()),
+                }
+            }
+            IpcRequest::GetLayerHistory { uid, keyboard } => {
+                let pending: Vec<_> = self
+                    .matching_processors(uid, keyboard.as_deref())
+                    .into_iter()
+                    .filter_map(|(name, event_path, handle)| {
+                        let (reply_tx, reply_rx) = mpsc::channel();
+                        handle
+                            .command_tx
+                            .send(ProcessorCommand::ReportLayerHistory(reply_tx))
+                            .ok()
+                            .map(|()| ((name, event_path.display().to_string()), reply_rx))
+                    })
+                    .collect();
+                let mut histories: Vec<_> = collect_replies(pending, Duration::from_millis(200))
+                    .await
+                    .into_iter()
+                    .map(
+                        |((name, event_path), (layers, changes))| crate::ipc::LayerHistory {
+                            keyboard: name,
+                            event_path,
+                            active_layers: layers.into_iter().map(|layer| layer.0).collect(),
+                            changes,
+                        },
+                    )
+                    .collect();
+                histories.sort_by(|a, b| a.event_path.cmp(&b.event_path));
+                IpcResponse::LayerHistory(histories)
+            }
+            IpcRequest::GetMtTrace { uid, keyboard } => {
+                let pending: Vec<_> = self
+                    .matching_processors(uid, keyboard.as_deref())
+                    .into_iter()
+                    .filter_map(|(name, event_path, handle)| {
+                        let (reply_tx, reply_rx) = mpsc::channel();
+                        handle
+                            .command_tx
+                            .send(ProcessorCommand::ReportMtTrace(reply_tx))
+                            .ok()
+                            .map(|()| ((name, event_path.display().to_string()), reply_rx))
+                    })
+                    .collect();
+                let mut traces: Vec<_> = collect_replies(pending, Duration::from_millis(200))
+                    .await
+                    .into_iter()
+                    .map(|((name, event_path), decisions)| crate::ipc::MtTrace {
+                        keyboard: name,
+                        event_path,
+                        decisions,
+                    })
+                    .collect();
+                traces.sort_by(|a, b| a.event_path.cmp(&b.event_path));
+                IpcResponse::MtTrace(traces)
+            }
+            IpcRequest::GetEffectiveConfig { keyboard, json } => {
+                match self.effective_config(&keyboard, json).await {
+                    Ok(response) => response,
+                    Err(e) => IpcResponse::Error(e.to_string()),
+                }
+            }
+            IpcRequest::GetStatus => IpcResponse::Status(self.daemon_status()),
             IpcRequest::Reload => {
                 info!("Config reload requested via IPC");
                 match self.reload_all_configs().await {
@@ -1354,7 +2535,7 @@ impl AsyncDaemon {
             }
             IpcRequest::Shutdown => {
                 info!("Shutdown requested via IPC");
-                // TODO: Implement graceful shutdown
+                // The event loop shuts down once this response is sent
                 IpcResponse::Ok
             }
         }
@@ -1364,15 +2545,182 @@ impl AsyncDaemon {
     async fn process_niri_event(&mut self, event: crate::window_manager::WindowManagerEvent) {
         match event {
             crate::window_manager::WindowManagerEvent::WindowFocusChanged(window_info) => {
-                let should_enable = crate::niri::should_enable_gamemode(&window_info);
+                let window = window_info.app_id.as_deref().unwrap_or("unknown window");
+                let overlay = self.overlay_rule_match(window_info.app_id.as_deref()).await;
+                let (should_enable, reason) = match detect_game_mode(
+                    window_info.app_id.as_deref(),
+                    window_info.pid,
+                    window_info.title.as_deref(),
+                ) {
+                    GameModeState::GameMode(why) => (true, format!("{} ({})", why, window)),
+                    GameModeState::Normal if overlay.is_some() => (
+                        true,
+                        format!("{} overlay ({})", overlay.unwrap_or_default(), window),
+                    ),
+                    GameModeState::Normal
+                        if window_info.fullscreen && self.fullscreen_rule_enabled().await =>
+                    {
+                        debug!("Focused window is fullscreen, enabling game mode");
+                        (true, format!("fullscreen window ({})", window))
+                    }
+                    GameModeState::Normal => (false, format!("no game detected ({})", window)),
+                };
                 debug!("Niri window focus changed, game mode: {}", should_enable);
-                self.set_game_mode_all(should_enable).await;
+                if self.game_mode_manual {
+                    debug!("Game mode was set by hand, not following {}", window);
+                } else {
+                    self.set_game_mode_all(should_enable, reason).await;
+                }
+                self.focused_window = Some(window_info);
+                self.apply_window_layers().await;
             }
         }
     }
 
-    /// Set game mode for all active processors
-    async fn set_game_mode_all(&mut self, enabled: bool) {
+    /// Switch users to the profile their override or schedule selects
+    ///
+    /// A switch restarts that user's processors so the profile's settings take effect.
+    async fn apply_profiles(&mut self) {
+        let (weekday, minute) = crate::local_weekday_and_minute();
+        let mut switches = Vec::new();
+        for (&uid, config_mgr) in &self.user_configs {
+            let config = config_mgr.get_config().await;
+            let wanted = match self.profile_overrides.get(&uid) {
+                Some(profile) => Some(profile.clone()),
+                None => config
+                    .scheduled_profile(weekday, minute)
+                    .map(str::to_string),
+            }
+            .filter(|profile| profile != "default" && config.profiles.contains_key(profile));
+            let current = self.active_profiles.get(&uid).cloned();
+            if wanted != current {
+                switches.push((uid, current, wanted));
+            }
+        }
+        if switches.is_empty() {
+            return;
+        }
+
+        for (uid, previous, profile) in switches {
+            let name = |p: &Option<String>| p.clone().unwrap_or_else(|| "default".to_string());
+            info!(
+                "Switching user {} from profile {} to {}",
+                uid,
+                name(&previous),
+                name(&profile)
+            );
+            match &profile {
+                Some(p) => self.active_profiles.insert(uid, p.clone()),
+                None => self.active_profiles.remove(&uid),
+            };
+
+            // Keep ownership so sync restarts the boards for the same user
+            let owned: Vec<KeyboardId> = self
+                .keyboard_owners
+                .iter()
+                .filter(|(_, owner)| **owner == uid)
+                .map(|(kbd_id, _)| kbd_id.clone())
+                .collect();
+            for kbd_id in owned {
+                let _ = self.stop_processors_for_keyboard(&kbd_id).await;
+            }
+
+            self.run_user_hook(
+                uid,
+                |hooks| &hooks.on_profile_switch,
+                "profile_switch",
+                vec![
+                    ("KEYMUX_PROFILE", name(&profile)),
+                    ("KEYMUX_PREVIOUS_PROFILE", name(&previous)),
+                ],
+            )
+            .await;
+        }
+
+        self.sync_keyboards_to_users().await;
+    }
+
+    /// Push each user's window_layers match, snippet setting and the focused app_id
+    /// for the focused window to their processors
+    async fn apply_window_layers(&self) {
+        let Some(window) = &self.focused_window else {
+            return;
+        };
+        let mut layers: HashMap<u32, (Option<crate::config::Layer>, bool)> = HashMap::new();
+        for (_, uid, handle) in self.active_processors.values() {
+            if !layers.contains_key(uid) {
+                let Some(config_mgr) = self.user_configs.get(uid) else {
+                    continue;
+                };
+                let config = config_mgr.get_config().await;
+                let layer = window_layer(&config, window);
+                let snippets = config.snippets.enabled_for(window.app_id.as_deref());
+                layers.insert(*uid, (layer, snippets));
+            }
+            let (layer, snippets) = &layers[uid];
+            let _ = handle
+                .command_tx
+                .send(ProcessorCommand::SetWindowLayer(layer.clone()));
+            let _ = handle
+                .command_tx
+                .send(ProcessorCommand::SetSnippetsEnabled(*snippets));
+            let _ = handle
+                .command_tx
+                .send(ProcessorCommand::SetFocusedApp(window.app_id.clone()));
+        }
+    }
+
+    /// Whether a user with grabbed keyboards wants fullscreen windows to enable game mode
+    async fn fullscreen_rule_enabled(&self) -> bool {
+        let owners: HashSet<u32> = self.keyboard_owners.values().copied().collect();
+        for uid in owners {
+            if let Some(config_mgr) = self.user_configs.get(&uid) {
+                if config_mgr
+                    .get_config()
+                    .await
+                    .game_mode
+                    .fullscreen_enables_game_mode
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// The game mode overlay a user with grabbed keyboards has for this app_id
+    async fn overlay_rule_match(&self, app_id: Option<&str>) -> Option<String> {
+        let owners: HashSet<u32> = self.keyboard_owners.values().copied().collect();
+        for uid in owners {
+            if let Some(config_mgr) = self.user_configs.get(&uid) {
+                let config = config_mgr.get_config().await;
+                if let Some(name) = config.game_mode.overlay_for_app(app_id) {
+                    return Some(name.to_string());
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether some user's config (or one of its keyboard blocks) defines the overlay
+    async fn overlay_defined(&self, name: &str) -> bool {
+        for config_mgr in self.user_configs.values() {
+            let config = config_mgr.get_config().await;
+            let in_keyboard_block = config
+                .per_keyboard_overrides
+                .values()
+                .filter_map(|cfg| cfg.game_mode.as_ref())
+                .any(|game_mode| game_mode.overlays.contains_key(name));
+            if config.game_mode.overlays.contains_key(name) || in_keyboard_block {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Set game mode for all active processors, recording why
+    async fn set_game_mode_all(&mut self, enabled: bool, reason: String) {
+        self.game_mode_reason = reason;
         // Only update if the state actually changed
         if self.game_mode_active == enabled {
             return;
@@ -1387,9 +2735,603 @@ impl AsyncDaemon {
         // Store the new state so new threads will get it
         self.game_mode_active = enabled;
 
-        // Send to all active threads
-        for (_, _, handle) in self.active_processors.values() {
-            let _ = handle.game_mode_tx.send(enabled);
+        // Send to all active threads, except keyboards with their own override
+        for (kbd_id, _, handle) in self.active_processors.values() {
+            if !self.keyboard_game_mode.contains_key(kbd_id) {
+                let _ = handle.game_mode_tx.send(enabled);
+            }
+        }
+
+        let uids: Vec<u32> = self.user_configs.keys().copied().collect();
+        for uid in uids {
+            self.notify_game_mode(uid, enabled).await;
+            self.run_user_hook(
+                uid,
+                |hooks| &hooks.on_game_mode,
+                "game_mode",
+                vec![("KEYMUX_GAME_MODE", u8::from(enabled).to_string())],
+            )
+            .await;
+        }
+    }
+
+    /// Tell owners when a keyboard's battery drops to their `low_battery_percent`, once
+    /// per discharge
+    async fn check_batteries(&mut self) {
+        for (kbd_id, meta) in &self.all_keyboards {
+            let Some(&uid) = self.keyboard_owners.get(kbd_id) else {
+                continue;
+            };
+            let notifications = self.user_notifications(uid).await;
+            let Some(threshold) = notifications.low_battery_percent else {
+                continue;
+            };
+            let Some(level) = meta
+                .connected
+                .then(|| meta.paths.iter().find_map(|path| battery_for_node(path)))
+                .flatten()
+            else {
+                continue;
+            };
+            if level.charging || level.percent > threshold {
+                self.low_battery_warned.remove(kbd_id);
+            } else if self.low_battery_warned.insert(kbd_id.clone()) {
+                info!("Battery of {} is at {}", meta.name, level);
+                crate::notify::send(
+                    uid,
+                    &notifications,
+                    NotificationKind::Battery,
+                    "Keyboard battery low",
+                    &format!("{} is at {}", meta.name, level),
+                );
+            }
+        }
+    }
+
+    /// A user's notification preferences (the defaults while no config is loaded)
+    async fn user_notifications(&self, uid: u32) -> NotificationsConfig {
+        match self.user_configs.get(&uid) {
+            Some(config_mgr) => config_mgr.get_config().await.notifications,
+            None => NotificationsConfig::default(),
+        }
+    }
+
+    /// Send a user a notification, if their config turns notifications of its kind on
+    async fn notify_user(&self, uid: u32, kind: NotificationKind, title: &str, message: &str) {
+        let notifications = self.user_notifications(uid).await;
+        crate::notify::send(uid, &notifications, kind, title, message);
+    }
+
+    /// Show the game mode notification, if the user turned it on
+    async fn notify_game_mode(&self, uid: u32, enabled: bool) {
+        let Some(config_mgr) = self.user_configs.get(&uid) else {
+            return;
+        };
+        let config = config_mgr.get_config().await;
+        if config.notifications.game_mode {
+            let state = if enabled { "on" } else { "off" };
+            crate::notify::osd(
+                uid,
+                &config.notifications,
+                NotificationKind::GameMode,
+                "Game mode",
+                state,
+            );
+        }
+    }
+
+    /// Run one of a user's lifecycle hooks, if they configured it
+    async fn run_user_hook(
+        &self,
+        uid: u32,
+        select: fn(&crate::config::HooksConfig) -> &Option<String>,
+        event: &str,
+        env: Vec<(&str, String)>,
+    ) {
+        let Some(config_mgr) = self.user_configs.get(&uid) else {
+            return;
+        };
+        let config = config_mgr.get_config().await;
+        if let Some(hook) = select(&config.hooks) {
+            let config_dir = config_mgr
+                .get_config_path()
+                .parent()
+                .map(|p| p.to_path_buf())
+                .unwrap_or_else(|| PathBuf::from("."));
+            crate::hooks::run_hook(hook, uid, config_dir, event, env);
+        }
+    }
+
+    /// Run a keyboard's own `on_connect` or `on_disconnect` from per_keyboard_overrides
+    async fn run_keyboard_hook(&self, uid: u32, kbd_id: &KeyboardId, connected: bool) {
+        let Some(config_mgr) = self.user_configs.get(&uid) else {
+            return;
+        };
+        let config = config_mgr.get_config().await;
+        let Some(keyboard) = config.keyboard_override(&kbd_id.to_string()) else {
+            return;
+        };
+        let (hook, event) = if connected {
+            (&keyboard.on_connect, "keyboard_connect")
+        } else {
+            (&keyboard.on_disconnect, "keyboard_disconnect")
+        };
+        let Some(hook) = hook else {
+            return;
+        };
+        let name = self
+            .all_keyboards
+            .get(kbd_id)
+            .map(|meta| meta.name.clone())
+            .unwrap_or_default();
+        let config_dir = config_mgr
+            .get_config_path()
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| PathBuf::from("."));
+        crate::hooks::run_hook(
+            hook,
+            uid,
+            config_dir,
+            event,
+            vec![
+                ("KEYMUX_KEYBOARD_ID", kbd_id.to_string()),
+                ("KEYMUX_KEYBOARD_NAME", name),
+            ],
+        );
+    }
+
+    /// Pick one running processor, optionally restricted to a user and a keyboard pattern
+    ///
+    /// The first matching keyboard by ID is used, through its primary event file: a
+    /// logical keyboard runs one thread per event file, and its paths are ordered by
+    /// USB interface, so the first is the main key interface rather than a
+    /// consumer-control or vendor node.
+    fn pick_processor(
+        &self,
+        uid: Option<u32>,
+        keyboard: Option<&str>,
+    ) -> Option<(&KeyboardId, &ProcessorHandle)> {
+        let mut candidates: Vec<_> = self
+            .active_processors
+            .iter()
+            .filter(|(_, (_, owner_uid, _))| uid.is_none_or(|uid| uid == *owner_uid))
+            .filter(|(_, (kbd_id, _, _))| {
+                keyboard.is_none_or(|pattern| {
+                    kbd_id.to_string().contains(pattern)
+                        || self
+                            .all_keyboards
+                            .get(kbd_id)
+                            .is_some_and(|meta| meta.name.contains(pattern))
+                })
+            })
+            .collect();
+        candidates.sort_by_key(|(_, (kbd_id, _, handle))| (kbd_id.to_string(), handle.index));
+        candidates
+            .first()
+            .map(|(_, (kbd_id, _, handle))| (kbd_id, handle))
+    }
+
+    /// Effective game mode for a keyboard (its override, or the global state)
+    fn game_mode_for(&self, kbd_id: &KeyboardId) -> bool {
+        self.keyboard_game_mode
+            .get(kbd_id)
+            .copied()
+            .unwrap_or(self.game_mode_active)
+    }
+
+    /// Take in what a processor reports or asks for
+    async fn handle_processor_event(&mut self, event: ProcessorEvent) {
+        match event {
+            ProcessorEvent::LayerChanged {
+                keyboard_id,
+                uid,
+                layer,
+            } => {
+                debug!("Layer of {} (user {}) is now {}", keyboard_id, uid, layer.0);
+            }
+            ProcessorEvent::ToggleKeyboard { uid, pattern } => {
+                self.toggle_keyboard(uid, &pattern).await;
+            }
+            ProcessorEvent::SwitchProfile { uid, profile } => {
+                info!("Profile switch by user {}'s key: {}", uid, profile);
+                let profile = (profile != "auto").then_some(profile);
+                if let Err(e) = self.set_profile_override(&[uid], profile).await {
+                    warn!("Profile switch for user {} failed: {}", uid, e);
+                }
+            }
+            ProcessorEvent::Heartbeat { event_path } => {
+                if self.stalled_processors.remove(&event_path) {
+                    info!("Processor for {} is responding again", event_path.display());
+                }
+                self.processor_heartbeats
+                    .insert(event_path, std::time::Instant::now());
+            }
+            ProcessorEvent::Busy {
+                event_path,
+                turns_per_sec,
+            } => {
+                warn!(
+                    "Processor for {} spun at {} turns/s and is throttled",
+                    event_path.display(),
+                    turns_per_sec
+                );
+                // Tell the owner once per processor; the log has every occurrence
+                let Some((kbd_id, uid, _)) = self.active_processors.get(&event_path) else {
+                    return;
+                };
+                let uid = *uid;
+                let name = self
+                    .all_keyboards
+                    .get(kbd_id)
+                    .map_or_else(|| kbd_id.to_string(), |meta| meta.name.clone());
+                if self.busy_processors.insert(event_path) {
+                    self.notify_user(
+                        uid,
+                        NotificationKind::Error,
+                        "Keyboard throttled",
+                        &format!(
+                            "{} floods events faster than any typing and is slowed down \
+                             so it doesn't use a whole CPU core. A failing cable or \
+                             controller is the usual cause.",
+                            name
+                        ),
+                    )
+                    .await;
+                }
+            }
+        }
+    }
+
+    /// Uptime, memory and each processor thread's liveness and backlog
+    fn daemon_status(&self) -> crate::ipc::DaemonStatus {
+        // VmRSS and Threads lines of /proc/self/status
+        let proc_status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+        let proc_field = |name: &str| {
+            proc_status
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .and_then(|value| value.split_whitespace().next()?.parse().ok())
+        };
+        let mut processors: Vec<crate::ipc::ProcessorInternals> = self
+            .active_processors
+            .iter()
+            .map(|(path, (id, uid, handle))| crate::ipc::ProcessorInternals {
+                keyboard: self
+                    .all_keyboards
+                    .get(id)
+                    .map_or_else(|| id.to_string(), |meta| meta.name.clone()),
+                event_path: path.display().to_string(),
+                uid: *uid,
+                alive: handle
+                    .thread_handle
+                    .as_ref()
+                    .is_some_and(|thread| !thread.is_finished()),
+                heartbeat_age_ms: self
+                    .processor_heartbeats
+                    .get(path)
+                    .map(|at| at.elapsed().as_millis() as u64),
+                stalled: self.stalled_processors.contains(path),
+                throttled: handle.status.throttled.load(Ordering::Relaxed),
+                command_backlog: handle.command_tx.len(),
+                events_in: handle.status.events_in.load(Ordering::Relaxed),
+                events_out: handle.status.events_out.load(Ordering::Relaxed),
+                evictions: handle.status.evictions.load(Ordering::Relaxed),
+            })
+            .collect();
+        processors.sort_by(|a, b| a.event_path.cmp(&b.event_path));
+        crate::ipc::DaemonStatus {
+            pid: std::process::id(),
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            rss_kb: proc_field("VmRSS:"),
+            threads: proc_field("Threads:"),
+            event_backlog: self.processor_event_rx.len(),
+            processors,
+        }
+    }
+
+    /// Warn once about processors that stopped sending heartbeats, e.g. stuck writing
+    /// to their virtual keyboard
+    fn check_processor_heartbeats(&mut self) {
+        let stall_after = event_processor::HEARTBEAT_INTERVAL * 3;
+        self.processor_heartbeats
+            .retain(|path, _| self.active_processors.contains_key(path));
+        self.stalled_processors
+            .retain(|path| self.active_processors.contains_key(path));
+        self.busy_processors
+            .retain(|path| self.active_processors.contains_key(path));
+        for (path, last) in &self.processor_heartbeats {
+            if last.elapsed() >= stall_after && self.stalled_processors.insert(path.clone()) {
+                warn!(
+                    "Processor for {} sent no heartbeat for {:?}, it may be stuck",
+                    path.display(),
+                    last.elapsed()
+                );
+            }
+        }
+    }
+
+    /// Override the profile of these users (None = follow the schedule, "default" = none)
+    async fn set_profile_override(
+        &mut self,
+        uids: &[u32],
+        profile: Option<String>,
+    ) -> Result<(), String> {
+        if let Some(name) = profile.as_deref().filter(|p| *p != "default") {
+            for uid in uids {
+                let Some(config_mgr) = self.user_configs.get(uid) else {
+                    return Err(format!("No config loaded for user {}", uid));
+                };
+                if !config_mgr.get_config().await.profiles.contains_key(name) {
+                    return Err(format!("Profile not defined: {}", name));
+                }
+            }
+        }
+        for &uid in uids {
+            match &profile {
+                Some(name) => self.profile_overrides.insert(uid, name.clone()),
+                None => self.profile_overrides.remove(&uid),
+            };
+        }
+        self.apply_profiles().await;
+        Ok(())
+    }
+
+    /// Enable a user's keyboards matching a pattern if none of them is, else disable them
+    ///
+    /// Saved as keyboard state, the same as `keymux toggle`.
+    async fn toggle_keyboard(&mut self, uid: u32, pattern: &str) {
+        let Some(config_mgr) = self.user_configs.get(&uid) else {
+            warn!("Keyboard toggle from user {} without a loaded config", uid);
+            return;
+        };
+        let config = config_mgr.get_config().await;
+        let config_path = config_mgr.get_config_path();
+
+        let matched = self.find_keyboards_matching(pattern);
+        if matched.is_empty() {
+            warn!("Keyboard toggle: no keyboard matches {:?}", pattern);
+            return;
+        }
+        let any_enabled = matched.iter().any(|kbd_id| {
+            self.all_keyboards.get(kbd_id).is_some_and(|meta| {
+                let event_path = meta
+                    .paths
+                    .first()
+                    .and_then(|p| p.file_name().and_then(|n| n.to_str()));
+                config.is_keyboard_enabled(&kbd_id.to_string(), Some(&meta.name), event_path)
+            })
+        });
+        let action = if any_enabled {
+            EnableDisable::Disable
+        } else {
+            EnableDisable::Enable
+        };
+        info!(
+            "Keyboard toggle by user {}: {:?} {} keyboard(s) matching {:?}",
+            uid,
+            action,
+            matched.len(),
+            pattern
+        );
+
+        let entries = crate::config::keyboard_state::with_entry(
+            &crate::config::keyboard_state::to_entries(&config.enabled_keyboards),
+            pattern,
+            action,
+        );
+        if let Err(e) = crate::config::keyboard_state::save(&config_path, uid, &entries) {
+            error!("Failed to save keyboard state for user {}: {}", uid, e);
+            return;
+        }
+        self.refresh_enabled_keyboards(Some(uid)).await;
+    }
+
+    /// Find known keyboards whose ID or name contains the pattern ("*" matches all)
+    /// The config a keyboard's processors get: its owner's config with the active
+    /// profile and then the keyboard's overrides merged in
+    async fn effective_config(&self, pattern: &str, json: bool) -> Result<IpcResponse> {
+        let kbd_id = match self.find_keyboards_matching(pattern).as_slice() {
+            [] => anyhow::bail!("No keyboards match: {}", pattern),
+            [kbd_id] => kbd_id.clone(),
+            matched => anyhow::bail!(
+                "\"{}\" matches {} keyboards, use a longer pattern or the keyboard ID",
+                pattern,
+                matched.len()
+            ),
+        };
+        let name = self
+            .all_keyboards
+            .get(&kbd_id)
+            .map_or_else(|| kbd_id.to_string(), |meta| meta.name.clone());
+        let uid = *self
+            .keyboard_owners
+            .get(&kbd_id)
+            .with_context(|| format!("{} isn't assigned to a user (is it enabled?)", name))?;
+        let base_config = self
+            .user_configs
+            .get(&uid)
+            .context("User config not loaded")?
+            .get_config()
+            .await;
+
+        let profile = self.active_profiles.get(&uid).cloned();
+        let base_config = match &profile {
+            Some(profile) => base_config.with_profile(profile),
+            None => base_config,
+        };
+        let config = base_config.for_keyboard(&kbd_id.to_string());
+        let config = if json {
+            serde_json::to_string_pretty(&config)?
+        } else {
+            ron::ser::to_string_pretty(&config, ron::ser::PrettyConfig::default())?
+        };
+        Ok(IpcResponse::EffectiveConfig {
+            keyboard: name,
+            profile,
+            config,
+        })
+    }
+
+    fn find_keyboards_matching(&self, pattern: &str) -> Vec<KeyboardId> {
+        self.all_keyboards
+            .iter()
+            .filter(|(id, meta)| {
+                pattern == "*" || id.to_string().contains(pattern) || meta.name.contains(pattern)
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Override (or clear the override of) game mode for keyboards matching a pattern
+    ///
+    /// Returns the number of keyboards affected.
+    fn set_keyboard_game_mode(&mut self, pattern: &str, enabled: Option<bool>) -> usize {
+        let matched = self.find_keyboards_matching(pattern);
+
+        // Other members of the matched keyboards' groups follow along
+        let mut affected = matched.clone();
+        for kbd_id in &matched {
+            let Some(member) = self.keyboard_groups.get(kbd_id) else {
+                continue;
+            };
+            for (id, other) in &self.keyboard_groups {
+                if other == member && !affected.contains(id) {
+                    affected.push(id.clone());
+                }
+            }
+        }
+
+        for kbd_id in &affected {
+            match enabled {
+                Some(state) => {
+                    self.keyboard_game_mode.insert(kbd_id.clone(), state);
+                }
+                None => {
+                    self.keyboard_game_mode.remove(kbd_id);
+                }
+            }
+
+            let state = self.game_mode_for(kbd_id);
+            info!("Setting game mode for {} to: {}", kbd_id, state);
+            for (id, _, handle) in self.active_processors.values() {
+                if id == kbd_id {
+                    let _ = handle.game_mode_tx.send(state);
+                }
+            }
+        }
+
+        matched.len()
+    }
+
+    /// Pick up the state a previous daemon saved on shutdown
+    fn restore_state(&mut self) {
+        let Some(state) = DaemonState::take() else {
+            return;
+        };
+        info!(
+            "Restoring state from previous daemon (game mode: {})",
+            state.game_mode_active
+        );
+        self.game_mode_active = state.game_mode_active;
+        self.game_mode_reason = "restored from the previous daemon".to_string();
+        self.game_mode_manual = state.game_mode_manual;
+        self.keyboard_game_mode = state
+            .keyboard_game_mode
+            .into_iter()
+            .map(|(id, enabled)| (KeyboardId::new(id), enabled))
+            .collect();
+        // Ownership only matters for keyboards that are still here
+        for (id, uid) in state.keyboard_owners {
+            let kbd_id = KeyboardId::new(id);
+            if self.all_keyboards.contains_key(&kbd_id) {
+                self.keyboard_owners.insert(kbd_id, uid);
+            }
+        }
+        let mut keyboard_locks = state.keyboard_locks;
+        let mut restored: HashMap<_, _> = state
+            .keyboard_layers
+            .into_iter()
+            .map(|(id, layers)| {
+                let locks = keyboard_locks.remove(&id).unwrap_or_default();
+                (KeyboardId::new(id), (layers, locks))
+            })
+            .collect();
+        for (id, locks) in keyboard_locks {
+            restored.insert(KeyboardId::new(id), (Vec::new(), locks));
+        }
+        self.restored_layers = restored;
+        self.profile_overrides = state.profile_overrides;
+    }
+
+    /// Save runtime state for the next daemon, then stop every processor
+    async fn shutdown(&mut self) {
+        let pending: Vec<_> = self
+            .active_processors
+            .values()
+            .filter_map(|(kbd_id, _, handle)| {
+                let (reply_tx, reply_rx) = mpsc::channel();
+                handle
+                    .command_tx
+                    .send(ProcessorCommand::ReportLayers(reply_tx))
+                    .ok()
+                    .map(|()| (kbd_id.clone(), reply_rx))
+            })
+            .collect();
+        let mut keyboard_layers: HashMap<String, Vec<Layer>> = HashMap::new();
+        let mut keyboard_locks: HashMap<String, TapLocks> = HashMap::new();
+        for (kbd_id, (layers, locks)) in collect_replies(pending, Duration::from_millis(200)).await
+        {
+            let saved = keyboard_layers.entry(kbd_id.to_string()).or_default();
+            for layer in layers {
+                if !saved.contains(&layer) {
+                    saved.push(layer);
+                }
+            }
+            let saved = keyboard_locks.entry(kbd_id.to_string()).or_default();
+            for lock in locks {
+                if !saved.contains(&lock) {
+                    saved.push(lock);
+                }
+            }
+        }
+        keyboard_layers.retain(|_, layers| !layers.is_empty());
+        keyboard_locks.retain(|_, locks| !locks.is_empty());
+
+        let state = DaemonState {
+            game_mode_active: self.game_mode_active,
+            game_mode_manual: self.game_mode_manual,
+            keyboard_game_mode: self
+                .keyboard_game_mode
+                .iter()
+                .map(|(id, enabled)| (id.to_string(), *enabled))
+                .collect(),
+            keyboard_owners: self
+                .keyboard_owners
+                .iter()
+                .map(|(id, uid)| (id.to_string(), *uid))
+                .collect(),
+            keyboard_layers,
+            keyboard_locks,
+            profile_overrides: self.profile_overrides.clone(),
+        };
+        match state.save() {
+            Ok(()) => info!("Saved runtime state to {:?}", DaemonState::path()),
+            Err(e) => warn!("Failed to save runtime state: {}", e),
+        }
+
+        let kbd_ids: HashSet<KeyboardId> = self
+            .active_processors
+            .values()
+            .map(|(kbd_id, _, _)| kbd_id.clone())
+            .collect();
+        for kbd_id in kbd_ids {
+            let _ = self.stop_processors_for_keyboard(&kbd_id).await;
+        }
+        info!("All processors stopped");
+
+        for (_, socket) in self.user_sockets.drain() {
+            socket.close();
         }
     }
 
@@ -1406,6 +3348,50 @@ impl AsyncDaemon {
         }
     }
 
+    /// Processors of keyboards owned by `uid` whose ID or name contains `keyboard`,
+    /// with the keyboard's name
+    fn matching_processors(
+        &self,
+        uid: Option<u32>,
+        keyboard: Option<&str>,
+    ) -> Vec<(String, &PathBuf, &ProcessorHandle)> {
+        self.active_processors
+            .iter()
+            .filter_map(|(event_path, (kbd_id, owner, handle))| {
+                let name = self
+                    .all_keyboards
+                    .get(kbd_id)
+                    .map(|meta| meta.name.clone())
+                    .unwrap_or_default();
+                let matches = uid.is_none_or(|uid| uid == *owner)
+                    && keyboard.is_none_or(|pattern| {
+                        kbd_id.to_string().contains(pattern) || name.contains(pattern)
+                    });
+                matches.then_some((name, event_path, handle))
+            })
+            .collect()
+    }
+
+    /// Have every processor save its adaptive stats, waiting until they have
+    async fn flush_adaptive_stats_all(&self) {
+        let replies: Vec<_> = self
+            .active_processors
+            .values()
+            .filter_map(|(_, _, handle)| {
+                let (reply_tx, reply_rx) = mpsc::channel();
+                handle
+                    .command_tx
+                    .send(ProcessorCommand::FlushStats(reply_tx))
+                    .ok()
+                    .map(|()| ((), reply_rx))
+            })
+            .collect();
+        let flushed = collect_replies(replies, Duration::from_millis(500))
+            .await
+            .len();
+        info!("Flushed adaptive stats of {} processor(s)", flushed);
+    }
+
     /// Refresh user sessions
     async fn refresh_sessions(&self) {
         if let Err(e) = self.session_manager.refresh_sessions().await {
@@ -1413,3 +3399,497 @@ impl AsyncDaemon {
         }
     }
 }
+
+/// Wait for the replies of processors already sent a request, up to `timeout` in total
+///
+/// The wait runs on a blocking thread, so the event loop keeps serving while a stuck
+/// processor runs out the clock.
+async fn collect_replies<K: Send + 'static, T: Send + 'static>(
+    pending: Vec<(K, mpsc::Receiver<T>)>,
+    timeout: Duration,
+) -> Vec<(K, T)> {
+    tokio::task::spawn_blocking(move || {
+        let deadline = std::time::Instant::now() + timeout;
+        pending
+            .into_iter()
+            .filter_map(|(key, reply_rx)| {
+                let left = deadline.saturating_duration_since(std::time::Instant::now());
+                reply_rx.recv_timeout(left).ok().map(|reply| (key, reply))
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Path and capabilities of a keyboard's event file (opened read-only, without grabbing)
+fn event_node_info(path: &std::path::Path, processing: bool) -> crate::ipc::EventNodeInfo {
+    let device = Device::open(path).ok();
+    let device = device.as_ref();
+    crate::ipc::EventNodeInfo {
+        path: path.display().to_string(),
+        processing,
+        keys: device
+            .and_then(Device::supported_keys)
+            .map_or(0, |keys| keys.iter().count()),
+        leds: device
+            .and_then(Device::supported_leds)
+            .is_some_and(|leds| leds.iter().next().is_some()),
+        pointer: device
+            .and_then(Device::supported_relative_axes)
+            .is_some_and(|axes| axes.contains(evdev::RelativeAxisType::REL_X)),
+    }
+}
+
+/// Changes listed under the reload notification, cut short so it stays readable
+fn reload_changes_summary(changes: Option<&Vec<String>>) -> String {
+    const MAX_LINES: usize = 8;
+    let Some(changes) = changes.filter(|changes| !changes.is_empty()) else {
+        return "\nNo changes".to_string();
+    };
+    let mut summary: String = changes
+        .iter()
+        .take(MAX_LINES)
+        .map(|change| format!("\n{}", change))
+        .collect();
+    if changes.len() > MAX_LINES {
+        summary.push_str(&format!("\n... and {} more", changes.len() - MAX_LINES));
+    }
+    summary
+}
+
+/// Layer the first matching `window_layers` rule selects for a window
+fn window_layer(
+    config: &crate::config::Config,
+    window: &WindowInfo,
+) -> Option<crate::config::Layer> {
+    config
+        .window_layers
+        .iter()
+        .find(|rule| rule.matches(window.app_id.as_deref(), window.title.as_deref()))
+        .map(|rule| rule.layer.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::restart_backoff::QUARANTINE_AFTER;
+
+    /// Daemon whose state files go to a scratch directory
+    fn test_daemon() -> AsyncDaemon {
+        crate::paths::set_overrides(crate::paths::PathOverrides {
+            state_dir: Some(
+                std::env::temp_dir().join(format!("keymux-daemon-test-{}", std::process::id())),
+            ),
+            ..Default::default()
+        });
+        AsyncDaemon::empty()
+    }
+
+    /// Register a connected keyboard owned by `uid` with an idle processor per path
+    fn add_keyboard(daemon: &mut AsyncDaemon, id: &str, uid: u32, paths: &[&str]) -> KeyboardId {
+        let kbd_id = KeyboardId::new(id.to_string());
+        let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        for (index, path) in paths.iter().enumerate() {
+            let (shutdown_tx, _) = crossbeam_channel::unbounded();
+            let (game_mode_tx, _) = mpsc::channel();
+            let (save_stats_tx, _) = mpsc::channel();
+            let (command_tx, _) = crossbeam_channel::unbounded();
+            let handle = ProcessorHandle {
+                shutdown_tx,
+                game_mode_tx,
+                save_stats_tx,
+                command_tx,
+                thread_handle: None,
+                index,
+                event_path: Arc::new(std::sync::Mutex::new(path.clone())),
+                status: Arc::new(ProcessorStatus::default()),
+            };
+            daemon
+                .active_processors
+                .insert(path.clone(), (kbd_id.clone(), uid, handle));
+        }
+        daemon.all_keyboards.insert(
+            kbd_id.clone(),
+            KeyboardMeta {
+                name: format!("Keyboard {}", id),
+                paths,
+                connected: true,
+                remapper_output: false,
+            },
+        );
+        daemon.keyboard_owners.insert(kbd_id.clone(), uid);
+        kbd_id
+    }
+
+    /// Commands the daemon sends the processor reading `path`
+    fn listen(
+        daemon: &mut AsyncDaemon,
+        path: &str,
+    ) -> crossbeam_channel::Receiver<ProcessorCommand> {
+        let (command_tx, command_rx) = crossbeam_channel::unbounded();
+        if let Some((_, _, handle)) = daemon.active_processors.get_mut(&PathBuf::from(path)) {
+            handle.command_tx = command_tx;
+        }
+        command_rx
+    }
+
+    fn running(daemon: &AsyncDaemon, kbd_id: &KeyboardId) -> usize {
+        daemon
+            .active_processors
+            .values()
+            .filter(|(id, _, _)| id == kbd_id)
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_disable_keyboard_leaves_the_others_running() {
+        let mut daemon = test_daemon();
+        let laptop = add_keyboard(&mut daemon, "aaaa:0001", 1000, &["/dev/input/event90"]);
+        let external = add_keyboard(
+            &mut daemon,
+            "bbbb:0002",
+            1000,
+            &["/dev/input/event91", "/dev/input/event92"],
+        );
+
+        let response = daemon
+            .handle_ipc_request(IpcRequest::DisableKeyboard("aaaa:0001".to_string()))
+            .await;
+        assert!(matches!(response, IpcResponse::Ok));
+        assert_eq!(running(&daemon, &laptop), 0);
+        assert!(!daemon.keyboard_owners.contains_key(&laptop));
+        assert_eq!(running(&daemon, &external), 2);
+        assert_eq!(daemon.keyboard_owners.get(&external), Some(&1000));
+
+        let response = daemon
+            .handle_ipc_request(IpcRequest::DisableKeyboard("cccc:0003".to_string()))
+            .await;
+        assert!(matches!(response, IpcResponse::Error(_)));
+        assert_eq!(running(&daemon, &external), 2);
+    }
+
+    #[tokio::test]
+    async fn test_authorize_keeps_users_to_their_own_keyboards() {
+        let mut daemon = test_daemon();
+        add_keyboard(&mut daemon, "aaaa:0001", 1000, &["/dev/input/event90"]);
+        add_keyboard(&mut daemon, "bbbb:0002", 1001, &["/dev/input/event91"]);
+        let alice = PeerCred {
+            pid: 1,
+            uid: 1000,
+            gid: 1000,
+        };
+
+        let mut own = IpcRequest::DisableKeyboard("aaaa".to_string());
+        assert!(daemon.authorize(&alice, &mut own).await.is_ok());
+        let mut foreign = IpcRequest::DisableKeyboard("bbbb".to_string());
+        assert!(daemon.authorize(&alice, &mut foreign).await.is_err());
+        let mut everything = IpcRequest::SetKeyboardGameMode("*".to_string(), Some(true));
+        assert!(daemon.authorize(&alice, &mut everything).await.is_err());
+
+        let mut request = IpcRequest::SetEnabledKeyboards {
+            entries: Vec::new(),
+            uid: 1001,
+        };
+        assert!(daemon.authorize(&alice, &mut request).await.is_err());
+
+        // Global game mode is off limits while someone else has a keyboard
+        let mut request = IpcRequest::SetGameMode(true);
+        assert!(daemon.authorize(&alice, &mut request).await.is_err());
+        let root = PeerCred {
+            pid: 1,
+            uid: 0,
+            gid: 0,
+        };
+        assert!(daemon.authorize(&root, &mut request).await.is_ok());
+
+        // No uid means the caller, and asking for someone else's is refused
+        let mut request = IpcRequest::GetProfile { uid: None };
+        assert!(daemon.authorize(&alice, &mut request).await.is_ok());
+        assert!(matches!(
+            request,
+            IpcRequest::GetProfile { uid: Some(1000) }
+        ));
+        let mut request = IpcRequest::GetLockedModifiers { uid: Some(1001) };
+        assert!(daemon.authorize(&alice, &mut request).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_focus_change_keeps_game_mode_set_by_hand() {
+        let mut daemon = test_daemon();
+        add_keyboard(&mut daemon, "aaaa:0001", 1000, &["/dev/input/event90"]);
+        let focus = |app_id: &str| {
+            crate::window_manager::WindowManagerEvent::WindowFocusChanged(WindowInfo {
+                app_id: Some(app_id.to_string()),
+                ..Default::default()
+            })
+        };
+
+        daemon
+            .handle_ipc_request(IpcRequest::SetGameMode(true))
+            .await;
+        daemon.process_niri_event(focus("foot")).await;
+        assert!(daemon.game_mode_active);
+
+        // Back to auto: the focused window decides again
+        daemon
+            .handle_ipc_request(IpcRequest::SetKeyboardGameMode("*".to_string(), None))
+            .await;
+        assert!(!daemon.game_mode_active);
+        daemon.process_niri_event(focus("steam_app_570")).await;
+        assert!(daemon.game_mode_active);
+    }
+
+    #[tokio::test]
+    async fn test_crashing_keyboard_restarts_then_quarantines() {
+        let mut daemon = test_daemon();
+        // Only keyboards whose event file is still there get restarted
+        let node = std::env::temp_dir().join(format!("keymux-test-event-{}", std::process::id()));
+        std::fs::write(&node, "").unwrap();
+        let path = node.to_str().unwrap();
+        let kbd_id = add_keyboard(&mut daemon, "cccc:0913", 60999, &[path]);
+
+        for crash in 1..QUARANTINE_AFTER {
+            daemon
+                .handle_dead_processor(&node, Some("panicked".to_string()))
+                .await;
+            let backoff = &daemon.processor_restarts[&kbd_id];
+            assert!(backoff.is_pending());
+            assert_eq!(backoff.attempts(), crash);
+            assert!(!daemon.quarantined.contains_key(&kbd_id));
+
+            // The restart comes up, and crashes again right away
+            if let Some(backoff) = daemon.processor_restarts.get_mut(&kbd_id) {
+                backoff.restarted(std::time::Instant::now());
+            }
+            add_keyboard(&mut daemon, "cccc:0913", 60999, &[path]);
+        }
+        daemon
+            .handle_dead_processor(&node, Some("panicked".to_string()))
+            .await;
+        assert!(daemon.quarantined.contains_key(&kbd_id));
+        assert!(!daemon.processor_restarts.contains_key(&kbd_id));
+        assert_eq!(running(&daemon, &kbd_id), 0);
+
+        daemon.quarantined.clear();
+        daemon.save_quarantine();
+        let _ = std::fs::remove_file(&node);
+    }
+
+    #[tokio::test]
+    async fn test_crashed_processor_not_restarted_for_a_new_owner_or_a_gone_node() {
+        let mut daemon = test_daemon();
+        let node = std::env::temp_dir().join(format!("keymux-test-owner-{}", std::process::id()));
+        std::fs::write(&node, "").unwrap();
+        let kbd_id = add_keyboard(&mut daemon, "dddd:0913", 60999, &[node.to_str().unwrap()]);
+
+        // Handed to another user while the processor was dying
+        daemon.keyboard_owners.insert(kbd_id.clone(), 61000);
+        daemon
+            .handle_dead_processor(&node, Some("panicked".to_string()))
+            .await;
+        assert!(!daemon.processor_restarts.contains_key(&kbd_id));
+        assert_eq!(running(&daemon, &kbd_id), 0);
+        let _ = std::fs::remove_file(&node);
+
+        // Unplugged: the event file went away with the keyboard
+        let gone = add_keyboard(&mut daemon, "eeee:0913", 60999, &["/dev/input/event-gone"]);
+        daemon
+            .handle_dead_processor(
+                std::path::Path::new("/dev/input/event-gone"),
+                Some("No such device".to_string()),
+            )
+            .await;
+        assert!(!daemon.processor_restarts.contains_key(&gone));
+        assert!(!daemon.all_keyboards[&gone].connected);
+        assert!(!daemon.keyboard_owners.contains_key(&gone));
+    }
+
+    #[tokio::test]
+    async fn test_silent_processor_reported_stuck_until_it_beats_again() {
+        let mut daemon = test_daemon();
+        add_keyboard(&mut daemon, "ffff:0941", 60999, &["/dev/input/event93"]);
+        let node = PathBuf::from("/dev/input/event93");
+        let beat = || ProcessorEvent::Heartbeat {
+            event_path: node.clone(),
+        };
+
+        daemon.handle_processor_event(beat()).await;
+        daemon.check_processor_heartbeats();
+        assert!(daemon.stalled_processors.is_empty());
+
+        // Three intervals without a heartbeat
+        let silent_since = std::time::Instant::now()
+            .checked_sub(event_processor::HEARTBEAT_INTERVAL * 3)
+            .unwrap();
+        daemon
+            .processor_heartbeats
+            .insert(node.clone(), silent_since);
+        daemon.check_processor_heartbeats();
+        assert!(daemon.stalled_processors.contains(&node));
+
+        daemon.handle_processor_event(beat()).await;
+        assert!(daemon.stalled_processors.is_empty());
+
+        // Stopped processors are forgotten
+        daemon
+            .processor_heartbeats
+            .insert(node.clone(), silent_since);
+        daemon.active_processors.clear();
+        daemon.check_processor_heartbeats();
+        assert!(daemon.processor_heartbeats.is_empty());
+        assert!(daemon.stalled_processors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_throttled_processor_reported_once_while_it_runs() {
+        let mut daemon = test_daemon();
+        add_keyboard(&mut daemon, "ffff:0989", 60999, &["/dev/input/event94"]);
+        let node = PathBuf::from("/dev/input/event94");
+        let busy = || ProcessorEvent::Busy {
+            event_path: node.clone(),
+            turns_per_sec: 20_000,
+        };
+
+        daemon.handle_processor_event(busy()).await;
+        daemon.handle_processor_event(busy()).await;
+        assert_eq!(daemon.busy_processors.len(), 1);
+
+        // A new processor on the same node gets its own report
+        daemon.active_processors.clear();
+        daemon.check_processor_heartbeats();
+        assert!(daemon.busy_processors.is_empty());
+
+        // Reports from processors that are gone are only logged
+        daemon.handle_processor_event(busy()).await;
+        assert!(daemon.busy_processors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_next_daemon_picks_up_game_mode_owners_and_layers() {
+        let mut daemon = test_daemon();
+        let kbd_id = add_keyboard(&mut daemon, "abcd:0905", 60999, &["/dev/input/event95"]);
+        // The processor reports a toggled layer and a TapLock lock
+        let command_rx = listen(&mut daemon, "/dev/input/event95");
+        let processor = thread::spawn(move || {
+            if let Ok(ProcessorCommand::ReportLayers(reply_tx)) = command_rx.recv() {
+                let layers = vec![Layer("nav".to_string())];
+                let _ = reply_tx.send((layers, vec![(KeyCode::KC_CAPS, KeyCode::KC_LSFT)]));
+            }
+        });
+        daemon
+            .handle_ipc_request(IpcRequest::SetGameMode(true))
+            .await;
+        daemon.profile_overrides.insert(60999, "work".to_string());
+        daemon.shutdown().await;
+        processor.join().unwrap();
+        assert_eq!(running(&daemon, &kbd_id), 0);
+
+        let mut next = AsyncDaemon::empty();
+        next.all_keyboards = daemon.all_keyboards.clone();
+        next.restore_state();
+        assert!(next.game_mode_active);
+        assert!(next.game_mode_manual);
+        assert_eq!(next.keyboard_owners.get(&kbd_id), Some(&60999));
+        assert_eq!(
+            next.restored_layers.get(&kbd_id),
+            Some(&(
+                vec![Layer("nav".to_string())],
+                vec![(KeyCode::KC_CAPS, KeyCode::KC_LSFT)]
+            ))
+        );
+        assert_eq!(
+            next.profile_overrides.get(&60999).map(String::as_str),
+            Some("work")
+        );
+
+        // Taken once: a third daemon starts fresh
+        assert!(DaemonState::take().is_none());
+    }
+
+    #[test]
+    fn test_private_socket_is_owner_only_and_speaks_for_its_user() {
+        use std::io::{Read, Write};
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("keymux-test-socket-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("keymux.sock");
+        // Left behind by a daemon that didn't get to clean up
+        std::fs::write(&path, "stale").unwrap();
+        let listener = bind_user_socket(&path, 0).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let (tx, mut rx) = tokio_mpsc::unbounded_channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_stop = Arc::clone(&stop);
+        let server = thread::spawn(move || {
+            let rate_limiter = std::sync::Mutex::new(RateLimiter::new());
+            serve_ipc(
+                listener,
+                &tx,
+                &rate_limiter,
+                Some(60999),
+                Some(&server_stop),
+            );
+        });
+        let client_path = path.clone();
+        let client = thread::spawn(move || {
+            let mut stream = std::os::unix::net::UnixStream::connect(client_path).unwrap();
+            let encoded = bincode::serialize(&IpcRequest::GetStatus).unwrap();
+            stream
+                .write_all(&(encoded.len() as u32).to_le_bytes())
+                .unwrap();
+            stream.write_all(&encoded).unwrap();
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).unwrap();
+            let mut response = vec![0u8; u32::from_le_bytes(len) as usize];
+            stream.read_exact(&mut response).unwrap();
+            bincode::deserialize::<IpcResponse>(&response).unwrap()
+        });
+
+        // Whoever connects, requests count as the socket's user
+        let (request, peer, resp_tx) = rx.blocking_recv().unwrap();
+        assert!(matches!(request, IpcRequest::GetStatus));
+        assert_eq!(peer.uid, 60999);
+        resp_tx.send(IpcResponse::Pong).unwrap();
+        assert!(matches!(client.join().unwrap(), IpcResponse::Pong));
+
+        UserSocket {
+            uid: 0,
+            path: path.clone(),
+            stop,
+        }
+        .close();
+        server.join().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_text_typed_through_the_users_own_keyboard() {
+        let mut daemon = test_daemon();
+        add_keyboard(&mut daemon, "aaaa:0890", 60999, &["/dev/input/event96"]);
+        add_keyboard(&mut daemon, "bbbb:0890", 61000, &["/dev/input/event97"]);
+        let mine = listen(&mut daemon, "/dev/input/event96");
+        let theirs = listen(&mut daemon, "/dev/input/event97");
+
+        let request = |uid| IpcRequest::TypeString {
+            text: "hello".to_string(),
+            enter: true,
+            uid: Some(uid),
+            keyboard: None,
+        };
+        let response = daemon.handle_ipc_request(request(60999)).await;
+        assert!(matches!(response, IpcResponse::Ok));
+        assert!(matches!(
+            mine.try_recv(),
+            Ok(ProcessorCommand::TypeString(text, true)) if text == "hello"
+        ));
+        assert!(theirs.try_recv().is_err());
+
+        // A user without keyboards has nothing to type through
+        let response = daemon.handle_ipc_request(request(61001)).await;
+        assert!(matches!(response, IpcResponse::Error(_)));
+    }
+}
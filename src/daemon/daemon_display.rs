@@ -68,18 +68,48 @@ impl DaemonDisplay {
 
         let mut max_name_width = 4; // "Name"
         let mut max_hw_id_width = 5; // "HW ID"
+        let mut max_owner_width = 5; // "Owner"
         let mut max_status_width = 8; // "Status"
 
         for kbd in keyboards {
             max_name_width = max_name_width.max(kbd.name.len());
             max_hw_id_width = max_hw_id_width.max(kbd.hardware_id.len());
+            max_owner_width = max_owner_width.max(Self::owner_str(kbd).len());
             max_status_width = max_status_width.max(Self::status_str(kbd).len());
         }
 
-        max_name_width + max_hw_id_width + max_status_width + 8 // spacing
+        max_name_width + max_hw_id_width + max_owner_width + max_status_width + 10
+        // spacing
+    }
+
+    fn owner_str(kbd: &crate::ipc::KeyboardInfo) -> String {
+        match (&kbd.owner_name, kbd.owner_uid) {
+            (Some(name), _) => name.clone(),
+            (None, Some(uid)) => format!("uid {}", uid),
+            (None, None) => "-".to_string(),
+        }
+    }
+
+    /// Layer, game mode, event counts and battery of a processed keyboard
+    fn state_str(kbd: &crate::ipc::KeyboardInfo) -> Option<String> {
+        let layer = kbd.active_layer.as_ref()?;
+        let mut state = format!(
+            "layer {}, game mode {}, {} keys in / {} out",
+            layer,
+            if kbd.game_mode { "on" } else { "off" },
+            kbd.events_in,
+            kbd.events_out
+        );
+        if let Some(battery) = kbd.battery {
+            state.push_str(&format!(", battery {}", battery));
+        }
+        Some(state)
     }
 
     fn status_str(kbd: &crate::ipc::KeyboardInfo) -> String {
+        if kbd.quarantine.is_some() {
+            return "⚠ Quarantined".to_string();
+        }
         match (&kbd.enabled, &kbd.matched_rule) {
             (true, Some(rule)) => format!("✓ Enabled by \"{}\"", rule),
             (true, None) => "✓ Enabled implicitly".to_string(),
@@ -91,24 +121,28 @@ impl DaemonDisplay {
     fn print_keyboard_table(&self, keyboards: &[crate::ipc::KeyboardInfo]) {
         let mut max_name_width = 4;
         let mut max_hw_id_width = 5; // "HW ID"
+        let mut max_owner_width = 5; // "Owner"
 
         for kbd in keyboards {
             max_name_width = max_name_width.max(kbd.name.len());
             max_hw_id_width = max_hw_id_width.max(kbd.hardware_id.len());
+            max_owner_width = max_owner_width.max(Self::owner_str(kbd).len());
         }
 
         // Header
         println!(
-            "  {:<width_name$}  {:<width_hw$}  {}",
+            "  {:<width_name$}  {:<width_hw$}  {:<width_owner$}  {}",
             "Name".bright_white().bold(),
             "HW ID".bright_white().bold(),
+            "Owner".bright_white().bold(),
             "Status".bright_white().bold(),
             width_name = max_name_width,
-            width_hw = max_hw_id_width
+            width_hw = max_hw_id_width,
+            width_owner = max_owner_width
         );
 
         // Separator
-        let separator_width = max_name_width + max_hw_id_width + 16;
+        let separator_width = max_name_width + max_hw_id_width + max_owner_width + 18;
         println!("  {}", "─".repeat(separator_width).dimmed());
 
         // Data rows
@@ -121,13 +155,23 @@ impl DaemonDisplay {
             };
 
             println!(
-                "  {:<width_name$}  {:<width_hw$}  {}",
+                "  {:<width_name$}  {:<width_hw$}  {:<width_owner$}  {}",
                 kbd.name.bright_white(),
                 kbd.hardware_id.dimmed(),
+                Self::owner_str(kbd),
                 status,
                 width_name = max_name_width,
                 width_hw = max_hw_id_width,
+                width_owner = max_owner_width,
             );
+            if let Some(state) = Self::state_str(kbd) {
+                println!(
+                    "  {:<width_name$}  {}",
+                    "",
+                    state.dimmed(),
+                    width_name = max_name_width
+                );
+            }
         }
     }
 
@@ -142,7 +186,18 @@ impl DaemonDisplay {
 
             println!("    - {} ({})", kbd.name.bright_white(), status);
             println!("      HW ID: {}", kbd.hardware_id.dimmed());
-            println!("      Path: {}", kbd.device_path.dimmed());
+            println!("      Owner: {}", Self::owner_str(kbd));
+            if let Some(state) = Self::state_str(kbd) {
+                println!("      State: {}", state.dimmed());
+            }
+            for node in &kbd.event_nodes {
+                println!(
+                    "      Path: {} ({}){}",
+                    node.path.dimmed(),
+                    node.capabilities(),
+                    if node.processing { ", processing" } else { "" }
+                );
+            }
         }
     }
 
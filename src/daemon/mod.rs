@@ -1,5 +1,11 @@
+pub mod check;
+pub mod config_watch;
 pub mod daemon;
 pub mod daemon_display;
+pub mod rate_limit;
+pub mod restart_backoff;
+pub mod shutdown_inhibit;
+pub mod state;
 
 pub use daemon::AsyncDaemon;
 pub use daemon_display::DaemonDisplay;
@@ -0,0 +1,80 @@
+/// Per-uid rate limiting for IPC requests
+///
+/// The root socket is world-writable, so any local user can talk to the daemon. Each
+/// uid gets a token bucket for ordinary requests and a much smaller one for requests
+/// that reload configs or restart processors, so a runaway script can't keep the
+/// daemon busy or restart everyone's keyboards in a loop.
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Ordinary requests: bursts of 30, then 10 per second
+const BURST: f64 = 30.0;
+const REFILL_PER_SEC: f64 = 10.0;
+/// Reloads, toggles, profile switches, shutdown: bursts of 3, then one every 2 seconds
+const EXPENSIVE_BURST: f64 = 3.0;
+const EXPENSIVE_REFILL_PER_SEC: f64 = 0.5;
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    /// Buckets by (uid, expensive)
+    buckets: HashMap<(u32, bool), Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a token for a request from `uid`, returning false if it should be refused
+    pub fn allow(&mut self, uid: u32, expensive: bool) -> bool {
+        self.allow_at(uid, expensive, Instant::now())
+    }
+
+    fn allow_at(&mut self, uid: u32, expensive: bool, now: Instant) -> bool {
+        let (burst, refill) = if expensive {
+            (EXPENSIVE_BURST, EXPENSIVE_REFILL_PER_SEC)
+        } else {
+            (BURST, REFILL_PER_SEC)
+        };
+        let bucket = self.buckets.entry((uid, expensive)).or_insert(Bucket {
+            tokens: burst,
+            last: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill).min(burst);
+        bucket.last = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_expensive_requests_limited_per_uid() {
+        let mut limiter = RateLimiter::new();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.allow_at(1000, true, start));
+        }
+        assert!(!limiter.allow_at(1000, true, start));
+        // Other users and cheap requests have their own buckets
+        assert!(limiter.allow_at(1001, true, start));
+        assert!(limiter.allow_at(1000, false, start));
+        // One token back after two seconds
+        assert!(limiter.allow_at(1000, true, start + Duration::from_secs(2)));
+        assert!(!limiter.allow_at(1000, true, start + Duration::from_secs(2)));
+    }
+}
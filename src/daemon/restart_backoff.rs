@@ -0,0 +1,123 @@
+/// Restart backoff for keyboards whose processors crash
+///
+/// A crashed keyboard's processors restart after `RESTART_MIN`, and the delay doubles
+/// with each crash in a row up to `RESTART_MAX`. Processors that ran for
+/// `STABLE_AFTER` since their last restart count as recovered, so the next crash
+/// starts over at the first delay. After `QUARANTINE_AFTER` crashes in a row the
+/// keyboard is quarantined instead.
+use std::time::{Duration, Instant};
+
+/// First delay before restarting a keyboard whose processor crashed
+const RESTART_MIN: Duration = Duration::from_millis(500);
+/// Longest delay between restarts of a keyboard that keeps crashing
+const RESTART_MAX: Duration = Duration::from_secs(30);
+/// Processors running this long since their last restart start over at the first delay
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+/// Crashes in a row after which a keyboard is quarantined instead of restarted
+pub const QUARANTINE_AFTER: u32 = 5;
+
+/// What to do about a crash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashResponse {
+    /// Restart the processors after this long
+    Restart(Duration),
+    /// Leave the keyboard alone after this many crashes in a row
+    Quarantine(u32),
+}
+
+pub struct RestartBackoff {
+    /// Restarts since the processors last ran for `STABLE_AFTER`
+    attempts: u32,
+    last_restart: Instant,
+    /// A restart is already scheduled
+    pending: bool,
+}
+
+impl RestartBackoff {
+    pub const fn new(now: Instant) -> Self {
+        Self {
+            attempts: 0,
+            last_restart: now,
+            pending: false,
+        }
+    }
+
+    pub const fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Restarts since the processors last ran for a while
+    pub const fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Count a crash at `now`, scheduling a restart unless it's time to quarantine
+    pub fn on_crash(&mut self, now: Instant) -> CrashResponse {
+        if now.saturating_duration_since(self.last_restart) >= STABLE_AFTER {
+            self.attempts = 0;
+        }
+        if self.attempts + 1 >= QUARANTINE_AFTER {
+            return CrashResponse::Quarantine(self.attempts + 1);
+        }
+        let delay = restart_delay(self.attempts);
+        self.attempts += 1;
+        self.pending = true;
+        CrashResponse::Restart(delay)
+    }
+
+    /// The scheduled restart happened at `now`
+    pub fn restarted(&mut self, now: Instant) {
+        self.pending = false;
+        self.last_restart = now;
+    }
+}
+
+/// Delay before the restart following `attempts` restarts in a row
+fn restart_delay(attempts: u32) -> Duration {
+    (RESTART_MIN * 2u32.pow(attempts.min(16))).min(RESTART_MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_doubles_up_to_the_cap_and_resets_once_stable() {
+        assert_eq!(restart_delay(0), Duration::from_millis(500));
+        assert_eq!(restart_delay(1), Duration::from_secs(1));
+        assert_eq!(restart_delay(2), Duration::from_secs(2));
+        assert_eq!(restart_delay(6), RESTART_MAX);
+        assert_eq!(restart_delay(u32::MAX), RESTART_MAX);
+
+        let start = Instant::now();
+        let mut backoff = RestartBackoff::new(start);
+        let mut now = start;
+        for delay_ms in [500, 1000] {
+            let response = backoff.on_crash(now);
+            assert_eq!(
+                response,
+                CrashResponse::Restart(Duration::from_millis(delay_ms))
+            );
+            assert!(backoff.is_pending());
+            now += Duration::from_millis(delay_ms);
+            backoff.restarted(now);
+        }
+
+        // Ran long enough since the last restart: back to the first delay
+        now += STABLE_AFTER;
+        let response = backoff.on_crash(now);
+        assert_eq!(response, CrashResponse::Restart(RESTART_MIN));
+        assert_eq!(backoff.attempts(), 1);
+
+        // Crashing again right after each restart ends in quarantine
+        for _ in 1..QUARANTINE_AFTER - 1 {
+            backoff.restarted(now);
+            assert!(matches!(backoff.on_crash(now), CrashResponse::Restart(_)));
+        }
+        backoff.restarted(now);
+        assert_eq!(
+            backoff.on_crash(now),
+            CrashResponse::Quarantine(QUARANTINE_AFTER)
+        );
+    }
+}
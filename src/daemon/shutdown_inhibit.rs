@@ -0,0 +1,146 @@
+//! Flush adaptive stats before the system powers off
+//!
+//! Processors save their stats every 30 seconds, so a poweroff loses up to half a
+//! minute of learning, more when the daemon is killed before it can stop cleanly. The
+//! watcher holds a logind delay inhibitor (`systemd-inhibit --mode=delay`) and follows
+//! logind's `PrepareForShutdown` signal with `busctl monitor`. When shutdown starts, the
+//! daemon is asked to flush and the lock is dropped once it has, or after
+//! `FLUSH_TIMEOUT`, well inside logind's default 5 second delay limit.
+
+use std::io::BufRead;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc as tokio_mpsc;
+use tracing::{debug, info, warn};
+
+/// How long shutdown is held back waiting for the flush
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Start the watcher thread; the receiver gets a reply sender each time shutdown
+/// starts, to be answered once stats are on disk
+pub fn start() -> tokio_mpsc::UnboundedReceiver<mpsc::Sender<()>> {
+    let (tx, rx) = tokio_mpsc::unbounded_channel();
+    thread::spawn(move || loop {
+        watch(&tx);
+        if tx.is_closed() {
+            return;
+        }
+        thread::sleep(Duration::from_secs(5));
+    });
+    rx
+}
+
+/// Run one `busctl monitor` until it exits
+fn watch(tx: &tokio_mpsc::UnboundedSender<mpsc::Sender<()>>) {
+    let mut monitor = match Command::new("busctl")
+        .args([
+            "--system",
+            "monitor",
+            "--json=short",
+            "--match",
+            "type='signal',interface='org.freedesktop.login1.Manager',member='PrepareForShutdown'",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            warn!(
+                "Can't watch for system shutdown (busctl: {}); stats may lose the last 30 seconds at poweroff",
+                e
+            );
+            return;
+        }
+    };
+
+    let mut inhibitor = take_inhibitor();
+    if let Some(stdout) = monitor.stdout.take() {
+        for line in std::io::BufReader::new(stdout)
+            .lines()
+            .map_while(Result::ok)
+        {
+            match prepare_for_shutdown(&line) {
+                Some(true) => {
+                    info!("System is shutting down, flushing adaptive stats");
+                    let (done_tx, done_rx) = mpsc::channel();
+                    if tx.send(done_tx).is_err() {
+                        break;
+                    }
+                    if done_rx.recv_timeout(FLUSH_TIMEOUT).is_err() {
+                        warn!("Stats flush didn't finish in time, letting shutdown continue");
+                    }
+                    release_inhibitor(inhibitor.take());
+                }
+                // Shutdown was cancelled; be ready for the next one
+                Some(false) if inhibitor.is_none() => inhibitor = take_inhibitor(),
+                _ => {}
+            }
+        }
+    }
+    release_inhibitor(inhibitor);
+    let _ = monitor.kill();
+    let _ = monitor.wait();
+    debug!("busctl monitor exited");
+}
+
+/// Hold a shutdown delay lock for as long as the returned child runs
+fn take_inhibitor() -> Option<Child> {
+    match Command::new("systemd-inhibit")
+        .args([
+            "--what=shutdown",
+            "--mode=delay",
+            "--who=keymux",
+            "--why=Saving adaptive timing stats",
+            "sleep",
+            "infinity",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => Some(child),
+        Err(e) => {
+            warn!("Failed to take shutdown inhibitor lock: {}", e);
+            None
+        }
+    }
+}
+
+fn release_inhibitor(inhibitor: Option<Child>) {
+    if let Some(mut child) = inhibitor {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// The argument of a `PrepareForShutdown` signal in a `busctl --json=short` line
+fn prepare_for_shutdown(line: &str) -> Option<bool> {
+    let message: serde_json::Value = serde_json::from_str(line).ok()?;
+    if message.get("member")?.as_str()? != "PrepareForShutdown" {
+        return None;
+    }
+    message.get("payload")?.get("data")?.get(0)?.as_bool()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_for_shutdown_parses_busctl_json() {
+        let line = r#"{"type":"signal","endian":"l","flags":1,"version":1,"cookie":1234,"timestamp-realtime":1700000000000000,"sender":":1.3","path":"/org/freedesktop/login1","interface":"org.freedesktop.login1.Manager","member":"PrepareForShutdown","payload":{"type":"b","data":[true]}}"#;
+        assert_eq!(prepare_for_shutdown(line), Some(true));
+        assert_eq!(
+            prepare_for_shutdown(&line.replace("[true]", "[false]")),
+            Some(false)
+        );
+        assert_eq!(
+            prepare_for_shutdown(&line.replace("PrepareForShutdown", "PrepareForSleep")),
+            None
+        );
+        assert_eq!(prepare_for_shutdown("Monitoring bus message stream."), None);
+    }
+}
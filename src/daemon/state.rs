@@ -0,0 +1,111 @@
+/// Runtime state carried across daemon restarts
+///
+/// Saved on shutdown and restored (then removed) on the next start, so restarting
+/// the daemon — e.g. for an upgrade in the middle of a game — keeps game mode,
+/// keyboard ownership, active layers, TapLock locks and profile overrides. It lives
+/// under /run, so a reboot always starts fresh.
+use crate::config::Layer;
+use crate::event_processor::actions::TapLocks;
+use crate::ipc::QuarantineInfo;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DaemonState {
+    /// Global game mode
+    #[serde(default)]
+    pub game_mode_active: bool,
+    /// Global game mode was set by hand rather than by window detection
+    #[serde(default)]
+    pub game_mode_manual: bool,
+    /// Per-keyboard game mode overrides by keyboard ID
+    #[serde(default)]
+    pub keyboard_game_mode: HashMap<String, bool>,
+    /// Owning user by keyboard ID
+    #[serde(default)]
+    pub keyboard_owners: HashMap<String, u32>,
+    /// Non-base layers left active by keyboard ID
+    #[serde(default)]
+    pub keyboard_layers: HashMap<String, Vec<Layer>>,
+    /// (physical key, modifier) of `TapLock` locks by keyboard ID
+    #[serde(default)]
+    pub keyboard_locks: HashMap<String, TapLocks>,
+    /// Profiles forced over IPC by user
+    #[serde(default)]
+    pub profile_overrides: HashMap<u32, String>,
+}
+
+impl DaemonState {
+    pub fn path() -> PathBuf {
+        crate::paths::state_dir().join("state.json")
+    }
+
+    /// Take the state a previous daemon left behind, if any
+    pub fn take() -> Option<Self> {
+        let path = Self::path();
+        let content = std::fs::read_to_string(&path).ok()?;
+        let _ = std::fs::remove_file(&path);
+        match serde_json::from_str(&content) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable state file {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Keyboards quarantined after crashing their processors, by keyboard ID
+///
+/// Kept apart from `DaemonState` and written as soon as they change, so a daemon that
+/// goes down with the keyboard still leaves it alone when it comes back.
+pub struct QuarantineNotes;
+
+impl QuarantineNotes {
+    pub fn path() -> PathBuf {
+        crate::paths::state_dir().join("quarantine.json")
+    }
+
+    pub fn load() -> HashMap<String, QuarantineInfo> {
+        let path = Self::path();
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return HashMap::new();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring unreadable quarantine file {:?}: {}", path, e);
+            HashMap::new()
+        })
+    }
+
+    pub fn save(notes: &HashMap<String, QuarantineInfo>) -> Result<()> {
+        let path = Self::path();
+        if notes.is_empty() {
+            return match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+                }
+                _ => Ok(()),
+            };
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let content = serde_json::to_string_pretty(notes)?;
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
@@ -3,6 +3,7 @@ use colored::Colorize;
 
 use keymux::config::Config;
 use keymux::daemon::DaemonDisplay;
+use keymux::ipc::{send_request, IpcRequest, IpcResponse};
 use keymux::ui::display::{
     ConfigDisplay, DeviceDisplay, KeyboardDisplay, PermissionsDisplay, SessionDisplay,
 };
@@ -64,6 +65,8 @@ pub fn run_debug(config_path: Option<&std::path::Path>) -> Result<()> {
 
     println!();
 
+    print_game_mode_status();
+
     // Window info
     println!("{}", "🪟 Window Info:".bright_yellow().bold());
 
@@ -210,3 +213,64 @@ pub fn run_debug(config_path: Option<&std::path::Path>) -> Result<()> {
 
     Ok(())
 }
+
+/// Game mode as the daemon sees it: state, what decided it, and the focused window
+fn print_game_mode_status() {
+    println!("{}", "🎮 Game Mode:".bright_yellow().bold());
+
+    match send_request(&IpcRequest::GetGameModeStatus) {
+        Ok(IpcResponse::GameModeStatus(status)) => {
+            let state = if status.active {
+                "on".bright_green().bold()
+            } else {
+                "off".dimmed()
+            };
+            println!("  State: {}", state);
+            println!("  Why: {}", status.reason.bright_white());
+            if let Some(overlay) = &status.forced_overlay {
+                println!(
+                    "  Overlay: {} {}",
+                    overlay.bright_white(),
+                    "(forced)".dimmed()
+                );
+            }
+            for (keyboard, enabled) in &status.keyboard_overrides {
+                println!(
+                    "  Override: {} {}",
+                    keyboard.bright_white(),
+                    if *enabled { "always on" } else { "always off" }
+                );
+            }
+        }
+        Ok(_) => println!("  {}", "✗ Unexpected response from daemon".bright_red()),
+        Err(_) => {
+            println!("  {}", "Daemon not running".dimmed());
+            println!();
+            return;
+        }
+    }
+
+    match send_request(&IpcRequest::GetActiveWindow) {
+        Ok(IpcResponse::ActiveWindow(Some(window))) => {
+            let or_unknown =
+                |value: &Option<String>| value.clone().unwrap_or_else(|| "unknown".to_string());
+            println!(
+                "  Focused window: {} {}",
+                or_unknown(&window.app_id).bright_white(),
+                format!("\"{}\"", or_unknown(&window.title)).dimmed()
+            );
+            if window.fullscreen {
+                println!("  Fullscreen: {}", "yes".bright_white());
+            }
+        }
+        Ok(IpcResponse::ActiveWindow(None)) => {
+            println!(
+                "  Focused window: {}",
+                "none reported (no window manager monitor in the daemon)".dimmed()
+            );
+        }
+        Ok(IpcResponse::Error(e)) => println!("  Focused window: {}", e.dimmed()),
+        _ => {}
+    }
+    println!();
+}
@@ -0,0 +1,34 @@
+use anyhow::Result;
+use colored::Colorize;
+use keymux::ipc::{send_request, IpcRequest, IpcResponse};
+
+/// Print the merged config the daemon runs a keyboard with
+///
+/// The config goes to stdout and the heading to stderr, so the output can be piped
+/// into a file or `jq` as is.
+pub fn run_effective(keyboard: &str, json: bool) -> Result<()> {
+    let request = IpcRequest::GetEffectiveConfig {
+        keyboard: keyboard.to_string(),
+        json,
+    };
+    match send_request(&request) {
+        Ok(IpcResponse::EffectiveConfig {
+            keyboard,
+            profile,
+            config,
+        }) => {
+            eprintln!(
+                "{} {}",
+                format!("Effective config of {}", keyboard)
+                    .bright_cyan()
+                    .bold(),
+                format!("(profile: {})", profile.as_deref().unwrap_or("default")).dimmed()
+            );
+            println!("{}", config);
+            Ok(())
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("{}", msg),
+        Ok(response) => anyhow::bail!("Unexpected response from daemon: {:?}", response),
+        Err(e) => anyhow::bail!("Failed to connect to daemon: {}", e),
+    }
+}
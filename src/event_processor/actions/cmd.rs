@@ -16,7 +16,7 @@ fn needs_shell(cmd: &str) -> bool {
         || cmd.contains(')')
 }
 
-fn get_user_info(uid: u32) -> Option<(String, std::path::PathBuf)> {
+pub(crate) fn get_user_info(uid: u32) -> Option<(String, std::path::PathBuf)> {
     std::process::Command::new("getent")
         .args(["passwd", &uid.to_string()])
         .output()
@@ -37,16 +37,18 @@ fn get_user_info(uid: u32) -> Option<(String, std::path::PathBuf)> {
         })
 }
 
-fn spawn_command(
+pub(crate) fn spawn_command(
     cmd: &str,
     config_dir: &std::path::Path,
     username: Option<&str>,
+    envs: &[(String, String)],
 ) -> std::io::Result<std::process::Child> {
     if needs_shell(cmd) {
         match username {
             Some(user) => std::process::Command::new("runuser")
                 .args(["-u", user, "--", "/bin/bash", "-c", cmd])
                 .current_dir(config_dir)
+                .envs(envs.iter().cloned())
                 .stdin(std::process::Stdio::null())
                 .stdout(std::process::Stdio::null())
                 .spawn(),
@@ -54,6 +56,7 @@ fn spawn_command(
                 .arg("-c")
                 .arg(cmd)
                 .current_dir(config_dir)
+                .envs(envs.iter().cloned())
                 .stdin(std::process::Stdio::null())
                 .stdout(std::process::Stdio::null())
                 .spawn(),
@@ -63,11 +66,13 @@ fn spawn_command(
             Some(user) => std::process::Command::new("runuser")
                 .args(["-u", user, "--", cmd])
                 .current_dir(config_dir)
+                .envs(envs.iter().cloned())
                 .stdin(std::process::Stdio::null())
                 .stdout(std::process::Stdio::null())
                 .spawn(),
             None => std::process::Command::new(cmd)
                 .current_dir(config_dir)
+                .envs(envs.iter().cloned())
                 .stdin(std::process::Stdio::null())
                 .stdout(std::process::Stdio::null())
                 .spawn(),
@@ -81,6 +86,10 @@ pub fn emit_cmd(
     ctx: &mut HandleContext<'_>,
 ) -> (EmitResult, Option<HeldAction>) {
     match action {
+        KeyAction::CMD(command) if ctx.dry_run => {
+            tracing::debug!("Dry run, not executing command '{}'", command);
+            (EmitResult::None, None)
+        }
         KeyAction::CMD(command) => {
             let cmd = command.clone();
             let config_dir = ctx.config_dir.clone();
@@ -102,7 +111,7 @@ pub fn emit_cmd(
                 let user_info = get_user_info(user_id);
                 let username = user_info.as_ref().map(|(u, _)| u.as_str());
 
-                if let Err(e) = spawn_command(&final_cmd, &config_dir, username) {
+                if let Err(e) = spawn_command(&final_cmd, &config_dir, username, &[]) {
                     tracing::error!("Failed to execute command '{}': {}", final_cmd, e);
                 }
             });
@@ -0,0 +1,114 @@
+/// Conditional actions - `If(condition, then_action, else_action)`
+///
+/// The condition is checked once, when the key goes down; the branch taken is kept
+/// until the key is released, even if the condition changes meanwhile. This gives
+/// context-dependent keys without copying a whole layer for one difference.
+///
+/// `OnlyGameMode(action)` and `OnlyTyping(action)` are shorthands for an `If` on game
+/// mode whose other branch is the key itself.
+use crate::config::{Condition, KeyAction};
+use crate::event_processor::actions::{EmitResult, HandleContext, HeldAction};
+use crate::keycode::KeyCode;
+
+impl Condition {
+    /// Whether the condition holds right now
+    pub fn holds(&self, ctx: &HandleContext<'_>) -> bool {
+        match self {
+            Self::GameModeActive => ctx.layer_stack.is_game_mode_active(),
+            Self::LayerActive(layer) => ctx.layer_stack.lookup_layers().any(|l| l == layer),
+            Self::ModifierHeld(modifier) => {
+                ctx.held_keys
+                    .values()
+                    .flatten()
+                    .any(|held| matches!(held, HeldAction::RegularKey(key) if key == modifier))
+                    || ctx
+                        .mt_processor
+                        .held_modifiers()
+                        .any(|key| key == *modifier)
+                    || ctx.taplock_processor.locked_modifiers().contains(modifier)
+            }
+            Self::AppMatches(pattern) => match regex::Regex::new(pattern) {
+                Ok(re) => ctx.focused_app.is_some_and(|app| re.is_match(app)),
+                Err(e) => {
+                    tracing::warn!("Invalid app_matches pattern {:?}: {}", pattern, e);
+                    false
+                }
+            },
+            Self::CapsLock => ctx.lock_state.caps_lock,
+            Self::NumLock => ctx.lock_state.num_lock,
+        }
+    }
+}
+
+pub fn emit_if(
+    action: &KeyAction,
+    keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> (EmitResult, Option<HeldAction>) {
+    let KeyAction::If(condition, then_action, else_action) = action else {
+        return (EmitResult::None, None);
+    };
+    let took_then = condition.holds(ctx);
+    let branch = if took_then { then_action } else { else_action };
+    let (result, held) = branch.emit(keycode, ctx);
+    (
+        result,
+        held.map(|held| HeldAction::IfManaged(took_then, Box::new(held))),
+    )
+}
+
+pub fn unemit_if(
+    action: &KeyAction,
+    held_action: HeldAction,
+    keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> EmitResult {
+    match (action, held_action) {
+        (KeyAction::If(_, then_action, else_action), HeldAction::IfManaged(took_then, held)) => {
+            let branch = if took_then { then_action } else { else_action };
+            branch.unemit(*held, keycode, ctx)
+        }
+        _ => EmitResult::None,
+    }
+}
+
+/// The `If` an `OnlyGameMode`/`OnlyTyping` wrapper stands for
+fn mode_if(action: &KeyAction) -> Option<KeyAction> {
+    let transparent = || Box::new(KeyAction::Transparent);
+    match action {
+        KeyAction::OnlyGameMode(inner) => Some(KeyAction::If(
+            Condition::GameModeActive,
+            inner.clone(),
+            transparent(),
+        )),
+        KeyAction::OnlyTyping(inner) => Some(KeyAction::If(
+            Condition::GameModeActive,
+            transparent(),
+            inner.clone(),
+        )),
+        _ => None,
+    }
+}
+
+pub fn emit_mode_only(
+    action: &KeyAction,
+    keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> (EmitResult, Option<HeldAction>) {
+    match mode_if(action) {
+        Some(if_action) => emit_if(&if_action, keycode, ctx),
+        None => (EmitResult::None, None),
+    }
+}
+
+pub fn unemit_mode_only(
+    action: &KeyAction,
+    held_action: HeldAction,
+    keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> EmitResult {
+    match mode_if(action) {
+        Some(if_action) => unemit_if(&if_action, held_action, keycode, ctx),
+        None => EmitResult::None,
+    }
+}
@@ -12,6 +12,7 @@
 /// When the action fires, it recursively calls .emit() on the inner action.
 use crate::config::{Config, KeyAction};
 use crate::event_processor::actions::{EmitResult, HeldAction, ProcessResult};
+use crate::event_processor::clock;
 use crate::keycode::KeyCode;
 use std::collections::HashMap;
 use std::time::Instant;
@@ -42,7 +43,7 @@ impl TdKey {
             keycode,
             tap_action,
             double_tap_action,
-            first_press_at: Instant::now(),
+            first_press_at: clock::now(),
             state: TdState::Undecided,
             tap_count: 0,
             last_emitted_action: None,
@@ -50,7 +51,7 @@ impl TdKey {
     }
 
     pub fn elapsed_since_press(&self) -> u128 {
-        self.first_press_at.elapsed().as_millis()
+        clock::elapsed(self.first_press_at).as_millis()
     }
 }
 
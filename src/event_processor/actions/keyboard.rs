@@ -0,0 +1,39 @@
+/// Keyboard toggling - `KeyboardToggle("pattern")`
+///
+/// The daemon owns which keyboards are enabled, so the action only asks it to flip
+/// the ones matching the pattern.
+use crate::config::KeyAction;
+use crate::event_processor::actions::{EmitResult, HandleContext, HeldAction};
+use crate::event_processor::ProcessorEvent;
+use crate::keycode::KeyCode;
+
+pub fn emit_keyboard_toggle(
+    action: &KeyAction,
+    _keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> (EmitResult, Option<HeldAction>) {
+    let KeyAction::KeyboardToggle(pattern) = action else {
+        return (EmitResult::None, None);
+    };
+    if ctx.dry_run {
+        return (EmitResult::None, None);
+    }
+    let event = ProcessorEvent::ToggleKeyboard {
+        uid: ctx.user_id,
+        pattern: pattern.clone(),
+    };
+    match ctx.daemon_tx.map(|tx| tx.send(event)) {
+        Some(Ok(())) => tracing::info!("Asked the daemon to toggle keyboards matching {}", pattern),
+        _ => tracing::warn!("No daemon to toggle keyboards matching {}", pattern),
+    }
+    (EmitResult::None, None)
+}
+
+pub fn unemit_keyboard_toggle(
+    _action: &KeyAction,
+    _held_action: HeldAction,
+    _keycode: KeyCode,
+    _ctx: &mut HandleContext<'_>,
+) -> EmitResult {
+    EmitResult::None
+}
@@ -1,16 +1,18 @@
 use crate::config::KeyAction;
 use crate::event_processor::actions::{EmitResult, HeldAction};
 use crate::event_processor::layer_stack::LayerStack;
+use crate::event_processor::ordering;
 use crate::keycode::KeyCode;
+use std::collections::HashMap;
 
 pub fn emit_layer(
     action: &KeyAction,
-    _keycode: KeyCode,
+    keycode: KeyCode,
     layer_stack: &mut LayerStack,
 ) -> (EmitResult, Option<HeldAction>) {
     match action {
         KeyAction::TO(layer) => {
-            layer_stack.activate_layer(layer.clone());
+            layer_stack.activate_layer_from(layer.clone(), keycode);
             (
                 EmitResult::LayerAction(layer.clone()),
                 Some(HeldAction::Layer(layer.clone())),
@@ -18,9 +20,9 @@ pub fn emit_layer(
         }
         KeyAction::TG(layer) => {
             if layer_stack.layers().contains(layer) {
-                layer_stack.deactivate_layer(layer);
+                layer_stack.deactivate_layer_from(layer, keycode);
             } else {
-                layer_stack.activate_layer(layer.clone());
+                layer_stack.activate_layer_from(layer.clone(), keycode);
             }
             (
                 EmitResult::LayerAction(layer.clone()),
@@ -28,7 +30,7 @@ pub fn emit_layer(
             )
         }
         KeyAction::MO(layer) => {
-            layer_stack.activate_layer(layer.clone());
+            layer_stack.activate_layer_from(layer.clone(), keycode);
             (
                 EmitResult::LayerAction(layer.clone()),
                 Some(HeldAction::Layer(layer.clone())),
@@ -38,15 +40,37 @@ pub fn emit_layer(
     }
 }
 
+/// Releases of a layer's `with_mods` as a key bound on it goes up
+///
+/// A modifier stays down while another key still holds it, from the same layer or
+/// as a plain key.
+pub fn release_layer_mods(
+    mods: &[KeyCode],
+    held_keys: &HashMap<KeyCode, Vec<HeldAction>>,
+) -> Vec<(KeyCode, bool)> {
+    let unheld: Vec<KeyCode> = mods
+        .iter()
+        .filter(|modifier| {
+            !held_keys.values().flatten().any(|held| match held {
+                HeldAction::LayerMods(others) => others.contains(modifier),
+                HeldAction::RegularKey(key) => key == *modifier,
+                _ => false,
+            })
+        })
+        .copied()
+        .collect();
+    ordering::release_after(Vec::new(), &unheld)
+}
+
 pub fn unemit_layer(
     action: &KeyAction,
     held_action: HeldAction,
-    _keycode: KeyCode,
+    keycode: KeyCode,
     layer_stack: &mut LayerStack,
 ) -> EmitResult {
     match (action, held_action) {
         (_, HeldAction::Layer(layer)) => {
-            layer_stack.deactivate_layer(&layer);
+            layer_stack.deactivate_layer_from(&layer, keycode);
             EmitResult::None
         }
         _ => EmitResult::None,
@@ -0,0 +1,223 @@
+/// System keyboard layout switching - `LayoutSwitch("de")`, `LayoutSwitch("next")`
+///
+/// The daemon runs as root, so the switch is sent to the user's session through
+/// whichever backend it finds running there: niri, Hyprland or sway over their IPC
+/// sockets, or setxkbmap on X11. `keyboard_layouts` lists the session's layouts in
+/// order, which turns names into indices for the compositors and lets the processor
+/// know the active layout, so typed text (`keymux type`) follows the switch.
+use crate::config::{Config, KeyAction};
+use crate::event_processor::actions::cmd::{get_user_info, spawn_command};
+use crate::event_processor::actions::{EmitResult, HandleContext, HeldAction};
+use crate::keycode::KeyCode;
+use std::path::{Path, PathBuf};
+
+/// Layout assumed when `keyboard_layouts` is empty
+const DEFAULT_LAYOUT: &str = "us";
+
+/// Where a switch goes, resolved against `keyboard_layouts`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LayoutTarget {
+    /// Position in `keyboard_layouts` (and the compositor's layout list)
+    Index(usize, String),
+    Next,
+    Prev,
+    /// A layout that isn't in `keyboard_layouts`
+    Name(String),
+}
+
+/// Tracks the active layout of a keyboard's session
+pub struct LayoutSwitcher {
+    layouts: Vec<String>,
+    current: String,
+}
+
+impl LayoutSwitcher {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            layouts: config.keyboard_layouts.clone(),
+            current: config
+                .keyboard_layouts
+                .first()
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_LAYOUT.to_string()),
+        }
+    }
+
+    /// Layout text is typed for
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// Resolve a LayoutSwitch argument and make it the active layout
+    fn switch(&mut self, target: &str) -> LayoutTarget {
+        let position = self.layouts.iter().position(|l| *l == self.current);
+        let len = self.layouts.len();
+        let index = match (target, position) {
+            ("next", Some(i)) => Some((i + 1) % len),
+            ("prev", Some(i)) => Some((i + len - 1) % len),
+            ("next" | "prev", None) if len > 0 => Some(0),
+            _ => self.layouts.iter().position(|l| l == target),
+        };
+        match index {
+            Some(i) => {
+                self.current = self.layouts[i].clone();
+                LayoutTarget::Index(i, self.current.clone())
+            }
+            None if target == "next" => LayoutTarget::Next,
+            None if target == "prev" => LayoutTarget::Prev,
+            None => {
+                self.current = target.to_string();
+                LayoutTarget::Name(target.to_string())
+            }
+        }
+    }
+}
+
+fn first_match(dir: &Path, matches: impl Fn(&str) -> bool) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(&matches)
+        })
+}
+
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Command (and environment) that performs the switch in the user's session
+fn layout_command(uid: u32, target: &LayoutTarget) -> Option<(String, Vec<(String, String)>)> {
+    let runtime_dir = PathBuf::from(format!("/run/user/{uid}"));
+    let mut envs = vec![(
+        "XDG_RUNTIME_DIR".to_string(),
+        runtime_dir.to_string_lossy().into_owned(),
+    )];
+    let step = match target {
+        LayoutTarget::Index(i, _) => Some(i.to_string()),
+        LayoutTarget::Next => Some("next".to_string()),
+        LayoutTarget::Prev => Some("prev".to_string()),
+        LayoutTarget::Name(_) => None,
+    };
+    let name = match target {
+        LayoutTarget::Index(_, name) | LayoutTarget::Name(name) => Some(name.as_str()),
+        LayoutTarget::Next | LayoutTarget::Prev => None,
+    };
+
+    if let Some(socket) = first_match(&runtime_dir, |n| {
+        n.starts_with("niri.") && n.ends_with(".sock")
+    }) {
+        envs.push((
+            "NIRI_SOCKET".to_string(),
+            socket.to_string_lossy().into_owned(),
+        ));
+        let cmd = format!("niri msg action switch-layout {}", quote(&step?));
+        return Some((cmd, envs));
+    }
+    if let Some(instance) = first_match(&runtime_dir.join("hypr"), |_| true) {
+        let signature = instance.file_name()?.to_string_lossy().into_owned();
+        envs.push(("HYPRLAND_INSTANCE_SIGNATURE".to_string(), signature));
+        let cmd = match (&step, target) {
+            (_, LayoutTarget::Name(name)) => {
+                format!("hyprctl keyword input:kb_layout {}", quote(name))
+            }
+            (Some(step), _) => format!("hyprctl switchxkblayout all {}", quote(step)),
+            (None, _) => return None,
+        };
+        return Some((cmd, envs));
+    }
+    if let Some(socket) = first_match(&runtime_dir, |n| n.starts_with("sway-ipc.")) {
+        envs.push((
+            "SWAYSOCK".to_string(),
+            socket.to_string_lossy().into_owned(),
+        ));
+        let cmd = match (&step, target) {
+            (_, LayoutTarget::Name(name)) => {
+                format!("swaymsg input type:keyboard xkb_layout {}", quote(name))
+            }
+            (Some(step), _) => {
+                format!(
+                    "swaymsg input type:keyboard xkb_switch_layout {}",
+                    quote(step)
+                )
+            }
+            (None, _) => return None,
+        };
+        return Some((cmd, envs));
+    }
+    if Path::new("/tmp/.X11-unix/X0").exists() {
+        envs.push(("DISPLAY".to_string(), ":0".to_string()));
+        return Some((format!("setxkbmap {}", quote(name?)), envs));
+    }
+    None
+}
+
+pub fn emit_layout(
+    action: &KeyAction,
+    _keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> (EmitResult, Option<HeldAction>) {
+    let KeyAction::LayoutSwitch(target) = action else {
+        return (EmitResult::None, None);
+    };
+    let resolved = ctx.layout_switcher.switch(target);
+    tracing::info!(
+        "Switching keyboard layout to {} (typing as {})",
+        target,
+        ctx.layout_switcher.current()
+    );
+    if ctx.dry_run {
+        return (EmitResult::None, None);
+    }
+
+    let config_dir = ctx.config_dir.clone();
+    let user_id = ctx.user_id;
+    std::thread::spawn(move || {
+        let Some((cmd, envs)) = layout_command(user_id, &resolved) else {
+            tracing::warn!(
+                "No way to switch to layout {:?} in user {}'s session (list it in keyboard_layouts)",
+                resolved,
+                user_id
+            );
+            return;
+        };
+        let username = get_user_info(user_id).map(|(user, _)| user);
+        if let Err(e) = spawn_command(&cmd, &config_dir, username.as_deref(), &envs) {
+            tracing::error!("Failed to switch layout with '{}': {}", cmd, e);
+        }
+    });
+    (EmitResult::None, None)
+}
+
+pub fn unemit_layout(
+    _action: &KeyAction,
+    _held_action: HeldAction,
+    _keycode: KeyCode,
+    _ctx: &mut HandleContext<'_>,
+) -> EmitResult {
+    EmitResult::None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_switch_cycles_known_layouts() {
+        let mut switcher = LayoutSwitcher {
+            layouts: vec!["us".to_string(), "de".to_string()],
+            current: "us".to_string(),
+        };
+        assert_eq!(switcher.switch("next"), LayoutTarget::Index(1, "de".into()));
+        assert_eq!(switcher.current(), "de");
+        assert_eq!(switcher.switch("next"), LayoutTarget::Index(0, "us".into()));
+        assert_eq!(switcher.switch("de"), LayoutTarget::Index(1, "de".into()));
+        assert_eq!(switcher.switch("fr"), LayoutTarget::Name("fr".into()));
+        assert_eq!(switcher.current(), "fr");
+        // Not in the list, so cycling starts over at the first layout
+        assert_eq!(switcher.switch("prev"), LayoutTarget::Index(0, "us".into()));
+    }
+}
@@ -1,23 +1,42 @@
 //! Action processors for keyboard events
 //!
 //! This module contains all the specialized processors for different action types:
-//! - MT (Mod-Tap): Tap/hold dual-function keys
+//! - MT (Mod-Tap): Tap/hold dual-function keys, FixedTerm keeping one off adaptive timing
 //! - DT (Double-Tap): Tap dance with single/double-tap detection
 //! - OSM (OneShot Modifier): One-shot modifiers that auto-release
 //! - SOCD (Simultaneous Opposite Cardinal Direction): Handling for opposing keys
 //! - CMD: Shell command execution
 //! - Layer: Layer switching (TO, TG, MO)
+//! - Turbo: Autofire while held
+//! - Script: User-defined behaviors in Rhai (optional `scripting` feature)
+//! - LayoutSwitch: System keyboard layout switching
+//! - KeyboardToggle: Enabling/disabling other keyboards through the daemon
+//! - ProfileSwitch: Switching the user's profile through the daemon
+//! - TapLock: Double-tap a modifier to lock it on, tap again to release
+//! - If: Pick between two actions by a condition checked on press
+//! - OnlyGameMode/OnlyTyping: An action for one mode, the key itself in the other
 
 pub mod cmd;
+pub mod conditional;
 pub mod dt;
+pub mod keyboard;
 pub mod layer;
+pub mod layout;
 pub mod mt;
 pub mod osm;
+pub mod profile;
+pub mod script;
 pub mod socd;
+pub mod taplock;
+pub mod turbo;
 
 use crate::config::{KeyAction, Layer};
 use crate::event_processor::layer_stack::LayerStack;
+use crate::event_processor::lock_state::LockState;
+use crate::event_processor::ordering;
+use crate::event_processor::EventSender;
 use crate::keycode::KeyCode;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ProcessResult {
@@ -28,6 +47,18 @@ pub enum ProcessResult {
     None,
 }
 
+impl ProcessResult {
+    /// Key events of a result, in order (typed strings aren't key events)
+    pub fn into_events(self) -> Vec<(KeyCode, bool)> {
+        match self {
+            Self::EmitKey(key, pressed) => vec![(key, pressed)],
+            Self::TapKeyPressRelease(key) => vec![(key, true), (key, false)],
+            Self::MultipleEvents(events) => events,
+            Self::TypeString(..) | Self::None => Vec::new(),
+        }
+    }
+}
+
 impl From<SocdResolution> for ProcessResult {
     fn from(res: SocdResolution) -> Self {
         match res {
@@ -87,6 +118,14 @@ pub enum HeldAction {
         double_tap_action: KeyAction,
     },
     OsmManaged,
+    ScriptManaged(String),
+    TurboManaged,
+    /// Tap-lock key; the wrapped action's held state if it acted on the press
+    TapLockManaged(Option<Box<HeldAction>>),
+    /// `If` key; whether the then-branch was taken, and that branch's held state
+    IfManaged(bool, Box<HeldAction>),
+    /// Modifiers pressed for the `with_mods` of the layer the key's binding came from
+    LayerMods(Vec<KeyCode>),
 }
 
 pub struct HandleContext<'a> {
@@ -94,20 +133,34 @@ pub struct HandleContext<'a> {
     pub dt_processor: &'a mut DtProcessor,
     pub osm_processor: &'a mut OsmProcessor,
     pub socd_processor: &'a mut SocdProcessor,
+    pub script_processor: &'a mut ScriptProcessor,
+    pub turbo_processor: &'a mut TurboProcessor,
+    pub taplock_processor: &'a mut TapLockProcessor,
+    pub layout_switcher: &'a mut LayoutSwitcher,
     pub layer_stack: &'a mut LayerStack,
+    /// Caps Lock / Num Lock when the key went down
+    pub lock_state: LockState,
+    /// What each key down is holding
+    pub held_keys: &'a HashMap<KeyCode, Vec<HeldAction>>,
+    /// app_id of the focused window, if the daemon reported one
+    pub focused_app: Option<&'a str>,
+    /// Where events for the daemon go (None when nothing is listening)
+    pub daemon_tx: Option<&'a EventSender>,
     pub config_dir: std::path::PathBuf,
     pub user_id: u32,
+    /// Skip side effects such as running commands
+    pub dry_run: bool,
 }
 
 pub fn handle_action_release(
     action: HeldAction,
     keycode: KeyCode,
-    ctx: HandleContext<'_>,
+    mut ctx: HandleContext<'_>,
 ) -> ProcessResult {
     match action {
         HeldAction::RegularKey(key) => ProcessResult::EmitKey(key, false),
         HeldAction::Layer(layer) => {
-            ctx.layer_stack.deactivate_layer(&layer);
+            ctx.layer_stack.deactivate_layer_from(&layer, keycode);
             ProcessResult::None
         }
         HeldAction::MtManaged => ctx
@@ -130,6 +183,18 @@ pub fn handle_action_release(
             let _ = osm::handle_osm_release(ctx.osm_processor, keycode);
             ProcessResult::None
         }
+        HeldAction::TurboManaged => {
+            turbo::handle_turbo_release(ctx.turbo_processor, keycode).to_process_result()
+        }
+        HeldAction::ScriptManaged(ref name) => {
+            let script = KeyAction::Script(name.clone());
+            unemit_script(&script, action, keycode, &mut ctx).to_process_result()
+        }
+        HeldAction::TapLockManaged(inner) => taplock::handle_taplock_release(inner, keycode, ctx),
+        HeldAction::IfManaged(_, inner) => handle_action_release(*inner, keycode, ctx),
+        HeldAction::LayerMods(mods) => {
+            ProcessResult::MultipleEvents(release_layer_mods(&mods, ctx.held_keys))
+        }
     }
 }
 
@@ -154,14 +219,12 @@ impl KeyAction {
     ) -> (EmitResult, Option<HeldAction>) {
         match self {
             Self::Key(output_key) => {
-                let events = ctx
-                    .mt_processor
-                    .on_other_key_press_for_resolutions(*output_key);
+                // Hands follow the physical key, so remapped layouts keep roll/chord detection
+                let events = ctx.mt_processor.on_other_key_press_for_resolutions(keycode);
                 if !events.is_empty() {
-                    let mut all_events = ctx.mt_processor.resolutions_to_events(&events);
-                    all_events.push((*output_key, true));
+                    let settled = ctx.mt_processor.resolutions_to_events(&events);
                     (
-                        EmitResult::EmitKeys(all_events),
+                        EmitResult::EmitKeys(ordering::press_after_settled(settled, *output_key)),
                         Some(HeldAction::RegularKey(*output_key)),
                     )
                 } else {
@@ -172,6 +235,7 @@ impl KeyAction {
                 }
             }
             Self::MT(..) => emit_mt(self, keycode, ctx),
+            Self::FixedTerm(..) => emit_fixed_term(self, keycode, ctx),
             Self::TO(..) | Self::TG(..) | Self::MO(..) => {
                 emit_layer(self, keycode, ctx.layer_stack)
             }
@@ -179,13 +243,20 @@ impl KeyAction {
             Self::CMD(..) => emit_cmd(self, keycode, ctx),
             Self::OSM(..) => emit_osm(self, keycode, ctx),
             Self::DT(..) => emit_dt(self, keycode, ctx),
+            Self::Script(..) => emit_script(self, keycode, ctx),
+            Self::Turbo(..) => emit_turbo(self, keycode, ctx),
+            Self::LayoutSwitch(..) => emit_layout(self, keycode, ctx),
+            Self::KeyboardToggle(..) => emit_keyboard_toggle(self, keycode, ctx),
+            Self::ProfileSwitch(..) => emit_profile_switch(self, keycode, ctx),
+            Self::TapLock(..) => emit_taplock(self, keycode, ctx),
+            Self::If(..) => emit_if(self, keycode, ctx),
+            Self::OnlyGameMode(..) | Self::OnlyTyping(..) => emit_mode_only(self, keycode, ctx),
             Self::Transparent => {
                 let resolutions = ctx.mt_processor.on_other_key_press_for_resolutions(keycode);
                 if !resolutions.is_empty() {
-                    let mut events = ctx.mt_processor.resolutions_to_events(&resolutions);
-                    events.push((keycode, true));
+                    let settled = ctx.mt_processor.resolutions_to_events(&resolutions);
                     (
-                        EmitResult::EmitKeys(events),
+                        EmitResult::EmitKeys(ordering::press_after_settled(settled, keycode)),
                         Some(HeldAction::RegularKey(keycode)),
                     )
                 } else {
@@ -206,14 +277,34 @@ impl KeyAction {
     ) -> EmitResult {
         match (&self, action.clone()) {
             (_, HeldAction::RegularKey(key)) => EmitResult::EmitKey(key, false),
+            (_, HeldAction::LayerMods(mods)) => {
+                EmitResult::EmitKeys(release_layer_mods(&mods, ctx.held_keys))
+            }
             (Self::TO(..) | Self::TG(..) | Self::MO(..), HeldAction::Layer(_)) => {
                 unemit_layer(self, action, keycode, ctx.layer_stack)
             }
             (Self::MT(..), HeldAction::MtManaged) => unemit_mt(self, action, keycode, ctx),
+            (Self::FixedTerm(inner), HeldAction::MtManaged) => {
+                unemit_mt(inner, action, keycode, ctx)
+            }
             (Self::SOCD(..), HeldAction::SocdManaged) => unemit_socd(self, action, keycode, ctx),
             (Self::DT(..), HeldAction::DtManaged { .. }) => unemit_dt(self, action, keycode, ctx),
             (Self::OSM(..), HeldAction::OsmManaged) => unemit_osm(self, action, keycode, ctx),
             (Self::CMD(..), _) => unemit_cmd(self, action, keycode, ctx),
+            (Self::LayoutSwitch(..), _) => unemit_layout(self, action, keycode, ctx),
+            (Self::KeyboardToggle(..), _) => unemit_keyboard_toggle(self, action, keycode, ctx),
+            (Self::ProfileSwitch(..), _) => unemit_profile_switch(self, action, keycode, ctx),
+            (Self::Script(..), HeldAction::ScriptManaged(_)) => {
+                unemit_script(self, action, keycode, ctx)
+            }
+            (Self::Turbo(..), HeldAction::TurboManaged) => unemit_turbo(self, action, keycode, ctx),
+            (Self::TapLock(..), HeldAction::TapLockManaged(_)) => {
+                unemit_taplock(self, action, keycode, ctx)
+            }
+            (Self::If(..), HeldAction::IfManaged(..)) => unemit_if(self, action, keycode, ctx),
+            (Self::OnlyGameMode(..) | Self::OnlyTyping(..), HeldAction::IfManaged(..)) => {
+                unemit_mode_only(self, action, keycode, ctx)
+            }
             _ => EmitResult::None,
         }
     }
@@ -228,10 +319,18 @@ impl KeyAction {
 
 // Re-export commonly used types and emit/unemit functions
 pub use cmd::{emit_cmd, unemit_cmd};
+pub use conditional::{emit_if, emit_mode_only, unemit_if, unemit_mode_only};
 pub use dt::{emit_dt, handle_dt_action, handle_dt_release, unemit_dt, DtProcessor, TdResolution};
-pub use layer::{emit_layer, unemit_layer};
+pub use keyboard::{emit_keyboard_toggle, unemit_keyboard_toggle};
+pub use layer::{emit_layer, release_layer_mods, unemit_layer};
+pub use layout::{emit_layout, unemit_layout, LayoutSwitcher};
 pub use mt::{
-    emit_mt, handle_mt_action, unemit_mt, MtAction, MtProcessor, MtResolution, RollingStats,
+    emit_fixed_term, emit_mt, handle_mt_action, unemit_mt, MtAction, MtProcessor, MtResolution,
+    RollingStats,
 };
 pub use osm::{emit_osm, handle_osm_action, handle_osm_release, unemit_osm, OsmProcessor};
+pub use profile::{emit_profile_switch, unemit_profile_switch};
+pub use script::{emit_script, unemit_script, ScriptProcessor};
 pub use socd::{emit_socd, handle_socd_action, unemit_socd, SocdProcessor, SocdResolution};
+pub use taplock::{emit_taplock, unemit_taplock, TapLockProcessor, TapLocks};
+pub use turbo::{emit_turbo, unemit_turbo, TurboProcessor};
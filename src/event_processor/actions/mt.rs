@@ -1,5 +1,6 @@
 use crate::config::{Config, KeyAction};
 use crate::event_processor::actions::{EmitResult, HeldAction};
+use crate::event_processor::clock;
 use crate::keycode::KeyCode;
 use serde::{Deserialize, Serialize};
 /// Advanced Mod-Tap (MT) system inspired by QMK
@@ -12,8 +13,14 @@ use serde::{Deserialize, Serialize};
 /// - Adaptive timing per-key/per-pair
 /// - Predictive intent scoring
 /// - Configurable behavior options
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Decisions remembered for `keymux mt-trace`
+const TRACE_LEN: usize = 64;
+
+/// Presses older than this say nothing about rolls (see `calculate_hold_intent`)
+const RECENT_PRESS_MAX_AGE: Duration = Duration::from_secs(1);
 
 /// Hand assignment for a key (for chord detection)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -57,6 +64,8 @@ pub struct MtKey {
     pub hold_intent_score: f32,
     /// Which hand this key is on
     pub hand: Hand,
+    /// Another key was pressed while this one was down
+    pub interrupted: bool,
 }
 
 impl MtKey {
@@ -65,16 +74,17 @@ impl MtKey {
             keycode,
             tap_key,
             hold_key,
-            pressed_at: Instant::now(),
+            pressed_at: clock::now(),
             state: MtKeyState::Undecided,
             hold_intent_score: 0.0,
             hand,
+            interrupted: false,
         }
     }
 
     /// Get duration since press
     pub fn duration(&self) -> Duration {
-        Instant::now() - self.pressed_at
+        clock::now() - self.pressed_at
     }
 
     /// Get duration in milliseconds
@@ -92,6 +102,9 @@ pub struct RollingStats {
     pub tap_sample_count: u32,
     /// Adaptive threshold for this key (ms) - stays ~30ms above avg tap
     pub adaptive_threshold: f32,
+    /// Exponentially weighted variance of the tap duration (ms²)
+    #[serde(default)]
+    pub tap_variance: f32,
 }
 
 impl RollingStats {
@@ -100,9 +113,20 @@ impl RollingStats {
             avg_tap_duration: 0.0,
             tap_sample_count: 0,
             adaptive_threshold: base_threshold,
+            tap_variance: 0.0,
         }
     }
 
+    /// Standard deviation of the tap duration (ms)
+    pub fn tap_stddev(&self) -> f32 {
+        self.tap_variance.sqrt()
+    }
+
+    /// Whether the learned threshold is trustworthy: enough taps, spread little enough
+    pub fn is_confident(&self, min_samples: u32, max_stddev_ms: f32) -> bool {
+        self.tap_sample_count >= min_samples.max(1) && self.tap_stddev() <= max_stddev_ms
+    }
+
     /// Update with new tap duration using exponential moving average
     /// Alpha = 2 / (N + 1) where N is window size
     /// For 100 sample window: alpha = 2/101 ≈ 0.0198
@@ -112,9 +136,11 @@ impl RollingStats {
         if self.tap_sample_count == 0 {
             self.avg_tap_duration = duration_ms;
         } else {
-            // Exponential moving average: EMA = α * new_value + (1 - α) * old_EMA
-            self.avg_tap_duration =
-                ALPHA.mul_add(duration_ms, (1.0 - ALPHA) * self.avg_tap_duration);
+            // Exponential moving average: EMA = α * new_value + (1 - α) * old_EMA, with the
+            // matching incremental variance
+            let diff = duration_ms - self.avg_tap_duration;
+            self.avg_tap_duration = ALPHA.mul_add(diff, self.avg_tap_duration);
+            self.tap_variance = (1.0 - ALPHA) * ALPHA.mul_add(diff * diff, self.tap_variance);
         }
 
         self.tap_sample_count += 1;
@@ -180,6 +206,12 @@ pub struct MtConfig {
     /// Window (ms) for detecting double-taps
     pub double_tap_window_ms: u32,
 
+    /// Quick-tap term (ms) - re-press within this long after a tap always taps (0 = off)
+    pub quick_tap_term_ms: u32,
+
+    /// Per-key quick-tap terms (ms) overriding quick_tap_term_ms
+    pub quick_tap_term_per_key: HashMap<KeyCode, u32>,
+
     /// Enable cross-hand unwrap - when holding a modifier on one hand,
     /// MT keys on the opposite hand will unwrap to their tap key
     pub cross_hand_unwrap: bool,
@@ -188,10 +220,31 @@ pub struct MtConfig {
     /// Default: 30ms means threshold = avg_tap + 30ms
     pub adaptive_target_margin_ms: u32,
 
+    /// Taps a key needs before its learned threshold is used
+    pub adaptive_min_samples: u32,
+
+    /// Largest tap duration standard deviation (ms) at which the learned threshold is used
+    pub adaptive_max_stddev_ms: f32,
+
     /// When holding an MT key and doing nothing, emit tap on release
     /// If true, holding then releasing without other action sends the tap key
     /// If false, holding then releasing without other action does nothing
     pub hold_do_nothing_emits_tap: bool,
+
+    /// Retro tapping - held past the tapping term with no other key pressed always taps
+    pub retro_tapping: bool,
+
+    /// Per-key retro tapping overriding retro_tapping
+    pub retro_tapping_per_key: HashMap<KeyCode, bool>,
+
+    /// Chordal hold - the next key's hand decides: opposite hand holds, same hand taps
+    pub chordal_hold: bool,
+
+    /// Per-key chordal hold overriding chordal_hold
+    pub chordal_hold_per_key: HashMap<KeyCode, bool>,
+
+    /// Record every decision for `keymux mt-trace`
+    pub trace_decisions: bool,
 }
 
 impl Default for MtConfig {
@@ -209,9 +262,18 @@ impl Default for MtConfig {
             chord_detection_window_ms: 50,
             double_tap_then_hold: false,
             double_tap_window_ms: 300,
+            quick_tap_term_ms: 0,
+            quick_tap_term_per_key: HashMap::new(),
             cross_hand_unwrap: true,
             adaptive_target_margin_ms: 30,
+            adaptive_min_samples: 20,
+            adaptive_max_stddev_ms: 25.0,
             hold_do_nothing_emits_tap: true,
+            retro_tapping: false,
+            retro_tapping_per_key: HashMap::new(),
+            chordal_hold: false,
+            chordal_hold_per_key: HashMap::new(),
+            trace_decisions: false,
         }
     }
 }
@@ -247,8 +309,18 @@ pub struct MtProcessor {
     /// Keys currently holding their tap key (double-tap-then-hold)
     holding_tap_key: HashMap<KeyCode, KeyCode>,
 
+    /// Keys down through a `FixedTerm` binding: fixed tapping term, nothing learned
+    fixed_term_keys: HashSet<KeyCode>,
+
     /// Game mode active (when true, pause adaptive timing learning)
     game_mode_active: bool,
+
+    /// A linked keyboard is holding a modifier (shared state, counts for cross-hand unwrap)
+    remote_modifier_held: bool,
+
+    /// Recent decisions, oldest first (only with trace_decisions)
+    decisions: VecDeque<MtDecision>,
+    next_decision_seq: u64,
 }
 
 impl MtProcessor {
@@ -268,23 +340,50 @@ impl MtProcessor {
                 chord_detection_window_ms: config.mt_config.chord_detection_window_ms,
                 double_tap_then_hold: config.mt_config.double_tap_then_hold,
                 double_tap_window_ms: config.mt_config.double_tap_window_ms,
+                quick_tap_term_ms: config.mt_config.quick_tap_term_ms,
+                quick_tap_term_per_key: config.mt_config.quick_tap_term_per_key.clone(),
                 cross_hand_unwrap: config.mt_config.cross_hand_unwrap,
                 adaptive_target_margin_ms: config.mt_config.adaptive_target_margin_ms,
+                adaptive_min_samples: config.mt_config.adaptive_min_samples,
+                adaptive_max_stddev_ms: config.mt_config.adaptive_max_stddev_ms,
                 hold_do_nothing_emits_tap: config.mt_config.hold_do_nothing_emits_tap,
+                retro_tapping: config.mt_config.retro_tapping,
+                retro_tapping_per_key: config.mt_config.retro_tapping_per_key.clone(),
+                chordal_hold: config.mt_config.chordal_hold,
+                chordal_hold_per_key: config.mt_config.chordal_hold_per_key.clone(),
+                trace_decisions: config.mt_config.trace_decisions,
             },
             undecided_keys: HashMap::new(),
             held_keys: HashMap::new(),
             rolling_stats: HashMap::new(),
-            hand_map: Self::build_default_hand_map(),
+            hand_map: Self::build_hand_map(&config.key_positions()),
             recent_presses: Vec::new(),
             max_history: 10,
             last_tap_time: HashMap::new(),
             holding_tap_key: HashMap::new(),
+            fixed_term_keys: HashSet::new(),
             game_mode_active: false,
+            remote_modifier_held: false,
+            decisions: VecDeque::new(),
+            next_decision_seq: 0,
+        }
+    }
+
+    /// Hand of each key the keyboard sends, going by its position (`sent -> position`
+    /// for keyboards whose firmware sends another layout)
+    fn build_hand_map(positions: &HashMap<KeyCode, KeyCode>) -> HashMap<KeyCode, Hand> {
+        let qwerty = Self::build_default_hand_map();
+        let mut map = qwerty.clone();
+        for (sent, position) in positions {
+            match qwerty.get(position) {
+                Some(hand) => map.insert(*sent, *hand),
+                None => map.remove(sent),
+            };
         }
+        map
     }
 
-    /// Build default hand assignment map (QWERTY layout)
+    /// Build default hand assignment map, by physical position (QWERTY key names)
     fn build_default_hand_map() -> HashMap<KeyCode, Hand> {
         let mut map = HashMap::new();
 
@@ -392,15 +491,43 @@ impl MtProcessor {
         tap_key: KeyCode,
         hold_key: KeyCode,
     ) -> Option<MtResolution> {
-
+        // Check for quick-tap: re-press soon after a tap holds the tap key (hold-to-repeat)
+        let quick_tap_term = self.quick_tap_term(keycode);
+        if quick_tap_term > 0 {
+            if let Some(last_tap) = self.last_tap_time.get(&keycode) {
+                if clock::elapsed(*last_tap).as_millis() < u128::from(quick_tap_term) {
+                    self.holding_tap_key.insert(keycode, tap_key);
+                    self.record_decision(
+                        keycode,
+                        clock::now(),
+                        0.0,
+                        false,
+                        MtReason::QuickTap,
+                        None,
+                    );
+                    return Some(MtResolution {
+                        keycode,
+                        action: MtAction::HoldPress(tap_key),
+                    });
+                }
+            }
+        }
 
         // Check for double-tap
         if self.config.double_tap_then_hold {
             if let Some(last_tap) = self.last_tap_time.get(&keycode) {
-                let elapsed = Instant::now().duration_since(*last_tap).as_millis() as u32;
+                let elapsed = clock::now().duration_since(*last_tap).as_millis() as u32;
                 if elapsed < self.config.double_tap_window_ms {
                     // Double-tap detected! Hold the tap key until released
                     self.holding_tap_key.insert(keycode, tap_key);
+                    self.record_decision(
+                        keycode,
+                        clock::now(),
+                        0.0,
+                        false,
+                        MtReason::DoubleTap,
+                        None,
+                    );
                     return Some(MtResolution {
                         keycode,
                         action: MtAction::HoldPress(tap_key),
@@ -415,14 +542,25 @@ impl MtProcessor {
         // Check for cross-hand unwrap
         if self.config.cross_hand_unwrap && hand != Hand::Unknown {
             // Check if there are any held modifiers on the opposite hand
-            let has_opposite_hand_mod = self.held_keys.values().any(|held_key| {
-                let held_hand = held_key.hand;
-                held_hand != Hand::Unknown && held_hand != hand
-            });
+            let has_opposite_hand_mod = self.remote_modifier_held
+                || self.held_keys.values().any(|held_key| {
+                    let held_hand = held_key.hand;
+                    held_key.state != MtKeyState::Tap
+                        && held_hand != Hand::Unknown
+                        && held_hand != hand
+                });
 
             if has_opposite_hand_mod {
                 // Unwrap to tap key - mark as unwrapped and store it
                 mt_key.state = MtKeyState::Unwrapped;
+                self.record_decision(
+                    keycode,
+                    mt_key.pressed_at,
+                    0.0,
+                    false,
+                    MtReason::CrossHandUnwrap,
+                    None,
+                );
                 self.held_keys.insert(keycode, mt_key);
 
                 return Some(MtResolution {
@@ -437,8 +575,11 @@ impl MtProcessor {
             mt_key.hold_intent_score = self.calculate_hold_intent(&mt_key);
         }
 
-        // Add to recent presses history
-        self.recent_presses.push((keycode, Instant::now()));
+        // Add to recent presses history, dropping those too old to matter
+        let now = clock::now();
+        self.recent_presses
+            .retain(|(_, at)| now.duration_since(*at) < RECENT_PRESS_MAX_AGE);
+        self.recent_presses.push((keycode, now));
         if self.recent_presses.len() > self.max_history {
             self.recent_presses.remove(0);
         }
@@ -449,8 +590,46 @@ impl MtProcessor {
 
     /// Another key pressed while MT key is pending (permissive hold trigger)
     pub fn on_other_key_press(&mut self, other_keycode: KeyCode) -> Vec<MtResolution> {
+        self.resolve_pending_on_press(other_keycode, false)
+    }
+
+    /// Resolve pending MT keys for a key press (`other_is_mt`: it's an MT key itself)
+    fn resolve_pending_on_press(
+        &mut self,
+        other_keycode: KeyCode,
+        other_is_mt: bool,
+    ) -> Vec<MtResolution> {
         let mut resolutions = Vec::new();
 
+        // Rules out retro tapping even when nothing below resolves the key
+        for mt_key in self.undecided_keys.values_mut() {
+            mt_key.interrupted = true;
+        }
+
+        let other_hand = self.get_hand(other_keycode);
+
+        // Chordal hold settles keys by hand alone, before the window heuristics
+        let undecided: Vec<_> = self.undecided_keys.keys().copied().collect();
+        for keycode in undecided {
+            let hand = self.get_hand(keycode);
+            if !self.chordal_hold(keycode) || hand == Hand::Unknown || other_hand == Hand::Unknown {
+                continue;
+            }
+            if hand != other_hand {
+                resolutions.extend(self.resolve_to_hold(
+                    keycode,
+                    MtReason::ChordalHoldOppositeHand,
+                    Some(other_keycode),
+                ));
+            } else if !other_is_mt {
+                resolutions.extend(self.resolve_to_tap(
+                    keycode,
+                    MtReason::ChordalHoldSameHand,
+                    Some(other_keycode),
+                ));
+            }
+        }
+
         if !self.config.permissive_hold
             && !self.config.same_hand_roll_detection
             && !self.config.opposite_hand_chord_detection
@@ -458,14 +637,17 @@ impl MtProcessor {
             return resolutions;
         }
 
-        let other_hand = self.get_hand(other_keycode);
-        let now = Instant::now();
+        let now = clock::now();
 
         // Check each undecided key
         let undecided: Vec<_> = self.undecided_keys.keys().copied().collect();
 
         for keycode in undecided {
             if let Some(mt_key) = self.undecided_keys.get(&keycode) {
+                // Same-hand MT keys left pending by chordal hold wait for multi-mod or release
+                if self.chordal_hold(keycode) && mt_key.hand != Hand::Unknown {
+                    continue;
+                }
                 let time_since_press = (now - mt_key.pressed_at).as_millis() as u32;
 
                 // Check for same-hand roll
@@ -475,7 +657,9 @@ impl MtProcessor {
                     && time_since_press < self.config.roll_detection_window_ms
                 {
                     // Same-hand roll detected - resolve to tap
-                    if let Some(resolved) = self.resolve_to_tap(keycode) {
+                    if let Some(resolved) =
+                        self.resolve_to_tap(keycode, MtReason::SameHandRoll, Some(other_keycode))
+                    {
                         resolutions.push(resolved);
                     }
                     continue;
@@ -489,7 +673,11 @@ impl MtProcessor {
                     && time_since_press < self.config.chord_detection_window_ms
                 {
                     // Opposite-hand chord detected - resolve to hold
-                    if let Some(resolved) = self.resolve_to_hold(keycode) {
+                    if let Some(resolved) = self.resolve_to_hold(
+                        keycode,
+                        MtReason::OppositeHandChord,
+                        Some(other_keycode),
+                    ) {
                         resolutions.push(resolved);
                     }
                     continue;
@@ -497,7 +685,9 @@ impl MtProcessor {
 
                 // Standard permissive hold
                 if self.config.permissive_hold {
-                    if let Some(resolved) = self.resolve_to_hold(keycode) {
+                    if let Some(resolved) =
+                        self.resolve_to_hold(keycode, MtReason::PermissiveHold, Some(other_keycode))
+                    {
                         resolutions.push(resolved);
                     }
                 }
@@ -517,6 +707,10 @@ impl MtProcessor {
     pub fn on_release(&mut self, keycode: KeyCode) -> Option<MtResolution> {
         // Check if this key is holding its tap key (double-tap-then-hold)
         if let Some(tap_key) = self.holding_tap_key.remove(&keycode) {
+            // Keep rapid re-presses tapping
+            if self.quick_tap_term(keycode) > 0 {
+                self.last_tap_time.insert(keycode, clock::now());
+            }
             // Release the held tap key
             return Some(MtResolution {
                 keycode,
@@ -529,11 +723,7 @@ impl MtProcessor {
             let duration_ms = mt_key.duration_ms() as u32;
 
             // Decide based on timing and intent score
-            let effective_threshold = if self.config.adaptive_timing {
-                self.get_adaptive_threshold(keycode)
-            } else {
-                self.config.tapping_term_ms
-            };
+            let (effective_threshold, _) = self.effective_threshold(keycode);
 
             let should_hold = if self.config.predictive_scoring {
                 // Use intent score with timing
@@ -545,21 +735,52 @@ impl MtProcessor {
 
             // Check if we should emit tap instead of hold when held past threshold
             let is_hold_timing = duration_ms >= effective_threshold;
+            let retro_tap = is_hold_timing && !mt_key.interrupted && self.retro_tapping(keycode);
             let emit_tap_on_hold_timeout = is_hold_timing
                 && self.config.hold_do_nothing_emits_tap
                 && mt_key.hold_intent_score <= 0.5; // No strong intent for hold
 
-            if emit_tap_on_hold_timeout {
+            let (hold, reason) = if retro_tap {
+                (false, MtReason::RetroTap)
+            } else if emit_tap_on_hold_timeout {
+                (false, MtReason::HoldDidNothing)
+            } else if should_hold && !is_hold_timing {
+                (true, MtReason::IntentScore)
+            } else if should_hold {
+                (true, MtReason::HeldPastThreshold)
+            } else {
+                (false, MtReason::ReleasedInTime)
+            };
+            self.record_decision(
+                keycode,
+                mt_key.pressed_at,
+                mt_key.hold_intent_score,
+                hold,
+                reason,
+                None,
+            );
+
+            if retro_tap {
+                // Retro tap: nothing else happened while held, so it was meant as a tap
+                if self.config.double_tap_then_hold || self.quick_tap_term(keycode) > 0 {
+                    self.last_tap_time.insert(keycode, clock::now());
+                }
+
+                Some(MtResolution {
+                    keycode,
+                    action: MtAction::TapPressRelease(mt_key.tap_key),
+                })
+            } else if emit_tap_on_hold_timeout {
                 // Hold-do-nothing-emits-tap: emit tap even though held past threshold
                 // Record tap time for double-tap detection
-                if self.config.double_tap_then_hold {
-                    self.last_tap_time.insert(keycode, Instant::now());
+                if self.config.double_tap_then_hold || self.quick_tap_term(keycode) > 0 {
+                    self.last_tap_time.insert(keycode, clock::now());
                 }
 
                 // Record ONLY taps (below threshold) for adaptive timing
                 // This prevents survivorship bias - only successful taps are tracked
                 // Skip recording when game mode is active
-                if self.config.adaptive_timing && !self.game_mode_active {
+                if self.adaptive_timing(keycode) && !self.game_mode_active {
                     self.update_tap_stats(keycode, duration_ms as f32);
                 }
 
@@ -580,14 +801,14 @@ impl MtProcessor {
             } else {
                 // Tap: emit tap key press and release
                 // Record tap time for double-tap detection
-                if self.config.double_tap_then_hold {
-                    self.last_tap_time.insert(keycode, Instant::now());
+                if self.config.double_tap_then_hold || self.quick_tap_term(keycode) > 0 {
+                    self.last_tap_time.insert(keycode, clock::now());
                 }
 
                 // Record ONLY taps (below threshold) for adaptive timing
                 // This prevents survivorship bias - only successful taps are tracked
                 // Skip recording when game mode is active
-                if self.config.adaptive_timing && !self.game_mode_active {
+                if self.adaptive_timing(keycode) && !self.game_mode_active {
                     self.update_tap_stats(keycode, duration_ms as f32);
                 }
 
@@ -601,9 +822,9 @@ impl MtProcessor {
         }
         // Check if it's a held key
         else if let Some(mt_key) = self.held_keys.remove(&keycode) {
-            // Check if it was unwrapped
-            if mt_key.state == MtKeyState::Unwrapped {
-                // Release the unwrapped tap key
+            // Check if it was unwrapped or resolved to tap while held
+            if matches!(mt_key.state, MtKeyState::Unwrapped | MtKeyState::Tap) {
+                // Release the tap key
                 Some(MtResolution {
                     keycode,
                     action: MtAction::ReleaseHold(mt_key.tap_key),
@@ -621,22 +842,51 @@ impl MtProcessor {
     }
 
     /// Resolve undecided key to tap
-    fn resolve_to_tap(&mut self, keycode: KeyCode) -> Option<MtResolution> {
+    fn resolve_to_tap(
+        &mut self,
+        keycode: KeyCode,
+        reason: MtReason,
+        other_key: Option<KeyCode>,
+    ) -> Option<MtResolution> {
         self.undecided_keys.remove(&keycode).map(|mut mt_key| {
             mt_key.state = MtKeyState::Tap;
+            self.record_decision(
+                keycode,
+                mt_key.pressed_at,
+                mt_key.hold_intent_score,
+                false,
+                reason,
+                other_key,
+            );
+            let tap_key = mt_key.tap_key;
+            // Kept until release so the tap key gets released too
+            self.held_keys.insert(keycode, mt_key);
 
             // Emit tap immediately
             MtResolution {
                 keycode,
-                action: MtAction::TapPress(mt_key.tap_key),
+                action: MtAction::TapPress(tap_key),
             }
         })
     }
 
     /// Resolve undecided key to hold
-    fn resolve_to_hold(&mut self, keycode: KeyCode) -> Option<MtResolution> {
+    fn resolve_to_hold(
+        &mut self,
+        keycode: KeyCode,
+        reason: MtReason,
+        other_key: Option<KeyCode>,
+    ) -> Option<MtResolution> {
         if let Some(mut mt_key) = self.undecided_keys.remove(&keycode) {
             mt_key.state = MtKeyState::Hold;
+            self.record_decision(
+                keycode,
+                mt_key.pressed_at,
+                mt_key.hold_intent_score,
+                true,
+                reason,
+                other_key,
+            );
             self.held_keys.insert(keycode, mt_key.clone());
 
             // Emit hold key press
@@ -676,7 +926,7 @@ impl MtProcessor {
         // If we have multiple mods on same hand, promote all to hold
         if left_count >= self.config.multi_mod_threshold {
             for keycode in left_keys {
-                if let Some(resolved) = self.resolve_to_hold(keycode) {
+                if let Some(resolved) = self.resolve_to_hold(keycode, MtReason::MultiMod, None) {
                     resolutions.push(resolved);
                 }
             }
@@ -684,7 +934,7 @@ impl MtProcessor {
 
         if right_count >= self.config.multi_mod_threshold {
             for keycode in right_keys {
-                if let Some(resolved) = self.resolve_to_hold(keycode) {
+                if let Some(resolved) = self.resolve_to_hold(keycode, MtReason::MultiMod, None) {
                     resolutions.push(resolved);
                 }
             }
@@ -704,7 +954,7 @@ impl MtProcessor {
         }
 
         // Check recent key press patterns
-        let now = Instant::now();
+        let now = clock::now();
         let recent_same_hand = self
             .recent_presses
             .iter()
@@ -722,18 +972,68 @@ impl MtProcessor {
         score.clamp(0.0, 1.0)
     }
 
-    /// Get adaptive threshold for a key based on tap statistics
-    fn get_adaptive_threshold(&self, keycode: KeyCode) -> u32 {
-        // Look up stats for this key
-        if let Some(stats) = self.rolling_stats.get(&(keycode, keycode)) {
-            if stats.tap_sample_count >= 1 {
-                // Use learned adaptive threshold (starts learning after first tap!)
-                return stats.adaptive_threshold as u32;
-            }
+    /// Tapping term in effect for a key, and whether adaptive timing learned it
+    fn effective_threshold(&self, keycode: KeyCode) -> (u32, bool) {
+        if !self.adaptive_timing(keycode) {
+            return (self.config.tapping_term_ms, false);
+        }
+        match self.get_adaptive_threshold(keycode) {
+            Some(threshold) => (threshold, true),
+            None => (self.config.tapping_term_ms, false),
+        }
+    }
+
+    /// Remember a decision for `keymux mt-trace` (no-op unless trace_decisions is on)
+    fn record_decision(
+        &mut self,
+        keycode: KeyCode,
+        pressed_at: Instant,
+        intent_score: f32,
+        hold: bool,
+        reason: MtReason,
+        other_key: Option<KeyCode>,
+    ) {
+        if !self.config.trace_decisions {
+            return;
         }
+        let (threshold_ms, adaptive) = self.effective_threshold(keycode);
+        if self.decisions.len() == TRACE_LEN {
+            self.decisions.pop_front();
+        }
+        self.decisions.push_back(MtDecision {
+            seq: self.next_decision_seq,
+            at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+            key: keycode,
+            hold,
+            reason,
+            held_ms: clock::elapsed(pressed_at).as_millis() as u32,
+            threshold_ms,
+            adaptive,
+            other_key,
+            intent_score: self.config.predictive_scoring.then_some(intent_score),
+        });
+        self.next_decision_seq += 1;
+    }
 
-        // Fall back to default only if no samples at all
-        self.config.tapping_term_ms
+    /// Recent decisions, oldest first (empty unless trace_decisions is on)
+    pub fn decisions(&self) -> Vec<MtDecision> {
+        self.decisions.iter().cloned().collect()
+    }
+
+    /// Learned threshold for a key, once its taps are many and consistent enough to
+    /// trust (None = use the tapping term)
+    fn get_adaptive_threshold(&self, keycode: KeyCode) -> Option<u32> {
+        self.rolling_stats
+            .get(&(keycode, keycode))
+            .filter(|stats| {
+                stats.is_confident(
+                    self.config.adaptive_min_samples,
+                    self.config.adaptive_max_stddev_ms,
+                )
+            })
+            .map(|stats| stats.adaptive_threshold as u32)
     }
 
     /// Update tap statistics - records actual tap durations and adjusts threshold
@@ -742,16 +1042,55 @@ impl MtProcessor {
         let base_threshold = self.config.tapping_term_ms as f32;
         let target_margin = self.config.adaptive_target_margin_ms as f32;
 
-
-
         let stats = self
             .rolling_stats
             .entry(key)
             .or_insert_with(|| RollingStats::new(base_threshold));
 
         stats.update_tap(duration_ms, target_margin);
+    }
+
+    /// Quick-tap term for a key (per-key override, else global; 0 = off)
+    fn quick_tap_term(&self, keycode: KeyCode) -> u32 {
+        self.config
+            .quick_tap_term_per_key
+            .get(&keycode)
+            .copied()
+            .unwrap_or(self.config.quick_tap_term_ms)
+    }
+
+    /// Whether chordal hold is on for a key (per-key override, else global)
+    fn chordal_hold(&self, keycode: KeyCode) -> bool {
+        self.config
+            .chordal_hold_per_key
+            .get(&keycode)
+            .copied()
+            .unwrap_or(self.config.chordal_hold)
+    }
 
+    /// Whether adaptive timing learns and applies a threshold for a key (on, unless
+    /// the key is down through a `FixedTerm` binding)
+    fn adaptive_timing(&self, keycode: KeyCode) -> bool {
+        self.config.adaptive_timing && !self.fixed_term_keys.contains(&keycode)
+    }
+
+    /// Keep the MT key being pressed on the fixed tapping term until it's released
+    pub fn set_fixed_term(&mut self, keycode: KeyCode) {
+        self.fixed_term_keys.insert(keycode);
+    }
+
+    /// Whether a key is down through a `FixedTerm` binding, so adaptive stats skip it
+    pub fn is_fixed_term(&self, keycode: KeyCode) -> bool {
+        self.fixed_term_keys.contains(&keycode)
+    }
 
+    /// Whether retro tapping is on for a key (per-key override, else global)
+    fn retro_tapping(&self, keycode: KeyCode) -> bool {
+        self.config
+            .retro_tapping_per_key
+            .get(&keycode)
+            .copied()
+            .unwrap_or(self.config.retro_tapping)
     }
 
     /// Check if any keys are pending (for external permissive hold logic)
@@ -759,6 +1098,34 @@ impl MtProcessor {
         !self.undecided_keys.is_empty()
     }
 
+    /// Modifiers currently emitted by MT keys resolved to hold
+    pub fn held_modifiers(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.held_keys
+            .values()
+            .filter(|mt_key| mt_key.state == MtKeyState::Hold)
+            .map(|mt_key| mt_key.hold_key)
+    }
+
+    /// Mark whether a linked keyboard is holding a modifier
+    pub const fn set_remote_modifier_held(&mut self, held: bool) {
+        self.remote_modifier_held = held;
+    }
+
+    /// A key was pressed on a linked keyboard - permissive hold for every pending key
+    pub fn on_remote_key_press(&mut self) -> Vec<MtResolution> {
+        for mt_key in self.undecided_keys.values_mut() {
+            mt_key.interrupted = true;
+        }
+        if !self.config.permissive_hold {
+            return Vec::new();
+        }
+        let undecided: Vec<_> = self.undecided_keys.keys().copied().collect();
+        undecided
+            .into_iter()
+            .filter_map(|keycode| self.resolve_to_hold(keycode, MtReason::LinkedKeyboard, None))
+            .collect()
+    }
+
     /// Get count of undecided keys
     pub fn undecided_count(&self) -> usize {
         self.undecided_keys.len()
@@ -791,8 +1158,8 @@ impl MtProcessor {
         );
     }
 
-    /// Save adaptive timing stats to file
-    pub fn save_stats(&self, path: &std::path::Path) -> Result<(), std::io::Error> {
+    /// Save adaptive timing stats to file, as `uid`
+    pub fn save_stats(&self, path: &std::path::Path, uid: u32) -> Result<(), std::io::Error> {
         if !self.config.adaptive_timing {
             return Ok(());
         }
@@ -803,14 +1170,9 @@ impl MtProcessor {
         }
 
         // Load existing stats and merge with current stats
-        let mut merged_stats = if path.exists() {
-            let json = std::fs::read_to_string(path)?;
-            // Load from string keys (JSON format)
-            serde_json::from_str::<HashMap<String, RollingStats>>(&json)
-                .unwrap_or_else(|_| HashMap::new())
-        } else {
-            HashMap::new()
-        };
+        let mut merged_stats =
+            crate::event_processor::stats_file::read::<HashMap<String, RollingStats>>(path, uid)?
+                .unwrap_or_default();
 
         // Merge: prefer our stats if we have them, otherwise keep existing
         // Convert tuple keys to string keys for JSON serialization
@@ -819,24 +1181,21 @@ impl MtProcessor {
             merged_stats.insert(key_str, stats.clone());
         }
 
-        let json = serde_json::to_string_pretty(&merged_stats)?;
-        std::fs::write(path, json)?;
-        Ok(())
+        crate::event_processor::stats_file::write(path, uid, &merged_stats)
     }
 
-    /// Load adaptive timing stats from file
-    pub fn load_stats(&mut self, path: &std::path::Path) -> Result<(), std::io::Error> {
+    /// Load adaptive timing stats from file, as `uid`
+    pub fn load_stats(&mut self, path: &std::path::Path, uid: u32) -> Result<(), std::io::Error> {
         if !self.config.adaptive_timing {
             return Ok(()); // Don't load if adaptive timing is disabled
         }
 
-        if !path.exists() {
-            return Ok(()); // File doesn't exist yet, that's okay
-        }
-
-        let json = std::fs::read_to_string(path)?;
         // Load from string keys (JSON format) and convert back to tuple keys
-        let string_map: HashMap<String, RollingStats> = serde_json::from_str(&json)?;
+        let Some(string_map) =
+            crate::event_processor::stats_file::read::<HashMap<String, RollingStats>>(path, uid)?
+        else {
+            return Ok(()); // File doesn't exist yet, that's okay
+        };
 
         self.rolling_stats.clear();
         for (key_str, stats) in string_map {
@@ -856,6 +1215,65 @@ impl MtProcessor {
     }
 }
 
+/// Why an MT key resolved the way it did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MtReason {
+    /// Re-pressed within the quick-tap term of a tap: holds the tap key
+    QuickTap,
+    /// Re-pressed within the double-tap window: holds the tap key
+    DoubleTap,
+    /// A modifier was held on the other hand (cross_hand_unwrap)
+    CrossHandUnwrap,
+    /// The next key was on the other hand (chordal_hold)
+    ChordalHoldOppositeHand,
+    /// The next key was on the same hand (chordal_hold)
+    ChordalHoldSameHand,
+    /// Another key on the same hand within the roll window
+    SameHandRoll,
+    /// A key on the other hand within the chord window
+    OppositeHandChord,
+    /// Another key was pressed while it was pending (permissive_hold)
+    PermissiveHold,
+    /// Several MT keys of one hand were pending together (multi_mod_detection)
+    MultiMod,
+    /// A key was pressed on a linked keyboard (permissive_hold)
+    LinkedKeyboard,
+    /// Released within the threshold
+    ReleasedInTime,
+    /// Released after the threshold
+    HeldPastThreshold,
+    /// Released within the threshold, but predictive scoring leaned to hold
+    IntentScore,
+    /// Held past the threshold with no other key pressed (retro_tapping)
+    RetroTap,
+    /// Held past the threshold without using it (hold_do_nothing_emits_tap)
+    HoldDidNothing,
+}
+
+/// One MT decision, as shown by `keymux mt-trace`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MtDecision {
+    /// Increases with every decision of this keyboard
+    pub seq: u64,
+    /// Milliseconds since the Unix epoch
+    pub at_ms: u64,
+    /// Physical key
+    pub key: KeyCode,
+    /// Resolved to hold (false = tap)
+    pub hold: bool,
+    pub reason: MtReason,
+    /// How long the key had been down when it was decided
+    pub held_ms: u32,
+    /// Tapping term in effect for the key
+    pub threshold_ms: u32,
+    /// The threshold was learned by adaptive timing
+    pub adaptive: bool,
+    /// Key whose press decided it
+    pub other_key: Option<KeyCode>,
+    /// Predictive intent score (with predictive_scoring)
+    pub intent_score: Option<f32>,
+}
+
 /// MT resolution result
 #[derive(Debug, Clone)]
 pub struct MtResolution {
@@ -887,7 +1305,7 @@ impl MtProcessor {
         tap_key: KeyCode,
         hold_key: KeyCode,
     ) -> (Vec<(KeyCode, bool)>, Option<MtResolution>) {
-        let other_resolutions = self.on_other_key_press(tap_key);
+        let other_resolutions = self.resolve_pending_on_press(keycode, true);
         let mut events = self.resolutions_to_events(&other_resolutions);
 
         if let Some(resolution) = self.on_press(keycode, tap_key, hold_key) {
@@ -901,7 +1319,9 @@ impl MtProcessor {
     }
 
     pub fn handle_release(&mut self, keycode: KeyCode) -> Option<MtResolution> {
-        self.on_release(keycode)
+        let resolution = self.on_release(keycode);
+        self.fixed_term_keys.remove(&keycode);
+        resolution
     }
 
     pub fn resolution_to_events(&self, resolution: &MtResolution) -> Vec<(KeyCode, bool)> {
@@ -992,6 +1412,21 @@ pub fn emit_mt(
     }
 }
 
+/// `FixedTerm(MT(..))`: the MT, kept off adaptive timing for this press
+pub fn emit_fixed_term(
+    action: &KeyAction,
+    keycode: KeyCode,
+    ctx: &mut super::HandleContext<'_>,
+) -> (EmitResult, Option<HeldAction>) {
+    match action {
+        KeyAction::FixedTerm(inner) => {
+            ctx.mt_processor.set_fixed_term(keycode);
+            emit_mt(inner, keycode, ctx)
+        }
+        _ => (EmitResult::None, None),
+    }
+}
+
 pub fn unemit_mt(
     action: &KeyAction,
     held_action: HeldAction,
@@ -1008,3 +1443,101 @@ pub fn unemit_mt(
         _ => EmitResult::None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_records_why_keys_resolved() {
+        let config = Config::parse(
+            "(tapping_term_ms: 200, mt_config: (trace_decisions: true, chordal_hold: false), remaps: {})",
+        )
+        .unwrap();
+        let mut processor = MtProcessor::new(&config);
+
+        // F held while J is pressed 100ms later: past the chord window, permissive hold
+        processor.on_press(KeyCode::KC_F, KeyCode::KC_F, KeyCode::KC_LSFT);
+        clock::advance(Duration::from_millis(100));
+        processor.on_other_key_press(KeyCode::KC_J);
+        processor.on_release(KeyCode::KC_F);
+
+        // D tapped quickly
+        processor.on_press(KeyCode::KC_D, KeyCode::KC_D, KeyCode::KC_LCTL);
+        clock::advance(Duration::from_millis(60));
+        processor.on_release(KeyCode::KC_D);
+
+        let decisions = processor.decisions();
+        assert_eq!(decisions.len(), 2);
+        assert!(decisions[0].hold);
+        assert_eq!(decisions[0].reason, MtReason::PermissiveHold);
+        assert_eq!(decisions[0].other_key, Some(KeyCode::KC_J));
+        assert_eq!(decisions[0].held_ms, 100);
+        assert!(!decisions[1].hold);
+        assert_eq!(decisions[1].reason, MtReason::ReleasedInTime);
+        assert_eq!(decisions[1].threshold_ms, 200);
+
+        // Off by default
+        let mut processor = MtProcessor::new(&Config::parse("(remaps: {})").unwrap());
+        processor.on_press(KeyCode::KC_D, KeyCode::KC_D, KeyCode::KC_LCTL);
+        processor.on_release(KeyCode::KC_D);
+        assert!(processor.decisions().is_empty());
+    }
+
+    #[test]
+    fn test_hands_follow_hardware_layout() {
+        let config = Config::parse(r#"(hardware_layout: Some("dvorak"))"#).unwrap();
+        let processor = MtProcessor::new(&config);
+        // Dvorak's J is QWERTY's C, under the left hand; its H is QWERTY's J
+        assert_eq!(processor.get_hand(KeyCode::KC_J), Hand::Left);
+        assert_eq!(processor.get_hand(KeyCode::KC_H), Hand::Right);
+        assert_eq!(processor.get_hand(KeyCode::KC_LSFT), Hand::Left);
+    }
+
+    #[test]
+    fn test_learned_threshold_needs_consistent_samples() {
+        let mut stats = RollingStats::new(200.0);
+        for _ in 0..19 {
+            stats.update_tap(80.0, 30.0);
+        }
+        assert!(!stats.is_confident(20, 25.0));
+        stats.update_tap(80.0, 30.0);
+        assert!(stats.is_confident(20, 25.0));
+
+        // Erratic taps spread too much to trust
+        let mut erratic = RollingStats::new(200.0);
+        for i in 0..100 {
+            erratic.update_tap(if i % 2 == 0 { 40.0 } else { 160.0 }, 30.0);
+        }
+        assert!(erratic.tap_stddev() > 25.0);
+        assert!(!erratic.is_confident(20, 25.0));
+    }
+
+    #[test]
+    fn test_fixed_term_keys_skip_adaptive_timing() {
+        let config =
+            Config::parse("(tapping_term_ms: 200, mt_config: (adaptive_timing: true), remaps: {})")
+                .unwrap();
+        let mut processor = MtProcessor::new(&config);
+        for _ in 0..30 {
+            for key in [KeyCode::KC_SPC, KeyCode::KC_F] {
+                if key == KeyCode::KC_SPC {
+                    processor.set_fixed_term(key);
+                }
+                processor.on_press(key, key, KeyCode::KC_LSFT);
+                clock::advance(Duration::from_millis(60));
+                processor.handle_release(key);
+                clock::advance(Duration::from_millis(200));
+            }
+        }
+
+        assert!(!processor.is_fixed_term(KeyCode::KC_SPC));
+        assert!(processor.effective_threshold(KeyCode::KC_F).1);
+        assert!(processor
+            .get_adaptive_stats()
+            .iter()
+            .all(|(key, _)| *key != KeyCode::KC_SPC));
+        processor.set_fixed_term(KeyCode::KC_F);
+        assert_eq!(processor.effective_threshold(KeyCode::KC_F), (200, false));
+    }
+}
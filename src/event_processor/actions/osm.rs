@@ -12,6 +12,7 @@
 /// - Timeout prevents accidental stuck modifiers
 use crate::config::{Config, KeyAction};
 use crate::event_processor::actions::{EmitResult, HeldAction, ProcessResult};
+use crate::event_processor::clock;
 use crate::keycode::KeyCode;
 use std::collections::HashMap;
 use std::time::Instant;
@@ -49,7 +50,7 @@ impl OsmKey {
         Self {
             keycode,
             modifier_key,
-            activated_at: Instant::now(),
+            activated_at: clock::now(),
             state: OsmState::Pressed,
             modifier_emitted: false,
         }
@@ -57,7 +58,7 @@ impl OsmKey {
 
     /// Time since activation
     pub fn elapsed(&self) -> u128 {
-        self.activated_at.elapsed().as_millis()
+        clock::elapsed(self.activated_at).as_millis()
     }
 }
 
@@ -146,7 +147,7 @@ impl OsmProcessor {
             // Tapped (released quickly) - activate one-shot
             if duration_ms < self.config.tapping_term_ms as u128 {
                 osm_key.state = OsmState::Active;
-                osm_key.activated_at = Instant::now(); // Reset timer for timeout
+                osm_key.activated_at = clock::now(); // Reset timer for timeout
                 let modifier_key = osm_key.modifier_key;
                 self.active_oneshots.insert(modifier_key, osm_key);
 
@@ -236,6 +237,11 @@ impl OsmProcessor {
         resolutions
     }
 
+    /// Drop an armed one-shot without releasing it (something else took over the modifier)
+    pub fn cancel(&mut self, modifier_key: KeyCode) {
+        self.active_oneshots.remove(&modifier_key);
+    }
+
     pub fn active_count(&self) -> usize {
         self.active_oneshots.len()
     }
@@ -0,0 +1,38 @@
+/// Profile switching - `ProfileSwitch("gaming")`, `ProfileSwitch("auto")`
+///
+/// Profiles apply to every keyboard of the user, so the daemon does the switch.
+use crate::config::KeyAction;
+use crate::event_processor::actions::{EmitResult, HandleContext, HeldAction};
+use crate::event_processor::ProcessorEvent;
+use crate::keycode::KeyCode;
+
+pub fn emit_profile_switch(
+    action: &KeyAction,
+    _keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> (EmitResult, Option<HeldAction>) {
+    let KeyAction::ProfileSwitch(profile) = action else {
+        return (EmitResult::None, None);
+    };
+    if ctx.dry_run {
+        return (EmitResult::None, None);
+    }
+    let event = ProcessorEvent::SwitchProfile {
+        uid: ctx.user_id,
+        profile: profile.clone(),
+    };
+    match ctx.daemon_tx.map(|tx| tx.send(event)) {
+        Some(Ok(())) => tracing::info!("Asked the daemon to switch to profile {}", profile),
+        _ => tracing::warn!("No daemon to switch to profile {}", profile),
+    }
+    (EmitResult::None, None)
+}
+
+pub fn unemit_profile_switch(
+    _action: &KeyAction,
+    _held_action: HeldAction,
+    _keycode: KeyCode,
+    _ctx: &mut HandleContext<'_>,
+) -> EmitResult {
+    EmitResult::None
+}
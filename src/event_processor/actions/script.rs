@@ -0,0 +1,282 @@
+//! Script actions - user-defined key behaviors written in Rhai
+//!
+//! `Script("name")` runs `~/.config/keymux/scripts/name.rhai`. A script may define
+//! `fn on_press()` and `fn on_release()`; if it defines neither, its top-level body
+//! runs on press. Scripts talk to keymux through these functions:
+//!
+//! - `press("KC_A")`, `release("KC_A")`, `tap("KC_A")` - emit keys
+//! - `layer_on("nav")`, `layer_off("nav")`, `layer_toggle("nav")` - change layers
+//! - `trigger_key()`, `current_layer()`, `is_game_mode()` - read state
+//!
+//! Only available when built with the `scripting` feature; otherwise Script
+//! actions log a warning and do nothing.
+
+use crate::config::{KeyAction, Layer};
+use crate::event_processor::actions::{EmitResult, HandleContext, HeldAction};
+use crate::keycode::KeyCode;
+
+/// Something a script asked keymux to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptEffect {
+    Key(KeyCode, bool),
+    LayerOn(Layer),
+    LayerOff(Layer),
+    LayerToggle(Layer),
+}
+
+/// State a script can read while it runs
+#[derive(Debug, Clone, Default)]
+pub struct ScriptState {
+    pub trigger: Option<KeyCode>,
+    pub layer: String,
+    pub game_mode: bool,
+}
+
+#[cfg(feature = "scripting")]
+pub use engine::ScriptProcessor;
+
+#[cfg(feature = "scripting")]
+mod engine {
+    use super::{ScriptEffect, ScriptState};
+    use crate::config::Layer;
+    use crate::keycode::KeyCode;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+    use tracing::{error, warn};
+
+    /// Upper bound on script work per key event so a runaway loop can't stall input
+    const MAX_OPERATIONS: u64 = 100_000;
+
+    pub struct ScriptProcessor {
+        engine: rhai::Engine,
+        /// Compiled scripts by name (None = failed to load, don't retry)
+        scripts: HashMap<String, Option<rhai::AST>>,
+        scripts_dir: PathBuf,
+        effects: Rc<RefCell<Vec<ScriptEffect>>>,
+        state: Rc<RefCell<ScriptState>>,
+    }
+
+    impl ScriptProcessor {
+        pub fn new(config_dir: &std::path::Path) -> Self {
+            let effects: Rc<RefCell<Vec<ScriptEffect>>> = Rc::default();
+            let state: Rc<RefCell<ScriptState>> = Rc::default();
+            let mut engine = rhai::Engine::new();
+            engine.set_max_operations(MAX_OPERATIONS);
+
+            let key_fx = |pressed: &'static [bool]| {
+                let effects = effects.clone();
+                move |name: &str| match KeyCode::from_name(name) {
+                    Some(key) => {
+                        let mut effects = effects.borrow_mut();
+                        for &p in pressed {
+                            effects.push(ScriptEffect::Key(key, p));
+                        }
+                    }
+                    None => warn!("Script used unknown key: {}", name),
+                }
+            };
+            engine.register_fn("press", key_fx(&[true]));
+            engine.register_fn("release", key_fx(&[false]));
+            engine.register_fn("tap", key_fx(&[true, false]));
+
+            let layer_fx = |make: fn(Layer) -> ScriptEffect| {
+                let effects = effects.clone();
+                move |name: &str| effects.borrow_mut().push(make(Layer::new(name)))
+            };
+            engine.register_fn("layer_on", layer_fx(ScriptEffect::LayerOn));
+            engine.register_fn("layer_off", layer_fx(ScriptEffect::LayerOff));
+            engine.register_fn("layer_toggle", layer_fx(ScriptEffect::LayerToggle));
+
+            let s = state.clone();
+            engine.register_fn("trigger_key", move || {
+                s.borrow()
+                    .trigger
+                    .map(|k| k.to_string())
+                    .unwrap_or_default()
+            });
+            let s = state.clone();
+            engine.register_fn("current_layer", move || s.borrow().layer.clone());
+            let s = state.clone();
+            engine.register_fn("is_game_mode", move || s.borrow().game_mode);
+
+            Self {
+                engine,
+                scripts: HashMap::new(),
+                scripts_dir: config_dir.join("scripts"),
+                effects,
+                state,
+            }
+        }
+
+        /// Run a script's press or release handler and collect what it asked for
+        pub fn run(&mut self, name: &str, pressed: bool, state: ScriptState) -> Vec<ScriptEffect> {
+            if !self.scripts.contains_key(name) {
+                let path = self.scripts_dir.join(format!("{name}.rhai"));
+                let ast = match self.engine.compile_file(path.clone()) {
+                    Ok(ast) => Some(ast),
+                    Err(e) => {
+                        error!("Failed to load script {:?}: {}", path, e);
+                        None
+                    }
+                };
+                self.scripts.insert(name.to_string(), ast);
+            }
+            let Some(Some(ast)) = self.scripts.get(name) else {
+                return Vec::new();
+            };
+
+            *self.state.borrow_mut() = state;
+            self.effects.borrow_mut().clear();
+
+            let handler = if pressed { "on_press" } else { "on_release" };
+            let has_handlers = ast
+                .iter_functions()
+                .any(|f| f.name == "on_press" || f.name == "on_release");
+            let result = if ast.iter_functions().any(|f| f.name == handler) {
+                self.engine
+                    .call_fn::<rhai::Dynamic>(&mut rhai::Scope::new(), ast, handler, ())
+                    .map(|_| ())
+            } else if pressed && !has_handlers {
+                self.engine.run_ast(ast)
+            } else {
+                Ok(())
+            };
+
+            if let Err(e) = result {
+                error!("Script '{}' {} failed: {}", name, handler, e);
+            }
+
+            std::mem::take(&mut *self.effects.borrow_mut())
+        }
+    }
+}
+
+/// Stand-in used when keymux is built without the `scripting` feature
+#[cfg(not(feature = "scripting"))]
+pub struct ScriptProcessor {
+    warned: bool,
+}
+
+#[cfg(not(feature = "scripting"))]
+impl ScriptProcessor {
+    pub const fn new(_config_dir: &std::path::Path) -> Self {
+        Self { warned: false }
+    }
+
+    pub fn run(&mut self, name: &str, _pressed: bool, _state: ScriptState) -> Vec<ScriptEffect> {
+        if !self.warned {
+            tracing::warn!(
+                "Script(\"{}\") ignored: keymux was built without the `scripting` feature",
+                name
+            );
+            self.warned = true;
+        }
+        Vec::new()
+    }
+}
+
+/// Run a script handler and apply its effects: layer changes now, keys via the result
+fn run_script(
+    name: &str,
+    keycode: KeyCode,
+    pressed: bool,
+    ctx: &mut HandleContext<'_>,
+) -> EmitResult {
+    let state = ScriptState {
+        trigger: Some(keycode),
+        layer: ctx.layer_stack.current_layer().0,
+        game_mode: ctx.layer_stack.is_game_mode_active(),
+    };
+
+    let mut keys = Vec::new();
+    for effect in ctx.script_processor.run(name, pressed, state) {
+        match effect {
+            ScriptEffect::Key(key, key_pressed) => keys.push((key, key_pressed)),
+            ScriptEffect::LayerOn(layer) => ctx.layer_stack.activate_layer_from(layer, keycode),
+            ScriptEffect::LayerOff(layer) => {
+                ctx.layer_stack.deactivate_layer_from(&layer, keycode);
+            }
+            ScriptEffect::LayerToggle(layer) => ctx.layer_stack.toggle_layer(layer),
+        }
+    }
+
+    if keys.is_empty() {
+        EmitResult::None
+    } else {
+        EmitResult::EmitKeys(keys)
+    }
+}
+
+pub fn emit_script(
+    action: &KeyAction,
+    keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> (EmitResult, Option<HeldAction>) {
+    match action {
+        KeyAction::Script(name) => (
+            run_script(name, keycode, true, ctx),
+            Some(HeldAction::ScriptManaged(name.clone())),
+        ),
+        _ => (EmitResult::None, None),
+    }
+}
+
+pub fn unemit_script(
+    _action: &KeyAction,
+    held_action: HeldAction,
+    keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> EmitResult {
+    match held_action {
+        HeldAction::ScriptManaged(name) => run_script(&name, keycode, false, ctx),
+        _ => EmitResult::None,
+    }
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_press_and_release_handlers() {
+        let dir = std::env::temp_dir().join(format!("keymux-script-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("scripts")).unwrap();
+        std::fs::write(
+            dir.join("scripts").join("demo.rhai"),
+            r#"
+                fn on_press() {
+                    if trigger_key() == "KC_CAPS" { tap("KC_ESC"); }
+                    layer_on("nav");
+                }
+                fn on_release() { press("KC_A"); release("KC_A"); }
+            "#,
+        )
+        .unwrap();
+
+        let mut scripts = ScriptProcessor::new(&dir);
+        let state = ScriptState {
+            trigger: Some(KeyCode::KC_CAPS),
+            ..Default::default()
+        };
+        assert_eq!(
+            scripts.run("demo", true, state.clone()),
+            vec![
+                ScriptEffect::Key(KeyCode::KC_ESC, true),
+                ScriptEffect::Key(KeyCode::KC_ESC, false),
+                ScriptEffect::LayerOn(Layer::new("nav")),
+            ]
+        );
+        assert_eq!(
+            scripts.run("demo", false, state.clone()),
+            vec![
+                ScriptEffect::Key(KeyCode::KC_A, true),
+                ScriptEffect::Key(KeyCode::KC_A, false),
+            ]
+        );
+        assert!(scripts.run("missing", true, state).is_empty());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::config::{Config, KeyAction};
+use crate::config::{Config, KeyAction, SocdMode};
 use crate::event_processor::actions::{EmitResult, HeldAction, ProcessResult};
 use crate::keycode::KeyCode;
 use std::collections::HashMap;
@@ -24,29 +24,37 @@ impl SocdGroup {
         }
     }
 
-    pub fn on_press(&mut self, keycode: KeyCode) -> Option<(Option<KeyCode>, Option<KeyCode>)> {
-        let old_active = self.active_key;
-
+    pub fn on_press(
+        &mut self,
+        keycode: KeyCode,
+        mode: SocdMode,
+    ) -> Option<(Option<KeyCode>, Option<KeyCode>)> {
         if !self.held_stack.contains(&keycode) {
             self.held_stack.push(keycode);
         }
-
-        let new_active = self.held_stack.last().copied();
-        self.active_key = new_active;
-
-        if old_active != new_active {
-            Some((old_active, new_active))
-        } else {
-            None
-        }
+        self.resolve(mode)
     }
 
-    pub fn on_release(&mut self, keycode: KeyCode) -> Option<(Option<KeyCode>, Option<KeyCode>)> {
-        let old_active = self.active_key;
-
+    pub fn on_release(
+        &mut self,
+        keycode: KeyCode,
+        mode: SocdMode,
+    ) -> Option<(Option<KeyCode>, Option<KeyCode>)> {
         self.held_stack.retain(|&k| k != keycode);
+        self.resolve(mode)
+    }
 
-        let new_active = self.held_stack.last().copied();
+    /// Pick the key that gets through from those held, returning (old, new) on a change
+    fn resolve(&mut self, mode: SocdMode) -> Option<(Option<KeyCode>, Option<KeyCode>)> {
+        let old_active = self.active_key;
+        let new_active = match mode {
+            SocdMode::LastInput => self.held_stack.last().copied(),
+            SocdMode::FirstInput => self.held_stack.first().copied(),
+            SocdMode::Neutral => match self.held_stack.as_slice() {
+                [only] => Some(*only),
+                _ => None,
+            },
+        };
         self.active_key = new_active;
 
         if old_active != new_active {
@@ -60,6 +68,8 @@ impl SocdGroup {
 pub struct SocdProcessor {
     key_to_group: HashMap<KeyCode, usize>,
     groups: Vec<SocdGroup>,
+    /// Resolution of keys set up by SOCD presets; other keys go by last input
+    modes: HashMap<KeyCode, SocdMode>,
 }
 
 impl SocdProcessor {
@@ -84,6 +94,7 @@ impl SocdProcessor {
         let processor = Self {
             key_to_group: key_to_group.clone(),
             groups: groups.clone(),
+            modes: HashMap::new(),
         };
 
         (processor, key_to_group, groups)
@@ -92,7 +103,8 @@ impl SocdProcessor {
     pub fn on_press(&mut self, keycode: KeyCode) -> Option<(Option<KeyCode>, Option<KeyCode>)> {
         if let Some(&group_id) = self.key_to_group.get(&keycode) {
             if let Some(group) = self.groups.get_mut(group_id) {
-                return group.on_press(keycode);
+                let mode = self.modes.get(&keycode).copied().unwrap_or_default();
+                return group.on_press(keycode, mode);
             }
         }
         None
@@ -101,7 +113,8 @@ impl SocdProcessor {
     pub fn on_release(&mut self, keycode: KeyCode) -> Option<(Option<KeyCode>, Option<KeyCode>)> {
         if let Some(&group_id) = self.key_to_group.get(&keycode) {
             if let Some(group) = self.groups.get_mut(group_id) {
-                return group.on_release(keycode);
+                let mode = self.modes.get(&keycode).copied().unwrap_or_default();
+                return group.on_release(keycode, mode);
             }
         }
         None
@@ -111,10 +124,16 @@ impl SocdProcessor {
 impl SocdProcessor {
     pub fn from_config(config: &Config) -> Self {
         let socd_definitions = build_socd_definitions(config);
-        let (processor, _, _) = Self::new(socd_definitions);
+        let (mut processor, _, _) = Self::new(socd_definitions);
+        processor.set_modes(config.game_mode.socd_modes(None));
         processor
     }
 
+    /// Resolve preset keys as the selected game mode overlay says
+    pub fn set_modes(&mut self, modes: HashMap<KeyCode, SocdMode>) {
+        self.modes = modes;
+    }
+
     pub fn handle_press(&mut self, keycode: KeyCode) -> SocdResolution {
         if let Some((old_active, new_active)) = self.on_press(keycode) {
             generate_socd_transition(old_active, new_active)
@@ -137,7 +156,7 @@ fn build_socd_definitions(config: &Config) -> HashMap<KeyCode, Vec<KeyCode>> {
     let extract_socd = |remaps: &HashMap<KeyCode, KeyAction>,
                         defs: &mut HashMap<KeyCode, Vec<KeyCode>>| {
         for action in remaps.values() {
-            if let KeyAction::SOCD(this_action, opposing_actions) = action {
+            if let KeyAction::SOCD(this_action, opposing_actions) = action.without_mode() {
                 if let KeyAction::Key(this_key) = this_action.as_ref() {
                     let mut opposing_keys = Vec::new();
                     for opp_action in opposing_actions {
@@ -156,7 +175,9 @@ fn build_socd_definitions(config: &Config) -> HashMap<KeyCode, Vec<KeyCode>> {
     for layer_config in config.layers.values() {
         extract_socd(&layer_config.remaps, &mut socd_definitions);
     }
-    extract_socd(&config.game_mode.remaps, &mut socd_definitions);
+    for remaps in config.game_mode.all_remaps() {
+        extract_socd(remaps, &mut socd_definitions);
+    }
     socd_definitions
 }
 
@@ -0,0 +1,212 @@
+/// Tap-lock processor - double-tap a modifier to lock it on
+///
+/// Wraps a plain modifier (`Key(KC_LSFT)`) or a one-shot one (`OSM(Key(KC_LSFT))`):
+/// - Tap: Whatever the wrapped action does
+/// - Double-tap (second press within the tapping term of the first tap): Locks the
+///   modifier down, Caps Lock style, cancelling a one-shot the first tap armed
+/// - Tap while locked: Releases the modifier
+///
+/// Locked modifiers are reported to the daemon so status bars can show them.
+use crate::config::{Config, KeyAction};
+use crate::event_processor::actions::{EmitResult, HeldAction, ProcessResult};
+use crate::event_processor::clock;
+use crate::keycode::KeyCode;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// (physical key, modifier it has locked down) of each lock, oldest first
+pub type TapLocks = Vec<(KeyCode, KeyCode)>;
+
+/// What a press of a tap-lock key does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapLockPress {
+    /// Act like the wrapped action
+    Delegate,
+    /// Double-tapped: hold the modifier down until the next tap
+    Lock(KeyCode),
+    /// Tapped while locked: let the modifier go
+    Unlock(KeyCode),
+}
+
+/// Tracks taps and locks of every tap-lock key on a keyboard
+pub struct TapLockProcessor {
+    tapping_term_ms: u32,
+    /// Keys currently down that act like their wrapped action, and when they went down
+    pressed_at: HashMap<KeyCode, Instant>,
+    /// When each key's last quick tap was released
+    last_tap: HashMap<KeyCode, Instant>,
+    locked: TapLocks,
+}
+
+impl TapLockProcessor {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            tapping_term_ms: config.tapping_term_ms,
+            pressed_at: HashMap::new(),
+            last_tap: HashMap::new(),
+            locked: Vec::new(),
+        }
+    }
+
+    pub fn on_press(&mut self, keycode: KeyCode, modifier: KeyCode) -> TapLockPress {
+        if let Some(index) = self.locked.iter().position(|(key, _)| *key == keycode) {
+            self.last_tap.remove(&keycode);
+            return TapLockPress::Unlock(self.locked.remove(index).1);
+        }
+        let now = clock::now();
+        let double_tapped = self.last_tap.remove(&keycode).is_some_and(|tapped| {
+            now.saturating_duration_since(tapped).as_millis() < u128::from(self.tapping_term_ms)
+        });
+        if double_tapped {
+            self.locked.push((keycode, modifier));
+            return TapLockPress::Lock(modifier);
+        }
+        self.pressed_at.insert(keycode, now);
+        TapLockPress::Delegate
+    }
+
+    /// A delegated press was released; remember it if it was a tap
+    pub fn on_release(&mut self, keycode: KeyCode) {
+        if let Some(pressed_at) = self.pressed_at.remove(&keycode) {
+            if clock::elapsed(pressed_at).as_millis() < u128::from(self.tapping_term_ms) {
+                self.last_tap.insert(keycode, clock::now());
+            }
+        }
+    }
+
+    /// Modifiers currently locked down, in the order they were locked
+    pub fn locked_modifiers(&self) -> Vec<KeyCode> {
+        self.locked.iter().map(|(_, modifier)| *modifier).collect()
+    }
+
+    pub fn locks(&self) -> TapLocks {
+        self.locked.clone()
+    }
+
+    /// Lock modifiers again after a daemon restart; returns the ones newly locked,
+    /// which still have to be pressed
+    pub fn restore(&mut self, locks: TapLocks) -> Vec<KeyCode> {
+        let mut restored = Vec::new();
+        for (keycode, modifier) in locks {
+            if self.locked.iter().any(|(key, _)| *key == keycode) {
+                continue;
+            }
+            self.locked.push((keycode, modifier));
+            restored.push(modifier);
+        }
+        restored
+    }
+}
+
+/// Modifier a wrapped action puts down: `Key(mod)` or `OSM(Key(mod))`
+pub const fn locked_modifier(action: &KeyAction) -> Option<KeyCode> {
+    match action {
+        KeyAction::Key(kc) => Some(*kc),
+        KeyAction::OSM(inner) => inner.as_keycode(),
+        _ => None,
+    }
+}
+
+pub fn emit_taplock(
+    action: &KeyAction,
+    keycode: KeyCode,
+    ctx: &mut super::HandleContext<'_>,
+) -> (EmitResult, Option<HeldAction>) {
+    let KeyAction::TapLock(inner) = action else {
+        return (EmitResult::None, None);
+    };
+    let Some(modifier) = locked_modifier(inner) else {
+        return inner.emit(keycode, ctx);
+    };
+
+    match ctx.taplock_processor.on_press(keycode, modifier) {
+        TapLockPress::Delegate => {
+            let (result, held) = inner.emit(keycode, ctx);
+            (result, Some(HeldAction::TapLockManaged(held.map(Box::new))))
+        }
+        TapLockPress::Lock(modifier) => {
+            // The first tap may have armed a one-shot; the lock replaces it
+            ctx.osm_processor.cancel(modifier);
+            (
+                EmitResult::EmitKey(modifier, true),
+                Some(HeldAction::TapLockManaged(None)),
+            )
+        }
+        TapLockPress::Unlock(modifier) => (
+            EmitResult::EmitKey(modifier, false),
+            Some(HeldAction::TapLockManaged(None)),
+        ),
+    }
+}
+
+/// Release a tap-lock key, passing the release on to the wrapped action if it acted
+pub fn handle_taplock_release(
+    inner: Option<Box<HeldAction>>,
+    keycode: KeyCode,
+    ctx: super::HandleContext<'_>,
+) -> ProcessResult {
+    ctx.taplock_processor.on_release(keycode);
+    match inner {
+        Some(inner) => super::handle_action_release(*inner, keycode, ctx),
+        None => ProcessResult::None,
+    }
+}
+
+pub fn unemit_taplock(
+    action: &KeyAction,
+    held_action: HeldAction,
+    keycode: KeyCode,
+    ctx: &mut super::HandleContext<'_>,
+) -> EmitResult {
+    match (action, held_action) {
+        (KeyAction::TapLock(inner), HeldAction::TapLockManaged(held)) => {
+            ctx.taplock_processor.on_release(keycode);
+            match held {
+                Some(held) => inner.unemit(*held, keycode, ctx),
+                None => EmitResult::None,
+            }
+        }
+        _ => EmitResult::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_double_tap_locks_and_next_tap_unlocks() {
+        let config = Config::parse("(tapping_term_ms: 200, remaps: {})").unwrap();
+        let mut processor = TapLockProcessor::new(&config);
+        let tap = |processor: &mut TapLockProcessor| {
+            let press = processor.on_press(KeyCode::KC_CAPS, KeyCode::KC_LSFT);
+            clock::advance(Duration::from_millis(50));
+            processor.on_release(KeyCode::KC_CAPS);
+            clock::advance(Duration::from_millis(50));
+            press
+        };
+
+        assert_eq!(tap(&mut processor), TapLockPress::Delegate);
+        assert_eq!(tap(&mut processor), TapLockPress::Lock(KeyCode::KC_LSFT));
+        assert_eq!(processor.locked_modifiers(), vec![KeyCode::KC_LSFT]);
+        assert_eq!(tap(&mut processor), TapLockPress::Unlock(KeyCode::KC_LSFT));
+        assert!(processor.locked_modifiers().is_empty());
+
+        // Taps further apart than the tapping term don't lock
+        assert_eq!(tap(&mut processor), TapLockPress::Delegate);
+        clock::advance(Duration::from_millis(300));
+        assert_eq!(tap(&mut processor), TapLockPress::Delegate);
+        assert!(processor.locked_modifiers().is_empty());
+
+        // Locks saved by a previous daemon come back once, then unlock with a tap
+        let saved = vec![(KeyCode::KC_CAPS, KeyCode::KC_LSFT)];
+        assert_eq!(processor.restore(saved.clone()), vec![KeyCode::KC_LSFT]);
+        assert!(processor.restore(saved).is_empty());
+        assert_eq!(
+            processor.locks(),
+            vec![(KeyCode::KC_CAPS, KeyCode::KC_LSFT)]
+        );
+        assert_eq!(tap(&mut processor), TapLockPress::Unlock(KeyCode::KC_LSFT));
+    }
+}
@@ -0,0 +1,151 @@
+/// Turbo (autofire) processor - repeatedly taps a key while it is held
+///
+/// `Turbo(KC_X, 20)` taps X twenty times per second for as long as the physical
+/// key is down. Taps are driven by `check_timers`, which the event loop polls
+/// between input events, so no thread ever sleeps waiting for the next tap.
+///
+/// By default Turbo keys only autofire in game mode and act as plain keys
+/// otherwise; set `game_mode.turbo_outside_game_mode` to autofire everywhere.
+use crate::config::{Config, KeyAction};
+use crate::event_processor::actions::{EmitResult, HandleContext, HeldAction};
+use crate::event_processor::clock;
+use crate::keycode::KeyCode;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// A held Turbo key
+#[derive(Debug, Clone)]
+struct TurboKey {
+    /// Key being tapped
+    output: KeyCode,
+    /// Time between a press and the following release (half a tap period)
+    half_period: Duration,
+    /// When the output next flips between pressed and released
+    next_flip: Instant,
+    /// Whether the output is currently pressed
+    down: bool,
+}
+
+/// Turbo processor - tracks every held Turbo key
+pub struct TurboProcessor {
+    /// Held Turbo keys by physical keycode
+    keys: HashMap<KeyCode, TurboKey>,
+    /// Autofire outside game mode too
+    outside_game_mode: bool,
+}
+
+impl TurboProcessor {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            keys: HashMap::new(),
+            outside_game_mode: config.game_mode.turbo_outside_game_mode,
+        }
+    }
+
+    /// Start autofiring `output`; returns the first press
+    pub fn on_press(&mut self, keycode: KeyCode, output: KeyCode, rate_hz: u32) -> (KeyCode, bool) {
+        let half_period = Duration::from_secs(1) / rate_hz.max(1) / 2;
+        self.keys.insert(
+            keycode,
+            TurboKey {
+                output,
+                half_period,
+                next_flip: clock::now() + half_period,
+                down: true,
+            },
+        );
+        (output, true)
+    }
+
+    /// Stop autofiring; releases the output if it is currently pressed
+    pub fn on_release(&mut self, keycode: KeyCode) -> Option<(KeyCode, bool)> {
+        self.keys
+            .remove(&keycode)
+            .filter(|turbo| turbo.down)
+            .map(|turbo| (turbo.output, false))
+    }
+
+    /// Flip every Turbo key whose half period has elapsed
+    pub fn check_timers(&mut self) -> Vec<(KeyCode, bool)> {
+        let now = clock::now();
+        let mut events = Vec::new();
+        for turbo in self.keys.values_mut() {
+            if now < turbo.next_flip {
+                continue;
+            }
+            turbo.down = !turbo.down;
+            events.push((turbo.output, turbo.down));
+            // Don't try to catch up on missed flips after a stall
+            turbo.next_flip = (turbo.next_flip + turbo.half_period).max(now);
+        }
+        events
+    }
+}
+
+pub fn emit_turbo(
+    action: &KeyAction,
+    keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> (EmitResult, Option<HeldAction>) {
+    match action {
+        KeyAction::Turbo(key_action, rate_hz) => {
+            let Some(output) = key_action.as_keycode() else {
+                return (EmitResult::None, None);
+            };
+            if !ctx.turbo_processor.outside_game_mode && !ctx.layer_stack.is_game_mode_active() {
+                // Gated off: behave exactly like the plain key
+                return KeyAction::Key(output).emit(keycode, ctx);
+            }
+            let (key, pressed) = ctx.turbo_processor.on_press(keycode, output, *rate_hz);
+            (
+                EmitResult::EmitKey(key, pressed),
+                Some(HeldAction::TurboManaged),
+            )
+        }
+        _ => (EmitResult::None, None),
+    }
+}
+
+pub fn unemit_turbo(
+    _action: &KeyAction,
+    held_action: HeldAction,
+    keycode: KeyCode,
+    ctx: &mut HandleContext<'_>,
+) -> EmitResult {
+    match held_action {
+        HeldAction::TurboManaged => handle_turbo_release(ctx.turbo_processor, keycode),
+        _ => EmitResult::None,
+    }
+}
+
+pub fn handle_turbo_release(turbo_processor: &mut TurboProcessor, keycode: KeyCode) -> EmitResult {
+    turbo_processor
+        .on_release(keycode)
+        .map_or(EmitResult::None, |(key, pressed)| {
+            EmitResult::EmitKey(key, pressed)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_turbo_flips_on_timer_and_releases() {
+        let mut turbo = TurboProcessor {
+            keys: HashMap::new(),
+            outside_game_mode: true,
+        };
+        assert_eq!(
+            turbo.on_press(KeyCode::KC_F, KeyCode::KC_X, 100),
+            (KeyCode::KC_X, true)
+        );
+        assert!(turbo.check_timers().is_empty());
+
+        clock::advance(Duration::from_millis(6));
+        assert_eq!(turbo.check_timers(), vec![(KeyCode::KC_X, false)]);
+        // Already released: letting go emits nothing more
+        assert_eq!(turbo.on_release(KeyCode::KC_F), None);
+        assert!(turbo.check_timers().is_empty());
+    }
+}
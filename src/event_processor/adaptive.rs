@@ -1,10 +1,18 @@
 use crate::event_processor::actions::mt::RollingStats;
 use crate::keycode::KeyCode;
 use std::collections::HashMap;
+use std::time::Duration;
+
+/// Presses waiting for their release; more means releases are going missing
+const MAX_PENDING_PRESSES: usize = 64;
+/// A press this old can't become a tap sample any more
+const PENDING_PRESS_MAX_AGE: Duration = Duration::from_secs(10);
 
 pub struct AdaptiveProcessor {
     all_key_stats: HashMap<KeyCode, RollingStats>,
     key_press_times: HashMap<KeyCode, std::time::Instant>,
+    /// Presses dropped from `key_press_times` without their release
+    evictions: u64,
 }
 
 impl AdaptiveProcessor {
@@ -12,19 +20,46 @@ impl AdaptiveProcessor {
         Self {
             all_key_stats: HashMap::new(),
             key_press_times: HashMap::new(),
+            evictions: 0,
         }
     }
 
     pub fn record_key_press(&mut self, keycode: KeyCode) {
+        if self.key_press_times.len() >= MAX_PENDING_PRESSES {
+            self.evict_pending_presses();
+        }
+        self.key_press_times.insert(keycode, super::clock::now());
+    }
+
+    /// Drop presses too old to be taps, or the oldest one when none are
+    fn evict_pending_presses(&mut self) {
+        let before = self.key_press_times.len();
         self.key_press_times
-            .insert(keycode, std::time::Instant::now());
+            .retain(|_, pressed| super::clock::elapsed(*pressed) < PENDING_PRESS_MAX_AGE);
+        if self.key_press_times.len() == before {
+            let oldest = self
+                .key_press_times
+                .iter()
+                .min_by_key(|(_, pressed)| **pressed)
+                .map(|(keycode, _)| *keycode);
+            if let Some(oldest) = oldest {
+                self.key_press_times.remove(&oldest);
+            }
+        }
+        self.evictions += (before - self.key_press_times.len()) as u64;
+    }
+
+    /// Presses dropped before their release came
+    pub const fn evictions(&self) -> u64 {
+        self.evictions
     }
 
-    pub fn record_key_release(&mut self, keycode: KeyCode, is_game_mode: bool) -> Option<f32> {
+    /// Record a tap's duration, unless `skip_learning` (game mode, or a key opted out)
+    pub fn record_key_release(&mut self, keycode: KeyCode, skip_learning: bool) -> Option<f32> {
         if let Some(press_time) = self.key_press_times.remove(&keycode) {
-            let duration_ms = press_time.elapsed().as_millis() as f32;
+            let duration_ms = super::clock::elapsed(press_time).as_millis() as f32;
             let threshold_ms = 130.0;
-            if duration_ms < threshold_ms && !is_game_mode {
+            if duration_ms < threshold_ms && !skip_learning {
                 let stats = self
                     .all_key_stats
                     .entry(keycode)
@@ -39,20 +74,20 @@ impl AdaptiveProcessor {
     pub fn save_adaptive_stats(&self, user_id: u32) -> Result<(), std::io::Error> {
         let home = Self::get_user_home(user_id);
         let all_path =
-            std::path::PathBuf::from(format!("{}/.config/keymux/all_key_stats.json", home));
-        self.save_all_key_stats(&all_path)?;
+            crate::paths::config_dir_in(std::path::Path::new(&home)).join("all_key_stats.json");
+        self.save_all_key_stats(&all_path, user_id)?;
         Ok(())
     }
 
     pub fn load_adaptive_stats(&mut self, user_id: u32) -> Result<(), std::io::Error> {
         let home = Self::get_user_home(user_id);
         let all_path =
-            std::path::PathBuf::from(format!("{}/.config/keymux/all_key_stats.json", home));
-        self.load_all_key_stats(&all_path)?;
+            crate::paths::config_dir_in(std::path::Path::new(&home)).join("all_key_stats.json");
+        self.load_all_key_stats(&all_path, user_id)?;
         Ok(())
     }
 
-    fn save_all_key_stats(&self, path: &std::path::Path) -> Result<(), std::io::Error> {
+    fn save_all_key_stats(&self, path: &std::path::Path, uid: u32) -> Result<(), std::io::Error> {
         if self.all_key_stats.is_empty() {
             return Ok(());
         }
@@ -62,19 +97,21 @@ impl AdaptiveProcessor {
             let key_str = format!("{:?}", keycode).replace("KC_", "");
             stats_map.insert(key_str, stats.clone());
         }
-        let json = serde_json::to_string_pretty(&stats_map)?;
-        std::fs::write(path, json)?;
+        super::stats_file::write(path, uid, &stats_map)?;
         tracing::info!("ALL KEYS: Saved {} key stats", self.all_key_stats.len());
         Ok(())
     }
 
-    fn load_all_key_stats(&mut self, path: &std::path::Path) -> Result<(), std::io::Error> {
-        if !path.exists() {
+    fn load_all_key_stats(
+        &mut self,
+        path: &std::path::Path,
+        uid: u32,
+    ) -> Result<(), std::io::Error> {
+        let Some(stats_map) =
+            super::stats_file::read::<std::collections::HashMap<String, RollingStats>>(path, uid)?
+        else {
             return Ok(());
-        }
-        let json = std::fs::read_to_string(path)?;
-        let stats_map: std::collections::HashMap<String, RollingStats> =
-            serde_json::from_str(&json)?;
+        };
         self.all_key_stats.clear();
         for (key_str, stats) in stats_map {
             let key_json = format!("\"KC_{}\"", key_str);
@@ -111,3 +148,29 @@ impl Default for AdaptiveProcessor {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unreleased_presses_stay_bounded() {
+        let mut adaptive = AdaptiveProcessor::new();
+        let keys: Vec<KeyCode> = (0..MAX_PENDING_PRESSES as u16 + 10)
+            .map(|code| KeyCode::KC_RAW(500 + code))
+            .collect();
+
+        // Releases that never come: a press goes for each new one
+        for key in &keys {
+            adaptive.record_key_press(*key);
+        }
+        assert_eq!(adaptive.key_press_times.len(), MAX_PENDING_PRESSES);
+        assert_eq!(adaptive.evictions(), 10);
+
+        // Once they're stale, all of them go at once
+        crate::event_processor::clock::advance(PENDING_PRESS_MAX_AGE);
+        adaptive.record_key_press(KeyCode::KC_A);
+        assert_eq!(adaptive.key_press_times.len(), 1);
+        assert_eq!(adaptive.evictions(), 10 + MAX_PENDING_PRESSES as u64);
+    }
+}
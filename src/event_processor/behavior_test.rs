@@ -0,0 +1,511 @@
+/// Runs a config's `tests` through a keymap processor on a virtual clock
+///
+/// The processor is the one the daemon uses, in dry-run mode so CMD actions don't run.
+/// Pauses in the input advance the clock a millisecond at a time, checking tap-dance
+/// and turbo timers like the processor loop does, so a test takes no real time.
+use super::{clock, KeymapProcessor};
+use crate::config::{BehaviorTest, Config};
+use crate::keycode::KeyCode;
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::time::Duration;
+
+enum Step {
+    Event(KeyCode, bool),
+    Pause(Duration),
+}
+
+/// Result of one test
+pub struct TestOutcome {
+    pub expected: Vec<(KeyCode, bool)>,
+    pub actual: Vec<(KeyCode, bool)>,
+}
+
+impl TestOutcome {
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+fn parse_steps(notation: &str, pauses: bool) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    for token in notation.split_whitespace() {
+        if let Some(ms) = token.strip_suffix("ms") {
+            if !pauses {
+                return Err(anyhow!("Pauses like '{}' only go in the input", token));
+            }
+            let ms: u64 = ms
+                .parse()
+                .map_err(|_| anyhow!("Invalid pause: '{}' (expected e.g. 250ms)", token))?;
+            steps.push(Step::Pause(Duration::from_millis(ms)));
+            continue;
+        }
+        let (name, pressed) = match token.split_at(token.len().min(1)) {
+            ("+", name) => (name, Some(true)),
+            ("-", name) => (name, Some(false)),
+            _ => (token, None),
+        };
+        let key = KeyCode::from_name(name)
+            .ok_or_else(|| anyhow!("Unknown key: '{}' (expected e.g. +KC_A)", name))?;
+        match pressed {
+            Some(pressed) => steps.push(Step::Event(key, pressed)),
+            None => {
+                steps.push(Step::Event(key, true));
+                steps.push(Step::Event(key, false));
+            }
+        }
+    }
+    Ok(steps)
+}
+
+/// Parse expected events (`+KC_A -KC_A`, or `KC_A` for a tap)
+pub fn parse_events(notation: &str) -> Result<Vec<(KeyCode, bool)>> {
+    Ok(parse_steps(notation, false)?
+        .into_iter()
+        .filter_map(|step| match step {
+            Step::Event(key, pressed) => Some((key, pressed)),
+            Step::Pause(_) => None,
+        })
+        .collect())
+}
+
+/// Events in test notation, with a press directly followed by its release shown as a tap
+pub fn format_events(events: &[(KeyCode, bool)]) -> String {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < events.len() {
+        let (key, pressed) = events[i];
+        if pressed && events.get(i + 1) == Some(&(key, false)) {
+            tokens.push(key.to_string());
+            i += 2;
+            continue;
+        }
+        tokens.push(format!("{}{}", if pressed { "+" } else { "-" }, key));
+        i += 1;
+    }
+    tokens.join(" ")
+}
+
+/// Feed a test's input to a fresh processor and collect what it sends
+pub fn run_behavior_test(
+    config: &Config,
+    config_path: &Path,
+    test: &BehaviorTest,
+) -> Result<TestOutcome> {
+    let steps = parse_steps(&test.input, true)?;
+    let expected = parse_events(&test.expect)?;
+
+    let mut keymap = KeymapProcessor::new(config, config_path.to_path_buf(), 0);
+    keymap.set_dry_run(true);
+    keymap.set_game_mode(test.game_mode);
+
+    let mut actual = Vec::new();
+    for step in steps {
+        match step {
+            Step::Event(key, pressed) => {
+                actual.extend(keymap.process_key(key, pressed).into_events());
+            }
+            Step::Pause(duration) => {
+                for _ in 0..duration.as_millis() {
+                    clock::advance(Duration::from_millis(1));
+                    actual.extend(keymap.check_dt_timeouts().into_events());
+                    actual.extend(keymap.check_turbo_timers().into_events());
+                    if let Some(result) = keymap.check_layer_timeouts() {
+                        actual.extend(result.into_events());
+                    }
+                }
+            }
+        }
+    }
+    Ok(TestOutcome { expected, actual })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_home_row_mod_tap_and_hold() {
+        let config = Config::parse(
+            r#"(
+                remaps: { KC_F: MT(KC_F, KC_LSFT) },
+                tests: [
+                    (name: "tap", input: "KC_F", expect: "KC_F"),
+                    (
+                        name: "hold",
+                        input: "+KC_F 300ms +KC_J -KC_J -KC_F",
+                        expect: "+KC_LSFT KC_J -KC_LSFT",
+                    ),
+                    (name: "wrong", input: "KC_F", expect: "KC_J"),
+                ],
+            )"#,
+        )
+        .unwrap();
+        let run = |i: usize| run_behavior_test(&config, Path::new("."), &config.tests[i]).unwrap();
+
+        assert!(run(0).passed());
+        assert!(run(1).passed(), "{}", format_events(&run(1).actual));
+        let wrong = run(2);
+        assert!(!wrong.passed());
+        assert_eq!(format_events(&wrong.actual), "KC_F");
+    }
+
+    #[test]
+    fn test_retro_tapping_per_key() {
+        let config = Config::parse(
+            r#"(
+                remaps: { KC_F: MT(KC_F, KC_LSFT), KC_J: MT(KC_J, KC_RSFT) },
+                mt_config: (
+                    hold_do_nothing_emits_tap: false,
+                    permissive_hold: false,
+                    retro_tapping_per_key: { KC_F: true },
+                ),
+                tests: [
+                    (name: "retro tap", input: "+KC_F 300ms -KC_F", expect: "KC_F"),
+                    (name: "plain hold", input: "+KC_J 300ms -KC_J", expect: "KC_RSFT"),
+                    (
+                        name: "interrupted",
+                        input: "+KC_F 100ms KC_H 200ms -KC_F",
+                        expect: "KC_H KC_LSFT",
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                outcome.passed(),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+
+    #[test]
+    fn test_chordal_hold_decides_by_hand() {
+        let config = Config::parse(
+            r#"(
+                remaps: { KC_F: MT(KC_F, KC_LSFT) },
+                mt_config: (chordal_hold: true),
+                tests: [
+                    (
+                        name: "opposite hand holds",
+                        input: "+KC_F 10ms KC_J -KC_F",
+                        expect: "+KC_LSFT KC_J -KC_LSFT",
+                    ),
+                    (
+                        name: "same hand taps",
+                        input: "+KC_F 10ms KC_G -KC_F",
+                        expect: "+KC_F KC_G -KC_F",
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                outcome.passed(),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+
+    #[test]
+    fn test_layer_idle_timeout_drops_held_layer() {
+        let config = Config::parse(
+            r#"(
+                layer_idle_timeout_ms: Some(1000),
+                remaps: { KC_LALT: MO("nav") },
+                layers: { "nav": (remaps: { KC_H: KC_LEFT }) },
+                tests: [
+                    (name: "held", input: "+KC_LALT 500ms KC_H -KC_LALT", expect: "KC_LEFT"),
+                    (name: "stuck", input: "+KC_LALT 1500ms KC_H -KC_LALT", expect: "KC_H"),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                outcome.passed(),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+
+    #[test]
+    fn test_if_decides_on_press() {
+        let config = Config::parse(
+            r#"(
+                remaps: {
+                    KC_LALT: MO("nav"),
+                    KC_H: If(layer_active("nav"), KC_LEFT, KC_H),
+                    KC_J: If(modifier_held(KC_LSFT), KC_PGDN, KC_J),
+                },
+                tests: [
+                    (name: "else", input: "KC_H KC_J", expect: "KC_H KC_J"),
+                    (name: "layer", input: "+KC_LALT KC_H -KC_LALT", expect: "KC_LEFT"),
+                    (
+                        name: "modifier",
+                        input: "+KC_LSFT KC_J -KC_LSFT",
+                        expect: "+KC_LSFT KC_PGDN -KC_LSFT",
+                    ),
+                    (
+                        name: "branch kept until release",
+                        input: "+KC_LALT +KC_H -KC_LALT -KC_H",
+                        expect: "+KC_LEFT -KC_LEFT",
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                outcome.passed(),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+
+    #[test]
+    fn test_mode_only_bindings_fall_through() {
+        let config = Config::parse(
+            r#"(
+                remaps: {
+                    KC_LALT: MO("nav"),
+                    KC_F: OnlyTyping(KC_B),
+                    KC_G: OnlyGameMode(KC_C),
+                },
+                layers: {
+                    "nav": (remaps: { KC_H: OnlyTyping(KC_LEFT) }),
+                },
+                tests: [
+                    (name: "typing", input: "KC_F KC_G", expect: "KC_B KC_G"),
+                    (name: "game", input: "KC_F KC_G", expect: "KC_F KC_C", game_mode: true),
+                    (name: "layer typing", input: "+KC_LALT KC_H -KC_LALT", expect: "KC_LEFT"),
+                    (
+                        name: "layer game",
+                        input: "+KC_LALT KC_H -KC_LALT",
+                        expect: "KC_H",
+                        game_mode: true,
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                outcome.passed(),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+
+    #[test]
+    fn test_macro_pad_sends_only_mapped_keys() {
+        let config = Config::parse(
+            r#"(
+                macro_pad: true,
+                remaps: { KC_1: KC_F13, KC_2: MO("media") },
+                layers: {
+                    "media": (remaps: { KC_3: KC_MPLY }),
+                },
+                tests: [
+                    (name: "mapped", input: "KC_1 KC_3 KC_4", expect: "KC_F13"),
+                    (name: "layer", input: "+KC_2 KC_3 KC_1 KC_4 -KC_2", expect: "KC_MPLY KC_F13"),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                outcome.passed(),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+
+    #[test]
+    fn test_blocking_layer_drops_unmapped_keys() {
+        let config = Config::parse(
+            r#"(
+                remaps: { KC_RALT: MO("strict"), KC_E: KC_R },
+                layers: {
+                    "strict": (
+                        remaps: { KC_W: KC_UP, KC_Q: Transparent },
+                        unmapped: block,
+                    ),
+                },
+                tests: [
+                    (name: "off", input: "KC_W KC_E", expect: "KC_W KC_R"),
+                    (
+                        name: "on",
+                        input: "+KC_RALT KC_W KC_E KC_Q -KC_RALT KC_E",
+                        expect: "KC_UP KC_Q KC_R",
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                outcome.passed(),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+
+    #[test]
+    fn test_socd_presets() {
+        let config = Config::parse(
+            r#"(
+                game_mode: (socd: [wasd_neutral, axis(KC_W, KC_S, first_input)]),
+                tests: [
+                    (
+                        name: "neutral",
+                        input: "+KC_A +KC_D -KC_A -KC_D",
+                        expect: "+KC_A -KC_A KC_D",
+                        game_mode: true,
+                    ),
+                    (
+                        name: "first input",
+                        input: "+KC_W +KC_S -KC_W -KC_S",
+                        expect: "+KC_W -KC_W KC_S",
+                        game_mode: true,
+                    ),
+                    (name: "plain outside game mode", input: "+KC_A +KC_D -KC_A -KC_D", expect: "+KC_A +KC_D -KC_A -KC_D"),
+                ],
+            )"#,
+        )
+        .unwrap();
+        assert!(config.validate_silent().is_ok());
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                outcome.passed(),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+
+    #[test]
+    fn test_keys_down_on_grab_release_plainly() {
+        let config = Config::parse(r#"(remaps: { KC_F: MT(KC_F, KC_LSFT) })"#).unwrap();
+        let mut keymap = KeymapProcessor::new(&config, Path::new(".").to_path_buf(), 0);
+        keymap.set_dry_run(true);
+        keymap.seed_held_keys([KeyCode::KC_F]);
+
+        assert_eq!(keymap.get_held_keys(), vec![KeyCode::KC_F]);
+        assert_eq!(
+            keymap.process_key(KeyCode::KC_F, false).into_events(),
+            vec![(KeyCode::KC_F, false)]
+        );
+        assert!(keymap.get_held_keys().is_empty());
+    }
+
+    #[test]
+    fn test_layer_with_mods_wraps_plain_keys() {
+        let config = Config::parse(
+            r#"(
+                remaps: { KC_RALT: MO("sym") },
+                layers: {
+                    "sym": (
+                        with_mods: [KC_LSFT],
+                        remaps: { KC_Q: KC_1, KC_W: KC_2, KC_E: TG("sym") },
+                    ),
+                },
+                tests: [
+                    (
+                        name: "shifted",
+                        input: "+KC_RALT KC_Q -KC_RALT",
+                        expect: "+KC_LSFT KC_1 -KC_LSFT",
+                    ),
+                    (
+                        name: "shift stays down while a layer key is held",
+                        input: "+KC_RALT +KC_Q +KC_W -KC_Q -KC_W -KC_RALT",
+                        expect: "+KC_LSFT +KC_1 +KC_2 -KC_1 -KC_2 -KC_LSFT",
+                    ),
+                    (name: "base untouched", input: "KC_Q", expect: "KC_Q"),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                outcome.passed(),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+
+    // Inputs release keys before their modifiers; letting a modifier go first is the
+    // user's own doing and comes out that way
+    #[test]
+    fn test_modifiers_stay_down_around_their_keys() {
+        let config = Config::parse(
+            r#"(
+                remaps: {
+                    KC_F: MT(KC_F, KC_LSFT),
+                    KC_D: MT(KC_D, KC_LCTL),
+                    KC_RALT: MO("sym"),
+                    KC_CAPS: OSM(KC_LSFT),
+                },
+                layers: {
+                    "sym": (with_mods: [KC_LSFT, KC_LCTL], remaps: { KC_Q: KC_1, KC_W: KC_2 }),
+                },
+                tests: [
+                    (name: "mt hold by permissive hold", input: "+KC_F +KC_J -KC_J -KC_F", expect: ""),
+                    (name: "mt hold by time", input: "+KC_F 300ms KC_J -KC_F", expect: ""),
+                    (name: "two mt holds", input: "+KC_D +KC_F +KC_J -KC_J -KC_F -KC_D", expect: ""),
+                    (name: "layer mods", input: "+KC_RALT +KC_Q +KC_W -KC_Q -KC_RALT -KC_W", expect: ""),
+                    (name: "layer under mt", input: "+KC_F 300ms +KC_RALT KC_Q -KC_RALT -KC_F", expect: ""),
+                    (name: "one shot", input: "KC_CAPS KC_J KC_K", expect: ""),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        for test in &config.tests {
+            let outcome = run_behavior_test(&config, Path::new("."), test).unwrap();
+            assert!(
+                crate::event_processor::ordering::mods_wrap_keys(&outcome.actual),
+                "{}: {}",
+                test.name,
+                format_events(&outcome.actual)
+            );
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Watchdog for a processor loop that stops sleeping
+//!
+//! The loop sleeps a millisecond whenever the keyboard has nothing to read, so it
+//! turns under a thousand times a second. A device that never runs out of events (a
+//! failing controller flooding reports) keeps it from sleeping at all and pegs a core.
+//! The guard counts loop turns per second; after `BUSY_SECONDS` seconds in a row above
+//! `BUSY_RATE` it throttles the loop with a sleep per turn for `THROTTLE_FOR`, then
+//! lets it run freely and counts again.
+
+use std::time::{Duration, Instant};
+
+/// Turns per second an idle-sleeping loop can't reach
+const BUSY_RATE: u64 = 5000;
+const BUSY_SECONDS: u32 = 3;
+const THROTTLE_FOR: Duration = Duration::from_secs(10);
+/// Sleep per turn while throttled, capping the loop at 100 turns a second
+pub const THROTTLE_SLEEP: Duration = Duration::from_millis(10);
+
+#[derive(Debug)]
+pub struct BusyGuard {
+    window_start: Instant,
+    turns: u64,
+    /// Consecutive seconds above BUSY_RATE
+    busy_seconds: u32,
+    throttled_until: Option<Instant>,
+}
+
+impl BusyGuard {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            turns: 0,
+            busy_seconds: 0,
+            throttled_until: None,
+        }
+    }
+
+    /// Count a loop turn; returns the turns per second when throttling starts
+    pub fn tick(&mut self, now: Instant) -> Option<u64> {
+        if let Some(until) = self.throttled_until {
+            if now < until {
+                return None;
+            }
+            *self = Self::new(now);
+        }
+
+        self.turns += 1;
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < Duration::from_secs(1) {
+            return None;
+        }
+        let rate = self.turns * 1000 / (elapsed.as_millis() as u64).max(1);
+        self.window_start = now;
+        self.turns = 0;
+        if rate <= BUSY_RATE {
+            self.busy_seconds = 0;
+            return None;
+        }
+        self.busy_seconds += 1;
+        if self.busy_seconds < BUSY_SECONDS {
+            return None;
+        }
+        self.busy_seconds = 0;
+        self.throttled_until = Some(now + THROTTLE_FOR);
+        Some(rate)
+    }
+
+    pub fn is_throttled(&self, now: Instant) -> bool {
+        self.throttled_until.is_some_and(|until| now < until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `turns_per_sec` evenly spread turns for `secs` seconds from `start`
+    fn run(guard: &mut BusyGuard, start: Instant, secs: u64, turns_per_sec: u64) -> Vec<u64> {
+        let step = Duration::from_secs(1) / turns_per_sec as u32;
+        (1..=secs * turns_per_sec)
+            .filter_map(|i| guard.tick(start + step * i as u32))
+            .collect()
+    }
+
+    #[test]
+    fn test_throttles_a_spinning_loop_only() {
+        let start = Instant::now();
+        let mut guard = BusyGuard::new(start);
+        assert!(run(&mut guard, start, 10, 900).is_empty());
+        assert!(!guard.is_throttled(start + Duration::from_secs(10)));
+
+        let start = start + Duration::from_secs(10);
+        let tripped = run(&mut guard, start, 4, 20_000);
+        assert_eq!(tripped.len(), 1);
+        assert!(tripped[0] > BUSY_RATE);
+        let after = start + Duration::from_secs(4);
+        assert!(guard.is_throttled(after));
+        assert!(!guard.is_throttled(after + THROTTLE_FOR));
+    }
+}
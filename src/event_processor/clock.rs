@@ -0,0 +1,27 @@
+/// Time source for tap/hold decisions
+///
+/// MT, DT, OSM, Turbo, sequences and adaptive timing read the time through here rather
+/// than `Instant::now()`, so `keymux test` can replay a config test on a virtual clock:
+/// [`advance`] moves time forward for the calling thread only. Processor threads never
+/// advance it, so for them this is the real clock.
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    static OFFSET: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+/// Current time (real time plus whatever this thread has advanced)
+pub fn now() -> Instant {
+    Instant::now() + OFFSET.with(Cell::get)
+}
+
+/// Time since `since`, on the same clock as [`now`]
+pub fn elapsed(since: Instant) -> Duration {
+    now().saturating_duration_since(since)
+}
+
+/// Move this thread's clock forward
+pub fn advance(by: Duration) {
+    OFFSET.with(|offset| offset.set(offset.get() + by));
+}
@@ -0,0 +1,137 @@
+/// Chatter filter applied to physical key events before the keymap
+///
+/// Worn switches bounce: a single press reaches us as press, release, press a few
+/// milliseconds apart. Releases are held back for the key's debounce window, and a
+/// press arriving inside it cancels the release, so the keymap (and MT/DT timing)
+/// only ever sees the one press. Times are the kernel's event timestamps, so a release
+/// and press read together after a stall are still told apart.
+use crate::config::Config;
+use crate::keycode::KeyCode;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+pub struct Debouncer {
+    default_window: Duration,
+    key_windows: HashMap<KeyCode, Duration>,
+    /// Held-back releases and when they go through
+    pending_releases: HashMap<KeyCode, SystemTime>,
+}
+
+impl Debouncer {
+    /// Debouncer for a keyboard's config, or None when no key is debounced
+    pub fn from_config(config: &Config) -> Option<Self> {
+        if config.debounce_ms == 0 && config.debounce_keys.values().all(|ms| *ms == 0) {
+            return None;
+        }
+        Some(Self {
+            default_window: Duration::from_millis(u64::from(config.debounce_ms)),
+            key_windows: config
+                .debounce_keys
+                .iter()
+                .map(|(key, ms)| (*key, Duration::from_millis(u64::from(*ms))))
+                .collect(),
+            pending_releases: HashMap::new(),
+        })
+    }
+
+    fn window(&self, key: KeyCode) -> Duration {
+        self.key_windows
+            .get(&key)
+            .copied()
+            .unwrap_or(self.default_window)
+    }
+
+    /// Key events that should reach the keymap now for one that happened `at`
+    pub fn filter(&mut self, key: KeyCode, pressed: bool, at: SystemTime) -> Vec<(KeyCode, bool)> {
+        let window = self.window(key);
+        if window.is_zero() {
+            return vec![(key, pressed)];
+        }
+        if !pressed {
+            self.pending_releases.insert(key, at + window);
+            return Vec::new();
+        }
+        match self.pending_releases.remove(&key) {
+            // Pressed again right after a release: the release was a bounce
+            Some(due) if due > at => {
+                tracing::debug!("Debounced chatter on {:?}", key);
+                Vec::new()
+            }
+            // The release was real, it just hadn't gone through yet
+            Some(_) => vec![(key, false), (key, true)],
+            None => vec![(key, true)],
+        }
+    }
+
+    /// Releases whose window passed without another press
+    pub fn take_expired(&mut self, now: SystemTime) -> Vec<KeyCode> {
+        let expired: Vec<KeyCode> = self
+            .pending_releases
+            .iter()
+            .filter(|(_, due)| **due <= now)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in &expired {
+            self.pending_releases.remove(key);
+        }
+        expired
+    }
+
+    /// Forget held-back releases (the keymap's held keys were already let go)
+    pub fn clear(&mut self) {
+        self.pending_releases.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chatter_is_dropped_and_real_release_delayed() {
+        let mut debouncer = Debouncer {
+            default_window: Duration::from_millis(10),
+            key_windows: HashMap::from([(KeyCode::KC_B, Duration::ZERO)]),
+            pending_releases: HashMap::new(),
+        };
+        let start = SystemTime::now();
+        let ms = |n| start + Duration::from_millis(n);
+
+        // Press, bounce release/press, then a real release
+        assert_eq!(
+            debouncer.filter(KeyCode::KC_A, true, start),
+            vec![(KeyCode::KC_A, true)]
+        );
+        assert!(debouncer.filter(KeyCode::KC_A, false, ms(2)).is_empty());
+        assert!(debouncer.filter(KeyCode::KC_A, true, ms(4)).is_empty());
+        assert!(debouncer.filter(KeyCode::KC_A, false, ms(50)).is_empty());
+        assert!(debouncer.take_expired(ms(55)).is_empty());
+        assert_eq!(debouncer.take_expired(ms(60)), vec![KeyCode::KC_A]);
+
+        // Keys with a zero window pass straight through
+        assert_eq!(
+            debouncer.filter(KeyCode::KC_B, false, start),
+            vec![(KeyCode::KC_B, false)]
+        );
+    }
+
+    #[test]
+    fn test_release_read_late_still_goes_through_before_the_press() {
+        let mut debouncer = Debouncer {
+            default_window: Duration::from_millis(10),
+            key_windows: HashMap::new(),
+            pending_releases: HashMap::new(),
+        };
+        let start = SystemTime::now();
+        let ms = |n| start + Duration::from_millis(n);
+
+        // A stall delivers a release and a press 30ms apart in the same read
+        debouncer.filter(KeyCode::KC_A, true, start);
+        assert!(debouncer.filter(KeyCode::KC_A, false, ms(100)).is_empty());
+        assert_eq!(
+            debouncer.filter(KeyCode::KC_A, true, ms(130)),
+            vec![(KeyCode::KC_A, false), (KeyCode::KC_A, true)]
+        );
+        assert!(debouncer.take_expired(ms(200)).is_empty());
+    }
+}
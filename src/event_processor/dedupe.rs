@@ -0,0 +1,101 @@
+//! Drop key events a keyboard reports on two of its event nodes
+//!
+//! Some keyboards send the same key on two interfaces (e.g. a boot keyboard and an
+//! NKRO node). Each node has its own processor, so both presses reach the output and
+//! every character comes out twice. With `dedupe_nodes_ms` set, the daemon hands the
+//! processors of one keyboard a shared `NodeEvents`: a press is dropped when another
+//! node pressed the same key within the window, and the release that goes with it is
+//! dropped too, so each processor still sees whole keystrokes.
+
+use crate::keycode::KeyCode;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Presses seen on a keyboard's event nodes
+#[derive(Debug, Default)]
+pub struct NodeEvents {
+    /// Last passed-through press of each key: the node it came from and its timestamp
+    presses: Mutex<HashMap<KeyCode, (u64, SystemTime)>>,
+    next_source: AtomicU64,
+}
+
+impl NodeEvents {
+    /// Join as one more node of the keyboard
+    pub fn register(self: &Arc<Self>, window: Duration) -> NodeDedup {
+        NodeDedup {
+            events: Arc::clone(self),
+            source: self.next_source.fetch_add(1, Ordering::Relaxed),
+            window,
+            dropped: HashSet::new(),
+        }
+    }
+}
+
+/// One event node's view of its keyboard's `NodeEvents`
+#[derive(Debug)]
+pub struct NodeDedup {
+    events: Arc<NodeEvents>,
+    source: u64,
+    window: Duration,
+    /// Keys whose press was another node's duplicate, until they are released
+    dropped: HashSet<KeyCode>,
+}
+
+impl NodeDedup {
+    /// Whether a key event (with its kernel timestamp) should reach the keymap
+    pub fn filter(&mut self, key: KeyCode, pressed: bool, at: SystemTime) -> bool {
+        if !pressed {
+            return !self.dropped.remove(&key);
+        }
+        let mut presses = self
+            .events
+            .presses
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let duplicate = presses.get(&key).is_some_and(|(source, seen)| {
+            let apart = at.duration_since(*seen).unwrap_or_else(|e| e.duration());
+            *source != self.source && apart <= self.window
+        });
+        if duplicate {
+            tracing::debug!("Dropped {:?} repeated on a second event node", key);
+            self.dropped.insert(key);
+            return false;
+        }
+        presses.insert(key, (self.source, at));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_node_keystroke_dropped() {
+        let events = Arc::new(NodeEvents::default());
+        let window = Duration::from_millis(5);
+        let mut boot = events.register(window);
+        let mut nkro = events.register(window);
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let ms = Duration::from_millis;
+
+        // The same keystroke on both nodes comes through once
+        assert!(boot.filter(KeyCode::KC_A, true, t0));
+        assert!(!nkro.filter(KeyCode::KC_A, true, t0 + ms(1)));
+        assert!(boot.filter(KeyCode::KC_A, false, t0 + ms(80)));
+        assert!(!nkro.filter(KeyCode::KC_A, false, t0 + ms(81)));
+
+        // Nodes can arrive in either order
+        assert!(nkro.filter(KeyCode::KC_B, true, t0 + ms(200)));
+        assert!(!boot.filter(KeyCode::KC_B, true, t0 + ms(199)));
+        assert!(!boot.filter(KeyCode::KC_B, false, t0 + ms(260)));
+        assert!(nkro.filter(KeyCode::KC_B, false, t0 + ms(261)));
+
+        // Outside the window, and fast repeats on one node, are real presses
+        assert!(nkro.filter(KeyCode::KC_A, true, t0 + ms(300)));
+        assert!(nkro.filter(KeyCode::KC_A, false, t0 + ms(301)));
+        assert!(nkro.filter(KeyCode::KC_A, true, t0 + ms(302)));
+    }
+}
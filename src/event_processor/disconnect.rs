@@ -0,0 +1,145 @@
+//! Waiting out a keyboard whose event file stopped reading
+//!
+//! KVM switches and flaky hubs drop the device for a moment, so after ENODEV or EIO
+//! the same event file is reopened up to `REOPEN_ATTEMPTS` times, `REOPEN_DELAY`
+//! apart. A keyboard still gone after that (ENODEV) pauses its processor for the
+//! disconnect grace, so the daemon can hand it back on reconnect with its layers and
+//! timing state intact; anything else ends the processor.
+
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Reopen attempts after ENODEV/EIO before treating the keyboard as gone
+pub const REOPEN_ATTEMPTS: u32 = 5;
+const REOPEN_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Debug)]
+pub struct Disconnect {
+    /// Start of the current wait (the read error or the last reopen attempt)
+    since: Instant,
+    /// Read error being retried and the attempts left; None once in the grace period
+    reopening: Option<(std::io::Error, u32)>,
+    /// How long to wait for a reconnect (None = until the daemon reattaches it)
+    grace: Option<Duration>,
+}
+
+impl Disconnect {
+    /// Reading the event file failed with `error` at `now`
+    pub const fn new(error: std::io::Error, now: Instant, grace: Option<Duration>) -> Self {
+        Self {
+            since: now,
+            reopening: Some((error, REOPEN_ATTEMPTS)),
+            grace,
+        }
+    }
+
+    /// Time to try opening the event file again
+    pub fn reopen_due(&self, now: Instant) -> bool {
+        self.reopening.is_some() && now.saturating_duration_since(self.since) >= REOPEN_DELAY
+    }
+
+    /// Number of the reopen attempt being made (1-based)
+    pub fn attempt(&self) -> u32 {
+        self.reopening
+            .as_ref()
+            .map_or(REOPEN_ATTEMPTS, |(_, left)| REOPEN_ATTEMPTS - left + 1)
+    }
+
+    /// A reopen attempt failed at `now`
+    ///
+    /// Returns the original error once the attempts run out, unless the keyboard is
+    /// unplugged and there's a grace period to wait for it.
+    pub fn reopen_failed(&mut self, now: Instant, keyboard_name: &str) -> std::io::Result<()> {
+        self.since = now;
+        let Some((error, attempts_left)) = self.reopening.take() else {
+            return Ok(());
+        };
+        if attempts_left > 1 {
+            self.reopening = Some((error, attempts_left - 1));
+            return Ok(());
+        }
+        if error.raw_os_error() != Some(libc::ENODEV) {
+            return Err(error);
+        }
+        match self.grace {
+            None => info!(
+                "Keyboard disconnected, pausing until it's back: {}",
+                keyboard_name
+            ),
+            Some(grace) if !grace.is_zero() => info!(
+                "Keyboard disconnected, pausing for up to {}ms: {}",
+                grace.as_millis(),
+                keyboard_name
+            ),
+            Some(_) => return Err(error),
+        }
+        Ok(())
+    }
+
+    /// The grace period ran out without the keyboard being reattached
+    pub fn expired(&self, now: Instant) -> bool {
+        self.reopening.is_none()
+            && self
+                .grace
+                .is_some_and(|grace| now.saturating_duration_since(self.since) >= grace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unplugged() -> std::io::Error {
+        std::io::Error::from_raw_os_error(libc::ENODEV)
+    }
+
+    /// Fail every reopen attempt, returning the time of the last one
+    fn fail_reopens(disconnect: &mut Disconnect, mut now: Instant) -> std::io::Result<Instant> {
+        for attempt in 1..=REOPEN_ATTEMPTS {
+            assert!(!disconnect.reopen_due(now + REOPEN_DELAY / 2));
+            now += REOPEN_DELAY;
+            assert!(disconnect.reopen_due(now));
+            assert_eq!(disconnect.attempt(), attempt);
+            disconnect.reopen_failed(now, "test")?;
+        }
+        Ok(now)
+    }
+
+    #[test]
+    fn test_reopens_before_giving_up_on_other_errors() {
+        let start = Instant::now();
+        let grace = Some(Duration::from_secs(5));
+
+        // Unplugged: retried, then waiting for a reconnect
+        let mut disconnect = Disconnect::new(unplugged(), start, grace);
+        let last = fail_reopens(&mut disconnect, start).unwrap();
+        assert!(!disconnect.reopen_due(last + REOPEN_DELAY));
+
+        // An I/O error that isn't an unplug ends the processor once retries run out
+        let error = std::io::Error::from_raw_os_error(libc::EIO);
+        let mut disconnect = Disconnect::new(error, start, grace);
+        let error = fail_reopens(&mut disconnect, start).unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::EIO));
+
+        // So does an unplug without a grace period
+        let mut disconnect = Disconnect::new(unplugged(), start, Some(Duration::ZERO));
+        assert!(fail_reopens(&mut disconnect, start).is_err());
+    }
+
+    #[test]
+    fn test_unplugged_keyboard_waits_out_the_grace_period() {
+        let start = Instant::now();
+        let grace = Duration::from_secs(5);
+
+        let mut disconnect = Disconnect::new(unplugged(), start, Some(grace));
+        assert!(!disconnect.expired(start + grace));
+        let paused = fail_reopens(&mut disconnect, start).unwrap();
+        assert!(!disconnect.expired(paused + grace - Duration::from_millis(1)));
+        assert!(disconnect.expired(paused + grace));
+
+        // `disconnect_grace_forever`: only the daemon reattaching it ends the wait
+        let mut disconnect = Disconnect::new(unplugged(), start, None);
+        let paused = fail_reopens(&mut disconnect, start).unwrap();
+        assert!(!disconnect.expired(paused + Duration::from_secs(86400)));
+    }
+}
@@ -0,0 +1,142 @@
+//! Keys a processor's virtual device has down, kept in a per-node state file
+//!
+//! A processor that stops without releasing its keys can leave them pressed in the
+//! compositor. Each processor writes the keys its virtual device holds to a file under
+//! the state dir (one per keyboard and event node) whenever they change, at most once
+//! a second, and removes it when it stops cleanly. The next processor for the node
+//! releases exactly the keys in a leftover file, plus those the keyboard reports down,
+//! instead of releasing every letter and modifier on the off chance.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct HeldOutput {
+    path: PathBuf,
+    /// evdev codes pressed on the virtual device and not released yet
+    keys: BTreeSet<u16>,
+    /// What the file says, to skip writes that change nothing
+    saved: BTreeSet<u16>,
+    last_save: Option<Instant>,
+}
+
+impl HeldOutput {
+    /// State for a keyboard's event node, e.g. `/dev/input/event5`
+    pub fn for_node(keyboard_id: &str, event_path: &Path) -> Self {
+        let node = event_path
+            .file_name()
+            .map_or_else(|| "unknown".into(), |name| name.to_string_lossy());
+        let id: String = keyboard_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        Self::at(
+            crate::paths::state_dir()
+                .join("held")
+                .join(format!("{}-{}.json", id, node)),
+        )
+    }
+
+    fn at(path: PathBuf) -> Self {
+        Self {
+            path,
+            keys: BTreeSet::new(),
+            saved: BTreeSet::new(),
+            last_save: None,
+        }
+    }
+
+    /// Keys the last processor on this node left down, if it didn't stop cleanly
+    pub fn take_leftover(&self) -> Vec<u16> {
+        let Ok(content) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        let _ = std::fs::remove_file(&self.path);
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring unreadable {}: {}", self.path.display(), e);
+            Vec::new()
+        })
+    }
+
+    /// Follow a key event sent on the virtual device
+    pub fn record(&mut self, code: u16, value: i32) {
+        match value {
+            1 => {
+                self.keys.insert(code);
+            }
+            0 => {
+                self.keys.remove(&code);
+            }
+            _ => {}
+        }
+    }
+
+    /// Write the held keys if they changed and the last write is a second old
+    pub fn save_if_due(&mut self) {
+        if self.keys == self.saved
+            || self
+                .last_save
+                .is_some_and(|at| at.elapsed() < SAVE_INTERVAL)
+        {
+            return;
+        }
+        self.last_save = Some(Instant::now());
+        let result = if self.keys.is_empty() {
+            match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        } else {
+            self.path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| {
+                    let codes: Vec<u16> = self.keys.iter().copied().collect();
+                    std::fs::write(&self.path, serde_json::to_string(&codes)?)
+                })
+        };
+        match result {
+            Ok(()) => self.saved.clone_from(&self.keys),
+            Err(e) => tracing::debug!("Failed to write {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+impl Drop for HeldOutput {
+    /// A processor stopping normally has released its keys
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leftover_keys_reach_the_next_processor() {
+        let path = std::env::temp_dir().join(format!("keymux-held-{}.json", std::process::id()));
+        let mut held = HeldOutput::at(path.clone());
+        held.record(42, 1);
+        held.record(30, 1);
+        held.record(30, 2);
+        held.record(30, 0);
+        held.save_if_due();
+
+        // A processor that died leaves its file behind
+        std::mem::forget(held);
+        let next = HeldOutput::at(path.clone());
+        assert_eq!(next.take_leftover(), vec![42]);
+        assert!(next.take_leftover().is_empty());
+
+        // One that stops normally doesn't
+        let mut held = HeldOutput::at(path.clone());
+        held.record(42, 1);
+        held.save_if_due();
+        drop(held);
+        assert!(!path.exists());
+    }
+}
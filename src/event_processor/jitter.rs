@@ -0,0 +1,61 @@
+//! Random pauses between the keys of typed text
+//!
+//! Typed text normally goes out in one write, like a paste. Some applications and
+//! anti-cheat heuristics drop input that arrives that fast, so with `typing_jitter_ms`
+//! set every key event of typed text is followed by a pause picked from the range.
+//! The pauses only need to look uneven, not be unpredictable, so a xorshift seeded
+//! from the clock stands in for a random number crate.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone)]
+pub struct TypingJitter {
+    min_ms: u32,
+    max_ms: u32,
+    state: u64,
+}
+
+impl TypingJitter {
+    /// Pauses between `min` and `max` ms (either order), or None for typing at once
+    pub fn new(range: Option<(u32, u32)>) -> Option<Self> {
+        let (a, b) = range?;
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Some(Self {
+            min_ms: a.min(b),
+            max_ms: a.max(b),
+            // xorshift never leaves zero
+            state: seed | 1,
+        })
+    }
+
+    /// How long to wait after the next key event
+    pub fn next_pause(&mut self) -> Duration {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        let span = u64::from(self.max_ms - self.min_ms) + 1;
+        Duration::from_millis(u64::from(self.min_ms) + self.state % span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pauses_stay_in_range() {
+        assert!(TypingJitter::new(None).is_none());
+
+        let mut jitter = TypingJitter::new(Some((40, 15))).unwrap();
+        let pauses: Vec<Duration> = (0..200).map(|_| jitter.next_pause()).collect();
+        assert!(pauses
+            .iter()
+            .all(|p| (15..=40).contains(&(p.as_millis() as u32))));
+        assert!(pauses.iter().any(|p| *p != pauses[0]));
+
+        let mut fixed = TypingJitter::new(Some((8, 8))).unwrap();
+        assert_eq!(fixed.next_pause(), Duration::from_millis(8));
+    }
+}
@@ -1,13 +1,32 @@
 use super::adaptive::AdaptiveProcessor;
-use crate::config::{Config, KeyAction};
+use crate::config::{Config, KeyAction, Layer, Unmapped};
+use crate::event_processor::actions::mt::MtDecision;
 use crate::event_processor::actions::{
-    handle_action_release, EmitResult, HandleContext, HeldAction, ProcessResult, TdResolution,
+    handle_action_release, EmitResult, HandleContext, HeldAction, ProcessResult, TapLocks,
+    TdResolution,
 };
-use crate::event_processor::layer_stack::LayerStack;
+use crate::event_processor::layer_stack::{LayerChange, LayerStack};
+use crate::event_processor::lock_state::LockState;
+use crate::event_processor::ordering;
+use crate::event_processor::sequence::SequenceMatcher;
 use crate::keycode::KeyCode;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
+/// Keys held at once beyond which presses are dropped; only a device flooding presses
+/// without releases gets here
+const MAX_HELD_KEYS: usize = 128;
+
+/// What a pressed key is bound to
+enum Lookup<'a> {
+    /// An action, with the `with_mods` of the layer it came from
+    Bound(&'a KeyAction, &'a [KeyCode]),
+    /// Nothing; the key types as itself
+    Unbound,
+    /// Nothing, and a strict layer or `macro_pad` keeps it from typing
+    Blocked,
+}
+
 pub struct KeymapProcessor {
     held_keys: HashMap<KeyCode, Vec<HeldAction>>,
     layer_stack: LayerStack,
@@ -15,9 +34,32 @@ pub struct KeymapProcessor {
     dt_processor: crate::event_processor::actions::DtProcessor,
     osm_processor: crate::event_processor::actions::OsmProcessor,
     socd_processor: crate::event_processor::actions::SocdProcessor,
+    script_processor: crate::event_processor::actions::ScriptProcessor,
+    turbo_processor: crate::event_processor::actions::TurboProcessor,
+    taplock_processor: crate::event_processor::actions::TapLockProcessor,
+    layout_switcher: crate::event_processor::actions::LayoutSwitcher,
     adaptive_processor: AdaptiveProcessor,
+    sequence_matcher: SequenceMatcher,
     config_dir: PathBuf,
     user_id: u32,
+    /// Decide outputs without side effects (CMD actions aren't run)
+    dry_run: bool,
+    /// Channel to the daemon for actions it has to carry out
+    daemon_tx: Option<crate::event_processor::EventSender>,
+    /// Drop layers held by keys after this long without key events (None = never)
+    layer_idle_timeout: Option<std::time::Duration>,
+    /// Time of the last key event
+    last_key_event: std::time::Instant,
+    /// Caps Lock / Num Lock as last reported by the LEDs or toggled by our output
+    lock_state: LockState,
+    /// app_id of the focused window, for `If(app_matches(..), ..)`
+    focused_app: Option<String>,
+    /// Game mode overlay chosen over IPC, ahead of the focused window's
+    forced_overlay: Option<String>,
+    /// Presses dropped at `MAX_HELD_KEYS`
+    dropped_presses: u64,
+    /// Keys without a binding send nothing (`macro_pad`)
+    macro_pad: bool,
 }
 
 impl KeymapProcessor {
@@ -34,17 +76,73 @@ impl KeymapProcessor {
             dt_processor: crate::event_processor::actions::DtProcessor::new(config),
             osm_processor: crate::event_processor::actions::OsmProcessor::new(config),
             socd_processor: crate::event_processor::actions::SocdProcessor::from_config(config),
+            script_processor: crate::event_processor::actions::ScriptProcessor::new(&config_dir),
+            turbo_processor: crate::event_processor::actions::TurboProcessor::new(config),
+            taplock_processor: crate::event_processor::actions::TapLockProcessor::new(config),
+            layout_switcher: crate::event_processor::actions::LayoutSwitcher::new(config),
             adaptive_processor: AdaptiveProcessor::new(),
+            sequence_matcher: SequenceMatcher::new(config),
             config_dir,
             user_id,
+            dry_run: false,
+            daemon_tx: None,
+            layer_idle_timeout: config
+                .layer_idle_timeout_ms
+                .map(std::time::Duration::from_millis),
+            last_key_event: super::clock::now(),
+            lock_state: LockState::default(),
+            focused_app: None,
+            forced_overlay: None,
+            dropped_presses: 0,
+            macro_pad: config.macro_pad,
         }
     }
 
+    /// Send requests for the daemon (such as `KeyboardToggle`) here
+    pub fn set_daemon_tx(&mut self, daemon_tx: crate::event_processor::EventSender) {
+        self.daemon_tx = Some(daemon_tx);
+    }
+
+    /// Only compute outputs, e.g. for a shadow config compared against the active one
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    pub fn is_game_mode(&self) -> bool {
+        self.layer_stack.is_game_mode_active()
+    }
+
     pub fn set_game_mode(&mut self, active: bool) {
         self.layer_stack.set_game_mode(active);
         self.mt_processor.set_game_mode(active);
     }
 
+    pub fn game_mode_overlay(&self) -> Option<&str> {
+        self.layer_stack.game_mode_overlay()
+    }
+
+    pub fn forced_game_mode_overlay(&self) -> Option<&str> {
+        self.forced_overlay.as_deref()
+    }
+
+    /// Force a game mode overlay (None = the one the focused window selects)
+    pub fn set_game_mode_overlay(&mut self, overlay: Option<String>) {
+        self.forced_overlay = overlay;
+        self.select_game_mode_overlay();
+    }
+
+    fn select_game_mode_overlay(&mut self) {
+        let overlay = self.forced_overlay.clone().or_else(|| {
+            self.layer_stack
+                .game_mode()
+                .overlay_for_app(self.focused_app.as_deref())
+                .map(String::from)
+        });
+        self.socd_processor
+            .set_modes(self.layer_stack.game_mode().socd_modes(overlay.as_deref()));
+        self.layer_stack.set_game_mode_overlay(overlay);
+    }
+
     pub fn check_dt_timeouts(&mut self) -> ProcessResult {
         let events = self.dt_processor.handle_check_timeouts();
         if events.is_empty() {
@@ -54,10 +152,221 @@ impl KeymapProcessor {
         }
     }
 
+    pub fn check_turbo_timers(&mut self) -> ProcessResult {
+        let events = self.turbo_processor.check_timers();
+        if events.is_empty() {
+            ProcessResult::None
+        } else {
+            ProcessResult::MultipleEvents(events)
+        }
+    }
+
+    /// Release keys holding a layer once no key event came for `layer_idle_timeout_ms`
+    ///
+    /// Guards against a layer stuck on because its key's release never arrived. Everything
+    /// else the keys hold is released with them. None when nothing timed out.
+    pub fn check_layer_timeouts(&mut self) -> Option<ProcessResult> {
+        let timeout = self.layer_idle_timeout?;
+        if super::clock::elapsed(self.last_key_event) < timeout {
+            return None;
+        }
+        let stuck: Vec<KeyCode> = self
+            .held_keys
+            .iter()
+            .filter(|(_, held)| held.iter().any(|held| matches!(held, HeldAction::Layer(_))))
+            .map(|(keycode, _)| *keycode)
+            .collect();
+        if stuck.is_empty() {
+            return None;
+        }
+
+        let mut events = Vec::new();
+        for keycode in stuck {
+            tracing::warn!(
+                "Releasing {} after {}ms without key events (layer idle timeout)",
+                keycode,
+                timeout.as_millis()
+            );
+            events.extend(self.process_key_release(keycode).into_events());
+        }
+        Some(if events.is_empty() {
+            ProcessResult::None
+        } else {
+            ProcessResult::MultipleEvents(events)
+        })
+    }
+
+    pub fn config_dir(&self) -> &std::path::Path {
+        &self.config_dir
+    }
+
+    /// System keyboard layout text is typed for
+    pub fn current_layout(&self) -> &str {
+        self.layout_switcher.current()
+    }
+
+    pub fn current_layer(&self) -> Layer {
+        self.layer_stack.current_layer()
+    }
+
+    /// Take in the state published by the user's other keyboards
+    pub fn apply_shared_state(&mut self, layers: Vec<Layer>, remote_modifier_held: bool) {
+        self.layer_stack.set_shared_layers(layers);
+        self.mt_processor
+            .set_remote_modifier_held(remote_modifier_held);
+    }
+
+    /// Activate the layer chosen by the focused window (None = no window layer)
+    pub fn set_window_layer(&mut self, layer: Option<Layer>) {
+        self.layer_stack.set_window_layer(layer);
+    }
+
+    /// The focused window changed (None = unknown)
+    pub fn set_focused_app(&mut self, app_id: Option<String>) {
+        self.focused_app = app_id;
+        self.select_game_mode_overlay();
+    }
+
+    /// A key was pressed on a linked keyboard; resolve pending MT keys to hold
+    pub fn on_remote_key_press(&mut self) -> ProcessResult {
+        let resolutions = self.mt_processor.on_remote_key_press();
+        let events = self.mt_processor.resolutions_to_events(&resolutions);
+        if events.is_empty() {
+            ProcessResult::None
+        } else {
+            ProcessResult::MultipleEvents(events)
+        }
+    }
+
+    /// Modifiers this keyboard is currently holding down on its virtual device
+    pub fn held_output_modifiers(&self) -> HashSet<KeyCode> {
+        let mut modifiers = self.held_modifiers_outside_layers();
+        for held in self.held_keys.values().flatten() {
+            if let HeldAction::LayerMods(mods) = held {
+                modifiers.extend(mods);
+            }
+        }
+        modifiers
+    }
+
+    /// Held modifiers other than those a layer's `with_mods` put down
+    fn held_modifiers_outside_layers(&self) -> HashSet<KeyCode> {
+        self.held_keys
+            .values()
+            .flatten()
+            .filter_map(|held| match held {
+                HeldAction::RegularKey(key) if key.is_modifier() => Some(*key),
+                _ => None,
+            })
+            .chain(self.mt_processor.held_modifiers())
+            .chain(self.taplock_processor.locked_modifiers())
+            .collect()
+    }
+
+    /// Modifiers locked down by double-tapping a `TapLock` key
+    pub fn locked_modifiers(&self) -> Vec<KeyCode> {
+        self.taplock_processor.locked_modifiers()
+    }
+
+    /// (physical key, modifier) of every `TapLock` lock, saved across daemon restarts
+    pub fn tap_locks(&self) -> TapLocks {
+        self.taplock_processor.locks()
+    }
+
+    /// Lock modifiers saved before a daemon restart and press them down again
+    pub fn restore_tap_locks(&mut self, locks: TapLocks) -> ProcessResult {
+        let presses: Vec<_> = self
+            .taplock_processor
+            .restore(locks)
+            .into_iter()
+            .map(|modifier| (modifier, true))
+            .collect();
+        if presses.is_empty() {
+            return ProcessResult::None;
+        }
+        ProcessResult::MultipleEvents(presses)
+    }
+
+    /// Caps Lock and Num Lock as the system sees them
+    pub fn lock_state(&self) -> LockState {
+        self.lock_state
+    }
+
+    /// The keyboard reported an LED change (`EV_LED`)
+    pub fn on_led(&mut self, led: u16, on: bool) {
+        self.lock_state.on_led(led, on);
+    }
+
+    /// A key press went out on the virtual device
+    pub fn on_emitted_press(&mut self, key: KeyCode) {
+        self.lock_state.on_emitted_press(key);
+    }
+
+    /// Local non-base layers, as published to linked keyboards
+    pub fn local_layers(&self) -> Vec<Layer> {
+        self.layer_stack.local_layers()
+    }
+
+    /// Local layers that stay active without a key holding them (worth keeping across restarts)
+    pub fn persistent_layers(&self) -> Vec<Layer> {
+        let held: Vec<&Layer> = self
+            .held_keys
+            .values()
+            .flatten()
+            .filter_map(|held| match held {
+                HeldAction::Layer(layer) => Some(layer),
+                _ => None,
+            })
+            .collect();
+        self.local_layers()
+            .into_iter()
+            .filter(|layer| !held.contains(&layer))
+            .collect()
+    }
+
+    /// Recent MT decisions, oldest first (with mt_config.trace_decisions)
+    pub fn mt_decisions(&self) -> Vec<MtDecision> {
+        self.mt_processor.decisions()
+    }
+
+    /// Active layers, base first, and the recent changes that led there
+    pub fn layer_history(&self) -> (Vec<Layer>, Vec<LayerChange>) {
+        (
+            self.layer_stack.layers().to_vec(),
+            self.layer_stack.history(),
+        )
+    }
+
+    /// Re-activate layers saved by a previous daemon
+    pub fn restore_layers(&mut self, layers: Vec<Layer>) {
+        for layer in layers {
+            self.layer_stack.activate_layer(layer);
+        }
+    }
+
+    pub fn has_pending_mt(&self) -> bool {
+        self.mt_processor.has_pending_keys()
+    }
+
+    /// Track keys that were down before the device was grabbed: releasing one sends
+    /// a plain release of that key, whatever it's bound to
+    pub fn seed_held_keys(&mut self, keys: impl IntoIterator<Item = KeyCode>) {
+        for key in keys {
+            self.held_keys
+                .entry(key)
+                .or_insert_with(|| vec![HeldAction::RegularKey(key)]);
+        }
+    }
+
     pub fn get_held_keys(&self) -> Vec<KeyCode> {
         self.held_keys.keys().copied().collect()
     }
 
+    /// Presses dropped or forgotten to keep per-key tracking bounded
+    pub const fn evictions(&self) -> u64 {
+        self.dropped_presses + self.adaptive_processor.evictions()
+    }
+
     pub fn save_adaptive_stats(&self, user_id: u32) -> Result<(), std::io::Error> {
         self.adaptive_processor.save_adaptive_stats(user_id)
     }
@@ -73,6 +382,7 @@ impl KeymapProcessor {
     }
 
     pub fn process_key(&mut self, keycode: KeyCode, pressed: bool) -> ProcessResult {
+        self.last_key_event = super::clock::now();
         if pressed {
             self.process_key_press(keycode)
         } else {
@@ -81,6 +391,16 @@ impl KeymapProcessor {
     }
 
     fn process_key_press(&mut self, keycode: KeyCode) -> ProcessResult {
+        if self.held_keys.len() >= MAX_HELD_KEYS && !self.held_keys.contains_key(&keycode) {
+            if self.dropped_presses == 0 {
+                tracing::warn!(
+                    "{} keys held at once, dropping further presses until some are released",
+                    MAX_HELD_KEYS
+                );
+            }
+            self.dropped_presses += 1;
+            return ProcessResult::None;
+        }
         self.adaptive_processor.record_key_press(keycode);
 
         let dt_timeout_events = self.dt_processor.handle_check_timeouts();
@@ -88,7 +408,16 @@ impl KeymapProcessor {
         // Notify DT of other key press for permissive hold
         let dt_permissive_events = self.dt_processor.on_other_key_press(keycode);
 
-        let action = self.lookup_action(keycode).cloned();
+        let (action, layer_mods, blocked) = match self.lookup_action(keycode) {
+            Lookup::Bound(action, mods) => (Some(action.clone()), mods.to_vec(), false),
+            Lookup::Unbound => (None, Vec::new(), false),
+            Lookup::Blocked => (None, Vec::new(), true),
+        };
+        // Only plain keys get their layer's modifiers wrapped around them
+        let layer_mods = match action {
+            Some(KeyAction::Key(_)) => layer_mods,
+            _ => Vec::new(),
+        };
 
         let (result, key_action) = match action {
             Some(KeyAction::DT(tap_action, double_tap_action)) => {
@@ -98,21 +427,81 @@ impl KeymapProcessor {
                 let mut ctx = self.make_context();
                 action.emit(keycode, &mut ctx)
             }
+            None if blocked => {
+                tracing::debug!("Unmapped {:?} blocked", keycode);
+                (EmitResult::None, None)
+            }
             None => {
                 let mut ctx = self.make_context();
                 KeyAction::Key(keycode).emit(keycode, &mut ctx)
             }
         };
 
+        // Modifiers held down some other way are left alone; of the rest, the layer
+        // presses those no other key from a `with_mods` layer holds yet
+        let layer_mods: Vec<KeyCode> = {
+            let external = self.held_modifiers_outside_layers();
+            layer_mods
+                .into_iter()
+                .filter(|m| !external.contains(m))
+                .collect()
+        };
+        let result = if layer_mods.is_empty() {
+            result
+        } else {
+            let already_held = self.held_output_modifiers();
+            let to_press: Vec<KeyCode> = layer_mods
+                .iter()
+                .filter(|m| !already_held.contains(m))
+                .copied()
+                .collect();
+            EmitResult::EmitKeys(ordering::press_before(
+                &to_press,
+                result.to_process_result().into_events(),
+            ))
+        };
+
         if let Some(ka) = key_action {
-            self.held_keys.insert(keycode, vec![ka]);
+            let mut held = vec![ka];
+            if !layer_mods.is_empty() {
+                held.push(HeldAction::LayerMods(layer_mods));
+            }
+            self.held_keys.insert(keycode, held);
         }
 
         // Combine timeout events and permissive hold events
         let mut all_dt_events = dt_timeout_events;
         all_dt_events.extend(dt_permissive_events);
 
-        self.combine_with_timeouts(all_dt_events, result.to_process_result())
+        let result = self.combine_with_timeouts(all_dt_events, result.to_process_result());
+
+        // The key still types; a sequence it completes fires after it
+        if self.layer_stack.is_game_mode_active() {
+            return result;
+        }
+        match self.sequence_matcher.on_press(keycode, super::clock::now()) {
+            Some(action) => {
+                let sequence_result = self.tap_action(keycode, &action);
+                self.combine_with_timeouts(result.into_events(), sequence_result)
+            }
+            None => result,
+        }
+    }
+
+    /// Press and immediately release an action that isn't bound to a held key
+    fn tap_action(&mut self, keycode: KeyCode, action: &KeyAction) -> ProcessResult {
+        let mut ctx = self.make_context();
+        let (result, held) = action.emit(keycode, &mut ctx);
+        let mut events = result.to_process_result().into_events();
+        if let Some(held) = held {
+            let ctx = self.make_context();
+            events.extend(handle_action_release(held, keycode, ctx).into_events());
+        }
+        if events.is_empty() {
+            ProcessResult::None
+        } else {
+            ProcessResult::MultipleEvents(events)
+        }
     }
 
     fn handle_dt_press(
@@ -147,8 +536,10 @@ impl KeymapProcessor {
     }
 
     fn process_key_release(&mut self, keycode: KeyCode) -> ProcessResult {
-        self.adaptive_processor
-            .record_key_release(keycode, self.layer_stack.is_game_mode_active());
+        self.adaptive_processor.record_key_release(
+            keycode,
+            self.layer_stack.is_game_mode_active() || self.mt_processor.is_fixed_term(keycode),
+        );
 
         let dt_timeout_events = self.dt_processor.handle_check_timeouts();
 
@@ -193,31 +584,61 @@ impl KeymapProcessor {
             dt_processor: &mut self.dt_processor,
             osm_processor: &mut self.osm_processor,
             socd_processor: &mut self.socd_processor,
+            script_processor: &mut self.script_processor,
+            turbo_processor: &mut self.turbo_processor,
+            taplock_processor: &mut self.taplock_processor,
+            layout_switcher: &mut self.layout_switcher,
             layer_stack: &mut self.layer_stack,
+            lock_state: self.lock_state,
+            held_keys: &self.held_keys,
+            focused_app: self.focused_app.as_deref(),
+            daemon_tx: self.daemon_tx.as_ref(),
             config_dir: self.config_dir.clone(),
             user_id: self.user_id,
+            dry_run: self.dry_run,
         }
     }
 
-    fn lookup_action(&self, keycode: KeyCode) -> Option<&KeyAction> {
-        if self.layer_stack.is_game_mode_active() {
-            if let Some(action) = self.layer_stack.game_mode_remaps().get(&keycode) {
-                return Some(action);
+    /// The action bound to a key, or whether an unbound key may type
+    fn lookup_action(&self, keycode: KeyCode) -> Lookup<'_> {
+        // OnlyGameMode/OnlyTyping bindings for the other mode fall through like Transparent
+        let game_mode = self.layer_stack.is_game_mode_active();
+        if game_mode {
+            if let Some(action) = self
+                .layer_stack
+                .game_mode_remaps()
+                .get(&keycode)
+                .and_then(|action| action.in_mode(true))
+            {
+                return Lookup::Bound(action, &[]);
             }
         }
 
-        for layer in self.layer_stack.layers().iter().rev() {
+        for layer in self.layer_stack.lookup_layers().rev() {
             if let Some(config) = self.layer_stack.layer_configs().get(layer) {
-                if let Some(action) = config.remaps.get(&keycode) {
-                    if action.is_transparent() {
-                        continue;
-                    }
-                    return Some(action);
+                match config
+                    .remaps
+                    .get(&keycode)
+                    .and_then(|action| action.in_mode(game_mode))
+                {
+                    Some(action) if action.is_transparent() => continue,
+                    Some(action) => return Lookup::Bound(action, &config.with_mods),
+                    None if config.unmapped == Unmapped::Block => return Lookup::Blocked,
+                    None => {}
                 }
             }
         }
 
-        self.layer_stack.base_remaps().get(&keycode)
+        match self
+            .layer_stack
+            .base_remaps()
+            .get(&keycode)
+            .and_then(|action| action.in_mode(game_mode))
+        {
+            Some(action) => Lookup::Bound(action, &[]),
+            None if self.macro_pad => Lookup::Blocked,
+            None => Lookup::Unbound,
+        }
     }
 
     fn combine_with_timeouts(
@@ -1,13 +1,43 @@
-use crate::config::{Config, KeyAction, Layer, LayerConfig};
+use crate::config::{Config, GameMode, KeyAction, Layer, LayerConfig};
 use crate::keycode::KeyCode;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Layer changes remembered for `keymux watch`
+const HISTORY_LEN: usize = 32;
+
+/// A layer turning on or off, as shown by `keymux watch`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerChange {
+    /// Increases with every change of this keyboard's layers
+    pub seq: u64,
+    /// Milliseconds since the Unix epoch
+    pub at_ms: u64,
+    pub layer: String,
+    pub active: bool,
+    /// Key that made the change (None = not a key, e.g. layers restored after a restart)
+    pub key: Option<KeyCode>,
+}
 
 #[derive(Debug, Clone)]
 pub struct LayerStack {
     layers: Vec<Layer>,
+    /// Recent activations and deactivations, oldest first
+    history: VecDeque<LayerChange>,
+    next_seq: u64,
+    /// Layers held on the user's other keyboards (shared state)
+    shared_layers: Vec<Layer>,
+    /// Layer selected by the focused window (window_layers rules)
+    window_layer: Option<Layer>,
     layer_configs: HashMap<Layer, LayerConfig>,
+    /// Sets of layers of which at most one is active (layer_groups)
+    layer_groups: Vec<Vec<Layer>>,
     base_remaps: HashMap<KeyCode, KeyAction>,
     game_mode_active: bool,
+    game_mode: GameMode,
+    /// Game mode overlay laid over game_mode.remaps (None = plain game mode remaps)
+    game_mode_overlay: Option<String>,
     game_mode_remaps: HashMap<KeyCode, KeyAction>,
 }
 
@@ -20,9 +50,16 @@ impl LayerStack {
 
         Self {
             layers: vec![Layer::base()],
+            history: VecDeque::new(),
+            next_seq: 0,
+            shared_layers: Vec::new(),
+            window_layer: None,
             layer_configs,
+            layer_groups: config.layer_groups.values().cloned().collect(),
             base_remaps: config.remaps.clone(),
             game_mode_active: false,
+            game_mode: config.game_mode.clone(),
+            game_mode_overlay: None,
             game_mode_remaps: config.game_mode.remaps.clone(),
         }
     }
@@ -35,16 +72,57 @@ impl LayerStack {
         &self.layers
     }
 
+    /// Layers to search for a remap, lowest priority first: base, the focused
+    /// window's layer, local layers, then layers active on linked keyboards
+    pub fn lookup_layers(&self) -> impl DoubleEndedIterator<Item = &Layer> {
+        let (base, rest) = self.layers.split_at(self.layers.len().min(1));
+        base.iter()
+            .chain(
+                self.window_layer
+                    .iter()
+                    .filter(|layer| !self.layers.contains(layer)),
+            )
+            .chain(rest)
+            .chain(
+                self.shared_layers
+                    .iter()
+                    .filter(|layer| !self.layers.contains(layer)),
+            )
+    }
+
+    /// Local non-base layers, as published to linked keyboards
+    pub fn local_layers(&self) -> Vec<Layer> {
+        self.layers
+            .iter()
+            .filter(|layer| !layer.is_base())
+            .cloned()
+            .collect()
+    }
+
+    pub fn set_shared_layers(&mut self, layers: Vec<Layer>) {
+        self.shared_layers = layers;
+    }
+
+    pub fn set_window_layer(&mut self, layer: Option<Layer>) {
+        self.window_layer = layer;
+    }
+
     pub fn activate_layer(&mut self, layer: Layer) {
-        if !self.layers.contains(&layer) {
-            self.layers.push(layer);
-        }
+        self.activate(layer, None);
     }
 
     pub fn deactivate_layer(&mut self, layer: &Layer) {
-        if !layer.is_base() {
-            self.layers.retain(|l| l != layer);
-        }
+        self.deactivate(layer, None);
+    }
+
+    /// Activate a layer on behalf of a key (recorded in the history)
+    pub fn activate_layer_from(&mut self, layer: Layer, key: KeyCode) {
+        self.activate(layer, Some(key));
+    }
+
+    /// Deactivate a layer on behalf of a key (recorded in the history)
+    pub fn deactivate_layer_from(&mut self, layer: &Layer, key: KeyCode) {
+        self.deactivate(layer, Some(key));
     }
 
     pub fn toggle_layer(&mut self, layer: Layer) {
@@ -55,6 +133,53 @@ impl LayerStack {
         }
     }
 
+    /// Recent layer changes, oldest first
+    pub fn history(&self) -> Vec<LayerChange> {
+        self.history.iter().cloned().collect()
+    }
+
+    fn activate(&mut self, layer: Layer, key: Option<KeyCode>) {
+        if !self.layers.contains(&layer) {
+            let rivals: Vec<Layer> = self
+                .layer_groups
+                .iter()
+                .filter(|group| group.contains(&layer))
+                .flatten()
+                .filter(|other| **other != layer)
+                .cloned()
+                .collect();
+            for rival in &rivals {
+                self.deactivate(rival, key);
+            }
+            self.record(&layer, true, key);
+            self.layers.push(layer);
+        }
+    }
+
+    fn deactivate(&mut self, layer: &Layer, key: Option<KeyCode>) {
+        if !layer.is_base() && self.layers.contains(layer) {
+            self.record(layer, false, key);
+            self.layers.retain(|l| l != layer);
+        }
+    }
+
+    fn record(&mut self, layer: &Layer, active: bool, key: Option<KeyCode>) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        let at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        self.history.push_back(LayerChange {
+            seq: self.next_seq,
+            at_ms,
+            layer: layer.0.clone(),
+            active,
+            key,
+        });
+        self.next_seq += 1;
+    }
+
     pub const fn set_game_mode(&mut self, active: bool) {
         self.game_mode_active = active;
     }
@@ -63,6 +188,20 @@ impl LayerStack {
         self.game_mode_active
     }
 
+    /// Select the game mode overlay (None = plain game mode remaps)
+    pub fn set_game_mode_overlay(&mut self, overlay: Option<String>) {
+        self.game_mode_remaps = self.game_mode.remaps_with_overlay(overlay.as_deref());
+        self.game_mode_overlay = overlay;
+    }
+
+    pub const fn game_mode(&self) -> &GameMode {
+        &self.game_mode
+    }
+
+    pub fn game_mode_overlay(&self) -> Option<&str> {
+        self.game_mode_overlay.as_deref()
+    }
+
     pub const fn base_remaps(&self) -> &HashMap<KeyCode, KeyAction> {
         &self.base_remaps
     }
@@ -75,3 +214,55 @@ impl LayerStack {
         &self.layer_configs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_records_changes_with_keys() {
+        let config = Config::parse("(remaps: {})").unwrap();
+        let mut stack = LayerStack::new(&config);
+        let nav = Layer("nav".to_string());
+
+        stack.activate_layer_from(nav.clone(), KeyCode::KC_LALT);
+        stack.activate_layer_from(nav.clone(), KeyCode::KC_LALT); // already active
+        stack.deactivate_layer(&nav);
+        let history = stack.history();
+        assert_eq!(history.len(), 2);
+        assert!(history[0].active && history[0].key == Some(KeyCode::KC_LALT));
+        assert!(!history[1].active && history[1].key.is_none());
+
+        for _ in 0..HISTORY_LEN {
+            stack.toggle_layer(nav.clone());
+        }
+        let history = stack.history();
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history.last().unwrap().seq, HISTORY_LEN as u64 + 1);
+    }
+
+    #[test]
+    fn test_layer_groups_are_exclusive() {
+        let config = Config::parse(
+            r#"(
+                layers: { "colemak": (remaps: {}), "gaming": (remaps: {}), "nav": (remaps: {}) },
+                layer_groups: { "alpha": ["colemak", "gaming"] },
+            )"#,
+        )
+        .unwrap();
+        let mut stack = LayerStack::new(&config);
+        let layer = |name: &str| Layer(name.to_string());
+
+        stack.activate_layer(layer("colemak"));
+        stack.activate_layer(layer("nav"));
+        stack.activate_layer_from(layer("gaming"), KeyCode::KC_F1);
+        assert_eq!(
+            stack.layers(),
+            &[Layer::base(), layer("nav"), layer("gaming")]
+        );
+        let last = &stack.history()[2..];
+        assert!(!last[0].active && last[0].layer == "colemak");
+        assert_eq!(last[0].key, Some(KeyCode::KC_F1));
+        assert!(last[1].active && last[1].layer == "gaming");
+    }
+}
@@ -0,0 +1,73 @@
+/// Caps Lock and Num Lock as the system sees them
+///
+/// The keyboard's LEDs are the source of truth: whoever owns the lock state (kernel
+/// console, compositor) sets them on every keyboard, and the grabbed device reports
+/// each change as an `EV_LED` event. Toggles keymux emits itself are applied right
+/// away as well, so actions see the new state before the LED catches up.
+use crate::keycode::KeyCode;
+
+/// evdev LED codes
+const LED_NUML: u16 = 0x00;
+const LED_CAPSL: u16 = 0x01;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockState {
+    pub caps_lock: bool,
+    pub num_lock: bool,
+}
+
+impl LockState {
+    /// Apply an `EV_LED` event; other LEDs are ignored
+    pub fn on_led(&mut self, led: u16, on: bool) {
+        match led {
+            LED_CAPSL => self.caps_lock = on,
+            LED_NUML => self.num_lock = on,
+            _ => {}
+        }
+    }
+
+    /// A key press was emitted; lock keys flip their lock
+    pub fn on_emitted_press(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::KC_CAPS => self.caps_lock = !self.caps_lock,
+            KeyCode::KC_NLCK => self.num_lock = !self.num_lock,
+            _ => {}
+        }
+    }
+
+    /// Whether shift has to be held to type `ch`, which needs `shift` without locks
+    ///
+    /// Caps Lock inverts shift for letters only, so typing "Hi" with it on takes
+    /// shift for the "i" and not for the "H".
+    pub fn shift_for(self, ch: char, shift: bool) -> bool {
+        shift != (self.caps_lock && ch.is_alphabetic())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leds_and_emitted_toggles_track_locks() {
+        let mut locks = LockState::default();
+        locks.on_emitted_press(KeyCode::KC_CAPS);
+        assert!(locks.caps_lock);
+        assert!(locks.shift_for('h', false));
+        assert!(!locks.shift_for('H', true));
+        assert!(locks.shift_for('!', true));
+        assert!(!locks.shift_for('1', false));
+
+        // The LED has the final word
+        locks.on_led(LED_CAPSL, false);
+        locks.on_led(LED_NUML, true);
+        assert_eq!(
+            locks,
+            LockState {
+                caps_lock: false,
+                num_lock: true
+            }
+        );
+        assert!(!locks.shift_for('h', false));
+    }
+}
@@ -1,34 +1,162 @@
-use crate::config::Config;
+use crate::config::{Config, Layer, NotificationKind, OutputPacing};
 use crate::keyboard_id::KeyboardId;
-use crate::keycode::KeyCode;
+use crate::keycode::{KeyCategory, KeyCode};
 use actions::ProcessResult as ProcResult;
 pub use actions::ProcessResult;
 use anyhow::{Context, Result};
+use busy_guard::BusyGuard;
+use debounce::Debouncer;
+use dedupe::NodeDedup;
+use disconnect::{Disconnect, REOPEN_ATTEMPTS};
 use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
-use evdev::{AttributeSet, Device, EventType, InputEvent, Key};
+use evdev::{AttributeSet, BusType, Device, EventType, InputEvent, InputId, Key};
+use held_output::HeldOutput;
+use jitter::TypingJitter;
 pub use keymap::KeymapProcessor;
+use layer_stack::LayerChange;
+use lock_state::LockState;
+use shared::{SharedKeyboardState, SharedStateHandle};
+use snippet::SnippetExpander;
+use std::collections::BTreeSet;
 use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 use tracing::{debug, error, info, warn};
 
 pub mod actions;
 pub mod adaptive;
+pub mod behavior_test;
+pub mod busy_guard;
+pub mod clock;
+pub mod debounce;
+pub mod dedupe;
+pub mod disconnect;
+pub mod held_output;
+pub mod jitter;
 pub mod keymap;
 pub mod layer_stack;
+pub mod lock_state;
+pub mod ordering;
+pub mod sequence;
+pub mod shared;
+pub mod snippet;
+pub mod stats_file;
 
 // SYN event constants
 const SYN_REPORT: i32 = 0;
 const SYN_CODE: u16 = 0;
+// MSC_SCAN: raw scancode sent just before the key event it belongs to
+const MSC_SCAN: u16 = 4;
+
+/// Live state of a processor thread, read by the daemon to answer `ListKeyboards`
+#[derive(Debug, Default)]
+pub struct ProcessorStatus {
+    /// Top active layer
+    pub layer: std::sync::Mutex<String>,
+    /// Key events read from the keyboard (repeats included)
+    pub events_in: AtomicU64,
+    /// Key events sent through the virtual keyboard
+    pub events_out: AtomicU64,
+    /// Modifiers locked on by `TapLock` keys
+    pub locked_modifiers: std::sync::Mutex<Vec<KeyCode>>,
+    /// Presses dropped or forgotten to keep per-key tracking bounded (a device
+    /// flooding presses without releases)
+    pub evictions: AtomicU64,
+    /// The loop is being slowed down for spinning (see `busy_guard`)
+    pub throttled: AtomicBool,
+}
+
+impl ProcessorStatus {
+    fn set_layer(&self, layer: &Layer) {
+        if let Ok(mut current) = self.layer.lock() {
+            current.clone_from(&layer.0);
+        }
+    }
+
+    fn set_locked_modifiers(&self, modifiers: Vec<KeyCode>) {
+        if let Ok(mut locked) = self.locked_modifiers.lock() {
+            *locked = modifiers;
+        }
+    }
+}
+
+/// Commands the daemon can send to a running processor thread
+pub enum ProcessorCommand {
+    /// Type a string through the virtual keyboard (true = press Enter afterwards)
+    TypeString(String, bool),
+    /// Feed key events through the keymap as if they came from the physical keyboard
+    InjectKeys(Vec<(KeyCode, bool)>),
+    /// Join the user's shared state bus (`share_state_across_keyboards`)
+    AttachSharedState(Arc<SharedKeyboardState>),
+    /// Drop keystrokes this keyboard's other event nodes already reported
+    AttachNodeEvents(Arc<dedupe::NodeEvents>),
+    /// Switch the layer selected by the focused window (None = clear it)
+    SetWindowLayer(Option<Layer>),
+    /// The focused window's app_id changed (None = unknown)
+    SetFocusedApp(Option<String>),
+    /// Force a game mode overlay (None = the one the focused window selects)
+    SetGameModeOverlay(Option<String>),
+    /// Act as the secondary half of a split keyboard: send keys to the primary half's
+    /// processor as `InjectKeys` instead of through our own keymap (None = stop)
+    ForwardKeys(Option<crossbeam_channel::Sender<ProcessorCommand>>),
+    /// Resume a processor paused by a disconnect on the reconnected event file
+    Reattach(Box<Device>),
+    /// Send back the layers that stay active without a held key and the `TapLock` locks
+    ReportLayers(std::sync::mpsc::Sender<(Vec<Layer>, actions::TapLocks)>),
+    /// Send back the active layers and recent layer changes
+    ReportLayerHistory(std::sync::mpsc::Sender<(Vec<Layer>, Vec<LayerChange>)>),
+    /// Send back the recent MT decisions
+    ReportMtTrace(std::sync::mpsc::Sender<Vec<actions::mt::MtDecision>>),
+    /// Re-activate layers and `TapLock` locks saved before a daemon restart
+    RestoreLayers(Vec<Layer>, actions::TapLocks),
+    /// Run this config alongside the active one and log where outputs differ (None = stop)
+    SetShadow(Option<Box<Config>>),
+    /// Turn snippet expansion on or off for the focused app
+    SetSnippetsEnabled(bool),
+    /// Save adaptive stats now and reply once they are written
+    FlushStats(std::sync::mpsc::Sender<()>),
+}
+
+/// What a processor tells the daemon: its state, and requests only the daemon can carry out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessorEvent {
+    /// The top active layer of a keyboard changed
+    LayerChanged {
+        keyboard_id: KeyboardId,
+        uid: u32,
+        layer: Layer,
+    },
+    /// Flip whether a user's keyboards matching a pattern (ID or name) are enabled
+    ToggleKeyboard { uid: u32, pattern: String },
+    /// Override the user's profile ("default" = no profile, "auto" = follow the schedule)
+    SwitchProfile { uid: u32, profile: String },
+    /// Sent every `HEARTBEAT_INTERVAL` while the processor loop is running
+    Heartbeat { event_path: PathBuf },
+    /// The processor loop spun without sleeping and is being throttled
+    Busy {
+        event_path: PathBuf,
+        turns_per_sec: u64,
+    },
+}
+
+/// Sending side of the channel from processors to the daemon
+pub type EventSender = tokio::sync::mpsc::UnboundedSender<ProcessorEvent>;
+
+/// How often a processor reports that its loop is still turning
+pub const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
 
 /// Run the event processor loop for a single keyboard event file.
 ///
 /// This is called directly inside the thread spawned by the daemon.
 /// It blocks until the keyboard is unplugged (ENODEV), a shutdown signal
-/// is received, or an unrecoverable error occurs.
+/// is received, or an unrecoverable error occurs. Returns the error that
+/// stopped it, if any, so the daemon can decide whether to restart it.
 #[allow(clippy::too_many_arguments)]
 pub fn run_processor(
     keyboard_id: KeyboardId,
     mut device: Device,
+    event_path: Arc<std::sync::Mutex<PathBuf>>,
     keyboard_name: String,
     config: Config,
     config_path: PathBuf,
@@ -36,10 +164,14 @@ pub fn run_processor(
     shutdown_rx: crossbeam_channel::Receiver<()>,
     game_mode_rx: std::sync::mpsc::Receiver<bool>,
     save_stats_rx: std::sync::mpsc::Receiver<()>,
-) {
-    if let Err(e) = run_event_processor(
+    command_rx: crossbeam_channel::Receiver<ProcessorCommand>,
+    daemon_tx: EventSender,
+    status: Arc<ProcessorStatus>,
+) -> Option<String> {
+    let result = run_event_processor(
         &keyboard_id,
         &mut device,
+        &event_path,
         &keyboard_name,
         &config,
         config_path,
@@ -47,16 +179,22 @@ pub fn run_processor(
         shutdown_rx,
         game_mode_rx,
         save_stats_rx,
-    ) {
+        command_rx,
+        daemon_tx,
+        status,
+    );
+    if let Err(e) = &result {
         error!("Event processor for {} failed: {}", keyboard_id, e);
     }
     info!("Event processor thread exiting for: {}", keyboard_id);
+    result.err().map(|e| e.to_string())
 }
 
 #[allow(clippy::too_many_arguments)]
 fn run_event_processor(
     keyboard_id: &KeyboardId,
     device: &mut Device,
+    event_path: &std::sync::Mutex<PathBuf>,
     keyboard_name: &str,
     config: &Config,
     config_path: PathBuf,
@@ -64,6 +202,9 @@ fn run_event_processor(
     shutdown_rx: crossbeam_channel::Receiver<()>,
     game_mode_rx: std::sync::mpsc::Receiver<bool>,
     save_stats_rx: std::sync::mpsc::Receiver<()>,
+    command_rx: crossbeam_channel::Receiver<ProcessorCommand>,
+    daemon_tx: EventSender,
+    status: Arc<ProcessorStatus>,
 ) -> Result<()> {
     info!(
         "Starting event processor for: {} ({})",
@@ -71,37 +212,166 @@ fn run_event_processor(
     );
 
     // Set device to non-blocking mode so we can check shutdown signal
-    let fd = device.as_raw_fd();
-    unsafe {
-        let flags = libc::fcntl(fd, libc::F_GETFL, 0);
-        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
-    }
-
-    // Grab the device for exclusive access
-    device.grab().context("Failed to grab device")?;
-    info!("Grabbed device: {}", keyboard_name);
+    set_nonblocking(device);
 
-    // Create virtual uinput device
-    let mut virtual_device = create_virtual_device(device, keyboard_name)?;
-    info!("Created virtual device for: {}", keyboard_name);
+    let mut virtual_device = if config.observe_only {
+        info!("Observing without grabbing: {}", keyboard_name);
+        KeyOutput::observing(config, Arc::clone(&status))
+    } else {
+        // Grab the device for exclusive access
+        device.grab().context("Failed to grab device")?;
+        info!("Grabbed device: {}", keyboard_name);
 
-    // SAFETY: Release all keys immediately on startup to prevent stuck keys
-    // This fixes the hotplug bug where keys remain held after reconnection
-    release_all_keys_on_startup(&mut virtual_device);
-    info!("Released all keys on startup for safety: {}", keyboard_name);
+        // Create virtual uinput device
+        let output = create_virtual_device(device, keyboard_name, config)?;
+        info!("Created virtual device for: {}", keyboard_name);
+        let consumer = create_consumer_device(device, keyboard_name, config)?;
+        if consumer.is_some() {
+            info!("Created consumer control device for: {}", keyboard_name);
+        }
+        let node = current_event_path(event_path);
+        let held = HeldOutput::for_node(&keyboard_id.to_string(), &node);
+        let leftover = held.take_leftover();
+        let mut output = KeyOutput {
+            device: Some(output),
+            consumer,
+            layout: String::new(),
+            snippets: SnippetExpander::from_config(config),
+            status: Arc::clone(&status),
+            locks: LockState::default(),
+            lock_toggles: Vec::new(),
+            held: Some(held),
+            jitter: TypingJitter::new(config.typing_jitter_ms),
+            pacing: config.output_pacing,
+        };
+        release_on_startup(&mut output, device, config, &leftover, keyboard_name);
+        output
+    };
 
     // Create keymap processor (QMK-inspired)
-    let mut keymap = KeymapProcessor::new(config, config_path, user_id);
+    let mut keymap = KeymapProcessor::new(config, config_path.clone(), user_id);
+    keymap.set_daemon_tx(daemon_tx.clone());
+    // An observed keyboard types as usual, so commands, scripts and switches bound in
+    // the config mustn't run on top of it
+    keymap.set_dry_run(config.observe_only);
+
+    // Start from the locks the LEDs show; EV_LED events keep them current
+    if let Ok(leds) = device.get_led_state() {
+        for led in leds.iter() {
+            keymap.on_led(led.0, true);
+        }
+    }
+
+    // Keys already down when we grabbed went out on the physical device; track them so
+    // their release reaches the compositor through ours
+    seed_held_keys(&mut keymap, device, keyboard_name);
 
     // Load adaptive timing stats from disk
     let _ = keymap.load_adaptive_stats(user_id); // Ignore errors if file doesn't exist
 
+    // Track the active layer so hooks and notifications fire only on real changes
+    let mut active_layer = keymap.current_layer();
+    status.set_layer(&active_layer);
+    let mut check_layer_change = |keymap: &KeymapProcessor| {
+        // Locks change on the same keys layers do, so they're published here as well
+        status.set_locked_modifiers(keymap.locked_modifiers());
+        status
+            .evictions
+            .store(keymap.evictions(), Ordering::Relaxed);
+        let current = keymap.current_layer();
+        if current == active_layer {
+            return;
+        }
+        debug!(
+            "Layer changed {} -> {} for: {}",
+            active_layer.0, current.0, keyboard_name
+        );
+        status.set_layer(&current);
+        let _ = daemon_tx.send(ProcessorEvent::LayerChanged {
+            keyboard_id: keyboard_id.clone(),
+            uid: user_id,
+            layer: current.clone(),
+        });
+        if config.observe_only {
+            active_layer = current;
+            return;
+        }
+        if let Some(text) = config.notifications.layer_text(&current) {
+            crate::notify::osd(
+                user_id,
+                &config.notifications,
+                NotificationKind::Layer,
+                "Layer",
+                &text,
+            );
+        }
+        if let Some(hook) = &config.hooks.on_layer_change {
+            crate::hooks::run_hook(
+                hook,
+                user_id,
+                keymap.config_dir().to_path_buf(),
+                "layer_change",
+                vec![
+                    ("KEYMUX_LAYER", current.0.clone()),
+                    ("KEYMUX_PREVIOUS_LAYER", active_layer.0.clone()),
+                    ("KEYMUX_KEYBOARD_ID", keyboard_id.to_string()),
+                    ("KEYMUX_KEYBOARD_NAME", keyboard_name.to_string()),
+                ],
+            );
+        }
+        active_layer = current;
+    };
+
+    // Keys this keyboard should never report
+    let ignored_keys: std::collections::HashSet<KeyCode> =
+        config.ignored_keys.iter().copied().collect();
+    // Keys going out exactly as they came in
+    let wake_keys: std::collections::HashSet<KeyCode> = config.wake_keys.iter().copied().collect();
+
+    // Scancode of the key event being reported, from the MSC_SCAN ahead of it
+    let mut last_scancode: Option<u32> = None;
+
+    // Chatter filter in front of the keymap, if any key is debounced
+    let mut debouncer = Debouncer::from_config(config);
+
+    // Config being compared against the active one, fed the same input without emitting
+    let mut shadow: Option<KeymapProcessor> = None;
+
+    // Membership in the user's shared state bus, once the daemon attaches one
+    let mut shared: Option<SharedStateHandle> = None;
+
+    // Filter for keys the keyboard repeats on its other event nodes, once attached
+    let mut node_dedup: Option<NodeDedup> = None;
+
+    // Primary half of the split keyboard this is the secondary half of
+    let mut forward_to: Option<crossbeam_channel::Sender<ProcessorCommand>> = None;
+
+    // Set while the event file is being reopened, and while the keyboard is gone but
+    // still within `disconnect_grace_ms` (or `disconnect_grace_forever`)
+    let mut disconnect: Option<Disconnect> = None;
+    // Hardware ID of the node being read, so a reopen never adopts another device
+    let mut node_id = KeyboardId::from_device(device, &current_event_path(event_path));
+    let disconnect_grace = (!config.disconnect_grace_forever)
+        .then(|| std::time::Duration::from_millis(u64::from(config.disconnect_grace_ms)));
+
     // Track last save time for periodic stats saving
     let mut last_stats_save = std::time::Instant::now();
     const STATS_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+    let mut last_heartbeat: Option<std::time::Instant> = None;
+    let mut busy_guard = BusyGuard::new(std::time::Instant::now());
 
     // Event processing loop
     loop {
+        // Type text for the layout the last LayoutSwitch selected
+        if virtual_device.layout != keymap.current_layout() {
+            virtual_device.layout = keymap.current_layout().to_string();
+        }
+        // Lock keys we sent flip the keymap's locks; type text for its view of them
+        for key in virtual_device.lock_toggles.drain(..) {
+            keymap.on_emitted_press(key);
+        }
+        virtual_device.locks = keymap.lock_state();
+
         // Check for shutdown signal (non-blocking)
         match shutdown_rx.try_recv() {
             Ok(()) => {
@@ -136,6 +406,9 @@ fn run_event_processor(
                     keyboard_name
                 );
                 keymap.set_game_mode(active);
+                if let Some(shadow) = shadow.as_mut() {
+                    shadow.set_game_mode(active);
+                }
             }
             Err(std::sync::mpsc::TryRecvError::Empty) => {
                 // No game mode toggle, continue
@@ -161,81 +434,336 @@ fn run_event_processor(
             }
         }
 
+        // Check for daemon commands (non-blocking)
+        match command_rx.try_recv() {
+            Ok(ProcessorCommand::TypeString(text, add_enter)) => {
+                info!(
+                    "Typing {} chars for: {}",
+                    text.chars().count(),
+                    keyboard_name
+                );
+                type_string(&mut virtual_device, &text, add_enter)?;
+            }
+            Ok(ProcessorCommand::InjectKeys(events)) => {
+                debug!(
+                    "Injecting {} key events for: {}",
+                    events.len(),
+                    keyboard_name
+                );
+                for (key, pressed) in events {
+                    pull_shared_state(shared.as_mut(), &mut keymap, pressed);
+                    let result = keymap.process_key(key, pressed);
+                    if let Some(shadow) = shadow.as_mut() {
+                        let shadow_result = shadow.process_key(key, pressed);
+                        log_shadow_diff(
+                            keyboard_name,
+                            Some(key),
+                            press_word(pressed),
+                            &result,
+                            &shadow_result,
+                        );
+                    }
+                    emit_process_result(&mut virtual_device, result)?;
+                    check_layer_change(&keymap);
+                    publish_shared_state(shared.as_ref(), &keymap);
+                }
+            }
+            Ok(ProcessorCommand::SetWindowLayer(layer)) => {
+                debug!(
+                    "Window layer {:?} for: {}",
+                    layer.as_ref().map(|l| l.0.as_str()),
+                    keyboard_name
+                );
+                if let Some(shadow) = shadow.as_mut() {
+                    shadow.set_window_layer(layer.clone());
+                }
+                keymap.set_window_layer(layer);
+            }
+            Ok(ProcessorCommand::SetFocusedApp(app_id)) => {
+                if let Some(shadow) = shadow.as_mut() {
+                    shadow.set_focused_app(app_id.clone());
+                }
+                keymap.set_focused_app(app_id);
+            }
+            Ok(ProcessorCommand::SetGameModeOverlay(overlay)) => {
+                if let Some(shadow) = shadow.as_mut() {
+                    shadow.set_game_mode_overlay(overlay.clone());
+                }
+                keymap.set_game_mode_overlay(overlay);
+                info!(
+                    "Game mode overlay {} for: {}",
+                    keymap.game_mode_overlay().unwrap_or("(none)"),
+                    keyboard_name
+                );
+            }
+            Ok(ProcessorCommand::ForwardKeys(primary)) => {
+                info!(
+                    "{} keys to the other half for: {}",
+                    if primary.is_some() {
+                        "Forwarding"
+                    } else {
+                        "No longer forwarding"
+                    },
+                    keyboard_name
+                );
+                forward_to = primary;
+            }
+            Ok(ProcessorCommand::AttachSharedState(state)) => {
+                info!("Sharing state with linked keyboards for: {}", keyboard_name);
+                shared = Some(state.register());
+            }
+            Ok(ProcessorCommand::AttachNodeEvents(events)) => {
+                let window = std::time::Duration::from_millis(u64::from(config.dedupe_nodes_ms));
+                node_dedup = Some(events.register(window));
+            }
+            Ok(ProcessorCommand::FlushStats(reply_tx)) => {
+                if let Err(e) = keymap.save_adaptive_stats(user_id) {
+                    warn!("Failed to flush stats for {}: {}", keyboard_name, e);
+                }
+                last_stats_save = std::time::Instant::now();
+                let _ = reply_tx.send(());
+            }
+            Ok(ProcessorCommand::ReportLayers(reply_tx)) => {
+                let _ = reply_tx.send((keymap.persistent_layers(), keymap.tap_locks()));
+            }
+            Ok(ProcessorCommand::ReportMtTrace(reply_tx)) => {
+                let _ = reply_tx.send(keymap.mt_decisions());
+            }
+            Ok(ProcessorCommand::ReportLayerHistory(reply_tx)) => {
+                let _ = reply_tx.send(keymap.layer_history());
+            }
+            Ok(ProcessorCommand::RestoreLayers(layers, locks)) => {
+                info!(
+                    "Restoring {} layer(s) and {} lock(s) for: {}",
+                    layers.len(),
+                    locks.len(),
+                    keyboard_name
+                );
+                keymap.restore_layers(layers);
+                let result = keymap.restore_tap_locks(locks);
+                emit_process_result(&mut virtual_device, result)?;
+                check_layer_change(&keymap);
+                publish_shared_state(shared.as_ref(), &keymap);
+            }
+            Ok(ProcessorCommand::SetShadow(shadow_config)) => {
+                shadow = shadow_config.map(|shadow_config| {
+                    info!("Comparing a shadow config for: {}", keyboard_name);
+                    let mut shadow =
+                        KeymapProcessor::new(&shadow_config, config_path.clone(), user_id);
+                    shadow.set_dry_run(true);
+                    // Same adaptive timings to start from, but never saved
+                    let _ = shadow.load_adaptive_stats(user_id);
+                    shadow.set_game_mode(keymap.is_game_mode());
+                    shadow
+                        .set_game_mode_overlay(keymap.forced_game_mode_overlay().map(String::from));
+                    shadow
+                });
+                if shadow.is_none() {
+                    info!("Stopped comparing the shadow config for: {}", keyboard_name);
+                }
+            }
+            Ok(ProcessorCommand::SetSnippetsEnabled(enabled)) => {
+                debug!(
+                    "Snippets {} for: {}",
+                    if enabled { "enabled" } else { "disabled" },
+                    keyboard_name
+                );
+                if let Some(snippets) = virtual_device.snippets.as_mut() {
+                    snippets.set_enabled(enabled);
+                }
+            }
+            Ok(ProcessorCommand::Reattach(new_device)) => {
+                set_nonblocking(&new_device);
+                *device = *new_device;
+                if !config.observe_only {
+                    device.grab().context("Failed to grab reconnected device")?;
+                }
+                node_id = KeyboardId::from_device(device, &current_event_path(event_path));
+                disconnect = None;
+                info!("Keyboard reconnected, resuming: {}", keyboard_name);
+                seed_held_keys(&mut keymap, device, keyboard_name);
+            }
+            Err(crossbeam_channel::TryRecvError::Empty) => {
+                // No command, continue
+            }
+            Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                debug!("Command channel disconnected for: {}", keyboard_name);
+            }
+        }
+
+        // Layers also change outside key handling: DT and idle timeouts, the focused
+        // window, game mode, restored layers and linked keyboards
+        check_layer_change(&keymap);
+
+        if let Some(held) = virtual_device.held.as_mut() {
+            held.save_if_due();
+        }
+
+        if last_heartbeat.is_none_or(|at| at.elapsed() >= HEARTBEAT_INTERVAL) {
+            let event_path = current_event_path(event_path);
+            let _ = daemon_tx.send(ProcessorEvent::Heartbeat { event_path });
+            last_heartbeat = Some(std::time::Instant::now());
+        }
+
+        let now = std::time::Instant::now();
+        if let Some(turns_per_sec) = busy_guard.tick(now) {
+            warn!(
+                "Processor loop spinning at {} turns/s, throttling: {}",
+                turns_per_sec, keyboard_name
+            );
+            let event_path = current_event_path(event_path);
+            let _ = daemon_tx.send(ProcessorEvent::Busy {
+                event_path,
+                turns_per_sec,
+            });
+        }
+        let throttled = busy_guard.is_throttled(now);
+        status.throttled.store(throttled, Ordering::Relaxed);
+        if throttled {
+            std::thread::sleep(busy_guard::THROTTLE_SLEEP);
+        }
+
         // Periodically save adaptive timing stats
         if last_stats_save.elapsed() >= STATS_SAVE_INTERVAL {
             let _ = keymap.save_adaptive_stats(user_id);
             last_stats_save = std::time::Instant::now();
         }
 
+        // KVM switches and flaky hubs drop the device for a moment, so try the
+        // same event file again a few times before giving up on it; while it's gone,
+        // wait for the daemon to reattach us or for the grace period to run out
+        if let Some(waiting) = disconnect.as_mut() {
+            let now = std::time::Instant::now();
+            if waiting.reopen_due(now) {
+                let path = current_event_path(event_path);
+                match reopen_device(&path, &node_id, !config.observe_only) {
+                    Ok(new_device) => {
+                        *device = new_device;
+                        disconnect = None;
+                        info!("Reopened {} for: {}", path.display(), keyboard_name);
+                        continue;
+                    }
+                    Err(e) => debug!(
+                        "Reopen attempt {}/{} of {} failed for {}: {}",
+                        waiting.attempt(),
+                        REOPEN_ATTEMPTS,
+                        path.display(),
+                        keyboard_name,
+                        e
+                    ),
+                }
+                waiting.reopen_failed(std::time::Instant::now(), keyboard_name)?;
+            } else if waiting.expired(now) {
+                info!("Keyboard did not reconnect in time: {}", keyboard_name);
+                let _ = keymap.save_adaptive_stats(user_id);
+                return Ok(());
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            continue;
+        }
+
         // Read events from physical keyboard (non-blocking)
+        let mut read_error = None;
         match device.fetch_events() {
             Ok(events) => {
                 for ev in events {
+                    if ev.event_type() == EventType::MISC && ev.code() == MSC_SCAN {
+                        last_scancode = Some(ev.value() as u32);
+                    }
+
                     // Process key events through keymap
                     if ev.event_type() == evdev::EventType::KEY {
-                        // Convert evdev key code to our KeyCode enum
-                        if let Some(input_key) = KeyCode::from_evdev_code(ev.code()) {
+                        status.events_in.fetch_add(1, Ordering::Relaxed);
+                        // Convert evdev key code to our KeyCode enum, or take the key
+                        // configured for its scancode
+                        let scancode = last_scancode.take();
+                        let input_key = scancode
+                            .and_then(|scancode| config.scancodes.get(&scancode).copied())
+                            .or_else(|| KeyCode::from_evdev_code(ev.code()));
+                        if let Some(input_key) = input_key {
                             let pressed = ev.value() == 1; // 1 = press, 0 = release, 2 = repeat
                             let repeat = ev.value() == 2;
 
+                            if ignored_keys.contains(&input_key) {
+                                continue;
+                            }
+
+                            // Wake keys skip the keymap, repeats and all
+                            if wake_keys.contains(&input_key) {
+                                virtual_device.emit(&[InputEvent::new_now(
+                                    EventType::KEY,
+                                    input_key.code(),
+                                    ev.value(),
+                                )])?;
+                                continue;
+                            }
+
                             // Ignore repeat events
                             if repeat {
                                 continue;
                             }
 
-                            // Process key through keymap (QMK-inspired)
-                            let result = keymap.process_key(input_key, pressed);
-
-                            match result {
-                                ProcessResult::EmitKey(output_key, output_pressed) => {
-                                    // Convert back to evdev and emit
-                                    let output_evdev = Key::new(output_key.code());
-                                    let output_event = InputEvent::new_now(
-                                        ev.event_type(),
-                                        output_evdev.code(),
-                                        i32::from(output_pressed),
-                                    );
-                                    virtual_device.emit(&[output_event])?;
-                                }
-                                ProcessResult::TypeString(text, add_enter) => {
-                                    // Type out the string character by character
-                                    type_string(&mut virtual_device, &text, add_enter)?;
+                            // The same keystroke already came in on another node
+                            if let Some(node_dedup) = node_dedup.as_mut() {
+                                if !node_dedup.filter(input_key, pressed, ev.timestamp()) {
+                                    continue;
                                 }
-                                ProcessResult::TapKeyPressRelease(tap_key) => {
-                                    // Emit tap key press and release
-                                    let key_evdev = Key::new(tap_key.code());
-                                    let press_event =
-                                        InputEvent::new_now(ev.event_type(), key_evdev.code(), 1);
-                                    virtual_device.emit(&[press_event])?;
-
-                                    std::thread::sleep(std::time::Duration::from_millis(5));
-
-                                    let release_event =
-                                        InputEvent::new_now(ev.event_type(), key_evdev.code(), 0);
-                                    virtual_device.emit(&[release_event])?;
+                            }
+
+                            // Drop switch bounce before MT/DT timing can see it
+                            let keys = match debouncer.as_mut() {
+                                Some(debouncer) => {
+                                    debouncer.filter(input_key, pressed, ev.timestamp())
                                 }
-                                ProcessResult::MultipleEvents(events) => {
-                                    // Emit multiple events in sequence
-                                    for (key, pressed) in events {
-                                        let key_evdev = Key::new(key.code());
-                                        let event = InputEvent::new_now(
-                                            ev.event_type(),
-                                            key_evdev.code(),
-                                            i32::from(pressed),
-                                        );
-                                        virtual_device.emit(&[event])?;
-                                        std::thread::sleep(std::time::Duration::from_millis(2));
+                                None => vec![(input_key, pressed)],
+                            };
+
+                            for (input_key, pressed) in keys {
+                                // The split's primary half decides what this key does
+                                if let Some(primary) = &forward_to {
+                                    let events = vec![(input_key, pressed)];
+                                    if primary.send(ProcessorCommand::InjectKeys(events)).is_ok() {
+                                        continue;
                                     }
+                                    info!("Other half is gone, typing alone: {}", keyboard_name);
+                                    forward_to = None;
                                 }
-                                ProcessResult::None => {
-                                    // Don't emit anything (consumed by layer switch, etc.)
+
+                                pull_shared_state(shared.as_mut(), &mut keymap, pressed);
+
+                                // Process key through keymap (QMK-inspired)
+                                let result = keymap.process_key(input_key, pressed);
+                                if let Some(shadow) = shadow.as_mut() {
+                                    let shadow_result = shadow.process_key(input_key, pressed);
+                                    log_shadow_diff(
+                                        keyboard_name,
+                                        Some(input_key),
+                                        press_word(pressed),
+                                        &result,
+                                        &shadow_result,
+                                    );
                                 }
+
+                                emit_process_result(&mut virtual_device, result)?;
+                                check_layer_change(&keymap);
+                                publish_shared_state(shared.as_ref(), &keymap);
                             }
                         } else {
                             // Unsupported key, pass through unchanged
+                            if let Some(scancode) = scancode.filter(|_| ev.value() == 1) {
+                                info!(
+                                    "Unknown key {} with scancode {:#x} on {} (bind it with `scancodes`)",
+                                    ev.code(),
+                                    scancode,
+                                    keyboard_name
+                                );
+                            }
                             virtual_device.emit(&[ev])?;
                         }
                     } else {
+                        if ev.event_type() == EventType::LED {
+                            keymap.on_led(ev.code(), ev.value() != 0);
+                        }
                         // Non-key event (SYN, etc.), pass through
                         virtual_device.emit(&[ev])?;
                     }
@@ -245,6 +773,15 @@ fn run_event_processor(
                 // No events available - check for DT timeouts
                 // This allows hold detection to work even when no keys are being pressed
                 let timeout_result = keymap.check_dt_timeouts();
+                if let Some(shadow) = shadow.as_mut() {
+                    log_shadow_diff(
+                        keyboard_name,
+                        None,
+                        "DT timeout",
+                        &timeout_result,
+                        &shadow.check_dt_timeouts(),
+                    );
+                }
                 match timeout_result {
                     ProcResult::MultipleEvents(events) => {
                         // Emit timeout events (hold first action, single-tap, etc.)
@@ -263,39 +800,555 @@ fn run_event_processor(
                     }
                 }
 
+                // Releases that outlived their debounce window were real
+                if let Some(debouncer) = debouncer.as_mut() {
+                    for key in debouncer.take_expired(std::time::SystemTime::now()) {
+                        if let Some(primary) = &forward_to {
+                            let events = vec![(key, false)];
+                            if primary.send(ProcessorCommand::InjectKeys(events)).is_ok() {
+                                continue;
+                            }
+                            forward_to = None;
+                        }
+                        pull_shared_state(shared.as_mut(), &mut keymap, false);
+                        let result = keymap.process_key(key, false);
+                        if let Some(shadow) = shadow.as_mut() {
+                            let shadow_result = shadow.process_key(key, false);
+                            log_shadow_diff(
+                                keyboard_name,
+                                Some(key),
+                                "release",
+                                &result,
+                                &shadow_result,
+                            );
+                        }
+                        emit_process_result(&mut virtual_device, result)?;
+                        check_layer_change(&keymap);
+                        publish_shared_state(shared.as_ref(), &keymap);
+                    }
+                }
+
+                // Advance held Turbo keys
+                let turbo_result = keymap.check_turbo_timers();
+                if let Some(shadow) = shadow.as_mut() {
+                    log_shadow_diff(
+                        keyboard_name,
+                        None,
+                        "Turbo timer",
+                        &turbo_result,
+                        &shadow.check_turbo_timers(),
+                    );
+                }
+                if turbo_result != ProcResult::None {
+                    emit_process_result(&mut virtual_device, turbo_result)?;
+                }
+
+                // Drop layers whose key release got lost
+                let layer_timeout_result = keymap.check_layer_timeouts();
+                if let Some(shadow) = shadow.as_mut() {
+                    log_shadow_diff(
+                        keyboard_name,
+                        None,
+                        "Layer idle timeout",
+                        layer_timeout_result.as_ref().unwrap_or(&ProcResult::None),
+                        &shadow.check_layer_timeouts().unwrap_or(ProcResult::None),
+                    );
+                }
+                if let Some(result) = layer_timeout_result {
+                    emit_process_result(&mut virtual_device, result)?;
+                    check_layer_change(&keymap);
+                    publish_shared_state(shared.as_ref(), &keymap);
+                }
+
+                // A key pressed on a linked keyboard resolves our pending MT keys to hold
+                if let Some(handle) = shared.as_mut() {
+                    if handle.take_remote_key_press() {
+                        let result = keymap.on_remote_key_press();
+                        if result != ProcResult::None {
+                            emit_process_result(&mut virtual_device, result)?;
+                            publish_shared_state(Some(handle), &keymap);
+                        }
+                    }
+                }
+
                 // Sleep briefly to avoid CPU spinning
                 // 1ms sleep provides excellent responsiveness while preventing busy-wait
                 std::thread::sleep(std::time::Duration::from_millis(1));
             }
+            Err(e) if matches!(e.raw_os_error(), Some(libc::ENODEV | libc::EIO)) => {
+                warn!("Read error on {}: {}", keyboard_name, e);
+                // Keys held now never see their release, so let them go;
+                // toggled layers and timing state survive
+                for key in keymap.get_held_keys() {
+                    let result = keymap.process_key(key, false);
+                    emit_process_result(&mut virtual_device, result)?;
+                }
+                check_layer_change(&keymap);
+                publish_shared_state(shared.as_ref(), &keymap);
+                if let Some(debouncer) = debouncer.as_mut() {
+                    debouncer.clear();
+                }
+                read_error = Some(e);
+            }
             Err(e) => return Err(e.into()),
         }
+
+        // Retried from the paused path above, so commands and heartbeats keep flowing
+        if let Some(e) = read_error {
+            let _ = device.ungrab();
+            disconnect = Some(Disconnect::new(
+                e,
+                std::time::Instant::now(),
+                disconnect_grace,
+            ));
+        }
     }
 }
 
-/// Create a virtual uinput device that mimics the physical keyboard
-fn create_virtual_device(physical_device: &Device, keyboard_name: &str) -> Result<VirtualDevice> {
-    let mut keys = AttributeSet::<Key>::new();
+/// The event file being read, which the daemon swaps when it reattaches the keyboard
+fn current_event_path(event_path: &std::sync::Mutex<PathBuf>) -> PathBuf {
+    event_path
+        .lock()
+        .map_or_else(|e| e.into_inner().clone(), |p| p.clone())
+}
 
-    // Copy all supported keys from physical device
-    if let Some(physical_keys) = physical_device.supported_keys() {
-        for key in physical_keys {
-            keys.insert(key);
+const fn press_word(pressed: bool) -> &'static str {
+    if pressed {
+        "press"
+    } else {
+        "release"
+    }
+}
+
+/// Log an input the shadow config would have handled differently
+///
+/// The journal shouldn't end up holding what was typed, so a difference is only
+/// spelled out at the info level when neither the input nor any output types text;
+/// otherwise the line only says typed text differed. Debug logs every detail.
+fn log_shadow_diff(
+    keyboard_name: &str,
+    input: Option<KeyCode>,
+    what: &str,
+    active: &ProcResult,
+    shadow: &ProcResult,
+) {
+    if active == shadow {
+        return;
+    }
+    let writes_text = |result: &ProcResult| match result {
+        ProcResult::TypeString(..) => true,
+        other => other
+            .clone()
+            .into_events()
+            .iter()
+            .any(|(key, _)| types_text(*key)),
+    };
+    let input_name = input.map_or_else(|| what.to_string(), |key| format!("{key:?} {what}"));
+    if input.is_some_and(types_text) || writes_text(active) || writes_text(shadow) {
+        info!(
+            "Shadow config differs on typed text ({}), details at debug level",
+            keyboard_name
+        );
+        debug!(
+            "Shadow config differs on {} ({}): active {:?}, shadow {:?}",
+            input_name, keyboard_name, active, shadow
+        );
+    } else {
+        info!(
+            "Shadow config differs on {} ({}): active {:?}, shadow {:?}",
+            input_name, keyboard_name, active, shadow
+        );
+    }
+}
+
+/// Whether a key types text (letters, digits, punctuation, space and the like)
+const fn types_text(key: KeyCode) -> bool {
+    matches!(
+        key.category(),
+        KeyCategory::Letter | KeyCategory::Number | KeyCategory::Numpad | KeyCategory::Special
+    )
+}
+
+/// Put a device in non-blocking mode so the loop can poll its channels
+fn set_nonblocking(device: &Device) {
+    let fd = device.as_raw_fd();
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL, 0);
+        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+    }
+}
+
+/// Track the keys a device reports as down as plain held keys
+fn seed_held_keys(keymap: &mut KeymapProcessor, device: &Device, keyboard_name: &str) {
+    let Ok(state) = device.get_key_state() else {
+        return;
+    };
+    let held: Vec<KeyCode> = state
+        .iter()
+        .filter_map(|key| KeyCode::from_evdev_code(key.code()))
+        .collect();
+    if !held.is_empty() {
+        info!(
+            "{} key(s) already down on grab, tracking them: {}",
+            held.len(),
+            keyboard_name
+        );
+        keymap.seed_held_keys(held);
+    }
+}
+
+/// Open and grab an event file again after a transient read error, as long as the
+/// same keyboard is still behind it
+fn reopen_device(path: &std::path::Path, expected: &KeyboardId, grab: bool) -> Result<Device> {
+    let mut device = Device::open(path)?;
+    let found = KeyboardId::from_device(&device, path);
+    if found != *expected {
+        anyhow::bail!("now belongs to {}", found);
+    }
+    set_nonblocking(&device);
+    if grab {
+        device.grab()?;
+    }
+    Ok(device)
+}
+
+/// Bring the keymap up to date with linked keyboards before processing a key.
+/// A press is announced first so their pending MT keys resolve before it is emitted.
+fn pull_shared_state(
+    shared: Option<&mut SharedStateHandle>,
+    keymap: &mut KeymapProcessor,
+    pressed: bool,
+) {
+    let Some(handle) = shared else {
+        return;
+    };
+    if pressed {
+        handle.announce_key_press();
+    }
+    keymap.apply_shared_state(
+        handle.remote_layers(),
+        !handle.remote_modifiers().is_empty(),
+    );
+}
+
+/// Publish this keyboard's modifiers, layers and pending MT keys to linked keyboards
+fn publish_shared_state(shared: Option<&SharedStateHandle>, keymap: &KeymapProcessor) {
+    if let Some(handle) = shared {
+        handle.publish(
+            keymap.held_output_modifiers(),
+            keymap.local_layers(),
+            keymap.has_pending_mt(),
+        );
+    }
+}
+
+/// Where processed events go: the virtual keyboard, or only the debug log when observing
+struct KeyOutput {
+    device: Option<VirtualDevice>,
+    /// Separate device for consumer-control keys, when the source has them
+    consumer: Option<VirtualDevice>,
+    /// System keyboard layout typed text has to be translated for
+    layout: String,
+    /// Text expansion watching what gets typed
+    snippets: Option<SnippetExpander>,
+    /// Counts the key events sent
+    status: Arc<ProcessorStatus>,
+    /// Caps Lock / Num Lock text is typed under
+    locks: LockState,
+    /// Lock keys pressed since the keymap last heard about it
+    lock_toggles: Vec<KeyCode>,
+    /// Keys down on the virtual device, for the next processor if this one dies
+    held: Option<HeldOutput>,
+    /// Pauses between the keys of typed text, when they shouldn't go out at once
+    jitter: Option<TypingJitter>,
+    /// Pauses between the events of one keymap output
+    pacing: OutputPacing,
+}
+
+impl KeyOutput {
+    /// Output for `observe_only`: the device is shared with everyone else and what the
+    /// keymap would type is kept to ourselves
+    fn observing(config: &Config, status: Arc<ProcessorStatus>) -> Self {
+        Self {
+            device: None,
+            consumer: None,
+            layout: String::new(),
+            snippets: SnippetExpander::from_config(config),
+            status,
+            locks: LockState::default(),
+            lock_toggles: Vec::new(),
+            held: None,
+            jitter: TypingJitter::new(config.typing_jitter_ms),
+            pacing: config.output_pacing,
+        }
+    }
+
+    /// Emit events, typing a snippet's expansion ahead of the trigger key that completes it
+    fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()> {
+        // Taken while expanding, so the expansion itself isn't watched
+        let Some(mut snippets) = self.snippets.take() else {
+            return self.write(events);
+        };
+        let mut start = 0;
+        let mut result = Ok(());
+        for (i, event) in events.iter().enumerate() {
+            if event.event_type() != EventType::KEY {
+                continue;
+            }
+            if let Some(expansion) =
+                snippets.on_output(event.code(), event.value(), &self.layout, self.locks)
+            {
+                debug!(
+                    "Expanding snippet ({} chars)",
+                    expansion.text.chars().count()
+                );
+                result = self
+                    .write(&events[start..i])
+                    .and_then(|()| self.type_expansion(&expansion));
+                start = i;
+                if result.is_err() {
+                    break;
+                }
+            }
+        }
+        self.snippets = Some(snippets);
+        result?;
+        self.write(&events[start..])
+    }
+
+    fn type_expansion(&mut self, expansion: &snippet::Expansion) -> std::io::Result<()> {
+        let mut erase = Vec::with_capacity(expansion.erase * 2);
+        for _ in 0..expansion.erase {
+            for value in [1, 0] {
+                erase.push(InputEvent::new(
+                    EventType::KEY,
+                    Key::KEY_BACKSPACE.code(),
+                    value,
+                ));
+                erase.push(InputEvent::new(
+                    EventType::SYNCHRONIZATION,
+                    SYN_CODE,
+                    SYN_REPORT,
+                ));
+            }
+        }
+        self.write(&erase)?;
+        type_string(self, &expansion.text, false).map_err(std::io::Error::other)
+    }
+
+    fn write(&mut self, events: &[InputEvent]) -> std::io::Result<()> {
+        match &mut self.device {
+            Some(device) => {
+                for event in events {
+                    if event.event_type() != EventType::KEY || event.value() != 1 {
+                        continue;
+                    }
+                    if let Some(key @ (KeyCode::KC_CAPS | KeyCode::KC_NLCK)) =
+                        KeyCode::from_evdev_code(event.code())
+                    {
+                        self.locks.on_emitted_press(key);
+                        self.lock_toggles.push(key);
+                    }
+                }
+                if let Some(held) = self.held.as_mut() {
+                    for event in events {
+                        if event.event_type() == EventType::KEY {
+                            held.record(event.code(), event.value());
+                        }
+                    }
+                }
+                let keys = events
+                    .iter()
+                    .filter(|event| event.event_type() == EventType::KEY)
+                    .count();
+                self.status
+                    .events_out
+                    .fetch_add(keys as u64, Ordering::Relaxed);
+                match &mut self.consumer {
+                    Some(consumer) => emit_split(device, consumer, events),
+                    None => device.emit(events),
+                }
+            }
+            None => {
+                for event in events {
+                    if event.event_type() == EventType::KEY {
+                        debug!("Observe only, not emitting: {:?}", event);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Send consumer-control keys to their own device and the rest to the keyboard,
+/// in the order they come
+fn emit_split(
+    keyboard: &mut VirtualDevice,
+    consumer: &mut VirtualDevice,
+    events: &[InputEvent],
+) -> std::io::Result<()> {
+    let mut start = 0;
+    let mut on_consumer = false;
+    for (i, event) in events.iter().enumerate() {
+        if event.event_type() != EventType::KEY {
+            continue;
+        }
+        let consumer_key = CONSUMER_KEYS.contains(&Key::new(event.code()));
+        if consumer_key != on_consumer {
+            let run = &events[start..i];
+            if !run.is_empty() {
+                if on_consumer {
+                    consumer.emit(run)?;
+                } else {
+                    keyboard.emit(run)?;
+                }
+            }
+            start = i;
+            on_consumer = consumer_key;
         }
     }
+    let run = &events[start..];
+    if run.is_empty() {
+        return Ok(());
+    }
+    if on_consumer {
+        consumer.emit(run)
+    } else {
+        keyboard.emit(run)
+    }
+}
+
+/// Emit the output of a keymap decision on the virtual device
+fn emit_process_result(virtual_device: &mut KeyOutput, result: ProcessResult) -> Result<()> {
+    match result {
+        ProcessResult::EmitKey(output_key, output_pressed) => {
+            // Convert back to evdev and emit
+            let output_evdev = Key::new(output_key.code());
+            let output_event = InputEvent::new_now(
+                EventType::KEY,
+                output_evdev.code(),
+                i32::from(output_pressed),
+            );
+            virtual_device.emit(&[output_event])?;
+        }
+        ProcessResult::TypeString(text, add_enter) => {
+            // Type out the string character by character
+            type_string(virtual_device, &text, add_enter)?;
+        }
+        ProcessResult::TapKeyPressRelease(tap_key) => {
+            let hold_ms = virtual_device.pacing.tap_hold_ms;
+            emit_paced(
+                virtual_device,
+                &[(tap_key, true), (tap_key, false)],
+                hold_ms,
+            )?;
+        }
+        ProcessResult::MultipleEvents(events) => {
+            let gap_ms = virtual_device.pacing.event_gap_ms;
+            emit_paced(virtual_device, &events, gap_ms)?;
+        }
+        ProcessResult::None => {
+            // Don't emit anything (consumed by layer switch, etc.)
+        }
+    }
+    Ok(())
+}
+
+/// Emit key events in sequence with `pause_ms` between them, or back to back when it's
+/// 0 (each event still in its own report, so a tap isn't merged into nothing; the
+/// device's emit ends each one with its SYN_REPORT)
+fn emit_paced(
+    virtual_device: &mut KeyOutput,
+    keys: &[(KeyCode, bool)],
+    pause_ms: u32,
+) -> Result<()> {
+    let pause = std::time::Duration::from_millis(u64::from(pause_ms));
+    for (i, &(key, pressed)) in keys.iter().enumerate() {
+        if i > 0 && !pause.is_zero() {
+            std::thread::sleep(pause);
+        }
+        virtual_device.emit(&[InputEvent::new_now(
+            EventType::KEY,
+            Key::new(key.code()).code(),
+            i32::from(pressed),
+        )])?;
+    }
+    Ok(())
+}
+
+/// Create a virtual uinput device that mimics the physical keyboard
+fn create_virtual_device(
+    physical_device: &Device,
+    keyboard_name: &str,
+    config: &Config,
+) -> Result<VirtualDevice> {
+    let mut keys = virtual_device_keys(physical_device, config);
+    if has_consumer_keys(physical_device, config) {
+        keys = keys
+            .iter()
+            .filter(|key| !CONSUMER_KEYS.contains(key))
+            .collect();
+    }
+    build_virtual_device(keyboard_name, "", physical_device, config, &keys)
+}
+
+/// Create the `<name> Consumer Control` device for the source's media and browser
+/// keys, if it has any
+///
+/// Real keyboards send these from a separate HID interface, and desktops route media
+/// keys by the device they come from, so they go out the same way.
+fn create_consumer_device(
+    physical_device: &Device,
+    keyboard_name: &str,
+    config: &Config,
+) -> Result<Option<VirtualDevice>> {
+    if !has_consumer_keys(physical_device, config) {
+        return Ok(None);
+    }
+    // All of them, so remaps onto a media key the source lacks still have somewhere to go
+    let keys: AttributeSet<Key> = CONSUMER_KEYS.iter().copied().collect();
+    build_virtual_device(
+        keyboard_name,
+        " Consumer Control",
+        physical_device,
+        config,
+        &keys,
+    )
+    .map(Some)
+}
+
+/// Whether the source sends any consumer-control key and the config splits them off
+fn has_consumer_keys(physical_device: &Device, config: &Config) -> bool {
+    config.split_consumer_control
+        && physical_device
+            .supported_keys()
+            .is_some_and(|keys| CONSUMER_KEYS.iter().any(|key| keys.contains(*key)))
+}
+
+fn build_virtual_device(
+    keyboard_name: &str,
+    suffix: &str,
+    physical_device: &Device,
+    config: &Config,
+    keys: &AttributeSet<Key>,
+) -> Result<VirtualDevice> {
+    let input_id = virtual_device_id(physical_device, config);
 
     let try_build = |name: &str| -> Result<VirtualDevice> {
         Ok(VirtualDeviceBuilder::new()?
             .name(name)
-            .with_keys(&keys)?
+            .input_id(input_id.clone())
+            .with_keys(keys)?
             .build()?)
     };
 
     // Try full name first, fall back to truncated if it fails (kernel name limit is 80 bytes)
-    let full_name = format!("keymux: {keyboard_name}");
+    let full_name = format!("keymux: {keyboard_name}{suffix}");
     try_build(&full_name).or_else(|_| {
-        let max_name_len = 80 - "keymux: ".len();
+        let max_name_len = 80 - "keymux: ".len() - suffix.len();
         let truncated = if keyboard_name.len() > max_name_len {
-            format!("keymux: {}...", &keyboard_name[..max_name_len])
+            format!("keymux: {}...{suffix}", &keyboard_name[..max_name_len])
         } else {
             full_name
         };
@@ -303,86 +1356,131 @@ fn create_virtual_device(physical_device: &Device, keyboard_name: &str) -> Resul
     })
 }
 
-/// Release all keys on startup (before keymap exists) to fix hotplug stuck keys
-fn release_all_keys_on_startup(virtual_device: &mut VirtualDevice) {
-    use evdev::InputEvent;
+/// Keys that arrive on a keyboard's HID consumer-control interface (usage page 0x0C)
+/// rather than the keyboard interface itself
+const CONSUMER_KEYS: &[Key] = &[
+    Key::KEY_MUTE,
+    Key::KEY_VOLUMEDOWN,
+    Key::KEY_VOLUMEUP,
+    Key::KEY_PLAYPAUSE,
+    Key::KEY_PLAYCD,
+    Key::KEY_PAUSECD,
+    Key::KEY_STOPCD,
+    Key::KEY_NEXTSONG,
+    Key::KEY_PREVIOUSSONG,
+    Key::KEY_FASTFORWARD,
+    Key::KEY_REWIND,
+    Key::KEY_RECORD,
+    Key::KEY_EJECTCD,
+    Key::KEY_BRIGHTNESSUP,
+    Key::KEY_BRIGHTNESSDOWN,
+    Key::KEY_KBDILLUMUP,
+    Key::KEY_KBDILLUMDOWN,
+    Key::KEY_KBDILLUMTOGGLE,
+    Key::KEY_CALC,
+    Key::KEY_MAIL,
+    Key::KEY_COMPUTER,
+    Key::KEY_SEARCH,
+    Key::KEY_HOMEPAGE,
+    Key::KEY_WWW,
+    Key::KEY_BOOKMARKS,
+    Key::KEY_BACK,
+    Key::KEY_FORWARD,
+    Key::KEY_REFRESH,
+    Key::KEY_MEDIA,
+    Key::KEY_CONFIG,
+];
 
-    // Release all modifiers (most critical for stuck keys)
-    let modifiers = [
-        Key::KEY_LEFTCTRL,
-        Key::KEY_RIGHTCTRL,
-        Key::KEY_LEFTSHIFT,
-        Key::KEY_RIGHTSHIFT,
-        Key::KEY_LEFTALT,
-        Key::KEY_RIGHTALT,
-        Key::KEY_LEFTMETA,
-        Key::KEY_RIGHTMETA,
-    ];
+/// Input ID of the virtual device: the uinput default vendor:product (so other tools
+/// can tell it's a remapper output) on USB, or on the source's bus if configured
+fn virtual_device_id(physical_device: &Device, config: &Config) -> InputId {
+    let bus_type = if config.copy_source_bustype {
+        physical_device.input_id().bus_type()
+    } else {
+        BusType::BUS_USB
+    };
+    InputId::new(bus_type, 0x1234, 0x5678, 0x111)
+}
 
-    for key in &modifiers {
-        let event = InputEvent::new_now(EventType::KEY, key.code(), 0);
-        let _ = virtual_device.emit(&[event]);
+/// Keys the virtual device advertises: what the board passes through plus what the
+/// config (and `keymux type`) can emit, so compositors see the keyboard it acts as
+fn virtual_device_keys(physical_device: &Device, config: &Config) -> AttributeSet<Key> {
+    let mut keys = AttributeSet::<Key>::new();
+
+    // Physical keys pass through unless the base layer remaps them away; scripts
+    // may emit anything, so keep everything when there are any
+    let keep_all = config.has_scripts();
+    if let Some(physical_keys) = physical_device.supported_keys() {
+        for key in physical_keys {
+            let remapped = KeyCode::from_evdev_code(key.code())
+                .is_some_and(|keycode| config.remaps.contains_key(&keycode));
+            if keep_all || !remapped {
+                keys.insert(key);
+            }
+        }
     }
 
-    // Release all letter keys (common for WASD/typing)
-    let letters = [
-        Key::KEY_A,
-        Key::KEY_B,
-        Key::KEY_C,
-        Key::KEY_D,
-        Key::KEY_E,
-        Key::KEY_F,
-        Key::KEY_G,
-        Key::KEY_H,
-        Key::KEY_I,
-        Key::KEY_J,
-        Key::KEY_K,
-        Key::KEY_L,
-        Key::KEY_M,
-        Key::KEY_N,
-        Key::KEY_O,
-        Key::KEY_P,
-        Key::KEY_Q,
-        Key::KEY_R,
-        Key::KEY_S,
-        Key::KEY_T,
-        Key::KEY_U,
-        Key::KEY_V,
-        Key::KEY_W,
-        Key::KEY_X,
-        Key::KEY_Y,
-        Key::KEY_Z,
-    ];
+    for keycode in config
+        .output_keys()
+        .into_iter()
+        .chain(config.scancodes.values().copied())
+    {
+        keys.insert(Key::new(keycode.code()));
+    }
 
-    for key in &letters {
-        let event = InputEvent::new_now(EventType::KEY, key.code(), 0);
-        let _ = virtual_device.emit(&[event]);
+    // Everything type_string can produce, in any layout it knows
+    for ch in ' '..='~' {
+        for layout in ["us", "de"] {
+            if let (Some(key), _) = char_to_key_for(layout, ch) {
+                keys.insert(key);
+            }
+        }
     }
+    keys.insert(Key::KEY_LEFTSHIFT);
+    keys.insert(Key::KEY_ENTER);
+    // Snippet expansion erases the abbreviation
+    keys.insert(Key::KEY_BACKSPACE);
 
-    // Release common navigation/control keys
-    let nav_keys = [
-        Key::KEY_UP,
-        Key::KEY_DOWN,
-        Key::KEY_LEFT,
-        Key::KEY_RIGHT,
-        Key::KEY_SPACE,
-        Key::KEY_ENTER,
-        Key::KEY_TAB,
-        Key::KEY_ESC,
-    ];
+    keys
+}
 
-    for key in &nav_keys {
-        let event = InputEvent::new_now(EventType::KEY, key.code(), 0);
-        let _ = virtual_device.emit(&[event]);
+/// Release the keys a processor that didn't stop cleanly left down on this node, and
+/// those the keyboard has down now, as far as the virtual device can send them
+fn release_on_startup(
+    virtual_device: &mut KeyOutput,
+    physical_device: &Device,
+    config: &Config,
+    leftover: &[u16],
+    keyboard_name: &str,
+) {
+    let sendable = virtual_device_keys(physical_device, config);
+    let mut keys: BTreeSet<u16> = leftover.iter().copied().collect();
+    if let Ok(down) = physical_device.get_key_state() {
+        keys.extend(down.iter().map(|key| key.code()));
     }
-
-    // Send final SYN_REPORT
-    let syn_event = InputEvent::new_now(EventType::SYNCHRONIZATION, SYN_CODE, SYN_REPORT);
-    let _ = virtual_device.emit(&[syn_event]);
+    keys.retain(|code| sendable.contains(Key::new(*code)));
+    if keys.is_empty() {
+        return;
+    }
+    info!(
+        "Releasing {} key(s) left down on startup: {}",
+        keys.len(),
+        keyboard_name
+    );
+    let mut events: Vec<InputEvent> = keys
+        .iter()
+        .map(|code| InputEvent::new_now(EventType::KEY, *code, 0))
+        .collect();
+    events.push(InputEvent::new_now(
+        EventType::SYNCHRONIZATION,
+        SYN_CODE,
+        SYN_REPORT,
+    ));
+    let _ = virtual_device.emit(&events);
 }
 
 /// Release all potentially held keys before shutdown
-fn release_all_keys(virtual_device: &mut VirtualDevice, keymap: &KeymapProcessor) {
+fn release_all_keys(virtual_device: &mut KeyOutput, keymap: &KeymapProcessor) {
     use evdev::InputEvent;
 
     // Get all keys that the keymap thinks are held
@@ -430,66 +1528,114 @@ fn release_all_keys(virtual_device: &mut VirtualDevice, keymap: &KeymapProcessor
 
 /// Type a string by emitting key events for each character
 /// Batches all events with SYN events into a single emit for INSTANT typing
-fn type_string(virtual_device: &mut VirtualDevice, text: &str, _add_enter: bool) -> Result<()> {
-    let mut events = Vec::with_capacity(text.len() * 8); // Pre-allocate for speed
+fn type_string(virtual_device: &mut KeyOutput, text: &str, add_enter: bool) -> Result<()> {
+    let keys = typed_keys(
+        &virtual_device.layout,
+        virtual_device.locks,
+        text,
+        add_enter,
+    );
+    let mut events = Vec::with_capacity(keys.len() * 2);
+    for (key, pressed) in keys {
+        events.push(InputEvent::new(
+            EventType::KEY,
+            key.code(),
+            i32::from(pressed),
+        ));
+        events.push(InputEvent::new(
+            EventType::SYNCHRONIZATION,
+            SYN_CODE,
+            SYN_REPORT,
+        ));
+    }
 
-    for ch in text.chars() {
-        let (key, needs_shift) = char_to_key(ch);
+    if let Some(mut jitter) = virtual_device.jitter.take() {
+        // One key event (and its report) at a time, with a random pause after each
+        let result = events.chunks(2).try_for_each(|event| {
+            virtual_device.emit(event)?;
+            std::thread::sleep(jitter.next_pause());
+            Ok::<_, std::io::Error>(())
+        });
+        virtual_device.jitter = Some(jitter);
+        return Ok(result?);
+    }
 
-        if let Some(key) = key {
-            // Press shift if needed
-            if needs_shift {
-                events.push(InputEvent::new(
-                    EventType::KEY,
-                    Key::KEY_LEFTSHIFT.code(),
-                    1,
-                ));
-                events.push(InputEvent::new(
-                    EventType::SYNCHRONIZATION,
-                    SYN_CODE,
-                    SYN_REPORT,
-                ));
-            }
+    // Emit ALL events at once - INSTANT like paste!
+    virtual_device.emit(&events)?;
 
-            // Press key
-            events.push(InputEvent::new(EventType::KEY, key.code(), 1));
-            events.push(InputEvent::new(
-                EventType::SYNCHRONIZATION,
-                SYN_CODE,
-                SYN_REPORT,
-            ));
+    Ok(())
+}
 
-            // Release key
-            events.push(InputEvent::new(EventType::KEY, key.code(), 0));
-            events.push(InputEvent::new(
-                EventType::SYNCHRONIZATION,
-                SYN_CODE,
-                SYN_REPORT,
-            ));
+/// Key presses and releases that type `text` in a layout, with Shift around the
+/// characters that need it
+fn typed_keys(layout: &str, locks: LockState, text: &str, add_enter: bool) -> Vec<(Key, bool)> {
+    let mut keys = Vec::with_capacity(text.len() * 4); // Pre-allocate for speed
+    for ch in text.chars() {
+        let (key, needs_shift) = char_to_key_for(layout, ch);
+        // With Caps Lock on, shift makes letters lowercase
+        let needs_shift = locks.shift_for(ch, needs_shift);
 
-            // Release shift if needed
-            if needs_shift {
-                events.push(InputEvent::new(
-                    EventType::KEY,
-                    Key::KEY_LEFTSHIFT.code(),
-                    0,
-                ));
-                events.push(InputEvent::new(
-                    EventType::SYNCHRONIZATION,
-                    SYN_CODE,
-                    SYN_REPORT,
-                ));
-            }
+        if let Some(key) = key {
+            let shift: &[Key] = if needs_shift {
+                &[Key::KEY_LEFTSHIFT]
+            } else {
+                &[]
+            };
+            keys.extend(ordering::wrap(shift, vec![(key, true), (key, false)]));
         }
     }
+    if add_enter {
+        keys.extend([(Key::KEY_ENTER, true), (Key::KEY_ENTER, false)]);
+    }
+    keys
+}
 
-    // Emit ALL events at once - INSTANT like paste!
-    virtual_device.emit(&events)?;
+/// Convert a character to an evdev Key and whether shift is needed, for a system layout
+///
+/// Layouts other than German are typed as US.
+fn char_to_key_for(layout: &str, ch: char) -> (Option<Key>, bool) {
+    if layout == "de" || layout.starts_with("de(") {
+        char_to_key_de(ch)
+    } else {
+        char_to_key(ch)
+    }
+}
 
-    Ok(())
+/// German QWERTZ; characters behind AltGr or dead keys can't be typed
+const fn char_to_key_de(ch: char) -> (Option<Key>, bool) {
+    match ch {
+        'y' => (Some(Key::KEY_Z), false),
+        'z' => (Some(Key::KEY_Y), false),
+        'Y' => (Some(Key::KEY_Z), true),
+        'Z' => (Some(Key::KEY_Y), true),
+        'a'..='z' | 'A'..='Z' | '0'..='9' | ' ' | ',' | '.' => char_to_key(ch),
+
+        '!' => (Some(Key::KEY_1), true),
+        '"' => (Some(Key::KEY_2), true),
+        '$' => (Some(Key::KEY_4), true),
+        '%' => (Some(Key::KEY_5), true),
+        '&' => (Some(Key::KEY_6), true),
+        '/' => (Some(Key::KEY_7), true),
+        '(' => (Some(Key::KEY_8), true),
+        ')' => (Some(Key::KEY_9), true),
+        '=' => (Some(Key::KEY_0), true),
+        '?' => (Some(Key::KEY_MINUS), true),
+        '+' => (Some(Key::KEY_RIGHTBRACE), false),
+        '*' => (Some(Key::KEY_RIGHTBRACE), true),
+        '#' => (Some(Key::KEY_BACKSLASH), false),
+        '\'' => (Some(Key::KEY_BACKSLASH), true),
+        '<' => (Some(Key::KEY_102ND), false),
+        '>' => (Some(Key::KEY_102ND), true),
+        ';' => (Some(Key::KEY_COMMA), true),
+        ':' => (Some(Key::KEY_DOT), true),
+        '-' => (Some(Key::KEY_SLASH), false),
+        '_' => (Some(Key::KEY_SLASH), true),
+
+        _ => (None, false),
+    }
 }
 
-/// Convert a character to an evdev Key and whether shift is needed
+/// Convert a character to an evdev Key and whether shift is needed (US layout)
 const fn char_to_key(ch: char) -> (Option<Key>, bool) {
     match ch {
         'a' => (Some(Key::KEY_A), false),
@@ -595,3 +1741,29 @@ const fn char_to_key(ch: char) -> (Option<Key>, bool) {
         _ => (None, false),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_only_output_sends_nothing() {
+        let config = Config::parse("(observe_only: true)").unwrap();
+        let status = Arc::new(ProcessorStatus::default());
+        let mut output = KeyOutput::observing(&config, Arc::clone(&status));
+
+        let result = ProcResult::MultipleEvents(vec![
+            (KeyCode::KC_CAPS, true),
+            (KeyCode::KC_CAPS, false),
+            (KeyCode::KC_A, true),
+            (KeyCode::KC_A, false),
+        ]);
+        emit_process_result(&mut output, result).unwrap();
+        type_string(&mut output, "hi", true).unwrap();
+
+        // Nothing went out, so Caps Lock wasn't toggled either
+        assert_eq!(status.events_out.load(Ordering::Relaxed), 0);
+        assert!(!output.locks.caps_lock);
+        assert!(output.lock_toggles.is_empty());
+    }
+}
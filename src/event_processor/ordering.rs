@@ -0,0 +1,90 @@
+//! Ordering of modifiers around the keys they modify
+//!
+//! A modifier has to go down before the key it applies to and come up only after that
+//! key is released, or the compositor sees a bare key (or a stray shifted one). Every
+//! place that puts modifiers around keys builds its events with these helpers instead
+//! of pushing presses and releases by hand: a layer's `with_mods`, MT holds resolved by
+//! the next key press, and text typed with Shift.
+
+#[cfg(test)]
+use crate::keycode::KeyCode;
+
+/// Press `mods` (in order) ahead of `keys`, leaving them down
+pub fn press_before<K: Copy>(mods: &[K], keys: Vec<(K, bool)>) -> Vec<(K, bool)> {
+    let mut events: Vec<(K, bool)> = mods.iter().map(|m| (*m, true)).collect();
+    events.extend(keys);
+    events
+}
+
+/// Release `mods` after `keys`, the last pressed first
+pub fn release_after<K: Copy>(mut keys: Vec<(K, bool)>, mods: &[K]) -> Vec<(K, bool)> {
+    keys.extend(mods.iter().rev().map(|m| (*m, false)));
+    keys
+}
+
+/// Press `key` after the events a press settles, like MT keys it resolved to hold,
+/// so their modifiers are already down when it arrives
+pub fn press_after_settled<K: Copy>(mut settled: Vec<(K, bool)>, key: K) -> Vec<(K, bool)> {
+    settled.push((key, true));
+    settled
+}
+
+/// `keys` with `mods` held down around them
+pub fn wrap<K: Copy>(mods: &[K], keys: Vec<(K, bool)>) -> Vec<(K, bool)> {
+    release_after(press_before(mods, keys), mods)
+}
+
+/// Whether no modifier goes up while a key pressed under it is still down
+#[cfg(test)]
+pub fn mods_wrap_keys(events: &[(KeyCode, bool)]) -> bool {
+    let mut mods_down: Vec<KeyCode> = Vec::new();
+    // Keys still down, with the modifiers that were down when each was pressed
+    let mut keys_down: Vec<(KeyCode, Vec<KeyCode>)> = Vec::new();
+    for &(key, pressed) in events {
+        match (key.is_modifier(), pressed) {
+            (true, true) => mods_down.push(key),
+            (true, false) => {
+                if keys_down.iter().any(|(_, under)| under.contains(&key)) {
+                    return false;
+                }
+                mods_down.retain(|m| *m != key);
+            }
+            (false, true) => keys_down.push((key, mods_down.clone())),
+            (false, false) => keys_down.retain(|(down, _)| *down != key),
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use KeyCode::{KC_A, KC_LCTL, KC_LSFT};
+
+    #[test]
+    fn test_mods_wrap_their_keys() {
+        let tap = vec![(KC_A, true), (KC_A, false)];
+        let events = wrap(&[KC_LCTL, KC_LSFT], tap.clone());
+        assert_eq!(
+            events,
+            vec![
+                (KC_LCTL, true),
+                (KC_LSFT, true),
+                (KC_A, true),
+                (KC_A, false),
+                (KC_LSFT, false),
+                (KC_LCTL, false),
+            ]
+        );
+        assert!(mods_wrap_keys(&events));
+        assert_eq!(wrap(&[], tap.clone()), tap);
+
+        // Shift let go before the key it shifted
+        assert!(!mods_wrap_keys(&[
+            (KC_LSFT, true),
+            (KC_A, true),
+            (KC_LSFT, false),
+            (KC_A, false),
+        ]));
+    }
+}
@@ -0,0 +1,103 @@
+/// Ordered key-sequence triggers, checked on every physical key press
+///
+/// Keeps the last few presses and their times. When they end with a rule's keys and
+/// the first of those came within the rule's timeout, the rule's action fires and the
+/// history starts over, so "Q Q Q" triggers a "Q Q" rule only once.
+use crate::config::{Config, KeyAction, SequenceRule};
+use crate::keycode::KeyCode;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+pub struct SequenceMatcher {
+    /// Rules with their timeouts, longest sequences first
+    rules: Vec<(SequenceRule, Duration)>,
+    history: VecDeque<(KeyCode, Instant)>,
+    /// Presses worth remembering (the longest sequence)
+    capacity: usize,
+}
+
+impl SequenceMatcher {
+    pub fn new(config: &Config) -> Self {
+        let mut rules: Vec<(SequenceRule, Duration)> = config
+            .sequences
+            .iter()
+            .filter(|rule| !rule.keys.is_empty())
+            .map(|rule| {
+                (
+                    rule.clone(),
+                    Duration::from_millis(u64::from(rule.timeout_ms)),
+                )
+            })
+            .collect();
+        // Stable sort keeps config order among sequences of the same length
+        rules.sort_by_key(|(rule, _)| std::cmp::Reverse(rule.keys.len()));
+        let capacity = rules.first().map_or(0, |(rule, _)| rule.keys.len());
+        Self {
+            rules,
+            history: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record a press, returning the action of a sequence it completes
+    pub fn on_press(&mut self, key: KeyCode, now: Instant) -> Option<KeyAction> {
+        if self.capacity == 0 {
+            return None;
+        }
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back((key, now));
+
+        let action = self.rules.iter().find_map(|(rule, timeout)| {
+            let start = self.history.len().checked_sub(rule.keys.len())?;
+            let typed = self.history.range(start..);
+            let in_order = typed.clone().map(|(key, _)| key).eq(rule.keys.iter());
+            let (_, first_at) = self.history[start];
+            (in_order && now.duration_since(first_at) <= *timeout).then(|| rule.action.clone())
+        })?;
+        self.history.clear();
+        Some(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_matches_in_order_within_timeout() {
+        let config = Config::parse(
+            r#"(
+                sequences: [
+                    (keys: [KC_Q, KC_Q], action: KC_F13, timeout_ms: 300),
+                    (keys: [KC_J, KC_Q, KC_Q], action: KC_F14),
+                ],
+            )"#,
+        )
+        .unwrap();
+        let mut matcher = SequenceMatcher::new(&config);
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+
+        assert_eq!(matcher.on_press(KeyCode::KC_Q, start), None);
+        assert_eq!(
+            matcher.on_press(KeyCode::KC_Q, ms(100)),
+            Some(KeyAction::Key(KeyCode::KC_F13))
+        );
+        // History starts over after a match
+        assert_eq!(matcher.on_press(KeyCode::KC_Q, ms(150)), None);
+
+        // Too slow
+        assert_eq!(matcher.on_press(KeyCode::KC_Q, ms(1000)), None);
+        assert_eq!(matcher.on_press(KeyCode::KC_Q, ms(1400)), None);
+
+        // The longer sequence wins
+        matcher.on_press(KeyCode::KC_J, ms(2000));
+        matcher.on_press(KeyCode::KC_Q, ms(2050));
+        assert_eq!(
+            matcher.on_press(KeyCode::KC_Q, ms(2100)),
+            Some(KeyAction::Key(KeyCode::KC_F14))
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Shared state bus for linking a user's keyboards
+//!
+//! Processors are isolated per event file, so by default a modifier or layer
+//! held on one board is invisible to another. When `share_state_across_keyboards`
+//! is enabled the daemon hands every processor owned by the same user one
+//! `SharedKeyboardState` (or one per `keyboard_groups` group otherwise), through
+//! which they publish:
+//!
+//! - held output modifiers (so MT cross-hand unwrap sees Shift held on a macropad)
+//! - active non-base layers (so MO on one board switches layers on the others)
+//! - key presses and pending MT keys (so a press on one board resolves MT on another
+//!   to hold *before* that press is emitted)
+
+use crate::config::Layer;
+use crate::keycode::KeyCode;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::Duration;
+
+/// How long a key press waits for another board's pending MT keys to resolve
+const MT_SYNC_TIMEOUT: Duration = Duration::from_millis(5);
+
+/// What one processor currently contributes to the shared state
+#[derive(Debug, Clone, Default)]
+struct SourceState {
+    modifiers: HashSet<KeyCode>,
+    layers: Vec<Layer>,
+    undecided_mt: bool,
+    last_press_seq: u64,
+}
+
+/// Per-user state shared between that user's processors
+#[derive(Debug, Default)]
+pub struct SharedKeyboardState {
+    sources: Mutex<HashMap<u64, SourceState>>,
+    /// Signalled whenever a source publishes or leaves, for presses waiting on MT keys
+    changed: Condvar,
+    next_source: AtomicU64,
+    press_seq: AtomicU64,
+}
+
+impl SharedKeyboardState {
+    /// Join the bus; the returned handle leaves it again when dropped
+    pub fn register(self: &Arc<Self>) -> SharedStateHandle {
+        let source = self.next_source.fetch_add(1, Ordering::Relaxed);
+        self.lock().insert(source, SourceState::default());
+        SharedStateHandle {
+            state: Arc::clone(self),
+            source,
+            seen_press_seq: self.press_seq.load(Ordering::Relaxed),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, SourceState>> {
+        // A panicking processor must not take every other board down with it
+        self.sources.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// One processor's membership in a `SharedKeyboardState`
+#[derive(Debug)]
+pub struct SharedStateHandle {
+    state: Arc<SharedKeyboardState>,
+    source: u64,
+    seen_press_seq: u64,
+}
+
+impl SharedStateHandle {
+    /// Publish this processor's held modifiers, layers and pending-MT status
+    pub fn publish(&self, modifiers: HashSet<KeyCode>, layers: Vec<Layer>, undecided_mt: bool) {
+        if let Some(own) = self.state.lock().get_mut(&self.source) {
+            own.modifiers = modifiers;
+            own.layers = layers;
+            own.undecided_mt = undecided_mt;
+        }
+        self.state.changed.notify_all();
+    }
+
+    /// Modifiers held by the user's other keyboards
+    pub fn remote_modifiers(&self) -> HashSet<KeyCode> {
+        self.state
+            .lock()
+            .iter()
+            .filter(|(source, _)| **source != self.source)
+            .flat_map(|(_, s)| s.modifiers.iter().copied())
+            .collect()
+    }
+
+    /// Non-base layers active on the user's other keyboards
+    pub fn remote_layers(&self) -> Vec<Layer> {
+        let mut layers: Vec<Layer> = Vec::new();
+        for (_, s) in self
+            .state
+            .lock()
+            .iter()
+            .filter(|(source, _)| **source != self.source)
+        {
+            for layer in &s.layers {
+                if !layers.contains(layer) {
+                    layers.push(layer.clone());
+                }
+            }
+        }
+        layers
+    }
+
+    /// Announce a key press, then give other boards' pending MT keys a moment to
+    /// resolve so their modifiers reach the compositor before this key does
+    ///
+    /// Returns right away unless another board has an MT key pending, and then as soon
+    /// as that board publishes its decision.
+    pub fn announce_key_press(&mut self) {
+        let seq = self.state.press_seq.fetch_add(1, Ordering::AcqRel) + 1;
+        self.seen_press_seq = seq;
+        let mut sources = self.state.lock();
+        if let Some(own) = sources.get_mut(&self.source) {
+            own.last_press_seq = seq;
+        }
+        let others_pending = |sources: &mut HashMap<u64, SourceState>| {
+            sources
+                .iter()
+                .any(|(source, s)| *source != self.source && s.undecided_mt)
+        };
+        if !others_pending(&mut sources) {
+            return;
+        }
+        let _ = self
+            .state
+            .changed
+            .wait_timeout_while(sources, MT_SYNC_TIMEOUT, others_pending)
+            .unwrap_or_else(PoisonError::into_inner);
+    }
+
+    /// Whether another keyboard pressed a key since the last call
+    pub fn take_remote_key_press(&mut self) -> bool {
+        let current = self.state.press_seq.load(Ordering::Acquire);
+        if current == self.seen_press_seq {
+            return false;
+        }
+        let newest_remote = self
+            .state
+            .lock()
+            .iter()
+            .filter(|(source, _)| **source != self.source)
+            .map(|(_, s)| s.last_press_seq)
+            .max()
+            .unwrap_or(0);
+        let pressed = newest_remote > self.seen_press_seq;
+        self.seen_press_seq = current;
+        pressed
+    }
+}
+
+impl Drop for SharedStateHandle {
+    fn drop(&mut self) {
+        self.state.lock().remove(&self.source);
+        self.state.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_state_between_sources() {
+        let state = Arc::new(SharedKeyboardState::default());
+        let mut main = state.register();
+        let mut pad = state.register();
+
+        pad.publish(
+            HashSet::from([KeyCode::KC_LSFT]),
+            vec![Layer::new("nav")],
+            false,
+        );
+        assert!(main.remote_modifiers().contains(&KeyCode::KC_LSFT));
+        assert_eq!(main.remote_layers(), vec![Layer::new("nav")]);
+        assert!(pad.remote_modifiers().is_empty());
+
+        // Own presses don't count as remote ones
+        main.announce_key_press();
+        assert!(!main.take_remote_key_press());
+        assert!(pad.take_remote_key_press());
+        assert!(!pad.take_remote_key_press());
+
+        drop(pad);
+        assert!(main.remote_modifiers().is_empty());
+        assert!(main.remote_layers().is_empty());
+    }
+}
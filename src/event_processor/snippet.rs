@@ -0,0 +1,221 @@
+/// Text expansion on the output side: watches what the virtual keyboard types
+///
+/// The word being typed is rebuilt from emitted key events (so remaps and layers are
+/// already applied). When a trigger key is pressed right after an abbreviation, the
+/// output erases the abbreviation with backspaces and types the expansion before
+/// letting the trigger key through. Shortcuts, navigation keys and anything that isn't
+/// text start a new word.
+use super::char_to_key_for;
+use super::lock_state::LockState;
+use crate::config::Config;
+use crate::keycode::KeyCode;
+use evdev::Key;
+use std::collections::HashMap;
+
+pub struct SnippetExpander {
+    expansions: HashMap<String, String>,
+    triggers: Vec<u16>,
+    /// Turned off for apps listed in `snippets.disabled_apps` (set by the daemon)
+    enabled: bool,
+    word: String,
+    /// Left and right shift apart, so letting go of one doesn't cancel the other
+    shift_held: [bool; 2],
+    /// Ctrl, Alt or Super held: keys are shortcuts, not text
+    shortcut_mods: usize,
+}
+
+/// What to type in place of an abbreviation
+#[derive(Debug, PartialEq, Eq)]
+pub struct Expansion {
+    /// Characters to erase first
+    pub erase: usize,
+    pub text: String,
+}
+
+impl SnippetExpander {
+    /// Expander for a keyboard's config, or None without snippets
+    pub fn from_config(config: &Config) -> Option<Self> {
+        if config.snippets.expansions.is_empty() {
+            return None;
+        }
+        Some(Self {
+            expansions: config.snippets.expansions.clone(),
+            triggers: config.snippets.triggers.iter().map(|k| k.code()).collect(),
+            enabled: config.snippets.enabled_for(None),
+            word: String::new(),
+            shift_held: [false; 2],
+            shortcut_mods: 0,
+        })
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.word.clear();
+    }
+
+    /// Character a key types in a layout, if any
+    fn key_char(layout: &str, code: u16, shift: bool) -> Option<char> {
+        (' '..='~').find(|ch| char_to_key_for(layout, *ch) == (Some(Key::new(code)), shift))
+    }
+
+    /// Character a key types with shift held or not, under Caps Lock if it's on
+    fn typed_char(layout: &str, code: u16, shift: bool, locks: LockState) -> Option<char> {
+        let ch = Self::key_char(layout, code, shift)?;
+        if locks.caps_lock && ch.is_alphabetic() {
+            return Self::key_char(layout, code, !shift);
+        }
+        Some(ch)
+    }
+
+    /// Track an emitted key event (value 1 = press, 0 = release, 2 = repeat) typed
+    /// under `locks`
+    pub fn on_output(
+        &mut self,
+        code: u16,
+        value: i32,
+        layout: &str,
+        locks: LockState,
+    ) -> Option<Expansion> {
+        let key = KeyCode::from_evdev_code(code);
+        if key.is_some_and(KeyCode::is_modifier) {
+            let pressed = value != 0;
+            if key == Some(KeyCode::KC_LSFT) {
+                self.shift_held[0] = pressed;
+            } else if key == Some(KeyCode::KC_RSFT) {
+                self.shift_held[1] = pressed;
+            } else if value != 2 {
+                self.shortcut_mods = if pressed {
+                    self.shortcut_mods + 1
+                } else {
+                    self.shortcut_mods.saturating_sub(1)
+                };
+            }
+            return None;
+        }
+        if value == 0 {
+            return None;
+        }
+        if !self.enabled || self.shortcut_mods > 0 {
+            self.word.clear();
+            return None;
+        }
+
+        if self.triggers.contains(&code) && value == 1 {
+            let word = std::mem::take(&mut self.word);
+            return self.expansions.get(&word).map(|text| Expansion {
+                erase: word.chars().count(),
+                text: text.clone(),
+            });
+        }
+        if code == Key::KEY_BACKSPACE.code() {
+            self.word.pop();
+            return None;
+        }
+        let shift = self.shift_held.contains(&true);
+        match Self::typed_char(layout, code, shift, locks) {
+            Some(ch) if ch != ' ' => self.word.push(ch),
+            _ => self.word.clear(),
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tap(key: Key, snippets: &mut SnippetExpander, locks: LockState) -> Option<Expansion> {
+        let expansion = snippets.on_output(key.code(), 1, "us", locks);
+        snippets.on_output(key.code(), 0, "us", locks);
+        expansion
+    }
+
+    fn expander() -> SnippetExpander {
+        let config = Config::parse(
+            r#"(
+                snippets: (
+                    expansions: { "btw": "by the way", "Addr": "1 Main St" },
+                ),
+            )"#,
+        )
+        .unwrap();
+        SnippetExpander::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_abbreviation_expands_on_trigger() {
+        let mut snippets = expander();
+        let none = LockState::default();
+
+        for key in [
+            Key::KEY_B,
+            Key::KEY_T,
+            Key::KEY_X,
+            Key::KEY_BACKSPACE,
+            Key::KEY_W,
+        ] {
+            assert_eq!(tap(key, &mut snippets, none), None);
+        }
+        assert_eq!(
+            tap(Key::KEY_SPACE, &mut snippets, none),
+            Some(Expansion {
+                erase: 3,
+                text: "by the way".to_string()
+            })
+        );
+
+        // Shift is tracked for capitals
+        snippets.on_output(Key::KEY_LEFTSHIFT.code(), 1, "us", none);
+        tap(Key::KEY_A, &mut snippets, none);
+        snippets.on_output(Key::KEY_LEFTSHIFT.code(), 0, "us", none);
+        for key in [Key::KEY_D, Key::KEY_D, Key::KEY_R] {
+            tap(key, &mut snippets, none);
+        }
+        assert_eq!(
+            tap(Key::KEY_TAB, &mut snippets, none).map(|e| e.erase),
+            Some(4)
+        );
+
+        // A shortcut in between starts a new word
+        tap(Key::KEY_B, &mut snippets, none);
+        snippets.on_output(Key::KEY_LEFTCTRL.code(), 1, "us", none);
+        tap(Key::KEY_T, &mut snippets, none);
+        snippets.on_output(Key::KEY_LEFTCTRL.code(), 0, "us", none);
+        tap(Key::KEY_W, &mut snippets, none);
+        assert_eq!(tap(Key::KEY_SPACE, &mut snippets, none), None);
+    }
+
+    #[test]
+    fn test_both_shifts_and_caps_lock_pick_the_case() {
+        let mut snippets = expander();
+        let none = LockState::default();
+        let caps = LockState {
+            caps_lock: true,
+            num_lock: false,
+        };
+
+        // Right shift still held after letting go of the left one
+        snippets.on_output(Key::KEY_LEFTSHIFT.code(), 1, "us", none);
+        snippets.on_output(Key::KEY_RIGHTSHIFT.code(), 1, "us", none);
+        snippets.on_output(Key::KEY_LEFTSHIFT.code(), 0, "us", none);
+        tap(Key::KEY_A, &mut snippets, none);
+        snippets.on_output(Key::KEY_RIGHTSHIFT.code(), 0, "us", none);
+        for key in [Key::KEY_D, Key::KEY_D, Key::KEY_R] {
+            tap(key, &mut snippets, none);
+        }
+        assert!(tap(Key::KEY_SPACE, &mut snippets, none).is_some());
+
+        // Caps Lock capitalizes letters, and shift under it lowers them
+        tap(Key::KEY_A, &mut snippets, caps);
+        snippets.on_output(Key::KEY_LEFTSHIFT.code(), 1, "us", caps);
+        for key in [Key::KEY_D, Key::KEY_D, Key::KEY_R] {
+            tap(key, &mut snippets, caps);
+        }
+        snippets.on_output(Key::KEY_LEFTSHIFT.code(), 0, "us", caps);
+        assert!(tap(Key::KEY_SPACE, &mut snippets, caps).is_some());
+        for key in [Key::KEY_B, Key::KEY_T, Key::KEY_W] {
+            tap(key, &mut snippets, caps);
+        }
+        assert_eq!(tap(Key::KEY_SPACE, &mut snippets, caps), None);
+    }
+}
@@ -0,0 +1,103 @@
+/// Crash-safe reading and writing of the JSON stats files
+///
+/// A file is written to a temp file next to it, synced, and renamed over the old one,
+/// so a crash or poweroff mid-write never leaves it half-written. The previous version
+/// is kept as `<name>.bak`. A file that doesn't parse anyway (written by something
+/// else, or by an older keymux) is moved aside as `<name>.corrupt` and the backup is
+/// loaded instead, so the learning since the last good save is all that's lost.
+///
+/// The files live in the user's config dir and are read and written by the root
+/// daemon, so all of it goes through `user_fs` with the user's filesystem IDs.
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Where the previous version of a stats file is kept
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// Replace `path` with `value` as pretty JSON as `uid`, keeping the old file as the backup
+pub fn write<T: Serialize>(path: &Path, uid: u32, value: &T) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    crate::user_fs::replace(uid, path, json.as_bytes(), Some(&backup_path(path)))
+}
+
+/// Read `path`, falling back to its backup when it is missing or doesn't parse
+///
+/// `Ok(None)` means neither exists yet.
+pub fn read<T: DeserializeOwned>(path: &Path, uid: u32) -> std::io::Result<Option<T>> {
+    crate::user_fs::as_user(uid, || {
+        if let Some(value) = read_one(path)? {
+            return Ok(Some(value));
+        }
+        let backup = backup_path(path);
+        let value = read_one(&backup)?;
+        if value.is_some() {
+            tracing::warn!("Loaded stats from backup {:?}", backup);
+        }
+        Ok(value)
+    })
+}
+
+fn read_one<T: DeserializeOwned>(path: &Path) -> std::io::Result<Option<T>> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match serde_json::from_str(&json) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) => {
+            let corrupt = with_suffix(path, ".corrupt");
+            tracing::warn!(
+                "Stats file {:?} is unreadable ({}), moved to {:?}",
+                path,
+                e,
+                corrupt
+            );
+            std::fs::rename(path, &corrupt)?;
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_truncated_file_recovers_from_backup() {
+        let dir = std::env::temp_dir().join(format!("keymux-stats-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("all_key_stats.json");
+        let first: HashMap<String, u32> = HashMap::from([("A".to_string(), 1)]);
+        let second: HashMap<String, u32> = HashMap::from([("A".to_string(), 2)]);
+
+        let uid = unsafe { libc::geteuid() };
+        write(&path, uid, &first).unwrap();
+        write(&path, uid, &second).unwrap();
+        assert_eq!(
+            read::<HashMap<String, u32>>(&path, uid).unwrap(),
+            Some(second)
+        );
+
+        // Cut off mid-write by something other than keymux
+        std::fs::write(&path, "{\"A\": ").unwrap();
+        assert_eq!(
+            read::<HashMap<String, u32>>(&path, uid).unwrap(),
+            Some(first)
+        );
+        assert!(with_suffix(&path, ".corrupt").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(read::<HashMap<String, u32>>(&path, uid).unwrap(), None);
+    }
+}
@@ -1,8 +1,35 @@
 use crate::cli::{GamemodeAction, WindowGamemodeAction};
 use anyhow::Result;
+use colored::Colorize;
+use keymux::ipc::{send_request, IpcRequest, IpcResponse};
 
-pub fn handle_gamemode_action(action: &GamemodeAction) -> Result<()> {
+pub fn handle_gamemode_action(action: &GamemodeAction, keyboard: Option<&str>) -> Result<()> {
     match action {
+        GamemodeAction::On => set_game_mode(Some(true), keyboard)?,
+        GamemodeAction::Off => set_game_mode(Some(false), keyboard)?,
+        GamemodeAction::Toggle => {
+            let current = query_game_mode(keyboard)?;
+            set_game_mode(Some(!current), keyboard)?;
+        }
+        GamemodeAction::Auto => set_game_mode(None, keyboard.or(Some("*")))?,
+        GamemodeAction::Status => {
+            let active = query_game_mode(keyboard)?;
+            let state = if active {
+                "on".bright_green().bold()
+            } else {
+                "off".dimmed()
+            };
+            match keyboard {
+                Some(pattern) => println!("Game mode ({}): {}", pattern.bright_white(), state),
+                None => println!("Game mode: {}", state),
+            }
+        }
+        GamemodeAction::Overlay { name } => {
+            if keyboard.is_some() {
+                anyhow::bail!("Overlays apply to every keyboard; drop --keyboard");
+            }
+            set_overlay(name)?;
+        }
         GamemodeAction::Window { action } => {
             handle_window_gamemode_action(action)?;
         }
@@ -10,9 +37,73 @@ pub fn handle_gamemode_action(action: &GamemodeAction) -> Result<()> {
     Ok(())
 }
 
-pub fn handle_window_gamemode_action(action: &WindowGamemodeAction) -> Result<()> {
-    use colored::Colorize;
+/// Ask the daemon for the global (or per-keyboard) game mode state
+fn query_game_mode(keyboard: Option<&str>) -> Result<bool> {
+    match send_request(&IpcRequest::GetGameMode(keyboard.map(str::to_string)))? {
+        IpcResponse::GameMode(active) => Ok(active),
+        IpcResponse::Error(e) => anyhow::bail!(e),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+}
 
+/// Set game mode globally, or override it for matching keyboards (None clears the override)
+fn set_game_mode(enabled: Option<bool>, keyboard: Option<&str>) -> Result<()> {
+    let request = match (keyboard, enabled) {
+        (Some(pattern), state) => IpcRequest::SetKeyboardGameMode(pattern.to_string(), state),
+        (None, Some(state)) => IpcRequest::SetGameMode(state),
+        (None, None) => return Ok(()),
+    };
+
+    match send_request(&request)? {
+        IpcResponse::Ok => {}
+        IpcResponse::Error(e) => anyhow::bail!(e),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+
+    let state = match enabled {
+        Some(true) => "on".bright_green().bold(),
+        Some(false) => "off".dimmed(),
+        None => "auto".bright_blue(),
+    };
+    match keyboard {
+        Some(pattern) if pattern != "*" => {
+            println!(
+                "  {} Game mode {} for {}",
+                "✓".bright_green().bold(),
+                state,
+                pattern.bright_white()
+            )
+        }
+        _ => println!("  {} Game mode {}", "✓".bright_green().bold(), state),
+    }
+    Ok(())
+}
+
+/// Force a game mode overlay for every keyboard ("auto" hands the choice back to the
+/// focused window)
+fn set_overlay(name: &str) -> Result<()> {
+    let overlay = (name != "auto").then(|| name.to_string());
+    match send_request(&IpcRequest::SetGameModeOverlay(overlay.clone()))? {
+        IpcResponse::Ok => {}
+        IpcResponse::Error(e) => anyhow::bail!(e),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+    match overlay {
+        Some(name) => println!(
+            "  {} Game mode overlay {}",
+            "✓".bright_green().bold(),
+            name.bright_white()
+        ),
+        None => println!(
+            "  {} Game mode overlay {}",
+            "✓".bright_green().bold(),
+            "follows the focused window".bright_blue()
+        ),
+    }
+    Ok(())
+}
+
+pub fn handle_window_gamemode_action(action: &WindowGamemodeAction) -> Result<()> {
     match action {
         WindowGamemodeAction::Invert => {
             println!("  {} Window invert not implemented yet", "ℹ".bright_blue());
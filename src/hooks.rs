@@ -0,0 +1,43 @@
+//! Lifecycle hooks - user commands run when the daemon's state changes
+//!
+//! Hooks are configured in the `hooks` block of a user's config and run as that
+//! user, from their `~/.config/keymux` directory, without blocking the caller.
+//! Every hook gets `KEYMUX_EVENT` plus event-specific `KEYMUX_*` variables.
+
+use crate::event_processor::actions::cmd::{get_user_info, spawn_command};
+use std::path::PathBuf;
+use tracing::{debug, error};
+
+/// Run a hook command in the background as `uid`
+///
+/// `event` is exported as `KEYMUX_EVENT`; `env` holds the event-specific variables.
+pub fn run_hook(
+    command: &str,
+    uid: u32,
+    config_dir: PathBuf,
+    event: &str,
+    env: Vec<(&str, String)>,
+) {
+    let command = command.to_string();
+    let mut envs: Vec<(String, String)> = vec![("KEYMUX_EVENT".to_string(), event.to_string())];
+    envs.extend(env.into_iter().map(|(k, v)| (k.to_string(), v)));
+    debug!("Running {} hook for user {}: {}", event, uid, command);
+
+    std::thread::spawn(move || {
+        let user_info = get_user_info(uid);
+        let username = user_info.as_ref().map(|(u, _)| u.as_str());
+
+        let final_cmd = match (command.strip_prefix('~'), &user_info) {
+            (Some(rest), Some((_, home))) => format!("{}{}", home.display(), rest),
+            _ => command,
+        };
+
+        match spawn_command(&final_cmd, &config_dir, username, &envs) {
+            Ok(mut child) => {
+                // Reap the child so hooks don't pile up as zombies
+                let _ = child.wait();
+            }
+            Err(e) => error!("Failed to run hook '{}': {}", final_cmd, e),
+        }
+    });
+}
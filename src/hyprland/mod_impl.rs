@@ -99,11 +99,7 @@ impl WaylandCompositor {
         match output {
             Ok(output) if output.status.success() => {
                 let Ok(json_str) = String::from_utf8(output.stdout) else {
-                    return WindowInfo {
-                        app_id: None,
-                        pid: None,
-                        title: None,
-                    };
+                    return WindowInfo::default();
                 };
 
                 if let Ok(json) = serde_json::from_str::<serde_json::Value>(&json_str) {
@@ -122,20 +118,17 @@ impl WaylandCompositor {
                         .and_then(|v| v.as_str())
                         .map(String::from);
 
-                    return WindowInfo { app_id, pid, title };
+                    return WindowInfo {
+                        app_id,
+                        pid,
+                        title,
+                        ..Default::default()
+                    };
                 }
 
-                WindowInfo {
-                    app_id: None,
-                    pid: None,
-                    title: None,
-                }
+                WindowInfo::default()
             }
-            _ => WindowInfo {
-                app_id: None,
-                pid: None,
-                title: None,
-            },
+            _ => WindowInfo::default(),
         }
     }
 }
@@ -229,14 +222,7 @@ pub fn start_sway_monitor_sync(tx: Sender<WindowManagerEvent>) {
 }
 
 pub fn get_focused_window() -> WindowInfo {
-    detect_wayland_compositor().map_or(
-        WindowInfo {
-            app_id: None,
-            pid: None,
-            title: None,
-        },
-        |wm| wm.get_focused_window(),
-    )
+    detect_wayland_compositor().map_or(WindowInfo::default(), |wm| wm.get_focused_window())
 }
 
 pub fn should_enable_gamemode(window_info: &WindowInfo) -> bool {
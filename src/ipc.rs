@@ -1,5 +1,11 @@
 #![allow(clippy::cast_possible_truncation)]
 
+use crate::battery::BatteryLevel;
+use crate::config::EnableDisable;
+use crate::event_processor::actions::mt::MtDecision;
+use crate::event_processor::layer_stack::LayerChange;
+use crate::keycode::KeyCode;
+use crate::window_manager::WindowInfo;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
@@ -15,20 +21,167 @@ pub enum IpcRequest {
     ListKeyboards,
     /// Toggle enabled status of keyboards (returns current list for interactive selection)
     ToggleKeyboards,
-    /// Enable specific keyboard by hardware ID
+    /// Keyboard pattern (ID or name) was enabled in the caller's config
     EnableKeyboard(String),
-    /// Disable specific keyboard by hardware ID
+    /// Keyboard pattern (ID or name) was disabled in the caller's config
     DisableKeyboard(String),
+    /// Replace a user's enabled-keyboard state (kept outside config.ron)
+    SetEnabledKeyboards {
+        entries: Vec<(String, EnableDisable)>,
+        uid: u32,
+    },
     /// Set game mode state (true = on, false = off)
     SetGameMode(bool),
+    /// Override game mode for keyboards matching a pattern (None = follow global state)
+    SetKeyboardGameMode(String, Option<bool>),
+    /// Force a game mode overlay by name (None = let the focused window pick it)
+    SetGameModeOverlay(Option<String>),
+    /// Query game mode state, optionally for keyboards matching a pattern
+    GetGameMode(Option<String>),
+    /// Query game mode state with why it is on or off and any per-keyboard overrides
+    GetGameModeStatus,
+    /// Query the last focused window the daemon saw from the window manager
+    GetActiveWindow,
+    /// Type a string through a user's virtual keyboard
+    TypeString {
+        text: String,
+        /// Press Enter after the text
+        enter: bool,
+        /// Only type through keyboards owned by this user
+        uid: Option<u32>,
+        /// Only type through keyboards matching this pattern (ID or name)
+        keyboard: Option<String>,
+    },
+    /// Feed key events (key, pressed) into a keyboard's processing pipeline
+    InjectKeys {
+        events: Vec<(KeyCode, bool)>,
+        /// Only inject into keyboards owned by this user
+        uid: Option<u32>,
+        /// Only inject into keyboards matching this pattern (ID or name)
+        keyboard: Option<String>,
+    },
+    /// Override a user's active profile (None = follow the schedule, "default" = no profile)
+    SetProfile {
+        profile: Option<String>,
+        /// Only affect this user (None = every user)
+        uid: Option<u32>,
+    },
+    /// Query a user's active profile
+    GetProfile { uid: Option<u32> },
+    /// Query the modifiers a user has locked on with `TapLock` keys
+    GetLockedModifiers { uid: Option<u32> },
+    /// Query the active layers and recent layer changes of a user's keyboards
+    GetLayerHistory {
+        /// Only keyboards owned by this user
+        uid: Option<u32>,
+        /// Only keyboards matching this pattern (ID or name)
+        keyboard: Option<String>,
+    },
+    /// Query the recent MT decisions of a user's keyboards (mt_config.trace_decisions)
+    GetMtTrace {
+        /// Only keyboards owned by this user
+        uid: Option<u32>,
+        /// Only keyboards matching this pattern (ID or name)
+        keyboard: Option<String>,
+    },
+    /// Compare a config (RON text) against a user's active one on live input,
+    /// logging where outputs differ (None = stop comparing)
+    SetShadowConfig {
+        config: Option<String>,
+        /// Only affect this user (None = every user)
+        uid: Option<u32>,
+    },
+    /// Query the config a keyboard's processors run with, after the active profile
+    /// and per-keyboard overrides are merged in
+    GetEffectiveConfig {
+        /// Keyboard pattern (ID or name) matching a single keyboard
+        keyboard: String,
+        /// JSON instead of RON
+        json: bool,
+    },
+    /// Query daemon internals: uptime, memory and the state of each processor thread
+    GetStatus,
     /// Reload configuration from disk
     Reload,
+    /// Let keyboards matching a pattern (ID or name) that were quarantined for
+    /// crashing their processors run again
+    Unquarantine(String),
     /// Force save adaptive timing stats immediately
     SaveAdaptiveStats,
     /// Shutdown daemon
     Shutdown,
 }
 
+impl IpcRequest {
+    /// Short name for logs, without payloads like typed text
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Ping => "Ping",
+            Self::ListKeyboards => "ListKeyboards",
+            Self::ToggleKeyboards => "ToggleKeyboards",
+            Self::EnableKeyboard(_) => "EnableKeyboard",
+            Self::DisableKeyboard(_) => "DisableKeyboard",
+            Self::SetEnabledKeyboards { .. } => "SetEnabledKeyboards",
+            Self::SetGameMode(_) => "SetGameMode",
+            Self::SetKeyboardGameMode(..) => "SetKeyboardGameMode",
+            Self::SetGameModeOverlay(_) => "SetGameModeOverlay",
+            Self::GetGameMode(_) => "GetGameMode",
+            Self::GetGameModeStatus => "GetGameModeStatus",
+            Self::GetActiveWindow => "GetActiveWindow",
+            Self::TypeString { .. } => "TypeString",
+            Self::InjectKeys { .. } => "InjectKeys",
+            Self::SetProfile { .. } => "SetProfile",
+            Self::GetProfile { .. } => "GetProfile",
+            Self::GetLockedModifiers { .. } => "GetLockedModifiers",
+            Self::GetLayerHistory { .. } => "GetLayerHistory",
+            Self::GetMtTrace { .. } => "GetMtTrace",
+            Self::SetShadowConfig { .. } => "SetShadowConfig",
+            Self::GetEffectiveConfig { .. } => "GetEffectiveConfig",
+            Self::GetStatus => "GetStatus",
+            Self::Reload => "Reload",
+            Self::Unquarantine(_) => "Unquarantine",
+            Self::SaveAdaptiveStats => "SaveAdaptiveStats",
+            Self::Shutdown => "Shutdown",
+        }
+    }
+
+    /// Whether the request changes daemon state and belongs in the audit log
+    pub const fn is_audited(&self) -> bool {
+        !matches!(
+            self,
+            Self::Ping
+                | Self::ListKeyboards
+                | Self::GetGameMode(_)
+                | Self::GetGameModeStatus
+                | Self::GetActiveWindow
+                | Self::GetProfile { .. }
+                | Self::GetLockedModifiers { .. }
+                | Self::GetLayerHistory { .. }
+                | Self::GetMtTrace { .. }
+                | Self::GetEffectiveConfig { .. }
+                | Self::GetStatus
+                | Self::SaveAdaptiveStats
+        )
+    }
+
+    /// Whether the request reloads configs or restarts processors, and so gets a
+    /// tighter rate limit
+    pub const fn is_expensive(&self) -> bool {
+        matches!(
+            self,
+            Self::ToggleKeyboards
+                | Self::EnableKeyboard(_)
+                | Self::DisableKeyboard(_)
+                | Self::SetEnabledKeyboards { .. }
+                | Self::SetProfile { .. }
+                | Self::SetShadowConfig { .. }
+                | Self::Unquarantine(_)
+                | Self::Reload
+                | Self::Shutdown
+        )
+    }
+}
+
 /// IPC response from daemon to client
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum IpcResponse {
@@ -36,12 +189,116 @@ pub enum IpcResponse {
     Pong,
     /// List of keyboards with their info
     KeyboardList(Vec<KeyboardInfo>),
+    /// Current game mode state
+    GameMode(bool),
+    /// Game mode state and what decided it
+    GameModeStatus(GameModeStatus),
+    /// Last focused window (None = no window manager events seen yet)
+    ActiveWindow(Option<WindowInfo>),
+    /// Active profile (None = default) and whether it was set manually
+    Profile {
+        name: Option<String>,
+        overridden: bool,
+    },
+    /// Modifiers locked on across a user's keyboards, oldest lock first
+    LockedModifiers(Vec<KeyCode>),
+    /// Layer state of each matching keyboard event file
+    LayerHistory(Vec<LayerHistory>),
+    /// Recent MT decisions of each matching keyboard event file
+    MtTrace(Vec<MtTrace>),
+    /// Merged config of a keyboard, serialized as requested
+    EffectiveConfig {
+        /// Keyboard name
+        keyboard: String,
+        /// Active profile merged in (None = default)
+        profile: Option<String>,
+        config: String,
+    },
+    /// Daemon internals
+    Status(DaemonStatus),
     /// Operation succeeded
     Ok,
     /// Operation failed with error message
     Error(String),
 }
 
+/// Global game mode state, for scripts and `keymux debug`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameModeStatus {
+    pub active: bool,
+    /// What last set the global state, e.g. "Steam game (steam_app_570)"
+    pub reason: String,
+    /// Keyboards whose game mode ignores the global state (name, on)
+    pub keyboard_overrides: Vec<(String, bool)>,
+    /// Overlay forced with `keymux gamemode overlay` (None = picked by the focused window)
+    #[serde(default)]
+    pub forced_overlay: Option<String>,
+}
+
+/// Layers of one processor (event file) and how they got there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerHistory {
+    /// Keyboard name
+    pub keyboard: String,
+    /// Event file the processor reads
+    pub event_path: String,
+    /// Active layers, base first
+    pub active_layers: Vec<String>,
+    /// Recent layer changes, oldest first
+    pub changes: Vec<LayerChange>,
+}
+
+/// MT decisions of one processor (event file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MtTrace {
+    /// Keyboard name
+    pub keyboard: String,
+    /// Event file the processor reads
+    pub event_path: String,
+    /// Recent decisions, oldest first
+    pub decisions: Vec<MtDecision>,
+}
+
+/// Daemon internals for `keymux status`, to tell a leak or a stuck thread after long uptimes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub uptime_secs: u64,
+    /// Resident memory (None = /proc unreadable)
+    pub rss_kb: Option<u64>,
+    /// Threads of the daemon process
+    pub threads: Option<u64>,
+    /// Processor events waiting for the daemon's main loop
+    pub event_backlog: usize,
+    /// One per event file, by path
+    pub processors: Vec<ProcessorInternals>,
+}
+
+/// State of one processor thread
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessorInternals {
+    /// Keyboard name
+    pub keyboard: String,
+    /// Event file the processor reads
+    pub event_path: String,
+    pub uid: u32,
+    /// The thread is still running
+    pub alive: bool,
+    /// Time since its last heartbeat (None = none yet)
+    pub heartbeat_age_ms: Option<u64>,
+    /// Missed enough heartbeats to count as stuck
+    pub stalled: bool,
+    /// Slowed down for spinning without sleeping
+    #[serde(default)]
+    pub throttled: bool,
+    /// Daemon commands it hasn't picked up yet
+    pub command_backlog: usize,
+    pub events_in: u64,
+    pub events_out: u64,
+    /// Presses it dropped to keep per-key tracking bounded
+    pub evictions: u64,
+}
+
 /// Information about a detected keyboard
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyboardInfo {
@@ -62,14 +319,106 @@ pub struct KeyboardInfo {
     /// The config rule pattern that matched (e.g., "*", "1234", "Keychron")
     /// None if implicitly enabled/disabled (no explicit rule matched)
     pub matched_rule: Option<String>,
+    /// User whose processors hold the keyboard (None = nobody's)
+    pub owner_uid: Option<u32>,
+    pub owner_name: Option<String>,
+    /// Every event file of the keyboard
+    pub event_nodes: Vec<EventNodeInfo>,
+    /// Active layer of the keyboard's processors (None = not processed)
+    pub active_layer: Option<String>,
+    /// Whether game mode is on for this keyboard
+    pub game_mode: bool,
+    /// Key events read from the keyboard since its processors started
+    pub events_in: u64,
+    /// Key events sent through its virtual keyboard since then
+    pub events_out: u64,
+    /// Presses its processors dropped to keep per-key tracking bounded
+    pub evictions: u64,
+    /// Battery of a wireless keyboard that reports one
+    pub battery: Option<BatteryLevel>,
+    /// Set when its processors kept crashing and it's left alone until un-quarantined
+    pub quarantine: Option<QuarantineInfo>,
+}
+
+/// Why a keyboard was quarantined
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantineInfo {
+    /// Error of the last crash
+    pub error: String,
+    /// Crashes in a row, each within a minute of the restart before it
+    pub crashes: u32,
+    /// Unix time it was quarantined
+    pub since: u64,
+}
+
+/// One event file of a keyboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventNodeInfo {
+    pub path: String,
+    /// A processor thread is reading this file
+    pub processing: bool,
+    /// Number of keys the node reports
+    pub keys: usize,
+    /// Has keyboard LEDs (Caps Lock etc.)
+    pub leds: bool,
+    /// Also reports pointer motion (mouse or touchpad on the same node)
+    pub pointer: bool,
+}
+
+impl EventNodeInfo {
+    /// Short capability description, e.g. "104 keys, LEDs"
+    pub fn capabilities(&self) -> String {
+        let mut parts = vec![format!("{} keys", self.keys)];
+        if self.leds {
+            parts.push("LEDs".to_string());
+        }
+        if self.pointer {
+            parts.push("pointer".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// Credentials of the process on the other end of a Unix socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCred {
+    pub pid: i32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// Read the connecting process's credentials (SO_PEERCRED)
+pub fn peer_cred(stream: &UnixStream) -> Option<PeerCred> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            std::ptr::addr_of_mut!(cred).cast(),
+            &mut len,
+        )
+    };
+    (ret == 0).then_some(PeerCred {
+        pid: cred.pid,
+        uid: cred.uid,
+        gid: cred.gid,
+    })
 }
 
 /// Get the IPC socket path for root daemon
 pub fn get_root_socket_path() -> PathBuf {
-    Path::new("/run").join("keymux.sock")
+    crate::paths::root_socket_path()
 }
 
-/// Get the IPC socket path for user daemon (legacy, for compatibility)
+/// Get the caller's private IPC socket path (`$XDG_RUNTIME_DIR/keymux.sock`)
 pub fn get_user_socket_path() -> PathBuf {
     let (uid, _) = crate::get_actual_user_uid();
     let runtime_dir =
@@ -77,13 +426,21 @@ pub fn get_user_socket_path() -> PathBuf {
     Path::new(&runtime_dir).join("keymux.sock")
 }
 
-/// Get the IPC socket path (tries root first, falls back to user)
+/// Get the IPC socket path
+///
+/// Users talk to the daemon through their private socket when it exists; root
+/// (including sudo, which may act for any user) uses the system socket. An explicit
+/// `KEYMUX_SOCKET` always wins.
 pub fn get_socket_path() -> PathBuf {
-    let root_sock = get_root_socket_path();
-    if root_sock.exists() {
-        root_sock
+    if let Some(socket) = crate::paths::socket_override() {
+        return socket;
+    }
+    let is_root = unsafe { libc::geteuid() } == 0;
+    let user_sock = get_user_socket_path();
+    if !is_root && user_sock.exists() {
+        user_sock
     } else {
-        get_user_socket_path()
+        get_root_socket_path()
     }
 }
 
@@ -1,16 +1,75 @@
 use crate::keycode::KeyCode;
-use evdev::Device;
+use evdev::{AttributeSetRef, Device, EventType, Key};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 /// Check if a device is a keyboard by verifying it has letter keys
 pub fn is_keyboard_device(device: &Device) -> bool {
-    device.supported_keys().is_some_and(|keys| {
-        keys.contains(evdev::Key::new(KeyCode::KC_A.code()))
-            && keys.contains(evdev::Key::new(KeyCode::KC_Z.code()))
-            && keys.contains(evdev::Key::new(KeyCode::KC_SPC.code()))
-    })
+    device.supported_keys().is_some_and(has_letter_keys)
+}
+
+fn has_letter_keys(keys: &AttributeSetRef<Key>) -> bool {
+    keys.contains(Key::new(KeyCode::KC_A.code()))
+        && keys.contains(Key::new(KeyCode::KC_Z.code()))
+        && keys.contains(Key::new(KeyCode::KC_SPC.code()))
+}
+
+/// Check if a device emits key events at all (as opposed to LED- or ABS-only nodes)
+fn emits_keys(device: &Device) -> bool {
+    supports_key_events(device.supported_events(), device.supported_keys())
+}
+
+fn supports_key_events(
+    events: &AttributeSetRef<EventType>,
+    keys: Option<&AttributeSetRef<Key>>,
+) -> bool {
+    events.contains(EventType::KEY) && keys.is_some_and(|keys| keys.iter().next().is_some())
+}
+
+/// Check if a key node also acts as a pointer: a wheel or dial, or mouse buttons
+///
+/// Nodes moving a cursor (REL_X/REL_Y) never count as keyboards; these are the ones
+/// left, like a gaming keyboard's media wheel or its macro-key mouse buttons.
+pub fn is_pointer_node(device: &Device) -> bool {
+    device
+        .supported_relative_axes()
+        .is_some_and(|axes| axes.iter().next().is_some())
+        || device
+            .supported_keys()
+            .is_some_and(|keys| keys.contains(evdev::Key::BTN_LEFT))
+}
+
+/// Name fragments (lowercase) of virtual keyboards other remappers create
+const REMAPPER_OUTPUT_NAMES: &[&str] = &[
+    "keyd virtual",
+    "kanata",
+    "kmonad",
+    "xremap",
+    "evremap",
+    "input-remapper",
+];
+
+/// vendor:product of uinput devices built with library default IDs (including ours)
+const DEFAULT_UINPUT_ID: (u16, u16) = (0x1234, 0x5678);
+
+/// Check if a device is one of the virtual keyboards this daemon creates
+pub fn is_own_virtual_device(device: &Device) -> bool {
+    let name = device.name().unwrap_or_default();
+    name.contains("Keyboard Middleware Virtual Keyboard") || name.starts_with("keymux: ")
+}
+
+/// Check if a device looks like another remapper's output (or a stale uinput node)
+///
+/// Grabbing these would feed remapped keys back through a remapper, so the daemon
+/// leaves them alone unless the user lists them in `grab_remapper_outputs`.
+pub fn is_remapper_output(device: &Device) -> bool {
+    let name = device.name().unwrap_or_default().to_lowercase();
+    let id = device.input_id();
+    REMAPPER_OUTPUT_NAMES
+        .iter()
+        .any(|fragment| name.contains(fragment))
+        || (id.vendor(), id.product()) == DEFAULT_UINPUT_ID
 }
 
 /// Hardware-based keyboard identifier that persists across reboots
@@ -26,6 +85,8 @@ pub struct LogicalKeyboard {
     pub name: String,
     /// All event devices for this keyboard (sorted by input number)
     pub devices: Vec<(PathBuf, Device)>,
+    /// Virtual keyboard from another remapper, only grabbed when the config allows it
+    pub remapper_output: bool,
 }
 
 impl std::fmt::Debug for LogicalKeyboard {
@@ -34,6 +95,7 @@ impl std::fmt::Debug for LogicalKeyboard {
             .field("id", &self.id)
             .field("name", &self.name)
             .field("device_count", &self.devices.len())
+            .field("remapper_output", &self.remapper_output)
             .finish()
     }
 }
@@ -74,12 +136,28 @@ impl KeyboardId {
     ///
     /// For non-USB devices (built-in, Bluetooth) the port component is omitted and
     /// the ID falls back to vendor:product:version:bustype as before.
+    ///
+    /// Devices created through uhid (Bluetooth LE keyboards via BlueZ) or attached over
+    /// USB/IP can report vendor 0000. For those the vendor and product are taken from
+    /// the HID device in their sysfs DEVPATH when it has them, and the port component
+    /// is replaced by the device's HID unique ID (usually its Bluetooth address) or
+    /// phys string, which stay the same across reconnects.
     pub fn from_device(device: &Device, path: &Path) -> Self {
-        let id_vendor = device.input_id().vendor();
-        let id_product = device.input_id().product();
+        let mut id_vendor = device.input_id().vendor();
+        let mut id_product = device.input_id().product();
         let id_version = device.input_id().version();
         let id_bustype = device.input_id().bus_type();
 
+        let incomplete = id_vendor == 0;
+        if incomplete {
+            if let Some((vendor, product)) =
+                sysfs_target(path).as_deref().and_then(hid_ids_from_devpath)
+            {
+                id_vendor = vendor;
+                id_product = product;
+            }
+        }
+
         let base = format!(
             "{:04x}:{:04x}:{:04x}:{:04x}",
             id_vendor, id_product, id_version, id_bustype.0
@@ -87,7 +165,15 @@ impl KeyboardId {
 
         // Append USB port topology when available so identical models on different
         // ports get distinct IDs.
-        let hardware_id = match get_usb_port(path) {
+        let hint = if incomplete {
+            device
+                .unique_name()
+                .and_then(uniq_hint)
+                .or_else(|| device.physical_path().and_then(phys_hint))
+        } else {
+            None
+        };
+        let hardware_id = match hint.or_else(|| get_usb_port(path)) {
             Some(port) => format!("{}@{}", base, port),
             None => base,
         };
@@ -138,14 +224,7 @@ fn get_input_number(path: &Path) -> Option<u32> {
 /// Returns None for non-USB devices (e.g. built-in keyboards, Bluetooth), in which
 /// case the caller falls back to the hardware ID alone.
 fn get_usb_port(path: &Path) -> Option<String> {
-    let event_name = path.file_name()?.to_str()?;
-    if !event_name.starts_with("event") {
-        return None;
-    }
-
-    let sysfs_path = format!("/sys/class/input/{}", event_name);
-    let target = fs::read_link(&sysfs_path).ok()?;
-    let target_str = target.to_string_lossy();
+    let target_str = sysfs_target(path)?;
 
     // Walk path components looking for a USB port pattern like "3-4.2" or "3-4"
     // that is immediately followed by its config interface variant "3-4.2:1.0"
@@ -174,6 +253,58 @@ fn get_usb_port(path: &Path) -> Option<String> {
     deepest
 }
 
+/// Resolved sysfs device path of an event node, e.g. "../../devices/.../event5"
+fn sysfs_target(path: &Path) -> Option<String> {
+    let event_name = path.file_name()?.to_str()?;
+    let target = fs::read_link(format!("/sys/class/input/{}", event_name)).ok()?;
+    Some(target.to_string_lossy().into_owned())
+}
+
+/// Vendor and product of the HID device in a DEVPATH ("bus:vendor:product.instance")
+fn hid_ids_from_devpath(devpath: &str) -> Option<(u16, u16)> {
+    devpath.split('/').rev().find_map(|component| {
+        let (ids, instance) = component.split_once('.')?;
+        let mut parts = ids.split(':');
+        let (bus, vendor, product) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() || bus.len() != 4 || u16::from_str_radix(instance, 16).is_err() {
+            return None;
+        }
+        let vendor = u16::from_str_radix(vendor, 16).ok()?;
+        let product = u16::from_str_radix(product, 16).ok()?;
+        (vendor != 0).then_some((vendor, product))
+    })
+}
+
+/// ID suffix from a HID unique ID, e.g. "C8:7F:54:AA:BB:CC" -> "uniq-c87f54aabbcc"
+fn uniq_hint(uniq: &str) -> Option<String> {
+    let cleaned: String = uniq
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_lowercase();
+    (!cleaned.is_empty()).then(|| format!("uniq-{}", cleaned))
+}
+
+/// ID suffix from a phys string, without the per-interface "/inputN" part so all of a
+/// keyboard's nodes share it, e.g. "vhci_hcd.0-1/input0" -> "phys-vhci_hcd.0-1"
+fn phys_hint(phys: &str) -> Option<String> {
+    let phys = match phys.rsplit_once('/') {
+        Some((base, last)) if last.starts_with("input") => base,
+        _ => phys,
+    };
+    let cleaned: String = phys
+        .chars()
+        .map(|c| {
+            if c == '@' || c.is_whitespace() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    (!cleaned.is_empty()).then(|| format!("phys-{}", cleaned))
+}
+
 /// Find all keyboard devices and return them grouped by hardware ID
 /// Each logical keyboard may have multiple event devices (input0, input1, etc.)
 pub fn find_all_keyboards() -> HashMap<KeyboardId, LogicalKeyboard> {
@@ -181,59 +312,70 @@ pub fn find_all_keyboards() -> HashMap<KeyboardId, LogicalKeyboard> {
         HashMap::new();
 
     for (path, device) in evdev::enumerate() {
-        // Check if it's a keyboard device
-        if is_keyboard_device(&device) {
-            let name = device.name().unwrap_or("unknown").to_string();
+        let name = device.name().unwrap_or("unknown").to_string();
 
-            // Skip virtual keyboards created by this daemon
-            if name.contains("Keyboard Middleware Virtual Keyboard") || name.starts_with("keymux: ")
-            {
-                tracing::debug!("Skipping virtual keyboard: {}", name);
-                continue;
-            }
+        // Only nodes that emit key events are worth a processor; LED- or ABS-only
+        // siblings of a keyboard would just be grabbed for nothing
+        if !emits_keys(&device) {
+            tracing::debug!(
+                "Skipping node without key events: '{}' at {}",
+                name,
+                path.display()
+            );
+            continue;
+        }
+
+        if !is_keyboard_device(&device) {
+            continue;
+        }
 
-            // Skip mice - check for mouse buttons
-            if let Some(keys) = device.supported_keys() {
-                let has_mouse_buttons = keys.contains(evdev::Key::BTN_TOOL_MOUSE)
-                    || keys.contains(evdev::Key::BTN_TOOL_FINGER)
-                    || keys.contains(evdev::Key::BTN_TOOL_PEN);
+        // Never grab virtual keyboards created by this daemon, even stale ones
+        if is_own_virtual_device(&device) {
+            tracing::debug!("Skipping virtual keyboard: {}", name);
+            continue;
+        }
 
-                if has_mouse_buttons {
-                    tracing::debug!("Skipping mouse device (has mouse buttons): {}", name);
-                    continue;
-                }
+        // Skip mice - check for mouse buttons
+        if let Some(keys) = device.supported_keys() {
+            let has_mouse_buttons = keys.contains(evdev::Key::BTN_TOOL_MOUSE)
+                || keys.contains(evdev::Key::BTN_TOOL_FINGER)
+                || keys.contains(evdev::Key::BTN_TOOL_PEN);
+
+            if has_mouse_buttons {
+                tracing::debug!("Skipping mouse device (has mouse buttons): {}", name);
+                continue;
             }
+        }
 
-            // Skip mice - check for relative axes (mouse movement)
-            if let Some(rel_axes) = device.supported_relative_axes() {
-                let has_mouse_axes = rel_axes.contains(evdev::RelativeAxisType::REL_X)
-                    || rel_axes.contains(evdev::RelativeAxisType::REL_Y);
+        // Skip mice - check for relative axes (mouse movement)
+        if let Some(rel_axes) = device.supported_relative_axes() {
+            let has_mouse_axes = rel_axes.contains(evdev::RelativeAxisType::REL_X)
+                || rel_axes.contains(evdev::RelativeAxisType::REL_Y);
 
-                if has_mouse_axes {
-                    tracing::debug!("Skipping mouse device (has relative axes): {}", name);
-                    continue;
-                }
+            if has_mouse_axes {
+                tracing::debug!("Skipping mouse device (has relative axes): {}", name);
+                continue;
             }
+        }
 
-            // Get hardware ID, incorporating USB port for same-model disambiguation
-            let id = KeyboardId::from_device(&device, &path);
+        // Get hardware ID, incorporating USB port for same-model disambiguation
+        let id = KeyboardId::from_device(&device, &path);
 
-            // Get input number for sorting
-            let input_num = get_input_number(&path).unwrap_or(999);
+        // Get input number for sorting
+        let input_num = get_input_number(&path).unwrap_or(999);
 
-            tracing::debug!(
-                "Found keyboard device: '{}' at {} (ID: {}, input: {})",
-                name,
-                path.display(),
-                id,
-                input_num
-            );
+        tracing::debug!(
+            "Found keyboard device: '{}' at {} (ID: {}, input: {})",
+            name,
+            path.display(),
+            id,
+            input_num
+        );
 
-            device_groups
-                .entry(id)
-                .or_default()
-                .push((path, device, name, input_num));
-        }
+        device_groups
+            .entry(id)
+            .or_default()
+            .push((path, device, name, input_num));
     }
 
     // Convert grouped devices into LogicalKeyboards
@@ -245,6 +387,14 @@ pub fn find_all_keyboards() -> HashMap<KeyboardId, LogicalKeyboard> {
         // Use name from first device (lowest input number)
         let name = devices[0].2.clone();
         let lowest_input = devices[0].3;
+        let remapper_output = devices.iter().any(|(_, dev, _, _)| is_remapper_output(dev));
+        if remapper_output {
+            tracing::info!(
+                "Keyboard '{}' (ID: {}) looks like a remapper's virtual output",
+                name,
+                id
+            );
+        }
 
         tracing::info!(
             "Logical keyboard '{}' (ID: {}) has {} device(s), lowest input: input{}",
@@ -261,6 +411,7 @@ pub fn find_all_keyboards() -> HashMap<KeyboardId, LogicalKeyboard> {
                 .into_iter()
                 .map(|(path, dev, _, _)| (path, dev))
                 .collect(),
+            remapper_output,
         };
 
         keyboards.insert(id, logical_kb);
@@ -268,3 +419,54 @@ pub fn find_all_keyboards() -> HashMap<KeyboardId, LogicalKeyboard> {
 
     keyboards
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evdev::AttributeSet;
+
+    #[test]
+    fn test_only_nodes_with_keys_count() {
+        let events = AttributeSet::from_iter([EventType::KEY, EventType::LED]);
+        let leds_only = AttributeSet::from_iter([EventType::LED]);
+        let keyboard = AttributeSet::from_iter([Key::KEY_A, Key::KEY_Z, Key::KEY_SPACE]);
+        let media = AttributeSet::from_iter([Key::KEY_MUTE, Key::KEY_VOLUMEUP]);
+        let none = AttributeSet::<Key>::new();
+
+        // The LED node of a keyboard is left alone
+        assert!(!supports_key_events(&leds_only, None));
+        assert!(!supports_key_events(&events, Some(&none)));
+        assert!(supports_key_events(&events, Some(&keyboard)));
+
+        // A media-key node emits keys but isn't a keyboard on its own
+        assert!(supports_key_events(&events, Some(&media)));
+        assert!(!has_letter_keys(&media));
+        assert!(has_letter_keys(&keyboard));
+    }
+
+    #[test]
+    fn test_identity_hints_for_incomplete_ids() {
+        // BLE keyboard through BlueZ: the input device may say 0000, the HID device doesn't
+        assert_eq!(
+            hid_ids_from_devpath(
+                "../../devices/virtual/misc/uhid/0005:046D:B35B.0007/input/input31/event12"
+            ),
+            Some((0x046d, 0xb35b))
+        );
+        assert_eq!(
+            hid_ids_from_devpath(
+                "../../devices/virtual/misc/uhid/0005:0000:0000.0002/input/input9/event4"
+            ),
+            None
+        );
+        assert_eq!(
+            uniq_hint("C8:7F:54:AA:BB:CC").as_deref(),
+            Some("uniq-c87f54aabbcc")
+        );
+        assert_eq!(uniq_hint(""), None);
+        assert_eq!(
+            phys_hint("vhci_hcd.0-1/input0").as_deref(),
+            Some("phys-vhci_hcd.0-1")
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use evdev::Key;
 use serde::{Deserialize, Serialize};
 
 /// Key category for classification
@@ -27,43 +28,87 @@ pub enum KeyCategory {
     General,
 }
 
+/// Highest key code evdev defines (`KEY_MAX`)
+pub const KEY_MAX: u16 = 0x2ff;
+
 /// Macro for defining keycodes with optional metadata
 ///
-/// Syntax: `KC_NAME = code, category`
+/// Syntax: `KC_NAME = EVDEV_KEY, category, [ALIASES],` (aliases optional)
+///
+/// Codes come from evdev's own `KEY_*` constants, so this table is the only place a
+/// key name meets a code. Every evdev key without a name here is `KC_RAW(code)`.
 ///
 /// Categories: modifier, letter, number, function, special, navigation, numpad, media, international, lock, general
 ///
 /// Example:
 /// ```ignore
-/// KC_LCTL = 29, modifier,
-/// KC_A = 30, letter,
-/// KC_1 = 2, number,
+/// KC_LCTL = KEY_LEFTCTRL, modifier,
+/// KC_A = KEY_A, letter,
+/// KC_LGUI = KEY_LEFTMETA, modifier, [KC_LCMD, KC_LWIN],
 /// ```
 macro_rules! define_keycodes {
     // Main entry point - requires trailing commas on each entry
     (
         $(
-            $variant:ident = $code:expr, $category:ident,
+            $variant:ident = $evdev:ident, $category:ident, $([$($alias:ident),+ $(,)?],)?
         )*
     ) => {
         #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-        #[repr(u16)]
+        #[serde(try_from = "KeyCodeName")]
         #[allow(non_camel_case_types)]
         pub enum KeyCode {
             $(
-                $variant = $code,
+                $variant,
+            )*
+            /// Any other evdev key, by code (e.g. `KC_RAW(0x1d2)`)
+            KC_RAW(u16),
+        }
+
+        /// What a config may write for a key: names, aliases and raw codes
+        #[derive(Deserialize)]
+        #[allow(non_camel_case_types)]
+        enum KeyCodeName {
+            $(
+                $variant,
+                $($($alias,)+)?
             )*
+            KC_RAW(u16),
+        }
+
+        impl TryFrom<KeyCodeName> for KeyCode {
+            type Error = String;
+
+            fn try_from(name: KeyCodeName) -> Result<Self, Self::Error> {
+                match name {
+                    $(
+                        KeyCodeName::$variant $($(| KeyCodeName::$alias)+)? => Ok(Self::$variant),
+                    )*
+                    KeyCodeName::KC_RAW(code) => Self::from_evdev_code(code)
+                        .ok_or_else(|| format!("KC_RAW({}) is not a usable evdev key code", code)),
+                }
+            }
         }
 
         impl KeyCode {
+            /// Every named key, in table order
+            pub const NAMED: &'static [Self] = &[$(Self::$variant,)*];
+
+            $($($(
+                #[doc = concat!("Alias for ", stringify!($variant))]
+                pub const $alias: Self = Self::$variant;
+            )+)?)*
+
             /// Create a KeyCode from an evdev numeric code value
-            /// Returns None if the code is not supported/mapped
+            /// Keys without a name become `KC_RAW`; returns None only for KEY_UNKNOWN and
+            /// codes beyond KEY_MAX
             #[must_use]
             pub const fn from_evdev_code(code: u16) -> Option<Self> {
-                match code {
+                match Key::new(code) {
                     $(
-                        $code => Some(Self::$variant),
+                        Key::$evdev => Some(Self::$variant),
                     )*
+                    Key::KEY_UNKNOWN => None,
+                    _ if code <= KEY_MAX => Some(Self::KC_RAW(code)),
                     _ => None,
                 }
             }
@@ -71,7 +116,19 @@ macro_rules! define_keycodes {
             /// Get the evdev numeric code value for this KeyCode
             #[must_use]
             pub const fn code(self) -> u16 {
-                self as u16
+                match self {
+                    $(
+                        Self::$variant => Key::$evdev.code(),
+                    )*
+                    Self::KC_RAW(code) => code,
+                }
+            }
+
+            /// Check if this is a mouse, joystick or gamepad button (`BTN_*`) rather than
+            /// a key; udev takes a virtual keyboard advertising one for a pointer
+            #[must_use]
+            pub const fn is_button(self) -> bool {
+                matches!(self.code(), 0x100..=0x15f | 0x220..=0x223 | 0x2c0..=0x2e7)
             }
 
             /// Check if this key is a modifier (Ctrl, Shift, Alt, GUI)
@@ -87,16 +144,52 @@ macro_rules! define_keycodes {
                     $(
                         Self::$variant => define_keycodes!(@@category $category),
                     )*
+                    Self::KC_RAW(_) => KeyCategory::General,
                 }
             }
 
-            /// Get the name of this key (e.g., "KC_A")
+            /// Get the name of this key (e.g., "KC_A"); raw keys are all "KC_RAW", see
+            /// the `Display` impl for their code
             #[must_use]
             pub const fn name(self) -> &'static str {
                 match self {
                     $(
                         Self::$variant => stringify!($variant),
                     )*
+                    Self::KC_RAW(_) => "KC_RAW",
+                }
+            }
+
+            /// Other names a config may use for this key (e.g. ["KC_LCMD", "KC_LWIN"])
+            #[must_use]
+            pub const fn aliases(self) -> &'static [&'static str] {
+                match self {
+                    $(
+                        Self::$variant => &[$($(stringify!($alias),)+)?],
+                    )*
+                    Self::KC_RAW(_) => &[],
+                }
+            }
+
+            /// Name of the evdev key (e.g. "KEY_LEFTMETA"); "KEY_RAW" for raw keys
+            #[must_use]
+            pub const fn evdev_name(self) -> &'static str {
+                match self {
+                    $(
+                        Self::$variant => stringify!($evdev),
+                    )*
+                    Self::KC_RAW(_) => "KEY_RAW",
+                }
+            }
+
+            /// Parse a key from its name or alias (e.g., "KC_A", "KC_LCMD", "KC_RAW(464)")
+            #[must_use]
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(
+                        stringify!($variant) $($(| stringify!($alias))+)? => Some(Self::$variant),
+                    )*
+                    _ => Self::from_raw_name(name),
                 }
             }
         }
@@ -119,200 +212,248 @@ macro_rules! define_keycodes {
 // Generate the KeyCode enum with all keycodes
 define_keycodes! {
     // Letters
-    KC_A = 30, letter,
-    KC_B = 48, letter,
-    KC_C = 46, letter,
-    KC_D = 32, letter,
-    KC_E = 18, letter,
-    KC_F = 33, letter,
-    KC_G = 34, letter,
-    KC_H = 35, letter,
-    KC_I = 23, letter,
-    KC_J = 36, letter,
-    KC_K = 37, letter,
-    KC_L = 38, letter,
-    KC_M = 50, letter,
-    KC_N = 49, letter,
-    KC_O = 24, letter,
-    KC_P = 25, letter,
-    KC_Q = 16, letter,
-    KC_R = 19, letter,
-    KC_S = 31, letter,
-    KC_T = 20, letter,
-    KC_U = 22, letter,
-    KC_V = 47, letter,
-    KC_W = 17, letter,
-    KC_X = 45, letter,
-    KC_Y = 21, letter,
-    KC_Z = 44, letter,
+    KC_A = KEY_A, letter,
+    KC_B = KEY_B, letter,
+    KC_C = KEY_C, letter,
+    KC_D = KEY_D, letter,
+    KC_E = KEY_E, letter,
+    KC_F = KEY_F, letter,
+    KC_G = KEY_G, letter,
+    KC_H = KEY_H, letter,
+    KC_I = KEY_I, letter,
+    KC_J = KEY_J, letter,
+    KC_K = KEY_K, letter,
+    KC_L = KEY_L, letter,
+    KC_M = KEY_M, letter,
+    KC_N = KEY_N, letter,
+    KC_O = KEY_O, letter,
+    KC_P = KEY_P, letter,
+    KC_Q = KEY_Q, letter,
+    KC_R = KEY_R, letter,
+    KC_S = KEY_S, letter,
+    KC_T = KEY_T, letter,
+    KC_U = KEY_U, letter,
+    KC_V = KEY_V, letter,
+    KC_W = KEY_W, letter,
+    KC_X = KEY_X, letter,
+    KC_Y = KEY_Y, letter,
+    KC_Z = KEY_Z, letter,
 
     // Numbers
-    KC_1 = 2, number,
-    KC_2 = 3, number,
-    KC_3 = 4, number,
-    KC_4 = 5, number,
-    KC_5 = 6, number,
-    KC_6 = 7, number,
-    KC_7 = 8, number,
-    KC_8 = 9, number,
-    KC_9 = 10, number,
-    KC_0 = 11, number,
+    KC_1 = KEY_1, number,
+    KC_2 = KEY_2, number,
+    KC_3 = KEY_3, number,
+    KC_4 = KEY_4, number,
+    KC_5 = KEY_5, number,
+    KC_6 = KEY_6, number,
+    KC_7 = KEY_7, number,
+    KC_8 = KEY_8, number,
+    KC_9 = KEY_9, number,
+    KC_0 = KEY_0, number,
 
     // Modifiers
-    KC_LCTL = 29, modifier,
-    KC_LSFT = 42, modifier,
-    KC_LALT = 56, modifier,
-    KC_LGUI = 125, modifier,
-    KC_RCTL = 97, modifier,
-    KC_RSFT = 54, modifier,
-    KC_RALT = 100, modifier,
-    KC_RGUI = 126, modifier,
+    KC_LCTL = KEY_LEFTCTRL, modifier,
+    KC_LSFT = KEY_LEFTSHIFT, modifier,
+    KC_LALT = KEY_LEFTALT, modifier,
+    KC_LGUI = KEY_LEFTMETA, modifier, [KC_LCMD, KC_LWIN],
+    KC_RCTL = KEY_RIGHTCTRL, modifier,
+    KC_RSFT = KEY_RIGHTSHIFT, modifier,
+    KC_RALT = KEY_RIGHTALT, modifier,
+    KC_RGUI = KEY_RIGHTMETA, modifier, [KC_RCMD, KC_RWIN],
 
     // Special keys
-    KC_NO = 0, general,
-    KC_ESC = 1, special,
-    KC_CAPS = 58, lock,
-    KC_TAB = 15, special,
-    KC_SPC = 57, special,
-    KC_ENT = 28, special,
-    KC_BSPC = 14, special,
-    KC_DEL = 111, special,
-    KC_GRV = 41, special,
-    KC_MINS = 12, special,
-    KC_EQL = 13, special,
-    KC_LBRC = 26, special,
-    KC_RBRC = 27, special,
-    KC_BSLS = 43, special,
-    KC_SCLN = 39, special,
-    KC_QUOT = 40, special,
-    KC_COMM = 51, special,
-    KC_DOT = 52, special,
-    KC_SLSH = 53, special,
+    KC_NO = KEY_RESERVED, general,
+    KC_ESC = KEY_ESC, special, [KC_ESCAPE],
+    KC_CAPS = KEY_CAPSLOCK, lock,
+    KC_TAB = KEY_TAB, special,
+    KC_SPC = KEY_SPACE, special, [KC_SPACE],
+    KC_ENT = KEY_ENTER, special, [KC_ENTER],
+    KC_BSPC = KEY_BACKSPACE, special, [KC_BSPACE],
+    KC_DEL = KEY_DELETE, special,
+    KC_GRV = KEY_GRAVE, special,
+    KC_MINS = KEY_MINUS, special,
+    KC_EQL = KEY_EQUAL, special,
+    KC_LBRC = KEY_LEFTBRACE, special,
+    KC_RBRC = KEY_RIGHTBRACE, special,
+    KC_BSLS = KEY_BACKSLASH, special,
+    KC_SCLN = KEY_SEMICOLON, special,
+    KC_QUOT = KEY_APOSTROPHE, special,
+    KC_COMM = KEY_COMMA, special,
+    KC_DOT = KEY_DOT, special,
+    KC_SLSH = KEY_SLASH, special,
+    KC_NUBS = KEY_102ND, special,
 
     // Print Screen / System keys
-    KC_PSCR = 99, special,
-    KC_BRK = 101, special,
+    KC_PSCR = KEY_SYSRQ, special,
+    KC_PAUS = KEY_PAUSE, special, [KC_BRK],
 
     // Arrow keys
-    KC_LEFT = 105, navigation,
-    KC_DOWN = 108, navigation,
-    KC_UP = 103, navigation,
-    KC_RGHT = 106, navigation,
+    KC_LEFT = KEY_LEFT, navigation,
+    KC_DOWN = KEY_DOWN, navigation,
+    KC_UP = KEY_UP, navigation,
+    KC_RGHT = KEY_RIGHT, navigation,
 
     // Function keys
-    KC_F1 = 59, function,
-    KC_F2 = 60, function,
-    KC_F3 = 61, function,
-    KC_F4 = 62, function,
-    KC_F5 = 63, function,
-    KC_F6 = 64, function,
-    KC_F7 = 65, function,
-    KC_F8 = 66, function,
-    KC_F9 = 67, function,
-    KC_F10 = 68, function,
-    KC_F11 = 69, function,
-    KC_F12 = 70, function,
+    KC_F1 = KEY_F1, function,
+    KC_F2 = KEY_F2, function,
+    KC_F3 = KEY_F3, function,
+    KC_F4 = KEY_F4, function,
+    KC_F5 = KEY_F5, function,
+    KC_F6 = KEY_F6, function,
+    KC_F7 = KEY_F7, function,
+    KC_F8 = KEY_F8, function,
+    KC_F9 = KEY_F9, function,
+    KC_F10 = KEY_F10, function,
+    KC_F11 = KEY_F11, function,
+    KC_F12 = KEY_F12, function,
 
     // Special function keys
-    KC_F13 = 183, function,
-    KC_F14 = 184, function,
-    KC_F15 = 185, function,
-    KC_F16 = 186, function,
-    KC_F17 = 187, function,
-    KC_F18 = 188, function,
-    KC_F19 = 189, function,
-    KC_F20 = 190, function,
-    KC_F21 = 191, function,
-    KC_F22 = 192, function,
-    KC_F23 = 193, function,
-    KC_F24 = 194, function,
+    KC_F13 = KEY_F13, function,
+    KC_F14 = KEY_F14, function,
+    KC_F15 = KEY_F15, function,
+    KC_F16 = KEY_F16, function,
+    KC_F17 = KEY_F17, function,
+    KC_F18 = KEY_F18, function,
+    KC_F19 = KEY_F19, function,
+    KC_F20 = KEY_F20, function,
+    KC_F21 = KEY_F21, function,
+    KC_F22 = KEY_F22, function,
+    KC_F23 = KEY_F23, function,
+    KC_F24 = KEY_F24, function,
 
     // Lock keys
-    KC_SLCK = 214, lock,
-    KC_NLCK = 215, lock,
-    KC_PAUS = 216, special,
+    KC_SCRL = KEY_SCROLLLOCK, lock, [KC_SLCK],
+    KC_NLCK = KEY_NUMLOCK, lock, [KC_NUM],
 
     // Navigation
-    KC_INS = 110, navigation,
-    KC_HOME = 102, navigation,
-    KC_PGUP = 104, navigation,
-    KC_END = 107, navigation,
-    KC_PGDN = 109, navigation,
+    KC_INS = KEY_INSERT, navigation,
+    KC_HOME = KEY_HOME, navigation,
+    KC_PGUP = KEY_PAGEUP, navigation,
+    KC_END = KEY_END, navigation,
+    KC_PGDN = KEY_PAGEDOWN, navigation,
 
     // Numpad
-    KC_NUBS = 86, numpad,
-    KC_PSLS = 200, numpad,
-    KC_PAST = 201, numpad,
-    KC_PMNS = 82, numpad,
-    KC_PPLS = 87, numpad,
-    KC_PENT = 202, numpad,
-    KC_P1 = 203, numpad,
-    KC_P2 = 204, numpad,
-    KC_P3 = 205, numpad,
-    KC_P4 = 206, numpad,
-    KC_P5 = 207, numpad,
-    KC_P6 = 208, numpad,
-    KC_P7 = 209, numpad,
-    KC_P8 = 210, numpad,
-    KC_P9 = 211, numpad,
-    KC_P0 = 212, numpad,
-    KC_PDOT = 213, numpad,
+    KC_PSLS = KEY_KPSLASH, numpad, [KC_KP_SLASH],
+    KC_PAST = KEY_KPASTERISK, numpad, [KC_KP_ASTERISK],
+    KC_PMNS = KEY_KPMINUS, numpad, [KC_KP_MINUS],
+    KC_PPLS = KEY_KPPLUS, numpad, [KC_KP_PLUS],
+    KC_PENT = KEY_KPENTER, numpad, [KC_KP_ENTER],
+    KC_P1 = KEY_KP1, numpad, [KC_KP_1],
+    KC_P2 = KEY_KP2, numpad, [KC_KP_2],
+    KC_P3 = KEY_KP3, numpad, [KC_KP_3],
+    KC_P4 = KEY_KP4, numpad, [KC_KP_4],
+    KC_P5 = KEY_KP5, numpad, [KC_KP_5],
+    KC_P6 = KEY_KP6, numpad, [KC_KP_6],
+    KC_P7 = KEY_KP7, numpad, [KC_KP_7],
+    KC_P8 = KEY_KP8, numpad, [KC_KP_8],
+    KC_P9 = KEY_KP9, numpad, [KC_KP_9],
+    KC_P0 = KEY_KP0, numpad, [KC_KP_0],
+    KC_PDOT = KEY_KPDOT, numpad, [KC_KP_DOT],
+    KC_PEQL = KEY_KPEQUAL, numpad, [KC_KP_EQUAL],
+    KC_PCMM = KEY_KPCOMMA, numpad, [KC_KP_COMMA],
 
     // Media keys
-    KC_MUTE = 217, media,
-    KC_VOLD = 218, media,
-    KC_VOLU = 219, media,
+    KC_MUTE = KEY_MUTE, media,
+    KC_VOLD = KEY_VOLUMEDOWN, media,
+    KC_VOLU = KEY_VOLUMEUP, media,
+    KC_MPLY = KEY_PLAYPAUSE, media,
+    KC_MSTP = KEY_STOPCD, media,
+    KC_MNXT = KEY_NEXTSONG, media,
+    KC_MPRV = KEY_PREVIOUSSONG, media,
+    KC_BRIU = KEY_BRIGHTNESSUP, media,
+    KC_BRID = KEY_BRIGHTNESSDOWN, media,
 
     // Application keys
-    KC_APP = 220, special,
-    KC_HELP = 221, special,
-    KC_SCRL = 222, lock,
-    KC_ASST = 226, special,
+    KC_APP = KEY_COMPOSE, special,
+    KC_MENU = KEY_MENU, special,
+    KC_HELP = KEY_HELP, special,
+    KC_ASST = KEY_ASSISTANT, special,
+    KC_CALC = KEY_CALC, special,
+    KC_MAIL = KEY_MAIL, special,
+    KC_MYCM = KEY_COMPUTER, special,
+    KC_WSCH = KEY_SEARCH, special,
+    KC_WHOM = KEY_HOMEPAGE, special,
+    KC_WBAK = KEY_BACK, special,
+    KC_WFWD = KEY_FORWARD, special,
+    KC_WREF = KEY_REFRESH, special,
 
     // Power management
-    KC_PWR = 223, special,
-    KC_SLEP = 224, special,
-    KC_WAKE = 225, special,
+    KC_PWR = KEY_POWER, special,
+    KC_SLEP = KEY_SLEEP, special,
+    KC_WAKE = KEY_WAKEUP, special,
 
     // International keys (Japanese)
-    KC_INT1 = 121, international,
-    KC_INT2 = 122, international,
-    KC_INT3 = 123, international,
-    KC_INT4 = 124, international,
-    KC_INT5 = 128, international,
-
-    // Language keys
-    KC_LANG1 = 131, international,
-    KC_LANG2 = 132, international,
-    KC_LANG3 = 133, international,
-    KC_LANG4 = 134, international,
-    KC_LANG5 = 135, international,
-    KC_LANG6 = 136, international,
-    KC_LANG7 = 137, international,
-    KC_LANG8 = 138, international,
-    KC_LANG9 = 139, international,
-
-    // Korean keys
-    KC_HAEN = 140, international,
-    KC_HANJ = 141, international,
+    KC_INT1 = KEY_RO, international,
+    KC_INT2 = KEY_KATAKANAHIRAGANA, international,
+    KC_INT3 = KEY_YEN, international,
+    KC_INT4 = KEY_HENKAN, international,
+    KC_INT5 = KEY_MUHENKAN, international,
+
+    // Language keys (LANG1/LANG2 are the Korean Han/Yeong and Hanja keys)
+    KC_LANG1 = KEY_HANGEUL, international, [KC_HAEN],
+    KC_LANG2 = KEY_HANJA, international, [KC_HANJ],
+    KC_LANG3 = KEY_KATAKANA, international,
+    KC_LANG4 = KEY_HIRAGANA, international,
+    KC_LANG5 = KEY_ZENKAKUHANKAKU, international,
 }
 
-// Aliases for common alternative names (QMK compatibility)
 impl KeyCode {
-    /// Alias for KC_LGUI (Left Command key on Mac)
-    pub const KC_LCMD: Self = Self::KC_LGUI;
-    /// Alias for KC_RGUI (Right Command key on Mac)
-    pub const KC_RCMD: Self = Self::KC_RGUI;
-    /// Alias for KC_LGUI (Left Windows key)
-    pub const KC_LWIN: Self = Self::KC_LGUI;
-    /// Alias for KC_RGUI (Right Windows key)
-    pub const KC_RWIN: Self = Self::KC_RGUI;
-    /// Alias for KC_BSPC
-    pub const KC_BSPACE: Self = Self::KC_BSPC;
-    /// Alias for KC_ENT
-    pub const KC_ENTER: Self = Self::KC_ENT;
-    /// Alias for KC_ESC
-    pub const KC_ESCAPE: Self = Self::KC_ESC;
-    /// Alias for KC_SPC
-    pub const KC_SPACE: Self = Self::KC_SPC;
+    /// Parse `KC_RAW(code)` with a decimal or `0x` hex code
+    fn from_raw_name(name: &str) -> Option<Self> {
+        let code = name.strip_prefix("KC_RAW(")?.strip_suffix(')')?.trim();
+        let code = match code.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok()?,
+            None => code.parse().ok()?,
+        };
+        Self::from_evdev_code(code)
+    }
+}
+
+impl std::fmt::Display for KeyCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KC_RAW(code) => write!(f, "KC_RAW({:#x})", code),
+            key => f.write_str(key.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evdev_codes_round_trip() {
+        for &key in KeyCode::NAMED {
+            assert_eq!(KeyCode::from_evdev_code(key.code()), Some(key), "{}", key);
+            assert_eq!(KeyCode::from_name(key.name()), Some(key));
+            let ron = ron::to_string(&key).unwrap();
+            assert_eq!(ron::from_str::<KeyCode>(&ron).unwrap(), key);
+        }
+        for code in (0..=KEY_MAX).filter(|&code| code != Key::KEY_UNKNOWN.code()) {
+            let key = KeyCode::from_evdev_code(code).unwrap();
+            assert_eq!(key.code(), code);
+            assert_eq!(KeyCode::from_name(&key.to_string()), Some(key));
+        }
+        assert_eq!(KeyCode::from_evdev_code(Key::KEY_UNKNOWN.code()), None);
+        assert_eq!(KeyCode::from_evdev_code(KEY_MAX + 1), None);
+
+        assert_eq!(KeyCode::KC_F11.code(), Key::KEY_F11.code());
+        assert_eq!(KeyCode::from_name("KC_SLCK"), Some(KeyCode::KC_SCRL));
+        assert!(KeyCode::KC_SCRL.aliases().contains(&"KC_SLCK"));
+        assert_eq!(KeyCode::KC_LGUI.evdev_name(), "KEY_LEFTMETA");
+        assert_eq!(
+            ron::from_str::<KeyCode>("KC_KP_ASTERISK").unwrap(),
+            KeyCode::KC_PAST
+        );
+        // A raw code with a name is that key, so both spellings bind the same thing
+        assert_eq!(
+            ron::from_str::<KeyCode>("KC_RAW(30)").unwrap(),
+            KeyCode::KC_A
+        );
+        assert_eq!(
+            ron::from_str::<KeyCode>("KC_RAW(0x1d2)").unwrap(),
+            KeyCode::KC_RAW(0x1d2)
+        );
+    }
 }
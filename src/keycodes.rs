@@ -0,0 +1,54 @@
+use colored::Colorize;
+use keymux::keycode::KeyCode;
+
+/// Print every named key (or those matching `search`), grouped by category
+pub fn run_keycodes(search: Option<&str>) {
+    let search = search.map(str::to_uppercase);
+    let matches = |key: &KeyCode| {
+        search.as_deref().is_none_or(|search| {
+            key.name().contains(search)
+                || key.evdev_name().contains(search)
+                || key.aliases().iter().any(|alias| alias.contains(search))
+        })
+    };
+    let mut keys: Vec<KeyCode> = KeyCode::NAMED.iter().copied().filter(matches).collect();
+    // Table order within each category
+    keys.sort_by_key(|key| key.category() as u8);
+
+    println!();
+    if keys.is_empty() {
+        println!(
+            "  {} No key names contain \"{}\"",
+            "ℹ".bright_blue(),
+            search.unwrap_or_default()
+        );
+    }
+    let mut category = None;
+    for key in &keys {
+        if category != Some(key.category()) {
+            category = Some(key.category());
+            println!(
+                "{}",
+                format!("═══ {:?} ═══", key.category()).bright_cyan().bold()
+            );
+        }
+        let aliases = if key.aliases().is_empty() {
+            String::new()
+        } else {
+            format!("  also {}", key.aliases().join(", "))
+        };
+        println!(
+            "  {:<10} {:>5}  {:<22}{}",
+            key.name().bright_white().bold(),
+            key.code(),
+            key.evdev_name().dimmed(),
+            aliases.bright_yellow()
+        );
+    }
+    println!();
+    println!(
+        "  {}",
+        "Any other evdev key can be written as KC_RAW(code), e.g. KC_RAW(464)".dimmed()
+    );
+    println!();
+}
@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
+use evdev::{AttributeSet, Device, EventType, InputEvent, Key};
+use keymux::config::Config;
+use keymux::event_processor::jitter::TypingJitter;
+use keymux::event_processor::{run_processor, ProcessorStatus};
+use keymux::keyboard_id::KeyboardId;
+use keymux::keycode::KeyCode;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+const PROBE_NAME: &str = "keymux latency probe";
+/// Keys tapped on the probe, the first one the config doesn't remap
+const PROBE_KEYS: &[KeyCode] = &[
+    KeyCode::KC_F24,
+    KeyCode::KC_F23,
+    KeyCode::KC_F22,
+    KeyCode::KC_F21,
+];
+/// Longest a tap may take to come out before the run gives up
+const SAMPLE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Pause between taps, uneven so it can't line up with the processor's poll interval
+const TAP_SPACING_MS: (u32, u32) = (5, 15);
+
+/// Tap a key on a virtual keyboard and time it through the kernel alone, then through
+/// a keymux processor (grab, keymap, virtual keyboard) running the user's config
+///
+/// Both runs read the kernel timestamp of the event coming out, so the reader's own
+/// polling doesn't count, and the difference is what keymux adds.
+pub fn run_latency_test(samples: u32, config_path: Option<&Path>) -> Result<()> {
+    let config_path = match config_path {
+        Some(path) => path.to_path_buf(),
+        None => Config::default_path()?,
+    };
+    let config = Config::load_with_state(&config_path)
+        .with_context(|| format!("Failed to load {}", config_path.display()))?;
+    let probe_key = PROBE_KEYS
+        .iter()
+        .copied()
+        .find(|key| !config.remaps.contains_key(key))
+        .context("The config remaps every key the probe could tap (F21-F24)")?;
+
+    let mut keys = AttributeSet::<Key>::new();
+    keys.insert(Key::new(probe_key.code()));
+    let mut probe = VirtualDeviceBuilder::new()
+        .and_then(|builder| builder.name(PROBE_NAME).with_keys(&keys))
+        .and_then(VirtualDeviceBuilder::build)
+        .context("Failed to create the probe keyboard (needs write access to /dev/uinput)")?;
+    let probe_node = dev_node(&mut probe)?;
+
+    println!();
+    println!("{}", "═══ Latency test ═══".bright_cyan().bold());
+    println!(
+        "  {}",
+        format!(
+            "{} taps of {} per run, config {}",
+            samples,
+            probe_key,
+            config_path.display()
+        )
+        .dimmed()
+    );
+
+    let mut reader = open_nonblocking(&probe_node)?;
+    let baseline = measure(&mut probe, &mut reader, probe_key, samples)
+        .context("Taps on the probe never arrived")?;
+    drop(reader);
+
+    let mut processor = start_processor(&probe_node, &config, &config_path)?;
+    let keymux = measure(&mut probe, &mut processor.output, probe_key, samples);
+    processor.stop();
+    let keymux = keymux?;
+
+    println!();
+    print_stats("Passthrough", &baseline);
+    print_stats("keymux", &keymux);
+    let added = |pick: fn(&[Duration]) -> Duration| pick(&keymux).saturating_sub(pick(&baseline));
+    println!();
+    println!(
+        "  {} {} median, {} at p99",
+        "Added by keymux:".bright_white().bold(),
+        format_duration(added(median)).bright_green(),
+        format_duration(added(p99)).bright_green()
+    );
+    println!();
+    Ok(())
+}
+
+/// A processor on the probe, run the way the daemon runs one
+struct ProbeProcessor {
+    /// The virtual keyboard it types through
+    output: Device,
+    shutdown: crossbeam_channel::Sender<()>,
+    thread: std::thread::JoinHandle<Option<String>>,
+}
+
+impl ProbeProcessor {
+    /// Let the processor release its keys and ungrab the probe
+    fn stop(self) {
+        let _ = self.shutdown.send(());
+        let _ = self.thread.join();
+    }
+}
+
+/// Start a processor on the probe and open the virtual keyboard it types through
+fn start_processor(
+    probe_node: &Path,
+    config: &Config,
+    config_path: &Path,
+) -> Result<ProbeProcessor> {
+    let device = Device::open(probe_node)?;
+    let keyboard_id = KeyboardId::from_device(&device, probe_node);
+    let mut config = config.for_keyboard(&keyboard_id.to_string());
+    config.observe_only = false;
+
+    let (shutdown_tx, shutdown_rx) = crossbeam_channel::bounded(1);
+    let (game_mode_tx, game_mode_rx) = std::sync::mpsc::channel();
+    let (save_stats_tx, save_stats_rx) = std::sync::mpsc::channel();
+    let (command_tx, command_rx) = crossbeam_channel::unbounded();
+    let (daemon_tx, daemon_rx) = tokio::sync::mpsc::unbounded_channel();
+    let event_path = Arc::new(std::sync::Mutex::new(probe_node.to_path_buf()));
+    let config_path = config_path.to_path_buf();
+    let (uid, _) = keymux::get_actual_user_uid();
+    let thread = std::thread::spawn(move || {
+        // Keep the channels open for as long as the processor runs
+        let _channels = (game_mode_tx, save_stats_tx, command_tx, daemon_rx);
+        run_processor(
+            keyboard_id,
+            device,
+            event_path,
+            PROBE_NAME.to_string(),
+            config,
+            config_path,
+            uid,
+            shutdown_rx,
+            game_mode_rx,
+            save_stats_rx,
+            command_rx,
+            daemon_tx,
+            Arc::new(ProcessorStatus::default()),
+        )
+    });
+
+    let output_name = format!("keymux: {}", PROBE_NAME);
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while Instant::now() < deadline {
+        if thread.is_finished() {
+            let error = thread.join().ok().flatten();
+            anyhow::bail!(
+                "The processor stopped: {}",
+                error.unwrap_or_else(|| "no error given".to_string())
+            );
+        }
+        let output = evdev::enumerate()
+            .find(|(_, device)| device.name() == Some(output_name.as_str()))
+            .map(|(path, _)| path);
+        if let Some(path) = output {
+            let processor = ProbeProcessor {
+                output: open_nonblocking(&path)?,
+                shutdown: shutdown_tx,
+                thread,
+            };
+            return Ok(processor);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let _ = shutdown_tx.send(());
+    let _ = thread.join();
+    anyhow::bail!("The processor's virtual keyboard didn't show up")
+}
+
+/// Tap the probe key and time each press until it comes out of `output`
+fn measure(
+    probe: &mut VirtualDevice,
+    output: &mut Device,
+    key: KeyCode,
+    samples: u32,
+) -> Result<Vec<Duration>> {
+    let key = Key::new(key.code());
+    let mut spacing = TypingJitter::new(Some(TAP_SPACING_MS));
+    let mut latencies = Vec::with_capacity(samples as usize);
+    for _ in 0..samples {
+        let sent = SystemTime::now();
+        probe.emit(&[InputEvent::new(EventType::KEY, key.code(), 1)])?;
+        let arrived = wait_for_press(output)?.context("A tap didn't come out within a second")?;
+        latencies.push(arrived.duration_since(sent).unwrap_or_default());
+
+        probe.emit(&[InputEvent::new(EventType::KEY, key.code(), 0)])?;
+        if let Some(spacing) = spacing.as_mut() {
+            std::thread::sleep(spacing.next_pause());
+        }
+        // The release and anything else the tap sent
+        while output
+            .fetch_events()
+            .is_ok_and(|mut events| events.next().is_some())
+        {}
+    }
+    latencies.sort();
+    Ok(latencies)
+}
+
+/// Kernel timestamp of the next key press on a device
+fn wait_for_press(device: &mut Device) -> Result<Option<SystemTime>> {
+    let deadline = Instant::now() + SAMPLE_TIMEOUT;
+    while Instant::now() < deadline {
+        match device.fetch_events() {
+            Ok(events) => {
+                let press = events
+                    .filter(|ev| ev.event_type() == EventType::KEY && ev.value() == 1)
+                    .map(|ev| ev.timestamp())
+                    .next();
+                if press.is_some() {
+                    return Ok(press);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e.into()),
+        }
+        std::hint::spin_loop();
+    }
+    Ok(None)
+}
+
+fn dev_node(device: &mut VirtualDevice) -> Result<PathBuf> {
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while Instant::now() < deadline {
+        if let Some(Ok(path)) = device.enumerate_dev_nodes_blocking()?.next() {
+            return Ok(path);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    anyhow::bail!("The probe keyboard got no /dev/input node")
+}
+
+fn open_nonblocking(path: &Path) -> Result<Device> {
+    let device =
+        Device::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let fd = device.as_raw_fd();
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL, 0);
+        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+    }
+    Ok(device)
+}
+
+fn median(sorted: &[Duration]) -> Duration {
+    sorted[sorted.len() / 2]
+}
+
+fn p99(sorted: &[Duration]) -> Duration {
+    sorted[(sorted.len() * 99 / 100).min(sorted.len() - 1)]
+}
+
+fn print_stats(label: &str, sorted: &[Duration]) {
+    let mean = sorted.iter().sum::<Duration>() / sorted.len() as u32;
+    println!(
+        "  {:<12} min {}  median {}  mean {}  p99 {}  max {}",
+        label.bright_white().bold(),
+        format_duration(sorted[0]),
+        format_duration(median(sorted)),
+        format_duration(mean),
+        format_duration(p99(sorted)),
+        format_duration(sorted[sorted.len() - 1])
+    );
+}
+
+/// `0.412ms`
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
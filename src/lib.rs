@@ -1,15 +1,20 @@
 #![allow(clippy::pedantic, clippy::module_inception)]
 
+pub mod battery;
 pub mod config;
 pub mod daemon;
 pub mod event_processor;
+pub mod hooks;
 pub mod hyprland;
 pub mod ipc;
 pub mod keyboard_id;
 pub mod keycode;
 pub mod niri;
+pub mod notify;
+pub mod paths;
 pub mod session_manager;
 pub mod ui;
+pub mod user_fs;
 pub mod window_manager;
 pub mod x11;
 
@@ -52,3 +57,33 @@ pub fn get_user_home_dir(uid: u32) -> anyhow::Result<PathBuf> {
 
     Ok(PathBuf::from(home))
 }
+
+/// Get a user's UID from their username using getent
+pub fn get_user_uid_by_name(username: &str) -> anyhow::Result<u32> {
+    let output = std::process::Command::new("getent")
+        .arg("passwd")
+        .arg(username)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Unknown user: {}", username));
+    }
+
+    let entry = String::from_utf8(output.stdout)?;
+    entry
+        .trim()
+        .split(':')
+        .nth(2)
+        .and_then(|uid| uid.parse::<u32>().ok())
+        .ok_or_else(|| anyhow::anyhow!("Failed to parse UID for user {}", username))
+}
+
+/// Current local time as (weekday, minutes since midnight), with Monday = 0
+pub fn local_weekday_and_minute() -> (u32, u32) {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&now, &mut tm) };
+    // tm_wday counts from Sunday
+    let weekday = (tm.tm_wday + 6) % 7;
+    (weekday as u32, (tm.tm_hour * 60 + tm.tm_min) as u32)
+}
@@ -2,9 +2,12 @@ use anyhow::Result;
 use colored::Colorize;
 
 use keymux::config::Config;
+use keymux::ipc::{send_request, IpcRequest, IpcResponse, KeyboardInfo};
 use keymux::keyboard_id::{find_all_keyboards, KeyboardId};
 
-pub fn run_list() -> Result<()> {
+pub fn run_list(user: Option<&str>) -> Result<()> {
+    let (config_path, _) = crate::toggle::user_scope(user)?;
+
     println!();
     println!(
         "{}",
@@ -15,11 +18,13 @@ pub fn run_list() -> Result<()> {
         "{}",
         "═══════════════════════════════════════".bright_cyan()
     );
+    if let Some(user) = user {
+        println!("  {} {}", "User:".bright_yellow(), user.bright_white());
+    }
     println!();
 
-    // Load current config to check enabled keyboards
-    let config_path = Config::default_path()?;
-    let config = Config::load(&config_path)?;
+    // Load the user's config to check enabled keyboards
+    let config = Config::load_with_state(&config_path)?;
 
     // Find all keyboards
     let keyboards = find_all_keyboards();
@@ -38,6 +43,12 @@ pub fn run_list() -> Result<()> {
         return Ok(());
     }
 
+    // Ownership and live state, when the daemon is running
+    let daemon_info: Vec<KeyboardInfo> = match send_request(&IpcRequest::ListKeyboards) {
+        Ok(IpcResponse::KeyboardList(keyboards)) => keyboards,
+        _ => Vec::new(),
+    };
+
     // Sort keyboards by name
     let mut items: Vec<(KeyboardId, String)> = keyboards
         .into_iter()
@@ -62,6 +73,12 @@ pub fn run_list() -> Result<()> {
             println!("    {} {}", "○".dimmed(), name.dimmed());
         }
         println!("      {} {}", "ID:".dimmed(), id.to_string().dimmed());
+        if let Some(info) = daemon_info
+            .iter()
+            .find(|info| info.hardware_id == id.to_string())
+        {
+            print_daemon_state(info);
+        }
         println!();
     }
 
@@ -81,10 +98,14 @@ pub fn run_list() -> Result<()> {
             "⚠".bright_yellow(),
             "No keyboards enabled".yellow()
         );
+        let toggle = match user {
+            Some(user) => format!("sudo keymux toggle --user {}", user),
+            None => "keymux toggle".to_string(),
+        };
         println!(
             "  {} Run {} to enable keyboards",
             "Tip:".bright_yellow().bold(),
-            "keymux toggle".bright_white()
+            toggle.bright_white()
         );
     } else {
         println!(
@@ -99,3 +120,95 @@ pub fn run_list() -> Result<()> {
 
     Ok(())
 }
+
+/// Ask the daemon to run keyboards it quarantined again
+pub fn run_unquarantine(pattern: &str) -> Result<()> {
+    match send_request(&IpcRequest::Unquarantine(pattern.to_string())) {
+        Ok(IpcResponse::Ok) => {
+            println!(
+                "  {} {}",
+                "✓".bright_green().bold(),
+                format!("Released \"{}\" from quarantine", pattern).green()
+            );
+            Ok(())
+        }
+        Ok(IpcResponse::Error(msg)) => {
+            eprintln!("  {} {}", "✗".bright_red().bold(), msg.red());
+            anyhow::bail!("Failed to unquarantine");
+        }
+        Ok(response) => anyhow::bail!("Unexpected response from daemon: {:?}", response),
+        Err(e) => anyhow::bail!("Failed to connect to daemon: {}", e),
+    }
+}
+
+/// Who holds a keyboard and what state its processors are in
+fn print_daemon_state(info: &KeyboardInfo) {
+    let owner = match (&info.owner_name, info.owner_uid) {
+        (Some(name), Some(uid)) => format!("{} (uid {})", name, uid),
+        (None, Some(uid)) => format!("uid {}", uid),
+        _ => "nobody".to_string(),
+    };
+    println!("      {} {}", "Owner:".dimmed(), owner.bright_white());
+    if let Some(quarantine) = &info.quarantine {
+        println!(
+            "      {} {}",
+            "Quarantined:".bright_red(),
+            format!(
+                "crashed {} times in a row: {}",
+                quarantine.crashes, quarantine.error
+            )
+            .red()
+        );
+        println!(
+            "      {}",
+            format!("keymux unquarantine \"{}\" to try again", info.name).dimmed()
+        );
+    }
+    if let Some(battery) = info.battery {
+        let level = battery.to_string();
+        let level = if battery.percent <= 15 && !battery.charging {
+            level.bright_red()
+        } else {
+            level.normal()
+        };
+        println!("      {} {}", "Battery:".dimmed(), level);
+    }
+    if let Some(layer) = &info.active_layer {
+        println!(
+            "      {} layer {}, game mode {}",
+            "State:".dimmed(),
+            layer.bright_white(),
+            if info.game_mode {
+                "on".bright_yellow()
+            } else {
+                "off".normal()
+            }
+        );
+        println!(
+            "      {} {} keys in, {} out{}",
+            "Events:".dimmed(),
+            info.events_in,
+            info.events_out,
+            if info.evictions > 0 {
+                format!(", {} dropped (flooding?)", info.evictions)
+                    .bright_yellow()
+                    .to_string()
+            } else {
+                String::new()
+            }
+        );
+    }
+    for node in &info.event_nodes {
+        println!(
+            "      {} {} {}{}",
+            "Node:".dimmed(),
+            node.path,
+            format!("({})", node.capabilities()).dimmed(),
+            if node.processing {
+                format!(" {}", "●".bright_green())
+            } else {
+                String::new()
+            }
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use crate::typing::resolve_target_uid;
+use anyhow::Result;
+use keymux::ipc::{send_request, IpcRequest, IpcResponse};
+
+/// Print locked modifiers one per line; nothing when none are locked
+pub fn run_locked(user: Option<&str>) -> Result<()> {
+    let uid = resolve_target_uid(user)?;
+    match send_request(&IpcRequest::GetLockedModifiers { uid })? {
+        IpcResponse::LockedModifiers(modifiers) => {
+            for modifier in modifiers {
+                println!("{}", modifier);
+            }
+            Ok(())
+        }
+        IpcResponse::Error(e) => anyhow::bail!(e),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+}
@@ -4,13 +4,26 @@ use anyhow::Result;
 use clap::{CommandFactory, Parser};
 
 mod adaptive_stats;
+mod behavior_tests;
+mod capture;
 mod cli;
 mod gamemode;
+mod keycodes;
+mod latency_test;
 
 mod debug;
+mod effective;
 pub mod keycode;
 mod list;
+mod locked;
+mod mt_trace;
+mod preset;
+mod profile;
+mod shadow;
+mod status;
 mod toggle;
+mod typing;
+mod watch;
 
 pub use keymux::{get_actual_user_uid, get_user_home_dir};
 
@@ -27,7 +40,23 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match &cli.command {
-        Some(cli::Commands::Daemon { config, user }) => {
+        Some(cli::Commands::Daemon {
+            config,
+            user,
+            socket,
+            state_dir,
+            check,
+        }) => {
+            keymux::paths::set_overrides(keymux::paths::PathOverrides {
+                config: config.clone(),
+                socket: socket.clone(),
+                state_dir: state_dir.clone(),
+            });
+            if *check {
+                keymux::daemon::check::run_check()?;
+                return Ok(());
+            }
+
             tracing_subscriber::fmt()
                 .with_target(false)
                 .with_thread_ids(false)
@@ -58,17 +87,24 @@ fn main() -> Result<()> {
         Some(cli::Commands::BspwmDaemon) => {
             keymux::x11::run_bspwm_daemon()?;
         }
-        Some(cli::Commands::List) => {
-            list::run_list()?;
+        Some(cli::Commands::List { user }) => {
+            list::run_list(user.as_deref())?;
+        }
+        Some(cli::Commands::Unquarantine { keyboard }) => {
+            list::run_unquarantine(keyboard)?;
         }
-        Some(cli::Commands::Toggle { patterns, multi }) => {
+        Some(cli::Commands::Toggle {
+            patterns,
+            multi,
+            user,
+        }) => {
             if !*multi && patterns.is_empty() {
                 // Run interactive toggle menu
-                toggle::run_toggle(false, None)?;
+                toggle::run_toggle(false, None, user.as_deref())?;
             } else if !*multi && !patterns.is_empty() {
                 // Handle toggle patterns directly
-                let config_path = keymux::config::Config::default_path()?;
-                let mut config = keymux::config::Config::load(&config_path)?;
+                let (config_path, uid) = toggle::user_scope(user.as_deref())?;
+                let mut config = keymux::config::Config::load_with_state(&config_path)?;
                 let keyboards = keymux::keyboard_id::find_all_keyboards();
                 let items: Vec<_> = keyboards
                     .into_iter()
@@ -77,14 +113,20 @@ fn main() -> Result<()> {
                 toggle::handle_toggle_patterns(
                     &mut config,
                     &config_path,
+                    uid,
                     patterns.clone(),
                     &items,
                 )?;
             } else {
-                toggle::run_toggle(*multi, None)?;
+                toggle::run_toggle(*multi, None, user.as_deref())?;
             }
         }
-        Some(cli::Commands::Enable { patterns, multi }) => {
+        Some(cli::Commands::Enable {
+            patterns,
+            multi,
+            user,
+            quiet,
+        }) => {
             if !*multi && patterns.is_empty() {
                 if let Some(sub) = Cli::command()
                     .get_subcommands()
@@ -94,9 +136,18 @@ fn main() -> Result<()> {
                 }
                 std::process::exit(0);
             }
-            toggle::run_toggle(*multi, Some((true, patterns.clone())))?;
+            if *multi {
+                toggle::run_toggle(true, Some((true, patterns.clone())), user.as_deref())?;
+            } else {
+                toggle::run_set_enabled(true, patterns.clone(), user.as_deref(), *quiet)?;
+            }
         }
-        Some(cli::Commands::Disable { patterns, multi }) => {
+        Some(cli::Commands::Disable {
+            patterns,
+            multi,
+            user,
+            quiet,
+        }) => {
             if !*multi && patterns.is_empty() {
                 if let Some(sub) = Cli::command()
                     .get_subcommands()
@@ -106,10 +157,58 @@ fn main() -> Result<()> {
                 }
                 std::process::exit(0);
             }
-            toggle::run_toggle(*multi, Some((false, patterns.clone())))?;
+            if *multi {
+                toggle::run_toggle(true, Some((false, patterns.clone())), user.as_deref())?;
+            } else {
+                toggle::run_set_enabled(false, patterns.clone(), user.as_deref(), *quiet)?;
+            }
+        }
+        Some(cli::Commands::Gamemode { action, keyboard }) => {
+            gamemode::handle_gamemode_action(action, keyboard.as_deref())?;
+        }
+        Some(cli::Commands::Type {
+            text,
+            enter,
+            user,
+            keyboard,
+        }) => {
+            typing::run_type(text, *enter, user.as_deref(), keyboard.as_deref())?;
         }
-        Some(cli::Commands::Gamemode { action }) => {
-            gamemode::handle_gamemode_action(action)?;
+        Some(cli::Commands::Inject {
+            keys,
+            user,
+            keyboard,
+        }) => {
+            typing::run_inject(keys, user.as_deref(), keyboard.as_deref())?;
+        }
+        Some(cli::Commands::Profile { name, user }) => {
+            profile::run_profile(name.as_deref(), user.as_deref())?;
+        }
+        Some(cli::Commands::Locked { user }) => {
+            locked::run_locked(user.as_deref())?;
+        }
+        Some(cli::Commands::Watch { user, keyboard }) => {
+            watch::run_watch(user.as_deref(), keyboard.as_deref())?;
+        }
+        Some(cli::Commands::MtTrace {
+            user,
+            keyboard,
+            follow,
+        }) => {
+            mt_trace::run_mt_trace(user.as_deref(), keyboard.as_deref(), *follow)?;
+        }
+        Some(cli::Commands::Shadow { file, user }) => {
+            shadow::run_shadow(file.as_deref(), user.as_deref())?;
+        }
+        Some(cli::Commands::Config {
+            action: cli::ConfigAction::Preset { preset },
+        }) => {
+            preset::run_preset(preset)?;
+        }
+        Some(cli::Commands::Config {
+            action: cli::ConfigAction::Effective { keyboard, json },
+        }) => {
+            effective::run_effective(keyboard, *json)?;
         }
         Some(cli::Commands::Reload) => {
             run_reload()?;
@@ -117,6 +216,21 @@ fn main() -> Result<()> {
         Some(cli::Commands::Validate { config }) => {
             keymux::config::validate_config(config.as_deref())?;
         }
+        Some(cli::Commands::Keycodes { search }) => {
+            keycodes::run_keycodes(search.as_deref());
+        }
+        Some(cli::Commands::Capture { keyboard, seconds }) => {
+            capture::run_capture(keyboard.as_deref(), *seconds)?;
+        }
+        Some(cli::Commands::Test { filter, config }) => {
+            behavior_tests::run_tests(config.as_deref(), filter.as_deref())?;
+        }
+        Some(cli::Commands::LatencyTest { samples, config }) => {
+            latency_test::run_latency_test(*samples, config.as_deref())?;
+        }
+        Some(cli::Commands::Status { internals }) => {
+            status::run_status(*internals)?;
+        }
         Some(cli::Commands::Debug) => {
             debug::run_debug(None)?;
         }
@@ -205,6 +319,41 @@ fn run_reload() -> Result<()> {
     Ok(())
 }
 
+fn gamemode_completions() -> Vec<String> {
+    vec![
+        "on:Turn game mode on".to_string(),
+        "off:Turn game mode off".to_string(),
+        "toggle:Flip the current game mode state".to_string(),
+        "status:Show whether game mode is active".to_string(),
+        "auto:Let the focused window decide again".to_string(),
+        "window:Game mode for focused window".to_string(),
+    ]
+}
+
+fn escape_for_zsh(value: &str) -> String {
+    value.replace(':', "\\:")
+}
+
+/// `default`, `auto` and the profiles defined in the caller's config
+fn profile_completions() -> Vec<String> {
+    let mut profiles: Vec<String> = keymux::config::Config::default_path()
+        .and_then(|path| keymux::config::Config::load(&path))
+        .map(|config| config.profiles.into_keys().collect())
+        .unwrap_or_default();
+    profiles.sort();
+
+    let mut completions = vec![
+        "default:No profile".to_string(),
+        "auto:Follow the schedule".to_string(),
+    ];
+    completions.extend(
+        profiles
+            .iter()
+            .map(|name| format!("{}:Profile from config", escape_for_zsh(name))),
+    );
+    completions
+}
+
 fn handle_dynamic_completion(shell_name: &str) {
     use keymux::keyboard_id::find_all_keyboards;
 
@@ -313,10 +462,6 @@ set -e COMPLETE
 
     let mut completions: Vec<String> = Vec::new();
 
-    fn escape_for_zsh(value: &str) -> String {
-        value.replace(':', "\\:")
-    }
-
     match args.len() {
         0 => {
             // Get subcommands dynamically from clap
@@ -331,6 +476,10 @@ set -e COMPLETE
 
             // Check if it's a subcommand that takes keyboard arguments
             match first_arg.as_str() {
+                "toggle" | "enable" | "disable" if shell_name == "bash" => {
+                    // Bash shows candidates as they are, so no "value:description"
+                    completions = cli::get_keyboard_completions();
+                }
                 "toggle" | "enable" | "disable" => {
                     completions.push("*:all keyboards".to_string());
                     for (id, kb) in find_all_keyboards() {
@@ -344,8 +493,11 @@ set -e COMPLETE
                         ));
                     }
                 }
-                "gamemode" => {
-                    completions = vec!["window:Game mode for focused window".to_string()];
+                "gamemode" | "game-mode" => {
+                    completions = gamemode_completions();
+                }
+                "profile" => {
+                    completions = profile_completions();
                 }
                 _ => {
                     // Get subcommands dynamically from clap
@@ -372,6 +524,9 @@ set -e COMPLETE
         _ => {
             if let Some(first_arg) = args.first() {
                 match first_arg.as_str() {
+                    "toggle" | "enable" | "disable" if shell_name == "bash" => {
+                        completions = cli::get_keyboard_completions();
+                    }
                     "toggle" | "enable" | "disable" => {
                         completions.push("*:all keyboards".to_string());
                         for (id, kb) in find_all_keyboards() {
@@ -385,8 +540,11 @@ set -e COMPLETE
                             ));
                         }
                     }
-                    "gamemode" => {
-                        completions = vec!["window:Game mode for focused window".to_string()];
+                    "gamemode" | "game-mode" => {
+                        completions = gamemode_completions();
+                    }
+                    "profile" if args.len() == 2 => {
+                        completions = profile_completions();
                     }
                     _ => {}
                 }
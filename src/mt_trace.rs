@@ -0,0 +1,141 @@
+use crate::typing::resolve_target_uid;
+use crate::watch::local_time;
+use anyhow::Result;
+use colored::Colorize;
+use keymux::event_processor::actions::mt::{MtDecision, MtReason};
+use keymux::ipc::{send_request, IpcRequest, IpcResponse, MtTrace};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Show each keyboard's recent MT decisions, optionally following new ones
+pub fn run_mt_trace(user: Option<&str>, keyboard: Option<&str>, follow: bool) -> Result<()> {
+    let uid = resolve_target_uid(user)?;
+    let request = IpcRequest::GetMtTrace {
+        uid,
+        keyboard: keyboard.map(String::from),
+    };
+
+    let traces = fetch(&request)?;
+    if traces.is_empty() {
+        println!(
+            "  {} No active keyboards{}",
+            "ℹ".bright_blue(),
+            keyboard.map_or(String::new(), |pattern| format!(
+                " matching \"{}\"",
+                pattern
+            ))
+        );
+        return Ok(());
+    }
+
+    println!();
+    println!("{}", "═══ MT decisions ═══".bright_cyan().bold());
+    // Last decision shown per event file
+    let mut seen: HashMap<String, u64> = HashMap::new();
+    for trace in &traces {
+        println!();
+        println!(
+            "  {}  {}",
+            trace.keyboard.bright_white().bold(),
+            format!("({})", trace.event_path).dimmed()
+        );
+        if trace.decisions.is_empty() {
+            println!(
+                "    {}",
+                "No decisions yet (is mt_config.trace_decisions on?)".dimmed()
+            );
+        }
+        for decision in &trace.decisions {
+            println!("    {}", describe_decision(decision));
+        }
+        if let Some(last) = trace.decisions.last() {
+            seen.insert(trace.event_path.clone(), last.seq);
+        }
+    }
+    println!();
+    if !follow {
+        return Ok(());
+    }
+    println!("{}", "Watching for MT decisions (Ctrl+C to stop)".dimmed());
+
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        for trace in fetch(&request)? {
+            let last_seen = seen.get(&trace.event_path).copied();
+            for decision in &trace.decisions {
+                if last_seen.is_some_and(|seq| decision.seq <= seq) {
+                    continue;
+                }
+                println!(
+                    "  {}  {}",
+                    describe_decision(decision),
+                    trace.keyboard.dimmed()
+                );
+            }
+            if let Some(last) = trace.decisions.last() {
+                seen.insert(trace.event_path.clone(), last.seq);
+            }
+        }
+    }
+}
+
+fn fetch(request: &IpcRequest) -> Result<Vec<MtTrace>> {
+    match send_request(request)? {
+        IpcResponse::MtTrace(traces) => Ok(traces),
+        IpcResponse::Error(e) => anyhow::bail!(e),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+}
+
+/// `12:03:04.123  KC_F  hold  permissive hold (KC_J)  held 84ms, term 200ms`
+fn describe_decision(decision: &MtDecision) -> String {
+    let outcome = if decision.hold {
+        "hold".bright_magenta()
+    } else {
+        "tap ".bright_green()
+    };
+    let mut reason = describe_reason(decision.reason).to_string();
+    if let Some(other) = decision.other_key {
+        reason.push_str(&format!(" ({})", other));
+    }
+    let mut timing = format!(
+        "held {}ms, term {}ms",
+        decision.held_ms, decision.threshold_ms
+    );
+    if decision.adaptive {
+        timing.push_str(" (learned)");
+    }
+    if let Some(score) = decision.intent_score {
+        timing.push_str(&format!(", intent {:.2}", score));
+    }
+    format!(
+        "{}  {:<8} {}  {}  {}",
+        local_time(decision.at_ms).dimmed(),
+        decision.key.to_string().bright_white(),
+        outcome,
+        reason,
+        timing.dimmed()
+    )
+}
+
+const fn describe_reason(reason: MtReason) -> &'static str {
+    match reason {
+        MtReason::QuickTap => "quick tap",
+        MtReason::DoubleTap => "double tap",
+        MtReason::CrossHandUnwrap => "cross-hand unwrap",
+        MtReason::ChordalHoldOppositeHand => "chordal hold, other hand",
+        MtReason::ChordalHoldSameHand => "chordal hold, same hand",
+        MtReason::SameHandRoll => "same-hand roll",
+        MtReason::OppositeHandChord => "opposite-hand chord",
+        MtReason::PermissiveHold => "permissive hold",
+        MtReason::MultiMod => "multi-mod",
+        MtReason::LinkedKeyboard => "key on linked keyboard",
+        MtReason::ReleasedInTime => "released within term",
+        MtReason::HeldPastThreshold => "held past term",
+        MtReason::IntentScore => "intent score",
+        MtReason::RetroTap => "retro tap",
+        MtReason::HoldDidNothing => "held, nothing else pressed",
+    }
+}
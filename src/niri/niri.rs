@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 use std::process::Command;
 use std::sync::mpsc::Sender;
+use std::sync::{LazyLock, Mutex};
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::{error, info};
 
@@ -8,6 +9,12 @@ use crate::window_manager::{
     default_should_enable_gamemode, WindowInfo, WindowManager, WindowManagerEvent,
 };
 
+/// Logical width and height of an output
+type OutputSize = (f64, f64);
+
+/// Logical sizes of the connected outputs, dropped when workspaces change
+static OUTPUT_SIZES: LazyLock<Mutex<Option<Vec<OutputSize>>>> = LazyLock::new(Default::default);
+
 #[derive(Clone)]
 pub struct Niri;
 
@@ -47,55 +54,66 @@ impl Niri {
         None
     }
 
+    /// The focused window, from a single `niri msg --json focused-window`
     fn get_focused_window_info() -> WindowInfo {
-        let Ok(output) = Command::new("niri")
-            .args(["msg", "focused-window"])
-            .output()
-        else {
-            return WindowInfo {
-                app_id: None,
-                pid: None,
-                title: None,
-            };
+        let Some(window) = Self::query("focused-window") else {
+            return WindowInfo::default();
         };
 
-        if !output.status.success() {
-            return WindowInfo {
-                app_id: None,
-                pid: None,
-                title: None,
-            };
-        }
-
-        let Ok(text) = String::from_utf8(output.stdout) else {
-            return WindowInfo {
-                app_id: None,
-                pid: None,
-                title: None,
-            };
+        let window_size = &window["layout"]["window_size"];
+        let fullscreen = match (window_size[0].as_f64(), window_size[1].as_f64()) {
+            (Some(width), Some(height)) => Self::fills_an_output(width, height),
+            _ => false,
         };
 
-        let mut app_id = None;
-        let mut pid = None;
-        let mut title = None;
+        WindowInfo {
+            app_id: window["app_id"].as_str().map(String::from),
+            pid: window["pid"]
+                .as_u64()
+                .and_then(|pid| u32::try_from(pid).ok()),
+            title: window["title"].as_str().map(String::from),
+            fullscreen,
+        }
+    }
+
+    /// Whether a window of this size fills an output.
+    ///
+    /// niri's IPC has no fullscreen flag, but a fullscreen window is the only kind
+    /// whose size matches an output's logical size exactly (no gaps, bars or borders).
+    /// Output sizes are cached, so each event costs one `niri msg`.
+    fn fills_an_output(width: f64, height: f64) -> bool {
+        let mut sizes = OUTPUT_SIZES.lock().unwrap_or_else(|e| e.into_inner());
+        let sizes = sizes.get_or_insert_with(Self::output_sizes);
+        sizes
+            .iter()
+            .any(|(out_w, out_h)| (width - out_w).abs() < 1.0 && (height - out_h).abs() < 1.0)
+    }
 
-        for line in text.lines() {
-            let trimmed = line.trim();
+    /// Logical size of every connected output
+    fn output_sizes() -> Vec<OutputSize> {
+        let Some(outputs) = Self::query("outputs") else {
+            return Vec::new();
+        };
+        outputs
+            .as_object()
+            .into_iter()
+            .flat_map(|outputs| outputs.values())
+            .filter_map(|output| {
+                let logical = &output["logical"];
+                Some((logical["width"].as_f64()?, logical["height"].as_f64()?))
+            })
+            .collect()
+    }
 
-            if let Some(app_id_part) = trimmed.strip_prefix("App ID:") {
-                let id = app_id_part.trim().trim_matches('"');
-                app_id = Some(id.to_string());
-            } else if let Some(pid_part) = trimmed.strip_prefix("PID:") {
-                if let Ok(pid_num) = pid_part.trim().parse::<u32>() {
-                    pid = Some(pid_num);
-                }
-            } else if let Some(title_part) = trimmed.strip_prefix("Title:") {
-                let t = title_part.trim().trim_matches('"');
-                title = Some(t.to_string());
-            }
+    fn query(what: &str) -> Option<serde_json::Value> {
+        let output = Command::new("niri")
+            .args(["msg", "--json", what])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
         }
-
-        WindowInfo { app_id, pid, title }
+        serde_json::from_slice(&output.stdout).ok()
     }
 }
 
@@ -127,7 +145,13 @@ impl WindowManager for Niri {
     }
 
     fn parse_event(&self, line: &str) -> Option<WindowInfo> {
-        if line.starts_with("Window focus changed:") {
+        // Outputs coming and going move workspaces around
+        if line.starts_with("Workspaces changed:") {
+            *OUTPUT_SIZES.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+        // Layout changes cover a window entering or leaving fullscreen
+        if line.starts_with("Window focus changed:") || line.starts_with("Window layouts changed:")
+        {
             Some(Self::get_focused_window_info())
         } else {
             None
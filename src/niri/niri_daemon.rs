@@ -1,4 +1,4 @@
-use crate::config::GameMode;
+use crate::config::{Config, GameMode};
 use crate::ipc::{send_request, IpcRequest, IpcResponse};
 use crate::niri;
 use crate::niri::gamemode_detection::detect_game_mode;
@@ -6,7 +6,7 @@ use crate::window_manager::WindowManagerEvent::WindowFocusChanged;
 use anyhow::Result;
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use tracing::{error, info, warn};
 
 pub fn run_niri_daemon() -> Result<()> {
@@ -35,6 +35,8 @@ pub fn run_niri_daemon() -> Result<()> {
     let (niri_tx, niri_rx) = mpsc::channel();
     niri::start_niri_monitor_sync(niri_tx);
 
+    let mut fullscreen_rule = FullscreenRule::default();
+
     let mut current_game_mode = false;
 
     loop {
@@ -45,7 +47,8 @@ pub fn run_niri_daemon() -> Result<()> {
                     window_info.pid,
                     window_info.title.as_deref(),
                 );
-                let should_enable = state.is_game_mode();
+                let should_enable =
+                    state.is_game_mode() || (window_info.fullscreen && fullscreen_rule.enabled());
 
                 info!(
                     "Window focus: app_id={:?}, pid={:?}, gamemode={:?}",
@@ -88,3 +91,36 @@ pub fn run_niri_daemon() -> Result<()> {
     info!("Niri watcher stopped");
     Ok(())
 }
+
+/// Per-user opt-in: fullscreen windows count as games regardless of app_id
+///
+/// Read again whenever the config file changes, so a reload applies without
+/// restarting the watcher.
+#[derive(Default)]
+struct FullscreenRule {
+    modified: Option<SystemTime>,
+    enabled: bool,
+}
+
+impl FullscreenRule {
+    fn enabled(&mut self) -> bool {
+        let Ok(path) = Config::default_path() else {
+            return false;
+        };
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified != self.modified {
+            self.modified = modified;
+            self.enabled = Config::load(&path)
+                .is_ok_and(|config| config.game_mode.fullscreen_enables_game_mode);
+            info!(
+                "Fullscreen windows {} game mode",
+                if self.enabled {
+                    "enable"
+                } else {
+                    "don't enable"
+                }
+            );
+        }
+        self.enabled
+    }
+}
@@ -0,0 +1,216 @@
+//! Desktop notifications sent to a user's session
+//!
+//! Everything goes through `notify-send` run as the user, with the urgency and sound
+//! the user's `notifications` config picks for its kind. [`osd`] is for state changes
+//! (layer, game mode) that can flip quickly: it throttles per user and replaces the
+//! previous notification instead of stacking them.
+
+use crate::config::{NotificationKind, NotificationsConfig};
+use crate::event_processor::actions::cmd::get_user_info;
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+/// Send a desktop notification to a user, if they want notifications of its kind
+pub fn send(
+    uid: u32,
+    config: &NotificationsConfig,
+    kind: NotificationKind,
+    title: &str,
+    message: &str,
+) {
+    if !config.wants(kind) {
+        info!(
+            "User {} turned off {:?} notifications: {}",
+            uid, kind, title
+        );
+        return;
+    }
+    info!("Attempting to send notification to user {}: {}", uid, title);
+    let mut args = style_args(config, kind);
+    args.extend([title, message].map(String::from));
+    notify_send(uid, &args);
+}
+
+/// Urgency, and a request for silence if the user turned sounds off
+fn style_args(config: &NotificationsConfig, kind: NotificationKind) -> Vec<String> {
+    let mut args = vec!["-u".to_string(), config.urgency(kind).as_str().to_string()];
+    if !config.sounds {
+        args.extend(["-h", "boolean:suppress-sound:true"].map(String::from));
+    }
+    args
+}
+
+fn notify_send(uid: u32, args: &[String]) {
+    let Some((username, _)) = get_user_info(uid) else {
+        error!("Failed to get username for UID {}", uid);
+        return;
+    };
+
+    match Command::new("runuser")
+        .args(["-u", &username, "--", "/usr/bin/notify-send"])
+        .args(args)
+        .spawn()
+    {
+        Ok(mut child) => {
+            info!(
+                "Sent notification to user {} ({}): {:?}",
+                username, uid, args
+            );
+            // Reap it in the background so notifications don't pile up as zombies
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => error!(
+            "Failed to send notification to user {} ({}): {}",
+            username, uid, e
+        ),
+    }
+}
+
+/// What to do with an OSD message offered to the throttle
+#[derive(Debug, PartialEq, Eq)]
+enum Decision {
+    Send,
+    /// Send whatever is pending once this much time has passed
+    SendLater(Duration),
+    /// Already shown or queued (or a flush is already scheduled)
+    Skip,
+}
+
+/// Per-user OSD throttle: at most one message per window, the latest one winning
+#[derive(Default)]
+struct Throttle {
+    last_sent: Option<(Instant, String)>,
+    pending: Option<String>,
+    flush_scheduled: bool,
+}
+
+impl Throttle {
+    fn offer(&mut self, now: Instant, window: Duration, message: String) -> Decision {
+        let shown = self.last_sent.as_ref().map(|(_, text)| text.as_str());
+        if shown == Some(message.as_str()) && self.pending.is_none() {
+            return Decision::Skip;
+        }
+        match self.last_sent {
+            Some((at, _)) if now.saturating_duration_since(at) < window => {
+                // A change back to what's on screen cancels the queued one
+                self.pending = (shown != Some(message.as_str())).then_some(message);
+                if self.flush_scheduled || self.pending.is_none() {
+                    return Decision::Skip;
+                }
+                self.flush_scheduled = true;
+                Decision::SendLater(window - now.saturating_duration_since(at))
+            }
+            _ => {
+                self.pending = None;
+                self.last_sent = Some((now, message));
+                Decision::Send
+            }
+        }
+    }
+
+    /// Take the queued message once the window has passed
+    fn flush(&mut self, now: Instant) -> Option<String> {
+        self.flush_scheduled = false;
+        let message = self.pending.take()?;
+        self.last_sent = Some((now, message.clone()));
+        Some(message)
+    }
+}
+
+static THROTTLES: LazyLock<Mutex<HashMap<u32, Throttle>>> = LazyLock::new(Default::default);
+
+/// Show a short-lived state notification ("Layer: nav"), throttled per user
+pub fn osd(
+    uid: u32,
+    config: &NotificationsConfig,
+    kind: NotificationKind,
+    title: &str,
+    text: &str,
+) {
+    let message = format!("{}: {}", title, text);
+    let window = Duration::from_millis(config.throttle_ms);
+    let decision = THROTTLES.lock().unwrap().entry(uid).or_default().offer(
+        Instant::now(),
+        window,
+        message.clone(),
+    );
+
+    let style = style_args(config, kind);
+    let timeout = config.timeout_ms;
+    match decision {
+        Decision::Send => notify_send(uid, &osd_args(style, timeout, &message)),
+        Decision::SendLater(delay) => {
+            std::thread::spawn(move || {
+                std::thread::sleep(delay);
+                let message = THROTTLES
+                    .lock()
+                    .unwrap()
+                    .get_mut(&uid)
+                    .and_then(|throttle| throttle.flush(Instant::now()));
+                if let Some(message) = message {
+                    notify_send(uid, &osd_args(style, timeout, &message));
+                }
+            });
+        }
+        Decision::Skip => {}
+    }
+}
+
+fn osd_args(mut args: Vec<String>, timeout_ms: u32, message: &str) -> Vec<String> {
+    args.extend([
+        "-t".to_string(),
+        timeout_ms.to_string(),
+        "-a".to_string(),
+        "keymux".to_string(),
+        // Replace the previous OSD (dunst, mako, notify-osd and others honor this)
+        "-h".to_string(),
+        "string:x-canonical-private-synchronous:keymux".to_string(),
+        "keymux".to_string(),
+        message.to_string(),
+    ]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_keeps_latest_message() {
+        let window = Duration::from_millis(500);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut throttle = Throttle::default();
+
+        assert_eq!(throttle.offer(at(0), window, "nav".into()), Decision::Send);
+        assert_eq!(throttle.offer(at(10), window, "nav".into()), Decision::Skip);
+        assert_eq!(
+            throttle.offer(at(100), window, "sym".into()),
+            Decision::SendLater(Duration::from_millis(400))
+        );
+        // Only one flush per window, and it shows the newest state
+        assert_eq!(
+            throttle.offer(at(200), window, "num".into()),
+            Decision::Skip
+        );
+        assert_eq!(throttle.flush(at(500)).as_deref(), Some("num"));
+
+        // Flipping back to what's shown drops the queued change
+        assert_eq!(
+            throttle.offer(at(600), window, "base".into()),
+            Decision::SendLater(Duration::from_millis(400))
+        );
+        assert_eq!(
+            throttle.offer(at(700), window, "num".into()),
+            Decision::Skip
+        );
+        assert_eq!(throttle.flush(at(1000)), None);
+        assert_eq!(
+            throttle.offer(at(1600), window, "base".into()),
+            Decision::Send
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Where keymux keeps its files
+//!
+//! Each location can be moved with an environment variable, or for the daemon with the
+//! matching flag (which wins over the variable):
+//!
+//! - `KEYMUX_CONFIG` / `--config`: config file, default `~/.config/keymux/config.ron`.
+//!   A leading `~` is each user's home, so the daemon can move every user's config.
+//!   Stats and scripts live next to it.
+//! - `KEYMUX_SOCKET` / `--socket`: daemon socket, default `/run/keymux.sock`. Clients
+//!   with it set talk to exactly that socket instead of their private one.
+//! - `KEYMUX_STATE_DIR` / `--state-dir`: daemon runtime state, default `/run/keymux`.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const CONFIG_ENV: &str = "KEYMUX_CONFIG";
+pub const SOCKET_ENV: &str = "KEYMUX_SOCKET";
+pub const STATE_DIR_ENV: &str = "KEYMUX_STATE_DIR";
+
+/// Paths given on the daemon's command line
+#[derive(Debug, Default)]
+pub struct PathOverrides {
+    pub config: Option<PathBuf>,
+    pub socket: Option<PathBuf>,
+    pub state_dir: Option<PathBuf>,
+}
+
+static OVERRIDES: OnceLock<PathOverrides> = OnceLock::new();
+
+/// Use these paths over the environment for the rest of the process (first call wins)
+pub fn set_overrides(overrides: PathOverrides) {
+    let _ = OVERRIDES.set(overrides);
+}
+
+fn lookup(flag: fn(&PathOverrides) -> &Option<PathBuf>, env: &str) -> Option<PathBuf> {
+    OVERRIDES
+        .get()
+        .and_then(|overrides| flag(overrides).clone())
+        .or_else(|| {
+            std::env::var_os(env)
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+        })
+}
+
+/// Config path overridden by flag or `KEYMUX_CONFIG`, with `~` expanded to `home`
+pub fn config_override(home: &Path) -> Option<PathBuf> {
+    lookup(|overrides| &overrides.config, CONFIG_ENV).map(|path| expand_home(&path, home))
+}
+
+/// Config file of the user whose home directory is `home`
+pub fn config_path_in(home: &Path) -> PathBuf {
+    config_override(home).unwrap_or_else(|| home.join(".config/keymux/config.ron"))
+}
+
+/// Directory holding a user's config, stats and scripts
+pub fn config_dir_in(home: &Path) -> PathBuf {
+    let config = config_path_in(home);
+    config
+        .parent()
+        .map_or_else(|| home.to_path_buf(), Path::to_path_buf)
+}
+
+/// Socket overridden by flag or `KEYMUX_SOCKET`
+pub fn socket_override() -> Option<PathBuf> {
+    lookup(|overrides| &overrides.socket, SOCKET_ENV)
+}
+
+/// Socket the root daemon listens on
+pub fn root_socket_path() -> PathBuf {
+    socket_override().unwrap_or_else(|| PathBuf::from("/run/keymux.sock"))
+}
+
+/// Directory for state the daemon hands to its next run
+pub fn state_dir() -> PathBuf {
+    lookup(|overrides| &overrides.state_dir, STATE_DIR_ENV)
+        .unwrap_or_else(|| PathBuf::from("/run/keymux"))
+}
+
+fn expand_home(path: &Path, home: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home.join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_home() {
+        let home = Path::new("/home/alice");
+        assert_eq!(
+            expand_home(Path::new("~/dotfiles/keymux.ron"), home),
+            PathBuf::from("/home/alice/dotfiles/keymux.ron")
+        );
+        assert_eq!(
+            expand_home(Path::new("/etc/keymux/config.ron"), home),
+            PathBuf::from("/etc/keymux/config.ron")
+        );
+    }
+}
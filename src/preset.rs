@@ -0,0 +1,144 @@
+use crate::cli::PresetAction;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use keymux::config::{preset, Config};
+use keymux::keycode::KeyCode;
+use std::path::{Path, PathBuf};
+
+/// Add a generated block to the user's config (or print the result with `--dry-run`)
+pub fn run_preset(preset: &PresetAction) -> Result<()> {
+    match preset {
+        PresetAction::Hrm {
+            mods,
+            layout,
+            config,
+            dry_run,
+        } => {
+            let (path, content) = read_config(config.as_deref())?;
+            let (new_content, replaced) = preset::apply_home_row_mods(&content, mods, layout)?;
+            let description = format!(
+                "home-row mods ({}, {})",
+                mods.to_uppercase(),
+                layout.to_lowercase()
+            );
+            write_preset(&path, &new_content, &description, &replaced, *dry_run)
+        }
+        PresetAction::Layout {
+            name,
+            toggle,
+            config,
+            dry_run,
+        } => {
+            let toggle = parse_key(toggle.as_deref())?;
+            let (path, content) = read_config(config.as_deref())?;
+            let name = name.to_lowercase();
+            let (new_content, replaced) = preset::apply_layout_layer(&content, &name, toggle)?;
+            write_preset(
+                &path,
+                &new_content,
+                &format!("layer \"{}\"", name),
+                &replaced,
+                *dry_run,
+            )?;
+            if !*dry_run && toggle.is_none() {
+                println!(
+                    "  {} Turn it on with {} on a key, or pass {}",
+                    "ℹ".bright_blue(),
+                    format!("TG(\"{}\")", name).bright_white(),
+                    "--toggle KC_SCRL".bright_white()
+                );
+            }
+            Ok(())
+        }
+        PresetAction::Numpad {
+            hold,
+            toggle,
+            config,
+            dry_run,
+        } => {
+            let hold = parse_key(hold.as_deref())?;
+            let toggle = parse_key(toggle.as_deref())?;
+            let (path, content) = read_config(config.as_deref())?;
+            let (new_content, replaced) = preset::apply_numpad_layer(&content, hold, toggle)?;
+            write_preset(
+                &path,
+                &new_content,
+                &format!("layer \"{}\"", preset::NUMPAD_LAYER),
+                &replaced,
+                *dry_run,
+            )?;
+            if !*dry_run {
+                if hold.is_none() && toggle.is_none() {
+                    println!(
+                        "  {} Turn it on with {} or {} on a key, or pass {}",
+                        "ℹ".bright_blue(),
+                        format!("MO(\"{}\")", preset::NUMPAD_LAYER).bright_white(),
+                        format!("TG(\"{}\")", preset::NUMPAD_LAYER).bright_white(),
+                        "--hold KC_RALT".bright_white()
+                    );
+                }
+                // Keypad keys only type digits with Num Lock on
+                println!(
+                    "  {} The layer types digits while Num Lock is on",
+                    "ℹ".bright_blue()
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+fn parse_key(key: Option<&str>) -> Result<Option<KeyCode>> {
+    key.map(|key| {
+        KeyCode::from_name(key)
+            .ok_or_else(|| anyhow::anyhow!("Unknown key: {} (expected e.g. KC_SCRL)", key))
+    })
+    .transpose()
+}
+
+fn read_config(path: Option<&Path>) -> Result<(PathBuf, String)> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => Config::default_path()?,
+    };
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok((path, content))
+}
+
+fn write_preset(
+    path: &Path,
+    content: &str,
+    description: &str,
+    replaced: &[String],
+    dry_run: bool,
+) -> Result<()> {
+    // Never leave the user with a config the daemon can't load
+    Config::parse(content).context("Generated config does not parse (file left unchanged)")?;
+
+    if dry_run {
+        println!("{}", content);
+        return Ok(());
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!(
+        "  {} Added {} to {}",
+        "✓".bright_green().bold(),
+        description,
+        path.display().to_string().bright_white().bold()
+    );
+    if !replaced.is_empty() {
+        println!(
+            "  {} Replaced existing remaps: {}",
+            "⚠".bright_yellow(),
+            replaced.join(", ")
+        );
+    }
+    println!(
+        "  {} Apply it with {}",
+        "ℹ".bright_blue(),
+        "keymux reload".bright_white()
+    );
+    Ok(())
+}
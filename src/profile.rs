@@ -0,0 +1,46 @@
+use crate::typing::resolve_target_uid;
+use anyhow::Result;
+use colored::Colorize;
+use keymux::ipc::{send_request, IpcRequest, IpcResponse};
+
+/// Show the active profile, or switch to `name` ("auto" follows the schedule again)
+pub fn run_profile(name: Option<&str>, user: Option<&str>) -> Result<()> {
+    let uid = resolve_target_uid(user)?;
+    let Some(name) = name else {
+        return show_profile(uid);
+    };
+
+    let profile = (name != "auto").then(|| name.to_string());
+    match send_request(&IpcRequest::SetProfile { profile, uid })? {
+        IpcResponse::Ok => {
+            let message = if name == "auto" {
+                "Profile follows the schedule".to_string()
+            } else {
+                format!("Switched to profile {}", name.bright_white().bold())
+            };
+            println!("  {} {}", "✓".bright_green().bold(), message);
+            Ok(())
+        }
+        IpcResponse::Error(msg) => {
+            eprintln!("  {} {}", "✗".bright_red().bold(), msg.red());
+            anyhow::bail!("Failed to switch profile");
+        }
+        response => anyhow::bail!("Unexpected response from daemon: {:?}", response),
+    }
+}
+
+fn show_profile(uid: Option<u32>) -> Result<()> {
+    match send_request(&IpcRequest::GetProfile { uid })? {
+        IpcResponse::Profile { name, overridden } => {
+            let source = if overridden { "manual" } else { "schedule" };
+            println!(
+                "Profile: {} {}",
+                name.as_deref().unwrap_or("default").bright_white().bold(),
+                format!("({})", source).dimmed()
+            );
+            Ok(())
+        }
+        IpcResponse::Error(e) => anyhow::bail!(e),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+}
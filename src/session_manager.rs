@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::process::Command;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone)]
 pub struct UserSession {
@@ -19,9 +19,36 @@ pub enum SessionState {
     Idle,
 }
 
-/// Session Manager — wraps loginctl session state.
+/// Where session state comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionBackend {
+    /// systemd-logind or elogind, through loginctl
+    Logind,
+    /// Login records and console ownership, for systems without logind
+    /// (runit/OpenRC with seatd or plain getty logins)
+    Console,
+}
+
+impl SessionBackend {
+    /// Logind when loginctl can list sessions, the console fallback otherwise
+    pub fn detect() -> Self {
+        let logind = Command::new("loginctl")
+            .arg("list-sessions")
+            .arg("--no-legend")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if logind {
+            Self::Logind
+        } else {
+            Self::Console
+        }
+    }
+}
+
+/// Session Manager — wraps loginctl session state (or the console fallback).
 /// Keyboard ownership is tracked by AsyncDaemon directly, not here.
 pub struct SessionManager {
+    backend: SessionBackend,
     /// Map of UID to user session info
     user_sessions: Arc<RwLock<HashMap<u32, UserSession>>>,
 }
@@ -34,15 +61,31 @@ impl Default for SessionManager {
 
 impl SessionManager {
     pub fn new() -> Self {
+        let backend = SessionBackend::detect();
+        if backend == SessionBackend::Console {
+            info!("loginctl unavailable; tracking sessions from utmp and the active VT");
+        }
         Self {
+            backend,
             user_sessions: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
-    /// Refresh user session information from loginctl
+    pub const fn backend(&self) -> SessionBackend {
+        self.backend
+    }
+
+    /// Refresh user session information from loginctl (or the console fallback)
     pub async fn refresh_sessions(&self) -> Result<()> {
-        let sessions = list_user_sessions()?;
-        debug!("Found {} user sessions from loginctl", sessions.len());
+        let sessions = match self.backend {
+            SessionBackend::Logind => list_user_sessions()?,
+            SessionBackend::Console => console_sessions(),
+        };
+        debug!(
+            "Found {} user sessions from {:?}",
+            sessions.len(),
+            self.backend
+        );
         for s in &sessions {
             debug!(
                 "  Session: uid={}, username={}, state={:?}",
@@ -52,14 +95,14 @@ impl SessionManager {
 
         let mut user_sessions = self.user_sessions.write().await;
 
-        // Upsert all sessions returned by loginctl
+        // Upsert all sessions returned by the backend
         for session in sessions {
             user_sessions.insert(session.uid, session);
         }
 
-        // Drop sessions that are no longer reported as active by loginctl.
+        // Drop sessions that are no longer reported as active.
         // (Idle sessions are re-inserted on every refresh so they'll reappear
-        // as Active once loginctl reports them that way again.)
+        // as Active once the backend reports them that way again.)
         user_sessions.retain(|_, s| s.state == SessionState::Active);
         drop(user_sessions);
 
@@ -95,14 +138,8 @@ fn list_user_sessions() -> Result<Vec<UserSession>> {
         .context("Failed to run loginctl")?;
 
     if !output.status.success() {
-        warn!("loginctl command failed, assuming single user system");
-        // Fallback: assume current user
-        let uid = unsafe { libc::getuid() };
-        return Ok(vec![UserSession {
-            uid,
-            username: std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()),
-            state: SessionState::Active,
-        }]);
+        warn!("loginctl command failed, falling back to console sessions");
+        return Ok(console_sessions());
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -141,6 +178,103 @@ fn list_user_sessions() -> Result<Vec<UserSession>> {
     Ok(sessions)
 }
 
+/// glibc `struct utmp` on Linux: record size and the fields read here
+const UTMP_RECORD_SIZE: usize = 384;
+const UTMP_LINE: std::ops::Range<usize> = 8..40;
+const UTMP_USER: std::ops::Range<usize> = 44..76;
+const USER_PROCESS: i16 = 7;
+
+/// Logged-in (user, line) pairs from utmp records, e.g. ("alice", "tty1")
+fn parse_utmp(data: &[u8]) -> Vec<(String, String)> {
+    let field = |record: &[u8], range: std::ops::Range<usize>| {
+        let bytes = &record[range];
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    };
+    data.chunks_exact(UTMP_RECORD_SIZE)
+        .filter(|record| i16::from_ne_bytes([record[0], record[1]]) == USER_PROCESS)
+        .map(|record| (field(record, UTMP_USER), field(record, UTMP_LINE)))
+        .filter(|(user, _)| !user.is_empty())
+        .collect()
+}
+
+/// The foreground virtual terminal, e.g. "tty2"
+fn active_vt() -> Option<String> {
+    std::fs::read_to_string("/sys/class/tty/tty0/active")
+        .ok()
+        .map(|vt| vt.trim().to_string())
+        .filter(|vt| !vt.is_empty())
+}
+
+/// Sessions without logind
+///
+/// Console logins come from utmp; the one on the foreground VT (or an X display
+/// manager's `:N` line) is active. seatd/greetd setups don't always write utmp, so
+/// otherwise the owner of the foreground VT is taken as the active user, and failing
+/// that the only user with a runtime directory ("single active user" mode).
+fn console_sessions() -> Vec<UserSession> {
+    use std::os::unix::fs::MetadataExt;
+
+    let vt = active_vt();
+    let utmp = std::fs::read("/run/utmp")
+        .or_else(|_| std::fs::read("/var/run/utmp"))
+        .unwrap_or_default();
+
+    let mut sessions: HashMap<u32, UserSession> = HashMap::new();
+    for (username, line) in parse_utmp(&utmp) {
+        // Remote logins (pts) never own local keyboards
+        if !line.starts_with("tty") && !line.starts_with(':') {
+            continue;
+        }
+        let Ok(uid) = crate::get_user_uid_by_name(&username) else {
+            continue;
+        };
+        let active = line.starts_with(':') || vt.as_deref() == Some(line.as_str());
+        let session = sessions.entry(uid).or_insert(UserSession {
+            uid,
+            username,
+            state: SessionState::Idle,
+        });
+        if active {
+            session.state = SessionState::Active;
+        }
+    }
+    if sessions.values().any(|s| s.state == SessionState::Active) {
+        return sessions.into_values().collect();
+    }
+
+    let vt_owner = vt
+        .and_then(|vt| std::fs::metadata(format!("/dev/{vt}")).ok())
+        .map(|meta| meta.uid())
+        .filter(|&uid| uid != 0);
+    let uid = vt_owner.or_else(|| {
+        let runtime_uids: Vec<u32> = std::fs::read_dir("/run/user")
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+            .filter(|&uid| uid != 0)
+            .collect();
+        match runtime_uids.as_slice() {
+            [uid] => Some(*uid),
+            _ => None,
+        }
+    });
+    if let Some(uid) = uid {
+        let username = crate::event_processor::actions::cmd::get_user_info(uid)
+            .map_or_else(|| uid.to_string(), |(name, _)| name);
+        sessions.insert(
+            uid,
+            UserSession {
+                uid,
+                username,
+                state: SessionState::Active,
+            },
+        );
+    }
+    sessions.into_values().collect()
+}
+
 /// Get UID for a session
 fn get_session_uid(session_id: &str) -> Result<u32> {
     let output = Command::new("loginctl")
@@ -178,6 +312,30 @@ fn is_session_active(session_id: &str) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_utmp_user_processes() {
+        let record = |kind: i16, user: &str, line: &str| {
+            let mut record = vec![0u8; UTMP_RECORD_SIZE];
+            record[..2].copy_from_slice(&kind.to_ne_bytes());
+            record[UTMP_LINE][..line.len()].copy_from_slice(line.as_bytes());
+            record[UTMP_USER][..user.len()].copy_from_slice(user.as_bytes());
+            record
+        };
+        let data = [
+            record(USER_PROCESS, "alice", "tty1"),
+            record(8, "bob", "tty2"), // DEAD_PROCESS: logged out
+            record(USER_PROCESS, "carol", "pts/0"),
+        ]
+        .concat();
+        assert_eq!(
+            parse_utmp(&data),
+            vec![
+                ("alice".to_string(), "tty1".to_string()),
+                ("carol".to_string(), "pts/0".to_string()),
+            ]
+        );
+    }
+
     // TODO: Fix these tests by implementing the missing methods
     /*
     #[tokio::test]
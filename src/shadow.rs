@@ -0,0 +1,45 @@
+use crate::typing::resolve_target_uid;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use keymux::ipc::{send_request, IpcRequest, IpcResponse};
+use std::path::Path;
+
+/// Run `file` alongside the active config on live input, or stop comparing (None)
+///
+/// The daemon logs every input the two configs handle differently; nothing from the
+/// compared config is emitted and its commands don't run.
+pub fn run_shadow(file: Option<&Path>, user: Option<&str>) -> Result<()> {
+    let uid = resolve_target_uid(user)?;
+    let config = file
+        .map(|path| {
+            std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))
+        })
+        .transpose()?;
+
+    match send_request(&IpcRequest::SetShadowConfig { config, uid })? {
+        IpcResponse::Ok => {
+            match file {
+                Some(path) => {
+                    println!(
+                        "  {} Comparing {} against the active config",
+                        "✓".bright_green().bold(),
+                        path.display().to_string().bright_white().bold()
+                    );
+                    println!(
+                        "  {} Differences are logged: {}",
+                        "ℹ".bright_blue(),
+                        "journalctl -u keymux -f | grep Shadow".bright_white()
+                    );
+                }
+                None => println!("  {} Stopped comparing", "✓".bright_green().bold()),
+            }
+            Ok(())
+        }
+        IpcResponse::Error(msg) => {
+            eprintln!("  {} {}", "✗".bright_red().bold(), msg.red());
+            anyhow::bail!("Failed to set shadow config");
+        }
+        response => anyhow::bail!("Unexpected response from daemon: {:?}", response),
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use colored::Colorize;
+use keymux::ipc::{send_request, DaemonStatus, IpcRequest, IpcResponse};
+
+/// Show the daemon's uptime and memory, and with `internals` each processor thread
+pub fn run_status(internals: bool) -> Result<()> {
+    let status = match send_request(&IpcRequest::GetStatus)? {
+        IpcResponse::Status(status) => status,
+        IpcResponse::Error(e) => anyhow::bail!(e),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    };
+
+    println!();
+    println!("{}", "═══ Daemon ═══".bright_cyan().bold());
+    println!("  {} {}", "PID:".dimmed(), status.pid);
+    println!(
+        "  {} {}",
+        "Uptime:".dimmed(),
+        format_uptime(status.uptime_secs)
+    );
+    println!(
+        "  {} {}",
+        "Memory:".dimmed(),
+        status.rss_kb.map_or("unknown".to_string(), |kb| format!(
+            "{:.1} MiB",
+            kb as f64 / 1024.0
+        ))
+    );
+    if let Some(threads) = status.threads {
+        println!("  {} {}", "Threads:".dimmed(), threads);
+    }
+    println!(
+        "  {} {} running, {} stuck",
+        "Processors:".dimmed(),
+        status.processors.iter().filter(|p| p.alive).count(),
+        status
+            .processors
+            .iter()
+            .filter(|p| p.alive && p.stalled)
+            .count()
+    );
+    if status.event_backlog > 0 {
+        println!(
+            "  {} {} processor events waiting",
+            "Backlog:".dimmed(),
+            status.event_backlog
+        );
+    }
+
+    if internals {
+        print_processors(&status);
+    }
+    println!();
+    Ok(())
+}
+
+fn print_processors(status: &DaemonStatus) {
+    println!();
+    println!("{}", "═══ Processors ═══".bright_cyan().bold());
+    if status.processors.is_empty() {
+        println!("  {}", "No keyboards are being processed".dimmed());
+    }
+    for processor in &status.processors {
+        let state = if !processor.alive {
+            "exited".bright_red().bold()
+        } else if processor.stalled {
+            "stuck".bright_yellow().bold()
+        } else if processor.throttled {
+            "throttled".bright_yellow().bold()
+        } else {
+            "ok".bright_green()
+        };
+        println!();
+        println!(
+            "  {}  {}  {}",
+            processor.keyboard.bright_white().bold(),
+            format!("({}, uid {})", processor.event_path, processor.uid).dimmed(),
+            state
+        );
+        println!(
+            "    {} {}",
+            "Heartbeat:".dimmed(),
+            processor
+                .heartbeat_age_ms
+                .map_or("none yet".to_string(), |ms| format!("{}ms ago", ms))
+        );
+        println!(
+            "    {} {} commands queued",
+            "Backlog:".dimmed(),
+            processor.command_backlog
+        );
+        println!(
+            "    {} {} keys in, {} out, {} dropped",
+            "Events:".dimmed(),
+            processor.events_in,
+            processor.events_out,
+            processor.evictions
+        );
+    }
+}
+
+/// `3d 4h 12m`
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m {}s", minutes, secs % 60)
+    }
+}
@@ -1,11 +1,12 @@
 use anyhow::Result;
 use colored::Colorize;
 use dialoguer::MultiSelect;
-use std::collections::HashMap;
-
-use keymux::config::{Config, EnableDisable, EnabledKeyboardEntry, EnabledKeyboards};
+use keymux::config::{
+    keyboard_state, Config, EnableDisable, EnabledKeyboardEntry, EnabledKeyboards,
+};
 use keymux::ipc::{send_request, IpcRequest, IpcResponse};
 use keymux::keyboard_id::{find_all_keyboards, KeyboardId};
+use std::path::PathBuf;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ToggleAction {
@@ -14,10 +15,29 @@ pub enum ToggleAction {
     Disable,
 }
 
-pub fn run_toggle(multi: bool, action_patterns: Option<(bool, Vec<String>)>) -> Result<()> {
+/// Config path and uid whose keyboards are managed: the caller's, or `user`'s
+///
+/// Managing another user's keyboards needs root; the change still goes through the
+/// daemon, which applies it to that user's scope.
+pub fn user_scope(user: Option<&str>) -> Result<(PathBuf, u32)> {
+    let Some(name) = user else {
+        return Ok((Config::default_path()?, keymux::get_actual_user_uid().0));
+    };
+    let uid = keymux::get_user_uid_by_name(name)?;
+    if uid != keymux::get_actual_user_uid().0 && unsafe { libc::geteuid() } != 0 {
+        anyhow::bail!("Managing {}'s keyboards needs root (try sudo)", name);
+    }
+    Ok((Config::path_for_uid(uid)?, uid))
+}
+
+pub fn run_toggle(
+    multi: bool,
+    action_patterns: Option<(bool, Vec<String>)>,
+    user: Option<&str>,
+) -> Result<()> {
     // Load config and keyboards
-    let config_path = Config::default_path()?;
-    let mut config = Config::load(&config_path)?;
+    let (config_path, uid) = user_scope(user)?;
+    let mut config = Config::load_with_state(&config_path)?;
     let keyboards = find_all_keyboards();
 
     if keyboards.is_empty() {
@@ -51,7 +71,15 @@ pub fn run_toggle(multi: bool, action_patterns: Option<(bool, Vec<String>)>) ->
     // Handle CLI patterns (non-multi mode)
     if let Some((enable, patterns)) = action_patterns {
         if !multi && !patterns.is_empty() {
-            return handle_cli_patterns(&mut config, &config_path, enable, patterns, &items);
+            return handle_cli_patterns(
+                &mut config,
+                &config_path,
+                uid,
+                enable,
+                patterns,
+                &items,
+                false,
+            );
         }
         // If multi flag is set OR patterns are empty, run the appropriate multi-select
         let action = if enable {
@@ -59,38 +87,72 @@ pub fn run_toggle(multi: bool, action_patterns: Option<(bool, Vec<String>)>) ->
         } else {
             ToggleAction::Disable
         };
-        return run_multi_select(&mut config, &config_path, &items, action);
+        return run_multi_select(&mut config, &config_path, uid, &items, action);
     }
 
     // Multi-select mode for toggle (no args)
-    run_multi_select(&mut config, &config_path, &items, ToggleAction::Toggle)
+    run_multi_select(&mut config, &config_path, uid, &items, ToggleAction::Toggle)
+}
+
+/// Enable or disable keyboards without any interactive prompts
+///
+/// Intended for scripts and udev hooks: when `user` is given (typically from a
+/// root-run hook) that user's keyboard state is updated instead of the caller's.
+pub fn run_set_enabled(
+    enable: bool,
+    patterns: Vec<String>,
+    user: Option<&str>,
+    quiet: bool,
+) -> Result<()> {
+    let (config_path, uid) = user_scope(user)?;
+    let mut config = Config::load_with_state(&config_path)?;
+
+    let mut items: Vec<(KeyboardId, String)> = find_all_keyboards()
+        .into_iter()
+        .map(|(id, logical_kb)| (id, logical_kb.name))
+        .collect();
+    items.sort_by(|a, b| a.1.cmp(&b.1));
+
+    handle_cli_patterns(
+        &mut config,
+        &config_path,
+        uid,
+        enable,
+        patterns,
+        &items,
+        quiet,
+    )
 }
 
 fn handle_cli_patterns(
     config: &mut Config,
     config_path: &std::path::Path,
+    uid: u32,
     enable: bool,
     patterns: Vec<String>,
     items: &[(KeyboardId, String)],
+    quiet: bool,
 ) -> Result<()> {
-    println!();
-    println!(
-        "{}",
-        "═══════════════════════════════════════".bright_cyan()
-    );
-    println!(
-        "  {}",
-        if enable {
-            "Enable Keyboards".bright_cyan().bold()
-        } else {
-            "Disable Keyboards".bright_cyan().bold()
-        }
-    );
-    println!(
-        "{}",
-        "═══════════════════════════════════════".bright_cyan()
-    );
-    println!();
+    if !quiet {
+        println!();
+        println!(
+            "{}",
+            "═══════════════════════════════════════".bright_cyan()
+        );
+        println!(
+            "  {}",
+            if enable {
+                "Enable Keyboards".bright_cyan().bold()
+            } else {
+                "Disable Keyboards".bright_cyan().bold()
+            }
+        );
+        println!(
+            "{}",
+            "═══════════════════════════════════════".bright_cyan()
+        );
+        println!();
+    }
 
     // Get current entries (normalize to handle legacy Some* variants)
     let current_entries = match config.enabled_keyboards.normalize() {
@@ -100,7 +162,6 @@ fn handle_cli_patterns(
 
     let mut new_entries: Vec<EnabledKeyboardEntry> = Vec::new();
     let mut matched_any = false;
-    let mut pattern_comments: HashMap<String, String> = HashMap::new();
 
     for pattern in &patterns {
         let entry = if *pattern == "*" {
@@ -118,18 +179,13 @@ fn handle_cli_patterns(
                 .to_string();
 
             // Check if this pattern matches any keyboard and record the name
-            for (id, name) in items {
+            matched_any |= items.iter().any(|(id, name)| {
                 let id_str = id.to_string();
                 let base_id = id_str.split('@').next().unwrap_or(&id_str);
-                if base_id.contains(&normalized_pattern)
+                base_id.contains(&normalized_pattern)
                     || base_id.contains(pattern)
                     || name.contains(pattern)
-                {
-                    matched_any = true;
-                    // Store the base_id (without @...) as the key for comment
-                    pattern_comments.insert(pattern.clone(), name.clone());
-                }
-            }
+            });
 
             if enable {
                 EnabledKeyboardEntry::Bare(pattern.clone())
@@ -140,7 +196,7 @@ fn handle_cli_patterns(
         new_entries.push(entry);
     }
 
-    if !matched_any && !patterns.contains(&"*".to_string()) {
+    if !quiet && !matched_any && !patterns.contains(&"*".to_string()) {
         println!(
             "  {} Note: No currently connected keyboards matched: {}",
             "⚠".bright_yellow(),
@@ -173,9 +229,11 @@ fn handle_cli_patterns(
         EnabledKeyboards::List(final_entries)
     };
 
-    // Save and notify
-    config.save_enabled_keyboards_only_with_comments(config_path, Some(&pattern_comments))?;
-    send_reload_notification()?;
+    save_enabled_state(config, config_path, uid, quiet)?;
+
+    if quiet {
+        return Ok(());
+    }
 
     let status = if enable { "enabled" } else { "disabled" };
     println!(
@@ -195,6 +253,7 @@ fn handle_cli_patterns(
 pub fn handle_toggle_patterns(
     config: &mut Config,
     config_path: &std::path::Path,
+    uid: u32,
     patterns: Vec<String>,
     items: &[(KeyboardId, String)],
 ) -> Result<()> {
@@ -219,8 +278,6 @@ pub fn handle_toggle_patterns(
     // Determine which patterns to enable vs disable
     let mut enable_patterns: Vec<String> = Vec::new();
     let mut disable_patterns: Vec<String> = Vec::new();
-    let pattern_comments: std::collections::HashMap<String, String> =
-        std::collections::HashMap::new();
 
     for pattern in &patterns {
         // Check if this pattern matches any keyboard and determine current state
@@ -276,16 +333,6 @@ pub fn handle_toggle_patterns(
         } else {
             EnabledKeyboards::List(final_entries)
         };
-
-        config.save_enabled_keyboards_only_with_comments(config_path, Some(&pattern_comments))?;
-        send_reload_notification()?;
-
-        println!(
-            "  {} {}: {}",
-            "✓".bright_green().bold(),
-            "Enabled".green(),
-            enable_patterns.join(", ").green()
-        );
     }
 
     // Handle disable patterns if any
@@ -311,10 +358,19 @@ pub fn handle_toggle_patterns(
         } else {
             EnabledKeyboards::List(final_entries)
         };
+    }
 
-        config.save_enabled_keyboards_only_with_comments(config_path, Some(&pattern_comments))?;
-        send_reload_notification()?;
+    save_enabled_state(config, config_path, uid, false)?;
 
+    if !enable_patterns.is_empty() {
+        println!(
+            "  {} {}: {}",
+            "✓".bright_green().bold(),
+            "Enabled".green(),
+            enable_patterns.join(", ").green()
+        );
+    }
+    if !disable_patterns.is_empty() {
         println!(
             "  {} {}: {}",
             "✓".bright_green().bold(),
@@ -330,6 +386,7 @@ pub fn handle_toggle_patterns(
 fn run_multi_select(
     config: &mut Config,
     config_path: &std::path::Path,
+    uid: u32,
     items: &[(KeyboardId, String)],
     action: ToggleAction,
 ) -> Result<()> {
@@ -477,14 +534,6 @@ fn run_multi_select(
         })
         .collect();
 
-    // Build comments mapping for multi-select
-    let mut pattern_comments: HashMap<String, String> = HashMap::new();
-    for (id, name) in items {
-        let id_str = id.to_string();
-        let base_id = id_str.split('@').next().unwrap_or(&id_str).to_string();
-        pattern_comments.insert(base_id, name.clone());
-    }
-
     // Merge and deduplicate based on action
     let final_entries = match action {
         ToggleAction::Toggle => {
@@ -538,11 +587,7 @@ fn run_multi_select(
         EnabledKeyboards::List(final_entries)
     };
 
-    // Save config
-    config.save_enabled_keyboards_only_with_comments(config_path, Some(&pattern_comments))?;
-
-    // Send reload notification
-    send_reload_notification()?;
+    save_enabled_state(config, config_path, uid, false)?;
 
     println!();
     println!(
@@ -615,27 +660,50 @@ fn merge_and_deduplicate(
     result
 }
 
-fn send_reload_notification() -> Result<()> {
-    match send_request(&IpcRequest::ToggleKeyboards) {
+/// Store enabled_keyboards as keyboard state, leaving config.ron untouched
+///
+/// The daemon writes the state and reassigns keyboards in one go; when it isn't
+/// running the caller's own state is written directly and picked up on its next
+/// start, while another user's is only ever written by the daemon.
+fn save_enabled_state(
+    config: &Config,
+    config_path: &std::path::Path,
+    uid: u32,
+    quiet: bool,
+) -> Result<()> {
+    let entries = keyboard_state::to_entries(&config.enabled_keyboards);
+    match send_request(&IpcRequest::SetEnabledKeyboards {
+        entries: entries.clone(),
+        uid,
+    }) {
         Ok(IpcResponse::Ok) => {}
+        Ok(IpcResponse::Error(e)) => anyhow::bail!(e),
         Ok(_) => {
-            println!(
+            eprintln!(
                 "  {} {}",
                 "⚠".bright_yellow(),
                 "Unexpected response from daemon".yellow()
             );
         }
         Err(e) => {
-            println!(
-                "  {} {}",
-                "⚠".bright_yellow(),
-                format!("Daemon not running: {e}").yellow()
-            );
-            println!(
-                "  {} Start it with: {}",
-                "Tip:".bright_yellow().bold(),
-                "sudo systemctl start keymux".dimmed()
-            );
+            if uid != keymux::get_actual_user_uid().0 {
+                anyhow::bail!(
+                    "Daemon not running, so another user's keyboards can't be changed: {e}"
+                );
+            }
+            keyboard_state::save(config_path, uid, &entries)?;
+            if !quiet {
+                println!(
+                    "  {} {}",
+                    "⚠".bright_yellow(),
+                    format!("Daemon not running: {e}").yellow()
+                );
+                println!(
+                    "  {} Start it with: {}",
+                    "Tip:".bright_yellow().bold(),
+                    "sudo systemctl start keymux".dimmed()
+                );
+            }
         }
     }
     Ok(())
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use colored::Colorize;
+use keymux::ipc::{send_request, IpcRequest, IpcResponse};
+use keymux::keycode::KeyCode;
+
+/// Ask the daemon to type text through one of the user's virtual keyboards
+pub fn run_type(text: &str, enter: bool, user: Option<&str>, keyboard: Option<&str>) -> Result<()> {
+    let request = IpcRequest::TypeString {
+        text: text.to_string(),
+        enter,
+        uid: resolve_target_uid(user)?,
+        keyboard: keyboard.map(str::to_string),
+    };
+    send_input_request(&request, "Failed to type text")
+}
+
+/// Ask the daemon to run key events through one of the user's keyboards
+///
+/// Each key is written as `KC_A` (tap), `+KC_A` (press) or `-KC_A` (release).
+pub fn run_inject(keys: &[String], user: Option<&str>, keyboard: Option<&str>) -> Result<()> {
+    let mut events = Vec::with_capacity(keys.len() * 2);
+    for spec in keys {
+        let (name, press, release) = if let Some(name) = spec.strip_prefix('+') {
+            (name, true, false)
+        } else if let Some(name) = spec.strip_prefix('-') {
+            (name, false, true)
+        } else {
+            (spec.as_str(), true, true)
+        };
+
+        let key = KeyCode::from_name(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown key: {} (expected e.g. KC_A)", name))?;
+        if press {
+            events.push((key, true));
+        }
+        if release {
+            events.push((key, false));
+        }
+    }
+
+    let request = IpcRequest::InjectKeys {
+        events,
+        uid: resolve_target_uid(user)?,
+        keyboard: keyboard.map(str::to_string),
+    };
+    send_input_request(&request, "Failed to inject keys")
+}
+
+/// Which user's keyboards to target: the named user, or the caller
+pub fn resolve_target_uid(user: Option<&str>) -> Result<Option<u32>> {
+    match user {
+        Some(name) => Ok(Some(keymux::get_user_uid_by_name(name)?)),
+        None => {
+            // Plain root owns no keyboards, so let it use any of them
+            let (uid, is_sudo) = keymux::get_actual_user_uid();
+            Ok((uid != 0 || is_sudo).then_some(uid))
+        }
+    }
+}
+
+fn send_input_request(request: &IpcRequest, failure: &str) -> Result<()> {
+    match send_request(request) {
+        Ok(IpcResponse::Ok) => Ok(()),
+        Ok(IpcResponse::Error(msg)) => {
+            eprintln!("  {} {}", "✗".bright_red().bold(), msg.red());
+            anyhow::bail!("{}", failure);
+        }
+        Ok(response) => anyhow::bail!("Unexpected response from daemon: {:?}", response),
+        Err(e) => {
+            eprintln!(
+                "  {} {}",
+                "✗".bright_red().bold(),
+                format!("Failed to connect to daemon: {}", e).red()
+            );
+            anyhow::bail!("Daemon not running");
+        }
+    }
+}
@@ -156,7 +156,7 @@ impl ConfigDisplay {
         if self.config_path.exists() {
             println!("  Status: {}", "✓ Exists".bright_green());
 
-            match Config::load(&self.config_path) {
+            match Config::load_with_state(&self.config_path) {
                 Ok(config) => {
                     self.print_config_details(&config);
                 }
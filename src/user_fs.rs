@@ -0,0 +1,129 @@
+//! Files the root daemon writes inside users' home directories
+//!
+//! Everything under a user's home is theirs to rearrange, so any path the daemon
+//! writes there may have been swapped for a symlink to `/etc/shadow`. Writes run with
+//! the user's filesystem IDs (`setfsuid`), so the kernel checks them against the
+//! user's permissions instead of root's and new files come out owned by the user.
+//! Files are written to a fresh temp file created with `O_EXCL|O_NOFOLLOW` and renamed
+//! into place; a rename replaces a symlink rather than following it.
+
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Run `f` with the filesystem IDs of `uid` (and its primary group)
+///
+/// fsuid and fsgid are per thread, so other threads keep root's. Does nothing when not
+/// running as root or when `uid` is root.
+pub fn as_user<T>(uid: u32, f: impl FnOnce() -> std::io::Result<T>) -> std::io::Result<T> {
+    if uid == 0 || unsafe { libc::geteuid() } != 0 {
+        return f();
+    }
+    let gid = primary_gid(uid)?;
+    let (previous_gid, previous_uid) = unsafe { (libc::setfsgid(gid), libc::setfsuid(uid)) };
+    // setfsuid reports failure only through the ID it returns on the next call
+    let switched = unsafe { libc::setfsuid(uid) } == uid as i32
+        && unsafe { libc::setfsgid(gid) } == gid as i32;
+    let result = if switched {
+        f()
+    } else {
+        Err(std::io::Error::other(format!(
+            "failed to switch to the filesystem IDs of uid {}",
+            uid
+        )))
+    };
+    unsafe {
+        libc::setfsuid(previous_uid as u32);
+        libc::setfsgid(previous_gid as u32);
+    }
+    result
+}
+
+/// Replace `path` with `contents` as `uid`, moving the old file to `backup` if given
+pub fn replace(
+    uid: u32,
+    path: &Path,
+    contents: &[u8],
+    backup: Option<&Path>,
+) -> std::io::Result<()> {
+    as_user(uid, || {
+        let tmp = temp_path(path);
+        let written = write_new(&tmp, contents).and_then(|()| {
+            if let Some(backup) = backup {
+                match std::fs::rename(path, backup) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            std::fs::rename(&tmp, path)
+        });
+        if written.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        written
+    })
+}
+
+/// Create the directory `path` and its parents as `uid`
+pub fn create_dir_all(uid: u32, path: &Path) -> std::io::Result<()> {
+    as_user(uid, || std::fs::create_dir_all(path))
+}
+
+/// `<dir>/.<name>.<pid>-<nanos>.tmp`, unique enough that nobody can plant it in advance
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    path.with_file_name(format!(".{}.{}-{}.tmp", name, std::process::id(), nanos))
+}
+
+fn write_new(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .mode(0o644)
+        .open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+fn primary_gid(uid: u32) -> std::io::Result<u32> {
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let rc =
+        unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return Err(std::io::Error::other(format!(
+            "no passwd entry for uid {}",
+            uid
+        )));
+    }
+    Ok(passwd.pw_gid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_does_not_follow_symlinks() {
+        let dir = std::env::temp_dir().join(format!("keymux-user-fs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target");
+        std::fs::write(&target, "keep").unwrap();
+        let path = dir.join("state.json");
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+
+        let uid = unsafe { libc::geteuid() };
+        replace(uid, &path, b"new", Some(&dir.join("state.json.bak"))).unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "keep");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert!(!std::fs::symlink_metadata(&path).unwrap().is_symlink());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use crate::typing::resolve_target_uid;
+use anyhow::Result;
+use colored::Colorize;
+use keymux::event_processor::layer_stack::LayerChange;
+use keymux::ipc::{send_request, IpcRequest, IpcResponse, LayerHistory};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Show each keyboard's layers and recent layer changes, then follow new changes
+pub fn run_watch(user: Option<&str>, keyboard: Option<&str>) -> Result<()> {
+    let uid = resolve_target_uid(user)?;
+    let request = IpcRequest::GetLayerHistory {
+        uid,
+        keyboard: keyboard.map(String::from),
+    };
+
+    let histories = fetch(&request)?;
+    if histories.is_empty() {
+        println!(
+            "  {} No active keyboards{}",
+            "ℹ".bright_blue(),
+            keyboard.map_or(String::new(), |pattern| format!(
+                " matching \"{}\"",
+                pattern
+            ))
+        );
+        return Ok(());
+    }
+
+    println!();
+    println!("{}", "═══ Layers ═══".bright_cyan().bold());
+    // Last change shown per event file
+    let mut seen: HashMap<String, u64> = HashMap::new();
+    for history in &histories {
+        println!();
+        println!(
+            "  {} {}  {}",
+            history.keyboard.bright_white().bold(),
+            format!("({})", history.event_path).dimmed(),
+            history.active_layers.join(" → ").bright_green()
+        );
+        for change in &history.changes {
+            println!("    {}", describe_change(change));
+        }
+        if let Some(last) = history.changes.last() {
+            seen.insert(history.event_path.clone(), last.seq);
+        }
+    }
+    println!();
+    println!("{}", "Watching for layer changes (Ctrl+C to stop)".dimmed());
+
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        for history in fetch(&request)? {
+            let last_seen = seen.get(&history.event_path).copied();
+            for change in &history.changes {
+                if last_seen.is_some_and(|seq| change.seq <= seq) {
+                    continue;
+                }
+                println!(
+                    "  {}  {}",
+                    describe_change(change),
+                    history.keyboard.dimmed()
+                );
+            }
+            if let Some(last) = history.changes.last() {
+                seen.insert(history.event_path.clone(), last.seq);
+            }
+        }
+    }
+}
+
+fn fetch(request: &IpcRequest) -> Result<Vec<LayerHistory>> {
+    match send_request(request)? {
+        IpcResponse::LayerHistory(histories) => Ok(histories),
+        IpcResponse::Error(e) => anyhow::bail!(e),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+}
+
+/// `12:03:04.123  + nav  (KC_LALT)`
+fn describe_change(change: &LayerChange) -> String {
+    let sign = if change.active {
+        "+".bright_green()
+    } else {
+        "-".bright_red()
+    };
+    let trigger = change
+        .key
+        .map_or_else(|| "(no key)".to_string(), |key| format!("({})", key));
+    format!(
+        "{}  {} {}  {}",
+        local_time(change.at_ms).dimmed(),
+        sign,
+        change.layer.bright_white(),
+        trigger.dimmed()
+    )
+}
+
+/// Local wall-clock time of a Unix timestamp in ms, as HH:MM:SS.mmm
+pub fn local_time(at_ms: u64) -> String {
+    let secs = (at_ms / 1000) as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&secs, &mut tm) };
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        at_ms % 1000
+    )
+}
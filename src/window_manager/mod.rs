@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::mpsc::Sender;
 use std::thread;
@@ -5,11 +6,14 @@ use std::time::Duration;
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::{debug, error, info, warn};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct WindowInfo {
     pub app_id: Option<String>,
     pub pid: Option<u32>,
     pub title: Option<String>,
+    /// Window covers its whole output (only reported by niri so far)
+    #[serde(default)]
+    pub fullscreen: bool,
 }
 
 #[derive(Debug, Clone)]
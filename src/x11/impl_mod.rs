@@ -48,11 +48,7 @@ impl I3WindowManager {
         match output {
             Ok(output) if output.status.success() => {
                 let Ok(json_str) = String::from_utf8(output.stdout) else {
-                    return WindowInfo {
-                        app_id: None,
-                        pid: None,
-                        title: None,
-                    };
+                    return WindowInfo::default();
                 };
 
                 if let Ok(json) = serde_json::from_str::<serde_json::Value>(&json_str) {
@@ -74,20 +70,17 @@ impl I3WindowManager {
                         .and_then(|v| v.as_str())
                         .map(String::from);
 
-                    return WindowInfo { app_id, pid, title };
+                    return WindowInfo {
+                        app_id,
+                        pid,
+                        title,
+                        ..Default::default()
+                    };
                 }
 
-                WindowInfo {
-                    app_id: None,
-                    pid: None,
-                    title: None,
-                }
+                WindowInfo::default()
             }
-            _ => WindowInfo {
-                app_id: None,
-                pid: None,
-                title: None,
-            },
+            _ => WindowInfo::default(),
         }
     }
 }
@@ -148,11 +141,7 @@ impl BspwmWindowManager {
         match output {
             Ok(output) if output.status.success() => {
                 let Ok(json_str) = String::from_utf8(output.stdout) else {
-                    return WindowInfo {
-                        app_id: None,
-                        pid: None,
-                        title: None,
-                    };
+                    return WindowInfo::default();
                 };
 
                 if let Ok(json) = serde_json::from_str::<serde_json::Value>(&json_str) {
@@ -176,20 +165,17 @@ impl BspwmWindowManager {
                         .and_then(|v| v.as_str())
                         .map(String::from);
 
-                    return WindowInfo { app_id, pid, title };
+                    return WindowInfo {
+                        app_id,
+                        pid,
+                        title,
+                        ..Default::default()
+                    };
                 }
 
-                WindowInfo {
-                    app_id: None,
-                    pid: None,
-                    title: None,
-                }
+                WindowInfo::default()
             }
-            _ => WindowInfo {
-                app_id: None,
-                pid: None,
-                title: None,
-            },
+            _ => WindowInfo::default(),
         }
     }
 }
@@ -291,11 +277,7 @@ pub fn get_focused_window() -> WindowInfo {
     } else if is_bspwm_available() {
         BspwmWindowManager::new().get_focused_window()
     } else {
-        WindowInfo {
-            app_id: None,
-            pid: None,
-            title: None,
-        }
+        WindowInfo::default()
     }
 }
 